@@ -0,0 +1,269 @@
+//! Cursive's own crossterm backend, except for input, which is read with a crossterm
+//! new enough to report when the terminal gains or loses focus.
+//! Drawing is left entirely to the wrapped backend, which anything else written to the terminal
+//! has to wait on.
+
+use cursive::backend;
+use cursive::event::{Event, Key, MouseButton, MouseEvent};
+use cursive::theme::{Color, ColorPair, Effect};
+use cursive::{CbSink, Vec2};
+use once_cell::sync::Lazy;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crossterm::event::{
+    self as cevent, DisableFocusChange, EnableFocusChange, Event as CEvent, KeyCode, KeyEvent,
+    KeyEventKind, KeyModifiers, MouseButton as CMouseButton, MouseEvent as CMouseEvent,
+    MouseEventKind,
+};
+use crossterm::execute;
+
+use crate::visibility;
+
+// The modifiers held for the latest mouse event, which cursive has nowhere to put.
+// Events are handled as soon as they're polled, so this always goes with the one being handled.
+static MOUSE_MODIFIERS: AtomicU8 = AtomicU8::new(0);
+
+/// Whether Shift was held for the mouse event being handled.
+pub(crate) fn mouse_shift() -> bool {
+    mouse_modifiers().contains(KeyModifiers::SHIFT)
+}
+
+/// Whether Ctrl was held for the mouse event being handled.
+pub(crate) fn mouse_ctrl() -> bool {
+    mouse_modifiers().contains(KeyModifiers::CONTROL)
+}
+
+fn mouse_modifiers() -> KeyModifiers {
+    KeyModifiers::from_bits_truncate(MOUSE_MODIFIERS.load(Ordering::Relaxed))
+}
+
+// Escape sequences and such that aren't drawing, waiting to go out with the next refresh.
+static PASSTHROUGH: Lazy<Mutex<Vec<u8>>> = Lazy::new(Mutex::default);
+
+/// Sends something straight to the terminal, like a bell, once the screen is next refreshed.
+/// Writing it right away could land in the middle of a frame being drawn.
+pub(crate) fn write_raw(bytes: &[u8]) {
+    PASSTHROUGH.lock().unwrap().extend_from_slice(bytes);
+}
+
+pub(crate) struct Backend {
+    inner: Box<dyn backend::Backend>,
+    // Cursive has no events for focus changes, so those are sent as callbacks instead.
+    cb_sink: CbSink,
+    // Events that arrived together, like the characters of a paste, waiting to be handed out.
+    pending: VecDeque<Event>,
+}
+
+impl Backend {
+    pub fn init(cb_sink: CbSink) -> io::Result<Box<dyn backend::Backend>> {
+        let inner = cursive::backends::crossterm::Backend::init()?;
+        execute!(io::stdout(), EnableFocusChange)?;
+
+        Ok(Box::new(Self {
+            inner,
+            cb_sink,
+            pending: VecDeque::new(),
+        }))
+    }
+
+    /// Passes a focus change along to the UI. Nothing for the views to see, though.
+    fn set_focused(&self, focused: bool) -> Option<Event> {
+        // This only fails once cursive has stopped listening, at which point nobody cares.
+        let cb = move |siv: &mut cursive::Cursive| visibility::set_focused(siv, focused);
+        self.cb_sink.send(Box::new(cb)).ok();
+        None
+    }
+
+    fn translate_event(&mut self, event: CEvent) -> Option<Event> {
+        let event = match event {
+            CEvent::Key(key) => translate_key_event(key)?,
+            CEvent::Mouse(mouse) => translate_mouse_event(mouse)?,
+            CEvent::Resize(_, _) => Event::WindowResize,
+            CEvent::FocusGained => return self.set_focused(true),
+            CEvent::FocusLost => return self.set_focused(false),
+            CEvent::Paste(text) => {
+                self.pending.extend(translate_paste(&text));
+                return self.pending.pop_front();
+            }
+        };
+        Some(event)
+    }
+}
+
+impl Drop for Backend {
+    fn drop(&mut self) {
+        // The wrapped backend cleans up everything else once this returns.
+        execute!(io::stdout(), DisableFocusChange).ok();
+    }
+}
+
+impl backend::Backend for Backend {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn poll_event(&mut self) -> Option<Event> {
+        if let Some(event) = self.pending.pop_front() {
+            return Some(event);
+        }
+        match cevent::poll(Duration::from_millis(1)) {
+            Ok(true) => match cevent::read() {
+                Ok(event) => self.translate_event(event),
+                Err(e) => panic!("couldn't read an event from the terminal: {}", e),
+            },
+            _ => None,
+        }
+    }
+
+    fn set_title(&mut self, title: String) {
+        self.inner.set_title(title);
+    }
+
+    fn refresh(&mut self) {
+        self.inner.refresh();
+
+        let pending = std::mem::take(&mut *PASSTHROUGH.lock().unwrap());
+        if !pending.is_empty() {
+            let mut stdout = io::stdout();
+            let _ = stdout.write_all(&pending).and_then(|()| stdout.flush());
+        }
+    }
+
+    fn has_colors(&self) -> bool {
+        self.inner.has_colors()
+    }
+
+    fn screen_size(&self) -> Vec2 {
+        self.inner.screen_size()
+    }
+
+    fn print_at(&self, pos: Vec2, text: &str) {
+        self.inner.print_at(pos, text);
+    }
+
+    fn clear(&self, color: Color) {
+        self.inner.clear(color);
+    }
+
+    fn set_color(&self, colors: ColorPair) -> ColorPair {
+        self.inner.set_color(colors)
+    }
+
+    fn set_effect(&self, effect: Effect) {
+        self.inner.set_effect(effect);
+    }
+
+    fn unset_effect(&self, effect: Effect) {
+        self.inner.unset_effect(effect);
+    }
+}
+
+fn translate_key_event(event: KeyEvent) -> Option<Event> {
+    // Terminals only report releases when asked to, but some report them regardless.
+    if event.kind == KeyEventKind::Release {
+        return None;
+    }
+
+    let mods = event.modifiers;
+    let (ctrl, alt, shift) = (
+        KeyModifiers::CONTROL,
+        KeyModifiers::ALT,
+        KeyModifiers::SHIFT,
+    );
+    let translated = match event.code {
+        // A shifted character is already the uppercase one, and cursive has no use for the shift.
+        KeyCode::Char(c) => match mods - shift {
+            m if m == ctrl => Event::CtrlChar(c),
+            m if m == alt => Event::AltChar(c),
+            // Including Ctrl+Alt, which is how some terminals report AltGr.
+            _ => Event::Char(c),
+        },
+        // Crossterm doesn't report the shift that goes with a back tab.
+        KeyCode::BackTab => Event::Shift(Key::Tab),
+        code => {
+            let key = translate_key(code)?;
+            if mods == ctrl | alt {
+                Event::CtrlAlt(key)
+            } else if mods == ctrl | shift {
+                Event::CtrlShift(key)
+            } else if mods == alt | shift {
+                Event::AltShift(key)
+            } else if mods == ctrl {
+                Event::Ctrl(key)
+            } else if mods == alt {
+                Event::Alt(key)
+            } else if mods == shift {
+                Event::Shift(key)
+            } else {
+                Event::Key(key)
+            }
+        }
+    };
+    Some(translated)
+}
+
+fn translate_key(code: KeyCode) -> Option<Key> {
+    let key = match code {
+        KeyCode::Esc => Key::Esc,
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Left => Key::Left,
+        KeyCode::Right => Key::Right,
+        KeyCode::Up => Key::Up,
+        KeyCode::Down => Key::Down,
+        KeyCode::Home => Key::Home,
+        KeyCode::End => Key::End,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::Delete => Key::Del,
+        KeyCode::Insert => Key::Ins,
+        KeyCode::Enter => Key::Enter,
+        KeyCode::Tab => Key::Tab,
+        KeyCode::Pause => Key::PauseBreak,
+        KeyCode::F(n) if (1..=12).contains(&n) => Key::from_f(n),
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// Pasted text comes in all at once, but views only know about typed characters.
+fn translate_paste(text: &str) -> Vec<Event> {
+    text.replace("\r\n", "\n")
+        .chars()
+        .filter_map(|c| match c {
+            '\n' | '\r' => Some(Event::Key(Key::Enter)),
+            '\t' => Some(Event::Key(Key::Tab)),
+            c if c.is_control() => None,
+            c => Some(Event::Char(c)),
+        })
+        .collect()
+}
+
+fn translate_mouse_event(event: CMouseEvent) -> Option<Event> {
+    let mouse_event = match event.kind {
+        MouseEventKind::Down(button) => MouseEvent::Press(translate_button(button)),
+        MouseEventKind::Up(button) => MouseEvent::Release(translate_button(button)),
+        MouseEventKind::Drag(button) => MouseEvent::Hold(translate_button(button)),
+        MouseEventKind::ScrollDown => MouseEvent::WheelDown,
+        MouseEventKind::ScrollUp => MouseEvent::WheelUp,
+        // Only reported because drags can't be had without it, and nothing cares otherwise.
+        MouseEventKind::Moved => return None,
+    };
+    MOUSE_MODIFIERS.store(event.modifiers.bits(), Ordering::Relaxed);
+    Some(Event::Mouse {
+        offset: Vec2::zero(),
+        position: Vec2::new(event.column as usize, event.row as usize),
+        event: mouse_event,
+    })
+}
+
+fn translate_button(button: CMouseButton) -> MouseButton {
+    match button {
+        CMouseButton::Left => MouseButton::Left,
+        CMouseButton::Right => MouseButton::Right,
+        CMouseButton::Middle => MouseButton::Middle,
+    }
+}
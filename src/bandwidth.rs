@@ -0,0 +1,172 @@
+use once_cell::sync::Lazy;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::util::glyphs::glyphs;
+
+// A day of one-minute averages, so that the history has something to show right after starting.
+const SPAN: i64 = 24 * 60 * 60;
+const RESOLUTION: i64 = 60;
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    time: i64,
+    down: u64,
+    up: u64,
+}
+
+impl Sample {
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split_whitespace();
+        Some(Self {
+            time: fields.next()?.parse().ok()?,
+            down: fields.next()?.parse().ok()?,
+            up: fields.next()?.parse().ok()?,
+        })
+    }
+}
+
+/// The session's payload rates, averaged per minute, and kept on disk for a day.
+struct History {
+    samples: VecDeque<Sample>,
+    // The minute in progress: when it started, the sums of its rates, and how many there were.
+    current: Option<(i64, u64, u64, u64)>,
+    path: Option<PathBuf>,
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+impl History {
+    fn load() -> Self {
+        let path = crate::config::data_path("bandwidth");
+        let cutoff = now() - SPAN;
+
+        let samples = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|text| {
+                text.lines()
+                    .filter_map(Sample::parse)
+                    .filter(|sample| sample.time >= cutoff)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            samples,
+            current: None,
+            path,
+        }
+    }
+
+    fn record(&mut self, down: u64, up: u64) {
+        let now = now();
+        let minute = now - now % RESOLUTION;
+
+        match &mut self.current {
+            Some((start, d, u, n)) if *start == minute => {
+                *d += down;
+                *u += up;
+                *n += 1;
+                return;
+            }
+            _ => {}
+        }
+
+        if let Some((start, d, u, n)) = self.current.take() {
+            self.samples.push_back(Sample {
+                time: start,
+                down: d / n,
+                up: u / n,
+            });
+            while self.samples.front().map_or(false, |s| s.time < now - SPAN) {
+                self.samples.pop_front();
+            }
+            self.save();
+        }
+        self.current = Some((minute, down, up, 1));
+    }
+
+    /// Rewrites the whole file. It's a day of minutes at most, so it never gets big.
+    fn save(&self) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+
+        let text: String = self
+            .samples
+            .iter()
+            .map(|s| format!("{} {} {}\n", s.time, s.down, s.up))
+            .collect();
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).ok();
+        }
+        // Write then rename, so that quitting mid-write can't lose the whole history.
+        let tmp = path.with_extension("tmp");
+        if fs::write(&tmp, text).is_ok() {
+            fs::rename(&tmp, path).ok();
+        }
+    }
+}
+
+static HISTORY: Lazy<Mutex<History>> = Lazy::new(|| Mutex::new(History::load()));
+
+/// Picks up the history of whichever profile is now current.
+pub(crate) fn switch_profile() {
+    let mut history = HISTORY.lock().unwrap();
+    history.save();
+    *history = History::load();
+}
+
+/// Takes note of the session's current rates. Expected about once a second, from an async task.
+pub(crate) fn record(down: u64, up: u64) {
+    // The first call loads the history, and each new minute saves it.
+    tokio::task::block_in_place(|| HISTORY.lock().unwrap().record(down, up));
+}
+
+/// The last day's average rates, squeezed into `width` columns, oldest first.
+/// Columns from while dtui wasn't running are zero.
+pub(crate) fn buckets(width: usize) -> Vec<(u64, u64)> {
+    let end = now();
+    let start = end - SPAN;
+    let mut sums = vec![(0, 0, 0); width];
+
+    for sample in &HISTORY.lock().unwrap().samples {
+        if sample.time < start || width == 0 {
+            continue;
+        }
+        let i = ((sample.time - start) * width as i64 / SPAN) as usize;
+        let sum = &mut sums[i.min(width - 1)];
+        sum.0 += sample.down;
+        sum.1 += sample.up;
+        sum.2 += 1;
+    }
+
+    sums.into_iter()
+        .map(|(down, up, n)| if n == 0 { (0, 0) } else { (down / n, up / n) })
+        .collect()
+}
+
+/// Draws rates as a line of bars, scaled to the highest, which is also returned.
+pub(crate) fn sparkline(rates: impl Iterator<Item = u64> + Clone) -> (String, u64) {
+    let peak = rates.clone().max().unwrap_or(0);
+    let bars = glyphs().bars;
+
+    let line = rates
+        .map(|rate| match rate {
+            0 => " ",
+            _ => {
+                let eighths = (rate as f64 / peak as f64 * 8.0).ceil() as usize;
+                bars[eighths.clamp(1, 8) - 1]
+            }
+        })
+        .collect();
+
+    (line, peak)
+}
@@ -0,0 +1,37 @@
+use deluge_rpc::Session;
+use std::collections::HashSet;
+
+/// What the connected daemon can do, found out once right after connecting.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Capabilities {
+    pub version: String,
+    // If the daemon wouldn't say, everything is assumed to work.
+    methods: Option<HashSet<String>>,
+}
+
+impl Capabilities {
+    pub async fn detect(session: &Session) -> Self {
+        let version = session.daemon_info().await.unwrap_or_default();
+        let methods = session
+            .get_method_list()
+            .await
+            .ok()
+            .map(|methods| methods.into_iter().collect());
+        Self { version, methods }
+    }
+
+    /// Whether the daemon has an RPC method, e.g. "core.rename_folder".
+    pub fn supports(&self, method: &str) -> bool {
+        self.methods.as_ref().map_or(true, |m| m.contains(method))
+    }
+
+    fn major_version(&self) -> Option<u32> {
+        self.version.split('.').next()?.parse().ok()
+    }
+
+    /// Whether the daemon knows about torrent options that were added in Deluge 2.0,
+    /// such as ownership, sharing, and super seeding.
+    pub fn supports_v2_options(&self) -> bool {
+        self.major_version().map_or(true, |v| v >= 2)
+    }
+}
@@ -0,0 +1,172 @@
+use deluge_rpc::{InfoHash, Query, Session, TorrentOptions, TorrentState};
+use serde::Deserialize;
+
+use crate::util;
+
+const USAGE: &str = "\
+usage:
+    dtui                    start the interactive UI
+    dtui add <url|magnet>   add a torrent
+    dtui pause <hash>       pause a torrent
+    dtui resume <hash>      resume a torrent
+    dtui list [--json]      list all torrents
+
+options:
+    --config <profile>      use a profile's config file instead of the default one
+    --demo                  start the UI against made-up torrents, without a daemon
+                            (using the \"demo\" profile, unless --config says otherwise)";
+
+#[derive(Debug, Clone, Deserialize, Query)]
+struct ListedTorrent {
+    hash: InfoHash,
+    name: String,
+    state: TorrentState,
+    progress: f32,
+    total_size: u64,
+    download_payload_rate: u64,
+    upload_payload_rate: u64,
+}
+
+fn usage_error(msg: &str) -> ! {
+    eprintln!("dtui: {}\n\n{}", msg, USAGE);
+    std::process::exit(2)
+}
+
+fn parse_hash(s: Option<&String>) -> InfoHash {
+    let s = s.unwrap_or_else(|| usage_error("missing infohash"));
+    s.parse()
+        .unwrap_or_else(|_| usage_error(&format!("invalid infohash: {}", s)))
+}
+
+/// Handles `--config <profile>`, which can come before or after a subcommand.
+pub(crate) fn take_profile(args: &mut Vec<String>) {
+    let i = match args.iter().position(|arg| arg == "--config") {
+        Some(i) => i,
+        None => return,
+    };
+
+    let name = match args.get(i + 1) {
+        Some(name) if !name.is_empty() && !name.contains(['/', '\\']) => name.clone(),
+        Some(name) => usage_error(&format!("invalid profile name: {}", name)),
+        None => usage_error("missing profile name"),
+    };
+    crate::config::set_profile(&name);
+    args.drain(i..=i + 1);
+}
+
+/// Handles `--demo`, which only makes sense for the interactive UI.
+pub(crate) fn take_demo(args: &mut Vec<String>) -> bool {
+    let i = match args.iter().position(|arg| arg == "--demo") {
+        Some(i) => i,
+        None => return false,
+    };
+    args.remove(i);
+
+    if args.iter().any(|arg| is_command(arg)) {
+        usage_error("--demo can't be used with a subcommand");
+    }
+    // Keep whatever the demo does out of the real config. `--config` can still override this.
+    crate::config::set_profile("demo");
+    true
+}
+
+/// Returns whether `name` is one of the subcommands handled by `run`.
+pub(crate) fn is_command(name: &str) -> bool {
+    matches!(
+        name,
+        "add" | "pause" | "resume" | "list" | "help" | "--help" | "-h"
+    )
+}
+
+pub(crate) async fn run(args: &[String]) -> deluge_rpc::Result<()> {
+    let command = args[0].as_str();
+
+    if let "help" | "--help" | "-h" = command {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+
+    let handle = match crate::autoconnect().await? {
+        Some(handle) => handle,
+        None => {
+            eprintln!("dtui: no autoconnect host is configured");
+            std::process::exit(1)
+        }
+    };
+    let session = handle.get_session().unwrap();
+
+    match command {
+        "add" => add(session, &args[1..]).await,
+        "pause" => session.pause_torrent(parse_hash(args.get(1))).await,
+        "resume" => session.resume_torrent(parse_hash(args.get(1))).await,
+        "list" => list(session, &args[1..]).await,
+        _ => unreachable!(),
+    }
+}
+
+async fn add(session: &Session, args: &[String]) -> deluge_rpc::Result<()> {
+    let url = args.get(0).unwrap_or_else(|| usage_error("missing url"));
+    let options = TorrentOptions::default();
+
+    let hash = if url.starts_with("magnet:") {
+        session.add_torrent_magnet(url, &options).await?
+    } else {
+        session.add_torrent_url(url, &options, None).await?
+    };
+
+    match hash {
+        Some(hash) => println!("{}", hash),
+        None => eprintln!("dtui: the daemon did not add the torrent"),
+    }
+
+    Ok(())
+}
+
+async fn list(session: &Session, args: &[String]) -> deluge_rpc::Result<()> {
+    let json = match args.get(0).map(String::as_str) {
+        None => false,
+        Some("--json") => true,
+        Some(arg) => usage_error(&format!("unexpected argument: {}", arg)),
+    };
+
+    let mut torrents: Vec<ListedTorrent> = session
+        .get_torrents_status::<ListedTorrent>(None)
+        .await?
+        .into_iter()
+        .map(|(_, torrent)| torrent)
+        .collect();
+
+    torrents.sort_by(|a, b| a.name.cmp(&b.name));
+
+    if json {
+        let values: Vec<serde_json::Value> = torrents
+            .iter()
+            .map(|t| {
+                serde_json::json!({
+                    "hash": t.hash.to_string(),
+                    "name": t.name,
+                    "state": t.state.as_str(),
+                    "progress": t.progress,
+                    "total_size": t.total_size,
+                    "download_payload_rate": t.download_payload_rate,
+                    "upload_payload_rate": t.upload_payload_rate,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&values).unwrap());
+        return Ok(());
+    }
+
+    for t in &torrents {
+        println!(
+            "{} {:<11} {:>6}% {:>10} {}",
+            t.hash,
+            t.state.as_str(),
+            util::fmt::percentage(t.progress),
+            util::fmt::bytes(t.total_size),
+            t.name,
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,6 @@
+/// Copies text to the system clipboard with an OSC 52 escape sequence.
+/// Most terminals support this (as does tmux, with `set-clipboard on`), and it works over SSH.
+pub(crate) fn copy(text: &str) {
+    let seq = format!("\x1b]52;c;{}\x07", base64::encode(text));
+    crate::backend::write_raw(seq.as_bytes());
+}
@@ -0,0 +1,357 @@
+//! A command line in the style of deluge-console, opened with ':'.
+
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use cursive::event::{EventResult, Key};
+use cursive::traits::*;
+use cursive::view::Position;
+use cursive::views::{Dialog, EditView, LinearLayout, OnEventView, TextView};
+use cursive::Cursive;
+use deluge_rpc::{InfoHash, Session, TorrentOptions};
+use futures::executor::block_on;
+use uuid::Uuid;
+
+use crate::config;
+use crate::rpc::{self, Policy};
+use crate::views::torrents::TorrentsView;
+use crate::AppState;
+
+const COMMANDS: [&str; 6] = ["add", "pause", "resume", "rm", "config", "connect"];
+
+const USAGE: &str = "\
+add <url|magnet>
+pause [torrent...]
+resume [torrent...]
+rm [--data] <torrent...>
+config [key [value]]
+connect <address[:port]>";
+
+/// What tab completion can choose from, gathered when the command bar is opened.
+struct Completions {
+    torrents: Vec<(InfoHash, String)>,
+    hosts: Vec<String>,
+}
+
+impl Completions {
+    fn gather(siv: &mut Cursive) -> Self {
+        let torrents = siv
+            .call_on_name("torrents", |v: &mut TorrentsView| v.torrent_names())
+            .unwrap_or_default();
+
+        let hosts = config::read()
+            .connection_manager
+            .hosts
+            .values()
+            .map(|host| format!("{}:{}", host.address, host.port))
+            .collect();
+
+        Self { torrents, hosts }
+    }
+
+    fn candidates(&self, command: &str) -> Vec<String> {
+        match command {
+            "pause" | "resume" | "rm" => self
+                .torrents
+                .iter()
+                .flat_map(|(hash, name)| vec![name.clone(), hash.to_string()])
+                .collect(),
+            "connect" => self.hosts.clone(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Splits a line into words, treating "quoted phrases" as single words.
+fn split_args(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current: Option<String> = None;
+    let mut quoted = false;
+
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.get_or_insert_with(String::new);
+            }
+            c if c.is_whitespace() && !quoted => args.extend(current.take()),
+            c => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    args.extend(current);
+    args
+}
+
+fn quote(word: &str) -> String {
+    if word.contains(char::is_whitespace) {
+        format!("\"{}\"", word)
+    } else {
+        word.to_owned()
+    }
+}
+
+fn common_prefix<'a>(words: &[&'a str]) -> &'a str {
+    let first = words[0];
+    let mut len = first.len();
+    for word in &words[1..] {
+        len = first
+            .char_indices()
+            .zip(word.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((i, c), _)| i + c.len_utf8())
+            .min(len);
+    }
+    &first[..len]
+}
+
+/// Completes the last word of `line`, if there's anything to complete it with.
+fn complete(line: &str, completions: &Completions) -> Option<String> {
+    let args = split_args(line);
+    let starting_new_word = line.is_empty() || line.ends_with(char::is_whitespace);
+
+    let (word, index) = if starting_new_word {
+        ("", args.len())
+    } else {
+        (args.last()?.as_str(), args.len() - 1)
+    };
+
+    let candidates: Vec<String> = match index {
+        0 => COMMANDS.iter().map(|c| String::from(*c)).collect(),
+        _ => completions.candidates(&args[0]),
+    };
+
+    let lower = word.to_lowercase();
+    let matches: Vec<&str> = candidates
+        .iter()
+        .filter(|c| c.to_lowercase().starts_with(&lower))
+        .map(String::as_str)
+        .collect();
+
+    let completed = match matches.as_slice() {
+        [] => return None,
+        [only] => quote(only) + " ",
+        many => {
+            let prefix = common_prefix(many);
+            if prefix.chars().count() <= word.chars().count() {
+                return None;
+            }
+            quote(prefix)
+        }
+    };
+
+    let kept = &args[..index];
+    let mut new_line: Vec<String> = kept.iter().map(|a| quote(a)).collect();
+    new_line.push(completed);
+    Some(new_line.join(" "))
+}
+
+/// Finds the torrents referred to by each argument: a hash, a unique hash prefix, or a name.
+fn resolve_torrents(
+    args: &[String],
+    torrents: &[(InfoHash, String)],
+) -> Result<Vec<InfoHash>, String> {
+    let mut hashes = Vec::new();
+    for arg in args {
+        let lower = arg.to_lowercase();
+        let by_hash: Vec<InfoHash> = torrents
+            .iter()
+            .filter(|(hash, _)| lower.len() >= 4 && hash.to_string().starts_with(&lower))
+            .map(|(hash, _)| *hash)
+            .collect();
+        let by_name: Vec<InfoHash> = torrents
+            .iter()
+            .filter(|(_, name)| name.to_lowercase() == lower)
+            .map(|(hash, _)| *hash)
+            .collect();
+
+        match (by_hash.as_slice(), by_name.as_slice()) {
+            ([hash], _) | ([], [hash]) => hashes.push(*hash),
+            ([], []) => return Err(format!("No torrent matches {}", quote(arg))),
+            _ => return Err(format!("{} matches more than one torrent", quote(arg))),
+        }
+    }
+    Ok(hashes)
+}
+
+fn current_session(siv: &mut Cursive) -> Result<(Arc<Session>, Policy), String> {
+    let handle = siv.user_data::<AppState>().unwrap().get();
+    let session = handle
+        .get_session()
+        .cloned()
+        .ok_or_else(|| String::from("Not connected"))?;
+    Ok((session, Policy::for_host(handle.get_id())))
+}
+
+/// Waits on a request for as long as the host's policy allows, like the menus do.
+fn request<T>(
+    policy: &Policy,
+    fut: impl Future<Output = deluge_rpc::Result<T>>,
+) -> Result<T, String> {
+    block_on(rpc::request(policy, fut)).map_err(|e| e.to_string())
+}
+
+fn connect(siv: &mut Cursive, args: &[String]) -> Result<Option<String>, String> {
+    let target = match args {
+        [target] => target,
+        _ => return Err(String::from("usage: connect <address[:port]>")),
+    };
+
+    let found: Option<(Uuid, config::Host)> = config::read()
+        .connection_manager
+        .hosts
+        .iter()
+        .find(|(_, host)| {
+            *target == host.address || *target == format!("{}:{}", host.address, host.port)
+        })
+        .map(|(id, host)| (*id, host.clone()));
+
+    let (id, host) = found.ok_or_else(|| format!("No configured host matches {}", target))?;
+
+    crate::menu::connect(siv, id, host);
+    Ok(None)
+}
+
+fn config_command(
+    session: &Session,
+    policy: &Policy,
+    args: &[String],
+) -> Result<Option<String>, String> {
+    match args {
+        [] => {
+            let config: BTreeMap<String, serde_json::Value> =
+                request(policy, session.get_config())?;
+            let lines: Vec<String> = config
+                .iter()
+                .map(|(key, val)| format!("{}: {}", key, val))
+                .collect();
+            Ok(Some(lines.join("\n")))
+        }
+        [key] => {
+            let val: serde_json::Value = request(policy, session.get_config_value(key))?;
+            Ok(Some(format!("{}: {}", key, val)))
+        }
+        [key, val] => {
+            // Anything that isn't valid JSON is taken to be a string.
+            let val = serde_json::from_str(val)
+                .unwrap_or_else(|_| serde_json::Value::String(val.clone()));
+            let mut config = HashMap::new();
+            config.insert(key.as_str(), val);
+            request(policy, session.set_config(&config))?;
+            Ok(None)
+        }
+        _ => Err(String::from("usage: config [key [value]]")),
+    }
+}
+
+fn run(siv: &mut Cursive, line: &str) -> Result<Option<String>, String> {
+    let args = split_args(line);
+    let (command, args) = match args.split_first() {
+        Some((command, args)) => (command.as_str(), args),
+        None => return Ok(None),
+    };
+
+    if command == "connect" {
+        return connect(siv, args);
+    } else if !COMMANDS.contains(&command) {
+        return Err(format!("Unknown command: {}\n\n{}", command, USAGE));
+    }
+
+    let (session, policy) = current_session(siv)?;
+    let torrents = siv
+        .call_on_name("torrents", |v: &mut TorrentsView| v.torrent_names())
+        .unwrap_or_default();
+    let selected = siv
+        .call_on_name("torrents", |v: &mut TorrentsView| v.selected_torrents())
+        .unwrap_or_default();
+
+    // With no torrents given, pause and resume act on the selection.
+    let targets = |args: &[String]| match args {
+        [] => Ok(selected.clone()),
+        args => resolve_torrents(args, &torrents),
+    };
+
+    match command {
+        "add" => {
+            let url = args.get(0).ok_or("usage: add <url|magnet>")?;
+            let options = TorrentOptions::default();
+            let fut = async {
+                if url.starts_with("magnet:") {
+                    session.add_torrent_magnet(url, &options).await
+                } else {
+                    session.add_torrent_url(url, &options, None).await
+                }
+            };
+            match request(&policy, fut)? {
+                Some(hash) => {
+                    crate::menu::torrent_added(siv, Some(hash), url);
+                    Ok(None)
+                }
+                None => Err(String::from("The daemon did not add the torrent")),
+            }
+        }
+        "pause" | "resume" => {
+            for hash in targets(args)? {
+                if command == "pause" {
+                    request(&policy, session.pause_torrent(hash))?;
+                } else {
+                    request(&policy, session.resume_torrent(hash))?;
+                }
+            }
+            Ok(None)
+        }
+        "rm" => {
+            let (remove_data, args) = match args.split_first() {
+                Some((flag, rest)) if flag == "--data" => (true, rest),
+                _ => (false, args),
+            };
+            if args.is_empty() {
+                return Err(String::from("usage: rm [--data] <torrent...>"));
+            }
+            let hashes = resolve_torrents(args, &torrents)?;
+            request(&policy, session.remove_torrents(&hashes, remove_data))?;
+            Ok(None)
+        }
+        "config" => config_command(&session, &policy, args),
+        _ => unreachable!(),
+    }
+}
+
+fn execute(siv: &mut Cursive, line: &str) {
+    match run(siv, line) {
+        Ok(None) => (),
+        Ok(Some(output)) => siv.add_layer(Dialog::info(output).title(line.trim())),
+        Err(msg) => siv.add_layer(Dialog::info(msg).title("Error")),
+    }
+}
+
+pub(crate) fn show_command_bar(siv: &mut Cursive) {
+    let completions = Rc::new(Completions::gather(siv));
+
+    let edit = EditView::new()
+        .on_submit(|siv, line| {
+            siv.pop_layer();
+            execute(siv, line);
+        })
+        .with_name("command_bar");
+
+    let edit = OnEventView::new(edit).on_pre_event_inner(Key::Tab, move |v, _| {
+        let mut v = v.get_mut();
+        if let Some(line) = complete(&v.get_content(), &completions) {
+            v.set_content(line);
+        }
+        Some(EventResult::Consumed(None))
+    });
+
+    let bar = LinearLayout::horizontal()
+        .child(TextView::new(":"))
+        .child(edit.full_width());
+
+    let size = siv.screen_size();
+    siv.screen_mut().add_layer_at(
+        Position::absolute((0, size.y.saturating_sub(1))),
+        bar.fixed_width(size.x),
+    );
+}
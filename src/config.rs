@@ -1,3 +1,7 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 use lazy_static::lazy_static;
@@ -8,22 +12,96 @@ type FnvIndexMap<K, V> = indexmap::IndexMap<K, V, fnv::FnvBuildHasher>;
 
 const APP_NAME: &str = "dtui";
 
+/// Where a directory on a remote daemon's machine can be found locally, e.g. an sshfs mount.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PathMapping {
+    pub remote: String,
+    pub local: PathBuf,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Host {
     pub username: String,
     pub password: String, // ¯\_(ツ)_/¯
     pub address: String,
     pub port: u16,
+    #[serde(default)]
+    pub path_mappings: Vec<PathMapping>,
+    // The folder it's listed under in the Connection Manager, if any.
+    #[serde(default)]
+    pub group: Option<String>,
+    // In seconds. These fall back to the defaults in `rpc::Policy` if unset.
+    #[serde(default)]
+    pub connect_timeout: Option<u64>,
+    #[serde(default)]
+    pub request_timeout: Option<u64>,
+    #[serde(default)]
+    pub retries: Option<u64>, // for the periodic updates, after a request times out
+    #[serde(default)]
+    pub protocol: Protocol,
+}
+
+/// Which version of Deluge's RPC protocol a daemon speaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Protocol {
+    Auto, // whichever one it answers to
+    Deluge1,
+    Deluge2,
+}
+
+impl Default for Protocol {
+    fn default() -> Self {
+        Self::Auto
+    }
 }
 
 impl Default for Host {
     fn default() -> Self {
-        let (username, password, address) = Default::default();
+        let (username, password, address, path_mappings, group) = Default::default();
+        let (connect_timeout, request_timeout, retries) = Default::default();
         Self {
             username,
             password,
             address,
             port: 58846,
+            path_mappings,
+            group,
+            connect_timeout,
+            request_timeout,
+            retries,
+            protocol: Protocol::default(),
+        }
+    }
+}
+
+impl Host {
+    pub fn same_endpoint(&self, other: &Self) -> bool {
+        (&self.username, &self.address, self.port) == (&other.username, &other.address, other.port)
+    }
+
+    pub fn is_local(&self) -> bool {
+        matches!(self.address.as_str(), "localhost" | "127.0.0.1" | "::1")
+    }
+
+    pub fn can_resolve_paths(&self) -> bool {
+        self.is_local() || !self.path_mappings.is_empty()
+    }
+
+    /// Resolves a path on the daemon's machine to one that can be opened locally, if possible.
+    pub fn local_path(&self, remote: &str) -> Option<PathBuf> {
+        let remote = Path::new(remote);
+
+        let mapped = self
+            .path_mappings
+            .iter()
+            .filter_map(|m| Some((m, remote.strip_prefix(&m.remote).ok()?)))
+            .max_by_key(|(m, _)| m.remote.len())
+            .map(|(m, rest)| m.local.join(rest));
+
+        match mapped {
+            Some(path) => Some(path),
+            None if self.is_local() => Some(remote.to_path_buf()),
+            None => None,
         }
     }
 }
@@ -33,11 +111,477 @@ pub struct ConnectionManagerConfig {
     pub autoconnect: Option<Uuid>,
     pub hide_on_start: bool,
     pub hosts: FnvIndexMap<Uuid, Host>,
+    #[serde(default)]
+    pub collapsed_groups: Vec<String>,
+}
+
+impl ConnectionManagerConfig {
+    /// Adds every host that isn't already present, returning the ones that were added.
+    pub fn merge_hosts(&mut self, hosts: impl IntoIterator<Item = Host>) -> Vec<(Uuid, Host)> {
+        let mut added = Vec::new();
+        for host in hosts {
+            if self.hosts.values().any(|h| h.same_endpoint(&host)) {
+                continue;
+            }
+            let id = Uuid::new_v4();
+            self.hosts.insert(id, host.clone());
+            added.push((id, host));
+        }
+        added
+    }
+}
+
+fn deluge_config_dir() -> Option<PathBuf> {
+    if let Some(appdata) = std::env::var_os("APPDATA") {
+        return Some(PathBuf::from(appdata).join("deluge"));
+    }
+
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("deluge"))
+}
+
+fn parse_hostlist(text: &str) -> Vec<Host> {
+    // Deluge config files are two concatenated JSON objects: a version header, then the data.
+    // Each host is stored as [id, address, port, username, password].
+    let mut hosts = Vec::new();
+    for value in serde_json::Deserializer::from_str(text).into_iter::<serde_json::Value>() {
+        let mut value = match value {
+            Ok(value) => value,
+            Err(_) => break,
+        };
+        let entries = match value.get_mut("hosts").and_then(|v| v.as_array_mut()) {
+            Some(entries) => std::mem::take(entries),
+            None => continue,
+        };
+
+        for entry in entries {
+            let fields = match entry.as_array() {
+                Some(fields) if fields.len() >= 5 => fields,
+                _ => continue,
+            };
+            let port = fields[2].as_u64().filter(|&p| p <= u16::MAX as u64);
+            if let (Some(address), Some(port), Some(username), Some(password)) = (
+                fields[1].as_str(),
+                port,
+                fields[3].as_str(),
+                fields[4].as_str(),
+            ) {
+                hosts.push(Host {
+                    username: username.to_owned(),
+                    password: password.to_owned(),
+                    address: address.to_owned(),
+                    port: port as u16,
+                    ..Host::default()
+                });
+            }
+        }
+    }
+    hosts
+}
+
+/// Reads the hosts known to the Deluge GTK UI, from both the 2.x and 1.x hostlist files.
+pub fn deluge_hostlist() -> Vec<Host> {
+    let dir = match deluge_config_dir() {
+        Some(dir) => dir,
+        None => return Vec::new(),
+    };
+
+    ["hostlist.conf", "hostlist.conf.1.2"]
+        .iter()
+        .filter_map(|name| std::fs::read_to_string(dir.join(name)).ok())
+        .flat_map(|text| parse_hostlist(&text))
+        .collect()
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchDir {
+    pub path: PathBuf,
+    pub add_paused: bool,
+    pub download_location: Option<String>,
+    pub move_completed_path: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationsConfig {
+    pub enabled: bool,
+    pub desktop: bool, // otherwise, ring the terminal bell
+    pub torrent_finished: bool,
+    pub torrent_added: bool,
+    pub tracker_error: bool,
+    pub torrent_error: bool,
+    pub error_alerts: bool, // watch for new errors, mention them in the status bar, and notify if enabled
+    pub muted: Vec<String>, // hashes of torrents whose errors nobody wants to hear about
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            desktop: true,
+            torrent_finished: true,
+            torrent_added: true,
+            tracker_error: true,
+            torrent_error: true,
+            error_alerts: true,
+            muted: Vec::new(),
+        }
+    }
+}
+
+/// Shell commands to run when things happen to a torrent.
+/// They get DTUI_EVENT, DTUI_HASH, DTUI_NAME, and DTUI_PATH in their environment.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HooksConfig {
+    pub torrent_finished: Option<String>,
+    pub torrent_added: Option<String>,
+    pub tracker_error: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FiltersConfig {
+    pub show_zero_hits: bool,
+    pub visible: bool,
+    pub width: Option<usize>, // fits the content if unset
+}
+
+impl Default for FiltersConfig {
+    fn default() -> Self {
+        Self {
+            show_zero_hits: false,
+            visible: true,
+            width: None,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatusBarConfig {
+    pub show_overhead: bool,
+    pub overhead_percentage: bool, // of total traffic, rather than as rates
+    pub free_space_path: Option<String>, // on the daemon's machine; the default location if unset
+    pub low_space_threshold: u64, // bytes; any download location with less gets a warning. 0 for never
+}
+
+impl Default for StatusBarConfig {
+    fn default() -> Self {
+        Self {
+            show_overhead: true,
+            overhead_percentage: false,
+            free_space_path: None,
+            low_space_threshold: 5 * 1024 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GroupBy {
+    None,
+    State,
+    Label,
+}
+
+impl Default for GroupBy {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+/// What middle-clicking a torrent does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QuickAction {
+    Nothing,
+    TogglePause,
+    Reannounce,
+    Recheck,
+    Remove, // still asks first
+}
+
+impl Default for QuickAction {
+    fn default() -> Self {
+        Self::TogglePause
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ThemeName {
+    Dracula,
+    HighContrast,
+}
+
+impl Default for ThemeName {
+    fn default() -> Self {
+        Self::Dracula
+    }
+}
+
+/// How many colors the terminal can show. `Auto` goes by `COLORTERM` and `TERM`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorSupport {
+    Auto,
+    TrueColor,
+    Colors256,
+    Colors16,
+}
+
+impl Default for ColorSupport {
+    fn default() -> Self {
+        Self::Auto
+    }
+}
+
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InterfaceConfig {
+    pub confirm_shutdown: bool,
+    pub relative_dates: bool,
+    pub natural_sort: bool,
+    pub detailed_rows: bool, // two lines per torrent
+    pub group_by: GroupBy,
+    pub middle_click: QuickAction,
+    pub binary_units: bool, // KiB/MiB rather than KB/MB
+    pub smooth_speeds: bool,
+    pub ascii_only: bool, // for terminals and fonts that can't draw the fancy symbols
+    pub theme: ThemeName,
+    pub colors: ColorSupport,
+    pub speed_samples: usize, // how many updates the smoothed speeds roughly cover
+    pub byte_decimals: Option<usize>,
+    pub date_format: String, // strftime-style, as understood by chrono
+    // Share ratios below `ratio_low` are drawn red, then yellow below `ratio_high`, then green.
+    pub ratio_low: f64,
+    pub ratio_high: f64,
+}
+
+impl InterfaceConfig {
+    /// Passes along the preferences that get checked for every cell drawn, so that drawing
+    /// doesn't have to take the config lock for them.
+    fn publish(&self) {
+        crate::util::glyphs::set_ascii_only(self.ascii_only);
+        crate::util::fmt::set_byte_format(self.binary_units, self.byte_decimals);
+    }
+}
+
+impl Default for InterfaceConfig {
+    fn default() -> Self {
+        Self {
+            confirm_shutdown: true,
+            relative_dates: true,
+            natural_sort: false,
+            detailed_rows: false,
+            group_by: GroupBy::None,
+            middle_click: QuickAction::TogglePause,
+            binary_units: true,
+            smooth_speeds: false,
+            ascii_only: false,
+            theme: ThemeName::Dracula,
+            colors: ColorSupport::Auto,
+            speed_samples: 5,
+            byte_decimals: None,
+            date_format: String::from(DEFAULT_DATE_FORMAT),
+            ratio_low: 0.5,
+            ratio_high: 1.0,
+        }
+    }
+}
+
+/// External commands, each run with a path appended to its arguments.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommandsConfig {
+    pub open_file: String,
+    pub open_folder: String,
+}
+
+impl Default for CommandsConfig {
+    fn default() -> Self {
+        let cmd = if cfg!(target_os = "macos") {
+            "open"
+        } else if cfg!(windows) {
+            "explorer"
+        } else {
+            "xdg-open"
+        };
+
+        Self {
+            open_file: String::from(cmd),
+            open_folder: String::from(cmd),
+        }
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TorrentTabsConfig {
+    pub active_tab: Option<String>,
+    pub ticks: HashMap<String, f64>, // tab name -> seconds between updates, overriding the tab's own
+}
+
+/// A saved combination of filters, sort order, and search for the torrents list.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Workspace {
+    pub name: String,
+    pub filters: BTreeMap<String, Vec<String>>, // keyed by filter category, e.g. "State"
+    pub sort_column: Option<String>,
+    pub descending_sort: bool,
+    pub search: String,
+}
+
+impl Workspace {
+    fn new(name: &str) -> Self {
+        Self {
+            name: String::from(name),
+            ..Self::default()
+        }
+    }
+
+    fn filtered(mut self, key: &str, val: &str) -> Self {
+        self.filters.insert(key.to_owned(), vec![val.to_owned()]);
+        self
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkspacesConfig {
+    pub active: usize,
+    pub workspaces: Vec<Workspace>,
+}
+
+impl Default for WorkspacesConfig {
+    fn default() -> Self {
+        let recent = Workspace {
+            sort_column: Some(String::from("Added")),
+            descending_sort: true,
+            ..Workspace::new("Recent")
+        };
+
+        Self {
+            active: 0,
+            workspaces: vec![
+                Workspace::new("All"),
+                Workspace::new("Seeding").filtered("State", "Seeding"),
+                Workspace::new("Errors").filtered("Tracker", "Error"),
+                recent,
+            ],
+        }
+    }
+}
+
+/// What the scheduler does to the whole session while a rule is in effect.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ScheduleAction {
+    Pause,
+    Limit { download: f64, upload: f64 }, // KiB/s, like the daemon's own limits; -1 for none
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ScheduleRule {
+    pub days: Vec<String>, // e.g. "Mon" or "Saturday"; every day if empty
+    pub start: String,     // "HH:MM", local time
+    pub end: String,       // if it's before the start, the rule runs past midnight
+    pub action: ScheduleAction,
+}
+
+/// For daemons without the Scheduler plugin. Where rules overlap, the last one wins.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SchedulerConfig {
+    pub enabled: bool,
+    pub rules: Vec<ScheduleRule>,
+    pub in_effect: HashMap<Uuid, ScheduleEffect>, // by host, until it's been undone
+}
+
+/// What the scheduler did to a daemon, kept so that it can be undone even if dtui quits first.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleEffect {
+    pub action: ScheduleAction,
+    pub saved_limits: Option<(f64, f64)>, // the download and upload limits from before a Limit
+}
+
+/// Where the torrents list was left, to pick back up from on the next launch.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LastSession {
+    pub host: Option<Uuid>, // only restored when connected to the same one
+    pub selected: Option<String>,
+    pub scroll: usize, // in lines
+}
+
+/// A torrent added from within dtui, for File → Recently added.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecentTorrent {
+    pub hash: String,
+    pub source: String, // the URL or magnet it came from, for adding it again
+    pub name: Option<String>, // filled in once the daemon has told us
+}
+
+/// Newest first.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecentTorrentsConfig {
+    pub limit: usize,
+    pub torrents: Vec<RecentTorrent>,
+}
+
+impl Default for RecentTorrentsConfig {
+    fn default() -> Self {
+        Self {
+            limit: 10,
+            torrents: Vec::new(),
+        }
+    }
+}
+
+/// For shared terminals, e.g. a tmux session that other people can attach to.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LockConfig {
+    pub passphrase_hash: Option<String>, // of the one chosen the first time the screen is locked
+    pub idle_minutes: Option<u64>,       // lock automatically after this long without input
+}
+
+/// Per-table layout, keyed by column name.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TableConfig {
+    pub widths: HashMap<String, usize>,
+    pub order: Vec<String>,
+    pub hidden: Vec<String>,
 }
 
 #[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
+    pub version: u32, // which of the MIGRATIONS have been applied
+    #[serde(skip)]
+    pub read_only: bool, // written by a newer dtui, which saving would undo
     pub connection_manager: ConnectionManagerConfig,
+    pub watch_dirs: Vec<WatchDir>,
+    pub notifications: NotificationsConfig,
+    pub hooks: HooksConfig,
+    pub filters: FiltersConfig,
+    pub interface: InterfaceConfig,
+    pub status_bar: StatusBarConfig,
+    pub commands: CommandsConfig,
+    pub torrent_tabs: TorrentTabsConfig,
+    pub workspaces: WorkspacesConfig,
+    pub scheduler: SchedulerConfig,
+    pub last_session: LastSession,
+    pub lock: LockConfig,
+    pub recent_torrents: RecentTorrentsConfig,
+    pub keybindings: HashMap<String, String>, // action name -> key, e.g. "quit" -> "Ctrl+q"
+    pub tables: HashMap<String, TableConfig>,
 }
 
 impl Config {
@@ -45,21 +589,185 @@ impl Config {
         // Mutation isn't required, but exclusive access makes sense.
         // Moreover, if you didn't already have a mutable ref to the config,
         // then you can't possibly have any changes to save anyway.
-        confy::store(APP_NAME, Some(APP_NAME), self).unwrap()
+        self.interface.publish();
+        if cfg!(test) {
+            // Tests get to change settings, but not anybody's real config file.
+            return;
+        }
+        if self.read_only {
+            return;
+        }
+        confy::store(APP_NAME, Some(profile().as_str()), self).unwrap()
     }
 }
 
+/// The directory that every profile's config file is in.
+fn config_dir() -> Option<PathBuf> {
+    let config_path = confy::get_configuration_file_path(APP_NAME, Some(APP_NAME)).ok()?;
+    Some(config_path.parent()?.to_owned())
+}
+
+/// Where to keep a file that isn't config, but belongs with the current profile's.
+/// The default profile's go right next to its config file, and any other's go in
+/// a directory named after it, which may not exist yet.
+pub fn data_path(file_name: &str) -> Option<PathBuf> {
+    let dir = config_dir()?;
+    let profile = profile();
+    if profile == APP_NAME {
+        Some(dir.join(file_name))
+    } else {
+        Some(dir.join(profile).join(file_name))
+    }
+}
+
+/// The name of the profile whose file the config comes from. Each profile, like "work" or
+/// "home", is a separate file alongside the default one, with its own hosts, theme, and so on.
+pub fn profile() -> String {
+    PROFILE.read().unwrap().clone()
+}
+
+/// Picks the profile to load. Once the config's been used, this takes `switch_profile`.
+pub fn set_profile(name: &str) {
+    *PROFILE.write().unwrap() = name.to_owned();
+}
+
+/// Loads a different profile's config in place of the current one, creating it if need be.
+/// If it can't be loaded, the current one stays.
+pub fn switch_profile(name: &str) -> Result<(), String> {
+    let previous = profile();
+    set_profile(name);
+    match load() {
+        Ok(cfg) => {
+            cfg.interface.publish();
+            *CONFIG.write().unwrap() = cfg;
+            Ok(())
+        }
+        Err(e) => {
+            set_profile(&previous);
+            Err(e)
+        }
+    }
+}
+
+/// Every profile with a file, in alphabetical order.
+pub fn profiles() -> Vec<String> {
+    let dir = match config_dir() {
+        Some(dir) => dir,
+        None => return Vec::new(),
+    };
+
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "yml" {
+                return None;
+            }
+            Some(path.file_stem()?.to_str()?.to_owned())
+        })
+        .collect();
+
+    names.sort();
+    names
+}
+
+/// Each step upgrades a config file from the version at its index to the next one.
+/// Add one whenever the format changes in a way that `#[serde(default)]` can't paper over,
+/// like a renamed field, so that the old settings carry over instead of quietly disappearing.
+const MIGRATIONS: &[fn(&mut serde_yaml::Value)] = &[
+    // 0 -> 1: The version field itself.
+    |_| {},
+    // 1 -> 2: The lock passphrase is kept hashed.
+    |cfg| {
+        let lock = match cfg
+            .get_mut("lock")
+            .and_then(serde_yaml::Value::as_mapping_mut)
+        {
+            Some(lock) => lock,
+            None => return,
+        };
+        let passphrase = lock.remove(&"passphrase".into());
+        if let Some(passphrase) = passphrase.as_ref().and_then(serde_yaml::Value::as_str) {
+            let hash = crate::lock::hash_passphrase(passphrase);
+            lock.insert("passphrase_hash".into(), hash.into());
+        }
+    },
+];
+
+const CURRENT_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// Loads the config, migrating it to the current version first if need be.
+/// The old file is backed up before the migrated one replaces it.
+/// A config from a newer dtui is used as is, but never saved over.
+fn load() -> Result<Config, String> {
+    if cfg!(test) {
+        // Tests start from the defaults, whatever's in the real config file.
+        return Ok(Config::default());
+    }
+
+    let path = confy::get_configuration_file_path(APP_NAME, Some(profile().as_str()))
+        .map_err(|e| format!("couldn't find where the config goes: {}", e))?;
+
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let mut cfg = Config {
+                version: CURRENT_VERSION,
+                ..Config::default()
+            };
+            cfg.save();
+            return Ok(cfg);
+        }
+        Err(e) => return Err(format!("couldn't read {}: {}", path.display(), e)),
+    };
+
+    let invalid = |e: serde_yaml::Error| format!("{} is invalid: {}", path.display(), e);
+
+    let mut value: serde_yaml::Value = serde_yaml::from_str(&text).map_err(invalid)?;
+    let version = value.get("version").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+    if version >= CURRENT_VERSION {
+        let mut cfg: Config = serde_yaml::from_value(value).map_err(invalid)?;
+        cfg.read_only = version > CURRENT_VERSION;
+        return Ok(cfg);
+    }
+
+    // Rewriting it in the new format means an older dtui can't read it anymore.
+    let backup = path.with_extension(format!("v{}.bak", version));
+    fs::copy(&path, &backup).map_err(|e| format!("couldn't back up {}: {}", path.display(), e))?;
+
+    for migrate in MIGRATIONS.iter().skip(version as usize) {
+        migrate(&mut value);
+    }
+
+    let mut cfg: Config = serde_yaml::from_value(value).map_err(invalid)?;
+    cfg.version = CURRENT_VERSION;
+    cfg.save();
+    Ok(cfg)
+}
+
 lazy_static! {
+    static ref PROFILE: RwLock<String> = RwLock::new(String::from(APP_NAME));
     static ref CONFIG: Arc<RwLock<Config>> = {
-        let cfg: Config = confy::load(APP_NAME, Some(APP_NAME)).unwrap();
+        let cfg = load().unwrap_or_else(|e| {
+            eprintln!("dtui: {}", e);
+            std::process::exit(1)
+        });
         let cmgr = &cfg.connection_manager;
         if let Some(id) = cmgr.autoconnect {
             assert!(cmgr.hosts.contains_key(&id));
         }
+        cfg.interface.publish();
         Arc::new(RwLock::new(cfg))
     };
 }
 
+/// Loads the config right away, so that a broken one gets reported
+/// before the UI takes over the terminal.
+pub fn init() {
+    lazy_static::initialize(&CONFIG);
+}
+
 pub fn get_config() -> Arc<RwLock<Config>> {
     Arc::clone(&self::CONFIG)
 }
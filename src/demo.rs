@@ -0,0 +1,429 @@
+mod data;
+
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::io;
+use std::sync::{Arc, Mutex};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, mpsc};
+use tokio::time;
+use tokio_rustls::server::TlsStream;
+use uuid::Uuid;
+
+use crate::config::{Host, Protocol};
+use crate::shutdown;
+use crate::wire;
+use data::World;
+
+pub(crate) const HOST_ID: Uuid = Uuid::nil();
+const ADDRESS: &str = "127.0.0.1";
+const VERSION: &str = "2.0.5";
+// Every demo starts out with the same torrents.
+const SEED: u64 = 0x5eed;
+
+const RPC_RESPONSE: u8 = 1;
+const RPC_ERROR: u8 = 2;
+const RPC_EVENT: u8 = 3;
+const AUTH_LEVEL_ADMIN: u8 = 10;
+
+/// Everything `Daemon::call` answers.
+const METHODS: &[&str] = &[
+    "daemon.login",
+    "daemon.info",
+    "daemon.get_version",
+    "daemon.get_method_list",
+    "daemon.set_event_interest",
+    "daemon.shutdown",
+    "core.get_torrents_status",
+    "core.get_torrent_status",
+    "core.get_session_status",
+    "core.get_filter_tree",
+    "core.get_config",
+    "core.get_config_value",
+    "core.get_config_values",
+    "core.set_config",
+    "core.get_free_space",
+    "core.get_external_ip",
+    "core.add_torrent_url",
+    "core.add_torrent_magnet",
+    "core.add_torrent_file",
+    "core.remove_torrent",
+    "core.remove_torrents",
+    "core.pause_torrent",
+    "core.pause_torrents",
+    "core.resume_torrent",
+    "core.resume_torrents",
+    "core.pause_session",
+    "core.resume_session",
+    "core.force_recheck",
+    "core.force_reannounce",
+    "core.set_torrent_options",
+    "core.rename_files",
+    "core.rename_folder",
+    "core.move_storage",
+    "core.queue_up",
+    "core.queue_down",
+    "core.queue_top",
+    "core.queue_bottom",
+    "label.get_labels",
+    "label.set_torrent",
+];
+
+struct Daemon {
+    world: Mutex<World>,
+    events: broadcast::Sender<(&'static str, Value)>,
+}
+
+/// A string, or a list of them, like the torrent IDs that most methods take.
+fn strings(val: &Value) -> Vec<String> {
+    match val {
+        Value::String(s) => vec![s.clone()],
+        Value::Array(vals) => vals
+            .iter()
+            .filter_map(|v| Some(v.as_str()?.to_owned()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+impl Daemon {
+    fn emit(&self, name: &'static str, args: Value) {
+        // Nobody might be listening, which is fine.
+        self.events.send((name, args)).ok();
+    }
+
+    fn call(
+        &self,
+        method: &str,
+        args: &[Value],
+        interest: &mut HashSet<String>,
+    ) -> Result<Value, String> {
+        let null = Value::Null;
+        let arg = |i: usize| args.get(i).unwrap_or(&null);
+        let str_arg = |i: usize| arg(i).as_str().unwrap_or_default();
+
+        match method {
+            "daemon.login" => return Ok(json!(AUTH_LEVEL_ADMIN)),
+            "daemon.info" | "daemon.get_version" => return Ok(json!(VERSION)),
+            "daemon.get_method_list" => return Ok(json!(METHODS)),
+            "daemon.set_event_interest" => {
+                interest.extend(strings(arg(0)));
+                return Ok(json!(true));
+            }
+            "daemon.shutdown" => return Ok(Value::Null),
+            _ => (),
+        }
+
+        let mut world = self.world.lock().unwrap();
+        let mut events = Vec::new();
+
+        let reply = match method {
+            "core.get_torrents_status" => {
+                let ids = arg(0).get("id").map(strings);
+                let keys = strings(arg(1));
+                let statuses = world
+                    .torrents
+                    .iter()
+                    .filter(|(hash, _)| ids.as_ref().map_or(true, |ids| ids.contains(*hash)))
+                    .map(|(hash, t)| (hash.clone(), t.status(hash, &keys)))
+                    .collect();
+                Value::Object(statuses)
+            }
+            "core.get_torrent_status" => {
+                let hash = str_arg(0);
+                let keys = strings(arg(1));
+                world
+                    .torrents
+                    .get(hash)
+                    .map_or_else(|| json!({}), |t| t.status(hash, &keys))
+            }
+            "core.get_session_status" => world.session_status(&strings(arg(0))),
+            "core.get_filter_tree" => world.filter_tree(arg(0).as_bool().unwrap_or(true)),
+            "core.get_config" => Value::Object(world.config.clone()),
+            "core.get_config_value" => world.config.get(str_arg(0)).cloned().unwrap_or_default(),
+            "core.get_config_values" => {
+                let keys = strings(arg(0));
+                let config = &world.config;
+                let values = keys
+                    .into_iter()
+                    .filter_map(|k| Some((k.clone(), config.get(&k)?.clone())));
+                Value::Object(values.collect())
+            }
+            "core.set_config" => {
+                if let Value::Object(changes) = arg(0) {
+                    world.config.extend(changes.clone());
+                }
+                Value::Null
+            }
+            "core.get_free_space" => json!(world.free_space()),
+            "core.get_external_ip" => json!("203.0.113.42"),
+            "core.add_torrent_url" | "core.add_torrent_magnet" | "core.add_torrent_file" => {
+                let hash = world.add(str_arg(0))?;
+                events.push(("TorrentAddedEvent", json!([hash, false])));
+                json!(hash)
+            }
+            "core.remove_torrent" | "core.remove_torrents" => {
+                for hash in strings(arg(0)) {
+                    if world.torrents.shift_remove(&hash).is_some() {
+                        events.push(("TorrentRemovedEvent", json!([hash])));
+                    }
+                }
+                if method == "core.remove_torrent" {
+                    json!(true)
+                } else {
+                    json!([])
+                }
+            }
+            "core.pause_torrent"
+            | "core.pause_torrents"
+            | "core.resume_torrent"
+            | "core.resume_torrents" => {
+                let pause = method.starts_with("core.pause");
+                for hash in strings(arg(0)) {
+                    if let Some(t) = world.torrents.get_mut(&hash) {
+                        let changed = if pause { t.pause() } else { t.resume() };
+                        if changed {
+                            events.push(("TorrentStateChangedEvent", json!([hash, t.state])));
+                        }
+                    }
+                }
+                Value::Null
+            }
+            "core.pause_session" | "core.resume_session" => {
+                world.paused = method == "core.pause_session";
+                let event = if world.paused {
+                    "SessionPausedEvent"
+                } else {
+                    "SessionResumedEvent"
+                };
+                events.push((event, json!([])));
+                Value::Null
+            }
+            "core.force_recheck" => {
+                for hash in strings(arg(0)) {
+                    if let Some(t) = world.torrents.get_mut(&hash) {
+                        t.recheck();
+                        events.push(("TorrentStateChangedEvent", json!([hash, t.state])));
+                    }
+                }
+                Value::Null
+            }
+            "core.force_reannounce" => {
+                for hash in strings(arg(0)) {
+                    if let Some(t) = world.torrents.get_mut(&hash) {
+                        t.reannounce();
+                    }
+                }
+                Value::Null
+            }
+            "core.set_torrent_options" => {
+                if let Value::Object(options) = arg(1) {
+                    for hash in strings(arg(0)) {
+                        if let Some(t) = world.torrents.get_mut(&hash) {
+                            for (key, val) in options {
+                                t.set_option(key, val.clone());
+                            }
+                        }
+                    }
+                }
+                Value::Null
+            }
+            "core.rename_files" => {
+                if let Some(t) = world.torrents.get_mut(str_arg(0)) {
+                    for pair in arg(1).as_array().into_iter().flatten() {
+                        if let (Some(index), Some(path)) = (pair[0].as_u64(), pair[1].as_str()) {
+                            t.rename_file(index as usize, path);
+                        }
+                    }
+                }
+                Value::Null
+            }
+            "core.rename_folder" => {
+                if let Some(t) = world.torrents.get_mut(str_arg(0)) {
+                    t.rename_folder(str_arg(1), str_arg(2));
+                }
+                Value::Null
+            }
+            "core.move_storage" => {
+                for hash in strings(arg(0)) {
+                    if let Some(t) = world.torrents.get_mut(&hash) {
+                        t.move_storage(str_arg(1));
+                    }
+                }
+                Value::Null
+            }
+            "core.queue_up" | "core.queue_down" | "core.queue_top" | "core.queue_bottom" => {
+                let by = match method {
+                    "core.queue_up" => -1,
+                    "core.queue_down" => 1,
+                    "core.queue_top" => -(world.torrents.len() as i64),
+                    _ => world.torrents.len() as i64,
+                };
+                for hash in strings(arg(0)) {
+                    world.requeue(&hash, by);
+                }
+                Value::Null
+            }
+            "label.get_labels" => {
+                let mut labels: Vec<&str> =
+                    world.torrents.values().map(|t| t.label.as_str()).collect();
+                labels.retain(|label| !label.is_empty());
+                labels.sort_unstable();
+                labels.dedup();
+                json!(labels)
+            }
+            "label.set_torrent" => {
+                if let Some(t) = world.torrents.get_mut(str_arg(0)) {
+                    t.label = str_arg(1).to_owned();
+                }
+                Value::Null
+            }
+            _ => return Err(format!("{} isn't supported in demo mode", method)),
+        };
+
+        drop(world);
+        for (name, args) in events {
+            self.emit(name, args);
+        }
+        Ok(reply)
+    }
+
+    /// Answers one `[id, method, args, kwargs]` request.
+    fn handle(&self, request: &Value, interest: &mut HashSet<String>) -> Value {
+        let id = &request[0];
+        let method = request[1].as_str().unwrap_or_default();
+        let args = request[2].as_array().map_or(&[][..], Vec::as_slice);
+
+        match self.call(method, args, interest) {
+            Ok(result) => json!([RPC_RESPONSE, id, result]),
+            Err(msg) => json!([RPC_ERROR, id, "DemoError", [msg], {}, ""]),
+        }
+    }
+}
+
+async fn simulate(daemon: Arc<Daemon>) {
+    let mut interval = time::interval(time::Duration::from_secs(1));
+    loop {
+        tokio::select! {
+            _ = interval.tick() => (),
+            _ = shutdown::signaled() => return,
+        }
+        let events = daemon.world.lock().unwrap().tick();
+        for (name, args) in events {
+            daemon.emit(name, args);
+        }
+    }
+}
+
+async fn serve(daemon: Arc<Daemon>, stream: TlsStream<TcpStream>) -> io::Result<()> {
+    let (mut reader, mut writer) = tokio::io::split(stream);
+
+    // Reading a message can't be cancelled partway through, so it can't be a select branch.
+    let (send, mut messages) = mpsc::channel(16);
+    tokio::spawn(async move {
+        while let Ok(msg) = wire::read(&mut reader).await {
+            if send.send(msg).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut events = daemon.events.subscribe();
+    let mut interest = HashSet::new();
+
+    loop {
+        tokio::select! {
+            msg = messages.recv() => {
+                let msg: Value = match msg {
+                    Some(msg) => msg,
+                    None => return Ok(()),
+                };
+                // Clients send a list of requests, but a lone one is easy enough to take too.
+                let requests = match msg.as_array() {
+                    Some(list) if list.iter().all(Value::is_array) => list.clone(),
+                    _ => vec![msg],
+                };
+                for request in &requests {
+                    let reply = daemon.handle(request, &mut interest);
+                    wire::write(&mut writer, &reply).await?;
+                }
+            }
+            event = events.recv() => match event {
+                Ok((name, args)) if interest.contains(name) => {
+                    wire::write(&mut writer, &json!([RPC_EVENT, name, args])).await?;
+                }
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => (),
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            },
+            _ = shutdown::signaled() => return Ok(()),
+        }
+    }
+}
+
+/// Starts a stand-in for deluged on a free local port, returning the port.
+/// It makes up a few torrents, which download, seed, and answer requests
+/// much like real ones would, so that dtui can be tried out without a daemon.
+pub(crate) async fn start() -> io::Result<u16> {
+    listen(World::generate(SEED), true).await
+}
+
+/// Serves `world` on a free local port, returning the port.
+/// Unless it's `simulated`, nothing changes except by request.
+async fn listen(world: World, simulated: bool) -> io::Result<u16> {
+    let listener = TcpListener::bind((ADDRESS, 0)).await?;
+    let port = listener.local_addr()?.port();
+    let acceptor = wire::acceptor();
+
+    let daemon = Arc::new(Daemon {
+        world: Mutex::new(world),
+        events: broadcast::channel(64).0,
+    });
+
+    if simulated {
+        tokio::spawn(simulate(Arc::clone(&daemon)));
+    }
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let (acceptor, daemon) = (acceptor.clone(), Arc::clone(&daemon));
+            tokio::spawn(async move {
+                if let Ok(stream) = acceptor.accept(stream).await {
+                    serve(daemon, stream).await.ok();
+                }
+            });
+        }
+    });
+
+    Ok(port)
+}
+
+/// A session with a daemon of its own, holding a torrent for each of `sources`.
+/// Magnet links with a hash and a `dn` make for torrents that tests can pick out.
+/// The torrents stay put until asked to do something, so tests can tell what to expect.
+#[cfg(test)]
+pub(crate) async fn session_with(sources: &[String]) -> deluge_rpc::Session {
+    let mut world = World::empty(SEED);
+    for source in sources {
+        world.add(source).unwrap();
+    }
+    let port = listen(world, false).await.unwrap();
+
+    let host = host(port);
+    let mut session = deluge_rpc::Session::connect((host.address.as_str(), host.port))
+        .await
+        .unwrap();
+    session.login(&host.username, &host.password).await.unwrap();
+    session
+}
+
+/// How to log in to the demo daemon. It takes any username and password.
+pub(crate) fn host(port: u16) -> Host {
+    Host {
+        username: String::from("demo"),
+        password: String::from("demo"),
+        address: String::from(ADDRESS),
+        port,
+        // It only speaks 2.x, so there's no need to ask.
+        protocol: Protocol::Deluge2,
+        ..Host::default()
+    }
+}
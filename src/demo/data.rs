@@ -0,0 +1,836 @@
+use indexmap::IndexMap;
+use serde_json::{json, Map, Value};
+
+const KIB: u64 = 1024;
+const MIB: u64 = 1024 * KIB;
+const FREE_SPACE: u64 = 512 * 1024 * MIB;
+
+/// A small seeded xorshift, so that every demo starts out the same and screenshots can be retaken.
+pub(super) struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n.max(1)
+    }
+
+    /// Somewhere between `lo` and `hi`.
+    pub fn between(&mut self, lo: f64, hi: f64) -> f64 {
+        lo + (hi - lo) * (self.below(1_000_000) as f64 / 1_000_000.0)
+    }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len() as u64) as usize]
+    }
+
+    pub fn hash(&mut self) -> String {
+        format!(
+            "{:016x}{:016x}{:08x}",
+            self.next_u64(),
+            self.next_u64(),
+            self.next_u64() as u32
+        )
+    }
+}
+
+// Name, label, and files, with sizes in KiB. Multi-file torrents get a folder named after them.
+type Template = (&'static str, &'static str, &'static [(&'static str, u64)]);
+
+const TEMPLATES: &[Template] = &[
+    (
+        "ubuntu-22.04-desktop-amd64.iso",
+        "linux",
+        &[("", 3_500_000)],
+    ),
+    ("debian-11.3.0-amd64-netinst.iso", "linux", &[("", 387_000)]),
+    ("archlinux-2022.06.01-x86_64.iso", "linux", &[("", 840_000)]),
+    (
+        "Fedora-Workstation-Live-x86_64-36",
+        "linux",
+        &[
+            ("Fedora-Workstation-Live-x86_64-36-1.5.iso", 2_000_000),
+            ("Fedora-Workstation-36-1.5-x86_64-CHECKSUM", 1),
+        ],
+    ),
+    (
+        "openSUSE-Tumbleweed-DVD-x86_64-Current.iso",
+        "linux",
+        &[("", 4_400_000)],
+    ),
+    (
+        "Big Buck Bunny",
+        "movies",
+        &[
+            ("Big Buck Bunny.mp4", 270_000),
+            ("Big Buck Bunny.en.srt", 4),
+            ("poster.jpg", 300),
+        ],
+    ),
+    (
+        "Sintel",
+        "movies",
+        &[
+            ("Sintel.mp4", 129_000),
+            ("Subtitles/Sintel.de.srt", 3),
+            ("Subtitles/Sintel.en.srt", 3),
+            ("Subtitles/Sintel.es.srt", 3),
+            ("Subtitles/Sintel.fr.srt", 3),
+            ("poster.jpg", 250),
+        ],
+    ),
+    (
+        "Tears of Steel",
+        "movies",
+        &[
+            ("tears_of_steel_1080p.webm", 570_000),
+            ("tears_of_steel_en.srt", 5),
+        ],
+    ),
+    (
+        "Duck and Cover (1951)",
+        "movies",
+        &[("Duck and Cover.ogv", 90_000), ("Duck and Cover.txt", 2)],
+    ),
+    (
+        "Complete Works of William Shakespeare",
+        "books",
+        &[
+            ("Comedies/A Midsummer Night's Dream.epub", 280),
+            ("Comedies/Twelfth Night.epub", 240),
+            ("Histories/Henry V.epub", 300),
+            ("Tragedies/Hamlet.epub", 320),
+            ("Tragedies/Macbeth.epub", 260),
+        ],
+    ),
+    (
+        "Alice's Adventures in Wonderland (Audiobook)",
+        "books",
+        &[
+            ("01 - Down the Rabbit-Hole.mp3", 14_000),
+            ("02 - The Pool of Tears.mp3", 12_500),
+            ("03 - A Caucus-Race and a Long Tale.mp3", 11_000),
+            ("04 - The Rabbit Sends in a Little Bill.mp3", 15_000),
+        ],
+    ),
+    (
+        "enwiki-20220601-pages-articles-multistream.xml.bz2",
+        "",
+        &[("", 20_000_000)],
+    ),
+    (
+        "LibreOffice_7.3.4_Linux_x86-64_deb.tar.gz",
+        "software",
+        &[("", 220_000)],
+    ),
+    (
+        "blender-3.2.0-linux-x64.tar.xz",
+        "software",
+        &[("", 190_000)],
+    ),
+    (
+        "Apollo 11 Mission Audio",
+        "",
+        &[
+            ("Day 1/Launch.flac", 600_000),
+            ("Day 1/Translunar Injection.flac", 450_000),
+            ("Day 5/Landing.flac", 700_000),
+            ("Day 5/EVA.flac", 900_000),
+        ],
+    ),
+];
+
+const TRACKERS: &[&str] = &[
+    "tracker.example.org",
+    "tracker.example.net",
+    "bt.example.com",
+];
+
+const CLIENTS: &[&str] = &[
+    "qBittorrent 4.4.3",
+    "Transmission 3.00",
+    "Deluge 2.0.5",
+    "libtorrent (Rasterbar) 2.0.6",
+    "BiglyBT 3.1.0.0",
+    "µTorrent 3.5.5",
+];
+
+const COUNTRIES: &[&str] = &["US", "DE", "NL", "FR", "JP", "BR", "CA", "SE", "GB", "PL"];
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+struct Peer {
+    ip: String,
+    client: &'static str,
+    country: &'static str,
+    progress: f64,
+    share: f64, // of the torrent's speeds
+}
+
+pub(super) struct Torrent {
+    pub name: String,
+    pub state: &'static str,
+    pub label: String,
+    tracker: &'static str,
+    tracker_status: String,
+    message: String,
+    files: Vec<(String, u64)>, // path, size
+    done: Vec<u64>,
+    priorities: Vec<u8>,
+    uploaded: u64,
+    // The speeds it tends towards while active, and what it's doing right now.
+    base_rates: (u64, u64),
+    rates: (u64, u64),
+    max_speeds: (f64, f64),
+    peers: Vec<Peer>,
+    swarm: (i64, i64), // seeds, peers
+    time_added: i64,
+    completed_time: i64,
+    checking: u32, // ticks left until a re-check is done
+    pub queue: i64,
+    download_location: String,
+    // Anything set with set_torrent_options that isn't modelled above.
+    options: Map<String, Value>,
+}
+
+impl Torrent {
+    fn new(rng: &mut Rng, name: &str, label: &str, files: Vec<(String, u64)>) -> Self {
+        let peers = (0..3 + rng.below(10))
+            .map(|_| Peer {
+                ip: format!(
+                    "{}.{}:{}",
+                    rng.pick(&["192.0.2", "198.51.100", "203.0.113"]),
+                    1 + rng.below(254),
+                    1024 + rng.below(60000),
+                ),
+                client: *rng.pick(CLIENTS),
+                country: *rng.pick(COUNTRIES),
+                progress: if rng.below(3) == 0 {
+                    1.0
+                } else {
+                    rng.between(0.0, 1.0)
+                },
+                share: rng.between(0.1, 1.0),
+            })
+            .collect::<Vec<_>>();
+        let total_share: f64 = peers.iter().map(|p| p.share).sum();
+        let peers = peers
+            .into_iter()
+            .map(|p| Peer {
+                share: p.share / total_share,
+                ..p
+            })
+            .collect();
+
+        let options = json!({
+            "max_connections": -1,
+            "max_upload_slots": -1,
+            "auto_managed": true,
+            "stop_at_ratio": false,
+            "stop_ratio": 2.0,
+            "remove_at_ratio": false,
+            "owner": "demo",
+            "shared": false,
+            "prioritize_first_last_pieces": false,
+            "sequential_download": false,
+            "super_seeding": false,
+            "move_completed": false,
+            "move_completed_path": "/srv/complete",
+        });
+
+        Self {
+            name: name.to_owned(),
+            state: "Downloading",
+            label: label.to_owned(),
+            tracker: *rng.pick(TRACKERS),
+            tracker_status: String::from("Announce OK"),
+            message: String::from("OK"),
+            done: vec![0; files.len()],
+            priorities: vec![4; files.len()],
+            files,
+            uploaded: 0,
+            base_rates: (
+                (rng.between(50.0, 4000.0) * KIB as f64) as u64,
+                (rng.between(5.0, 400.0) * KIB as f64) as u64,
+            ),
+            rates: (0, 0),
+            max_speeds: (-1.0, -1.0),
+            peers,
+            swarm: (rng.below(400) as i64, rng.below(900) as i64),
+            time_added: now() - rng.below(60 * 24 * 3600) as i64,
+            completed_time: 0,
+            checking: 0,
+            queue: -1,
+            download_location: String::from("/srv/downloads"),
+            options: match options {
+                Value::Object(map) => map,
+                _ => unreachable!(),
+            },
+        }
+    }
+
+    fn total_size(&self) -> u64 {
+        self.files.iter().map(|(_, size)| size).sum()
+    }
+
+    fn wanted(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.files.len()).filter(move |&i| self.priorities[i] > 0)
+    }
+
+    fn total_wanted(&self) -> u64 {
+        self.wanted().map(|i| self.files[i].1).sum()
+    }
+
+    fn total_done(&self) -> u64 {
+        self.done.iter().sum()
+    }
+
+    fn is_complete(&self) -> bool {
+        self.wanted().all(|i| self.done[i] >= self.files[i].1)
+    }
+
+    fn progress(&self) -> f64 {
+        match self.total_wanted() {
+            0 => 100.0,
+            wanted => {
+                self.wanted().map(|i| self.done[i]).sum::<u64>() as f64 / wanted as f64 * 100.0
+            }
+        }
+    }
+
+    fn finish(&mut self) {
+        self.done = self.files.iter().map(|(_, size)| *size).collect();
+        self.state = "Seeding";
+        self.completed_time = self.time_added + 3600;
+    }
+
+    /// Where a resumed or re-checked torrent ends up.
+    fn active_state(&self) -> &'static str {
+        if self.is_complete() {
+            "Seeding"
+        } else {
+            "Downloading"
+        }
+    }
+
+    /// Everything the daemon could be asked about this torrent, or just `keys` if there are any.
+    pub fn status(&self, hash: &str, keys: &[String]) -> Value {
+        let size = self.total_size();
+        let done = self.total_done();
+        let remaining = self.total_wanted().saturating_sub(done);
+        let piece_length = if size > 1024 * MIB { 4 * MIB } else { MIB };
+        let (down, up) = self.rates;
+
+        let active = matches!(self.state, "Downloading" | "Seeding");
+        let peers: Vec<Value> = if active {
+            self.peers
+                .iter()
+                .map(|p| {
+                    json!({
+                        "ip": p.ip,
+                        "client": p.client,
+                        "country": p.country,
+                        "progress": p.progress,
+                        "seed": (p.progress >= 1.0) as u32,
+                        "down_speed": (down as f64 * p.share) as u64,
+                        "up_speed": (up as f64 * p.share) as u64,
+                    })
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let num_seeds = peers.iter().filter(|p| p["seed"] == 1).count();
+
+        let mut offset = 0;
+        let files: Vec<Value> = self
+            .files
+            .iter()
+            .enumerate()
+            .map(|(index, (path, size))| {
+                let file = json!({ "index": index, "offset": offset, "path": path, "size": size });
+                offset += size;
+                file
+            })
+            .collect();
+        let file_progress: Vec<f64> = (self.done.iter().zip(&self.files))
+            .map(|(done, (_, size))| {
+                if *size == 0 {
+                    1.0
+                } else {
+                    *done as f64 / *size as f64
+                }
+            })
+            .collect();
+
+        let eta = match down {
+            0 => 0,
+            down => (remaining / down) as i64,
+        };
+        let ratio = match done {
+            0 => 0.0,
+            done => self.uploaded as f64 / done as f64,
+        };
+
+        let mut status = json!({
+            "hash": hash,
+            "name": self.name,
+            "state": self.state,
+            "message": self.message,
+            "label": self.label,
+            "progress": self.progress(),
+            "total_size": size,
+            "total_wanted": self.total_wanted(),
+            "total_done": done,
+            "all_time_download": done,
+            "total_payload_download": done,
+            "total_uploaded": self.uploaded,
+            "total_payload_upload": self.uploaded,
+            "download_payload_rate": down,
+            "upload_payload_rate": up,
+            "max_download_speed": self.max_speeds.0,
+            "max_upload_speed": self.max_speeds.1,
+            "num_seeds": num_seeds,
+            "num_peers": peers.len() - num_seeds,
+            "total_seeds": self.swarm.0,
+            "total_peers": self.swarm.1,
+            "distributed_copies": self.swarm.0 as f64 / 10.0,
+            "ratio": ratio,
+            "seed_rank": 0,
+            "eta": eta,
+            "time_added": self.time_added,
+            "completed_time": self.completed_time,
+            "active_time": now() - self.time_added,
+            "seeding_time": if self.completed_time > 0 { now() - self.completed_time } else { 0 },
+            "time_since_transfer": if down + up > 0 { 0 } else { 60 },
+            "last_seen_complete": now(),
+            "is_finished": self.is_complete(),
+            "paused": self.state == "Paused",
+            "queue": self.queue,
+            "download_location": self.download_location,
+            "save_path": self.download_location,
+            "num_files": self.files.len(),
+            "num_pieces": (size + piece_length - 1) / piece_length,
+            "piece_length": piece_length,
+            "creator": "mktorrent 1.1",
+            "comment": "Generated for dtui's demo mode",
+            "private": false,
+            "tracker_host": self.tracker,
+            "tracker_status": self.tracker_status,
+            "trackers": [{ "url": format!("https://{}/announce", self.tracker), "tier": 0 }],
+            "next_announce": 1800,
+            "files": files,
+            "file_progress": file_progress,
+            "file_priorities": self.priorities,
+            "peers": peers,
+        });
+
+        let map = status.as_object_mut().unwrap();
+        for (key, val) in &self.options {
+            map.insert(key.clone(), val.clone());
+        }
+        if !keys.is_empty() {
+            map.retain(|key, _| keys.contains(key));
+        }
+        status
+    }
+
+    pub fn set_option(&mut self, key: &str, val: Value) {
+        match key {
+            "file_priorities" => {
+                if let Ok(priorities) = serde_json::from_value::<Vec<u8>>(val) {
+                    for (old, new) in self.priorities.iter_mut().zip(priorities) {
+                        *old = new;
+                    }
+                }
+            }
+            "max_download_speed" => self.max_speeds.0 = val.as_f64().unwrap_or(-1.0),
+            "max_upload_speed" => self.max_speeds.1 = val.as_f64().unwrap_or(-1.0),
+            "name" => self.name = val.as_str().unwrap_or(&self.name).to_owned(),
+            _ => {
+                self.options.insert(key.to_owned(), val);
+            }
+        }
+    }
+
+    pub fn rename_file(&mut self, index: usize, path: &str) {
+        if let Some(file) = self.files.get_mut(index) {
+            file.0 = path.to_owned();
+        }
+    }
+
+    pub fn rename_folder(&mut self, old: &str, new: &str) {
+        for (path, _) in &mut self.files {
+            if let Some(rest) = path.strip_prefix(old) {
+                *path = format!("{}{}", new, rest);
+            }
+        }
+    }
+
+    pub fn move_storage(&mut self, dest: &str) {
+        self.download_location = dest.to_owned();
+    }
+
+    pub fn pause(&mut self) -> bool {
+        let changed = self.state != "Paused";
+        self.state = "Paused";
+        self.rates = (0, 0);
+        changed
+    }
+
+    pub fn resume(&mut self) -> bool {
+        let changed = self.state == "Paused";
+        if changed {
+            self.state = self.active_state();
+        }
+        changed
+    }
+
+    pub fn recheck(&mut self) {
+        self.state = "Checking";
+        self.checking = 3;
+        self.rates = (0, 0);
+    }
+
+    pub fn reannounce(&mut self) {
+        self.tracker_status = String::from("Announce OK");
+    }
+}
+
+/// Everything the demo daemon knows about.
+pub(super) struct World {
+    pub torrents: IndexMap<String, Torrent>,
+    pub config: Map<String, Value>,
+    pub paused: bool,
+    pub rng: Rng,
+}
+
+impl World {
+    pub fn generate(seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let mut torrents = IndexMap::new();
+
+        for (i, (name, label, files)) in TEMPLATES.iter().enumerate() {
+            let files = files
+                .iter()
+                .map(|(path, kib)| match *path {
+                    "" => (String::from(*name), kib * KIB),
+                    path => (format!("{}/{}", name, path), kib * KIB),
+                })
+                .collect();
+            let mut torrent = Torrent::new(&mut rng, name, label, files);
+
+            // Enough of each state to fill out the filters.
+            match i % 6 {
+                0 | 3 => torrent.finish(),
+                1 | 4 => {
+                    let fraction = rng.between(0.0, 0.9);
+                    for (done, (_, size)) in torrent.done.iter_mut().zip(&torrent.files) {
+                        *done = (*size as f64 * fraction) as u64;
+                    }
+                }
+                2 => {
+                    torrent.finish();
+                    torrent.pause();
+                }
+                _ => {
+                    torrent.state = "Queued";
+                    torrent.queue = 0;
+                }
+            }
+            torrents.insert(rng.hash(), torrent);
+        }
+
+        // Something for the error filters, and the notifications, to find.
+        if let Some((_, torrent)) = torrents.get_index_mut(7) {
+            torrent.tracker_status = String::from("Error: Connection timed out");
+        }
+        if let Some((_, torrent)) = torrents.get_index_mut(10) {
+            torrent.state = "Error";
+            torrent.message = String::from("Error: file not found");
+        }
+
+        Self {
+            torrents,
+            rng,
+            ..Self::empty(seed)
+        }
+    }
+
+    /// A daemon with no torrents, for starting from a known state.
+    pub fn empty(seed: u64) -> Self {
+        let config = json!({
+            "download_location": "/srv/downloads",
+            "move_completed": false,
+            "move_completed_path": "/srv/complete",
+            "add_paused": false,
+            "pre_allocate_storage": false,
+            "max_connections_global": 200,
+            "max_upload_slots_global": 4,
+            "max_download_speed": -1.0,
+            "max_upload_speed": -1.0,
+            "max_active_downloading": 3,
+            "max_active_seeding": 5,
+            "max_active_limit": 8,
+            "stop_seed_at_ratio": false,
+            "stop_seed_ratio": 2.0,
+            "listen_ports": [6881, 6891],
+            "random_port": true,
+            "dht": true,
+            "lsd": true,
+            "utpex": true,
+            "upnp": true,
+            "natpmp": true,
+            "allow_remote": false,
+            "new_release_check": false,
+        });
+
+        Self {
+            torrents: IndexMap::new(),
+            config: match config {
+                Value::Object(map) => map,
+                _ => unreachable!(),
+            },
+            paused: false,
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// Adds a torrent as if from a URL, magnet, or file, returning its hash.
+    pub fn add(&mut self, source: &str) -> Result<String, String> {
+        let query = source.strip_prefix("magnet:?").unwrap_or("");
+        let param = |key: &str| {
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix(key)?.strip_prefix('='))
+        };
+
+        let hash = param("xt")
+            .and_then(|xt| xt.strip_prefix("urn:btih:"))
+            .filter(|h| h.len() == 40 && h.chars().all(|c| c.is_ascii_hexdigit()))
+            .map(str::to_ascii_lowercase)
+            .unwrap_or_else(|| self.rng.hash());
+        if self.torrents.contains_key(&hash) {
+            return Err(String::from("Torrent already in session"));
+        }
+
+        let name = param("dn")
+            .map(|dn| dn.replace('+', " "))
+            .unwrap_or_else(|| {
+                let path = source.split(|c: char| c == '?' || c == '#').next().unwrap();
+                let name = path
+                    .rsplit(|c: char| c == '/' || c == '\\')
+                    .find(|s| !s.is_empty());
+                let name = name.unwrap_or("Untitled");
+                name.strip_suffix(".torrent").unwrap_or(name).to_owned()
+            });
+        let size = (self.rng.between(10.0, 4000.0) * MIB as f64) as u64;
+        let mut torrent = Torrent::new(&mut self.rng, &name, "", vec![(name.clone(), size)]);
+        torrent.time_added = now();
+        self.torrents.insert(hash.clone(), torrent);
+        Ok(hash)
+    }
+
+    /// Moves a torrent up or down in the queue, or to either end of it.
+    pub fn requeue(&mut self, hash: &str, by: i64) {
+        let mut queued: Vec<String> = {
+            let mut queued: Vec<_> = self.torrents.iter().filter(|(_, t)| t.queue >= 0).collect();
+            queued.sort_by_key(|(_, t)| t.queue);
+            queued.into_iter().map(|(h, _)| h.clone()).collect()
+        };
+        if let Some(i) = queued.iter().position(|h| h == hash) {
+            let h = queued.remove(i);
+            let j = (i as i64 + by).clamp(0, queued.len() as i64) as usize;
+            queued.insert(j, h);
+        }
+        for (pos, h) in queued.iter().enumerate() {
+            self.torrents[h].queue = pos as i64;
+        }
+    }
+
+    pub fn session_status(&self, keys: &[String]) -> Value {
+        let (down, up) = self
+            .torrents
+            .values()
+            .fold((0, 0), |(d, u), t| (d + t.rates.0, u + t.rates.1));
+        let peers: usize = (self.torrents.values())
+            .filter(|t| matches!(t.state, "Downloading" | "Seeding"))
+            .map(|t| t.peers.len())
+            .sum();
+
+        let mut status = json!({
+            "payload_download_rate": down as f64,
+            "payload_upload_rate": up as f64,
+            // Protocol overhead.
+            "download_rate": down as f64 * 1.04,
+            "upload_rate": up as f64 * 1.04,
+            "peer.num_peers_connected": peers,
+            "dht.dht_nodes": 312,
+            "has_incoming_connections": true,
+        });
+        if !keys.is_empty() {
+            status
+                .as_object_mut()
+                .unwrap()
+                .retain(|key, _| keys.contains(key));
+        }
+        status
+    }
+
+    pub fn filter_tree(&self, show_zero_hits: bool) -> Value {
+        let count = |f: &dyn Fn(&Torrent) -> bool| self.torrents.values().filter(|t| f(t)).count();
+        let all = self.torrents.len();
+
+        let mut states = vec![json!(["All", all])];
+        states.push(json!(["Active", count(&|t| t.rates.0 + t.rates.1 > 0)]));
+        for state in &[
+            "Downloading",
+            "Seeding",
+            "Paused",
+            "Checking",
+            "Queued",
+            "Error",
+        ] {
+            let n = count(&|t| t.state == *state);
+            if n > 0 || show_zero_hits {
+                states.push(json!([state, n]));
+            }
+        }
+
+        let mut trackers = vec![json!(["All", all])];
+        trackers.push(json!([
+            "Error",
+            count(&|t| t.tracker_status.starts_with("Error"))
+        ]));
+        for tracker in TRACKERS {
+            let n = count(&|t| t.tracker == *tracker);
+            if n > 0 || show_zero_hits {
+                trackers.push(json!([tracker, n]));
+            }
+        }
+
+        let mut labels: Vec<&str> = self.torrents.values().map(|t| t.label.as_str()).collect();
+        labels.sort_unstable();
+        labels.dedup();
+        let mut label_tree = vec![json!(["All", all])];
+        for label in labels {
+            label_tree.push(json!([label, count(&|t| t.label == label)]));
+        }
+
+        json!({
+            "state": states,
+            "tracker_host": trackers,
+            "label": label_tree,
+            "owner": [["All", all], ["demo", all]],
+        })
+    }
+
+    pub fn free_space(&self) -> u64 {
+        let used: u64 = self.torrents.values().map(Torrent::total_done).sum();
+        FREE_SPACE.saturating_sub(used)
+    }
+
+    /// Moves everything along by a second, returning the events that that set off.
+    pub fn tick(&mut self) -> Vec<(&'static str, Value)> {
+        let mut events = Vec::new();
+        let limit = |key: &str| {
+            let limit = self.config.get(key).and_then(Value::as_f64).unwrap_or(-1.0);
+            (limit > 0.0).then(|| (limit * KIB as f64) as u64)
+        };
+        let (global_down, global_up) = (limit("max_download_speed"), limit("max_upload_speed"));
+        let active = self
+            .torrents
+            .values()
+            .filter(|t| t.state == "Downloading")
+            .count()
+            .max(1) as u64;
+        let seeding = self
+            .torrents
+            .values()
+            .filter(|t| t.state == "Seeding")
+            .count()
+            .max(1) as u64;
+
+        for (hash, t) in self.torrents.iter_mut() {
+            let cap = |rate: u64, torrent_max: f64, global: Option<u64>, sharers: u64| {
+                let mut rate = rate;
+                if torrent_max > 0.0 {
+                    rate = rate.min((torrent_max * KIB as f64) as u64);
+                }
+                if let Some(global) = global {
+                    rate = rate.min(global / sharers);
+                }
+                rate
+            };
+            let jitter = self.rng.between(0.6, 1.4);
+
+            match t.state {
+                _ if self.paused => t.rates = (0, 0),
+                "Downloading" => {
+                    let down = cap(
+                        (t.base_rates.0 as f64 * jitter) as u64,
+                        t.max_speeds.0,
+                        global_down,
+                        active,
+                    );
+                    let up = cap(
+                        (t.base_rates.1 as f64 * jitter) as u64,
+                        t.max_speeds.1,
+                        global_up,
+                        active + seeding,
+                    );
+                    t.rates = (down, up);
+                    t.uploaded += up;
+
+                    let mut budget = down;
+                    let wanted: Vec<usize> = t.wanted().collect();
+                    for i in wanted {
+                        let take = budget.min(t.files[i].1 - t.done[i]);
+                        t.done[i] += take;
+                        budget -= take;
+                    }
+
+                    if t.is_complete() {
+                        t.state = "Seeding";
+                        t.completed_time = now();
+                        events.push(("TorrentFinishedEvent", json!([hash])));
+                        events.push(("TorrentStateChangedEvent", json!([hash, "Seeding"])));
+                    }
+                }
+                "Seeding" => {
+                    let up = cap(
+                        (t.base_rates.1 as f64 * jitter) as u64,
+                        t.max_speeds.1,
+                        global_up,
+                        active + seeding,
+                    );
+                    t.rates = (0, up);
+                    t.uploaded += up;
+                }
+                "Checking" => {
+                    t.checking = t.checking.saturating_sub(1);
+                    if t.checking == 0 {
+                        t.state = t.active_state();
+                        events.push(("TorrentStateChangedEvent", json!([hash, t.state])));
+                    }
+                }
+                _ => t.rates = (0, 0),
+            }
+        }
+
+        events
+    }
+}
@@ -0,0 +1,39 @@
+use deluge_rpc::{EventKind, Session};
+use std::collections::{HashMap, HashSet};
+use tokio::sync::Mutex;
+
+/// Which events each view thread wants from the daemon, for the lifetime of one session.
+/// The views all share the session, so it has to be told about all of their interests at once.
+#[derive(Debug, Default)]
+pub(crate) struct EventInterest {
+    inner: Mutex<Interests>,
+}
+
+#[derive(Debug, Default)]
+struct Interests {
+    views: HashMap<&'static str, HashSet<EventKind>>,
+    // What the daemon last agreed to. A request that failed or timed out leaves this behind,
+    // so that the next registration sends it again.
+    applied: HashSet<EventKind>,
+}
+
+impl EventInterest {
+    /// Replaces a view's interests, then applies everyone's if the daemon doesn't have them yet.
+    pub async fn register(
+        &self,
+        session: &Session,
+        view: &'static str,
+        kinds: HashSet<EventKind>,
+    ) -> deluge_rpc::Result<()> {
+        // Held across the request, so that a stale union can't be applied after a newer one.
+        let mut inner = self.inner.lock().await;
+        inner.views.insert(view, kinds);
+        let wanted: HashSet<EventKind> = inner.views.values().flatten().copied().collect();
+
+        if wanted != inner.applied {
+            session.set_event_interest(&wanted).await?;
+            inner.applied = wanted;
+        }
+        Ok(())
+    }
+}
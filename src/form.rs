@@ -1,35 +1,141 @@
 use std::rc::Rc;
 
-use cursive::view::{View, ViewWrapper};
-use cursive::views::{Dialog, EditView, ResizedView, TextArea};
+use cursive::view::{AnyCb, Selector, View, ViewWrapper};
+use cursive::views::{BoxedView, Dialog, EditView, LayerPosition, ResizedView, TextArea};
 use cursive::Cursive;
 
-fn make_cb<T, F>(f: F) -> impl Fn(&mut Cursive)
+/// Checks whether a form has been edited since the check was made.
+pub type ChangeCheck<T> = Box<dyn Fn(&T) -> bool>;
+
+/// Snapshots a form with `peek`, returning a check that compares a later snapshot against it.
+pub fn compare_with<T, D, F>(form: &T, peek: F) -> Option<ChangeCheck<T>>
 where
-    T: Form,
-    F: FnOnce(&mut Cursive, T::Data),
+    D: PartialEq + 'static,
+    F: Fn(&T) -> D + 'static,
 {
-    let mut f = Some(f);
-    let cb = move |siv: &mut Cursive| {
-        let f = match f.take() {
-            Some(f) => f,
-            None => return,
+    let initial = peek(form);
+    Some(Box::new(move |form| peek(form) != initial))
+}
+
+const GUARD_NAME: &str = "form_guard";
+
+/// Wraps the form in a dialog, remembering how it started out.
+struct FormGuard {
+    inner: BoxedView,
+    changed: Option<Box<dyn Fn(&dyn View) -> bool>>,
+}
+
+impl FormGuard {
+    fn new<T: Form>(form: T, track_changes: bool) -> Self {
+        let check = if track_changes {
+            form.track_changes()
+        } else {
+            None
         };
+        let changed = check.map(|check| {
+            let f = move |v: &dyn View| v.downcast_ref::<T>().map_or(false, |form| check(form));
+            Box::new(f) as Box<dyn Fn(&dyn View) -> bool>
+        });
 
-        let dialog: Box<Dialog> = siv
-            .pop_layer()
-            .expect("no layer")
-            .downcast::<Dialog>()
-            .ok()
-            .expect("top layer wasn't a Dialog");
+        Self {
+            inner: BoxedView::boxed(form),
+            changed,
+        }
+    }
+
+    fn is_changed(&self) -> bool {
+        self.changed.as_ref().map_or(false, |f| f(&*self.inner))
+    }
 
-        let form: Box<T> = dialog
-            .into_content()
+    fn into_form<T: Form>(self) -> T {
+        let form = BoxedView::unwrap(self.inner)
             .downcast::<T>()
             .ok()
             .expect("dialog's contents weren't Self");
+        *form
+    }
+}
+
+impl ViewWrapper for FormGuard {
+    cursive::wrap_impl!(self.inner: BoxedView);
+
+    fn wrap_call_on_any(&mut self, selector: &Selector, callback: AnyCb) {
+        match selector {
+            Selector::Name(name) if *name == GUARD_NAME => callback(self),
+            _ => self.inner.call_on_any(selector, callback),
+        }
+    }
+}
 
-        f(siv, form.into_data());
+/// Closes the topmost layer, first asking whether to discard any changes made to its form.
+pub fn dismiss(siv: &mut Cursive) {
+    let mut changed = false;
+    if let Some(layer) = siv.screen_mut().get_mut(LayerPosition::FromFront(0)) {
+        layer.call_on_any(&Selector::Name(GUARD_NAME), &mut |v| {
+            if let Some(guard) = v.downcast_ref::<FormGuard>() {
+                changed = guard.is_changed();
+            }
+        });
+    }
+
+    if !changed {
+        siv.pop_layer();
+        return;
+    }
+
+    let confirm = Dialog::text("Discard changes?")
+        .button("Discard", |siv| {
+            siv.pop_layer();
+            siv.pop_layer();
+        })
+        .dismiss_button("Keep editing");
+    siv.add_layer(confirm);
+}
+
+/// Looks at the form in the topmost layer, if that's a dialog made from a `T`.
+pub fn peek<T: Form, R>(siv: &mut Cursive, f: impl FnOnce(&T) -> R) -> Option<R> {
+    let mut f = Some(f);
+    let mut result = None;
+    let layer = siv.screen_mut().get_mut(LayerPosition::FromFront(0))?;
+    layer.call_on_any(&Selector::Name(GUARD_NAME), &mut |v| {
+        let form = v
+            .downcast_ref::<FormGuard>()
+            .and_then(|guard| (&*guard.inner).downcast_ref::<T>());
+        if let (Some(form), Some(f)) = (form, f.take()) {
+            result = Some(f(form));
+        }
+    });
+    result
+}
+
+/// Closes the topmost layer, which has to be a dialog made from a `T`, and hands over its data.
+pub fn submit<T: Form>(siv: &mut Cursive, f: impl FnOnce(&mut Cursive, T::Data)) {
+    let dialog: Box<Dialog> = siv
+        .pop_layer()
+        .expect("no layer")
+        .downcast::<Dialog>()
+        .ok()
+        .expect("top layer wasn't a Dialog");
+
+    let guard: Box<FormGuard> = dialog
+        .into_content()
+        .downcast::<FormGuard>()
+        .ok()
+        .expect("dialog's contents weren't a FormGuard");
+
+    f(siv, guard.into_form::<T>().into_data());
+}
+
+fn make_cb<T, F>(f: F) -> impl Fn(&mut Cursive)
+where
+    T: Form,
+    F: FnOnce(&mut Cursive, T::Data),
+{
+    let mut f = Some(f);
+    let cb = move |siv: &mut Cursive| {
+        if let Some(f) = f.take() {
+            submit::<T>(siv, f);
+        }
     };
     cursive::immut1!(cb)
 }
@@ -39,15 +145,34 @@ pub trait Form: View + Sized + 'static {
 
     fn into_data(self) -> Self::Data;
 
+    /// Returns a check for whether the form gets edited after this point, if it can tell.
+    /// Dialogs made from such forms ask before discarding changes.
+    fn track_changes(&self) -> Option<ChangeCheck<Self>> {
+        None
+    }
+
     fn into_dialog(
         self,
         dismiss_label: impl Into<String>,
         submit_label: impl Into<String>,
         on_submit: impl FnOnce(&mut Cursive, Self::Data) + 'static,
     ) -> Dialog {
-        Dialog::around(self)
+        Dialog::around(FormGuard::new(self, true))
             .button(submit_label, make_cb::<Self, _>(on_submit))
-            .dismiss_button(dismiss_label)
+            .button(dismiss_label, dismiss)
+    }
+
+    /// Like `into_dialog`, except that the submit button leaves the dialog open.
+    /// `on_submit` can `peek` at the form first, and then `submit` it, or not.
+    fn into_dialog_custom_submit(
+        self,
+        dismiss_label: impl Into<String>,
+        submit_label: impl Into<String>,
+        on_submit: impl Fn(&mut Cursive) + 'static,
+    ) -> Dialog {
+        Dialog::around(FormGuard::new(self, true))
+            .button(submit_label, on_submit)
+            .button(dismiss_label, dismiss)
     }
 
     fn into_dialog_custom_dismiss(
@@ -57,7 +182,8 @@ pub trait Form: View + Sized + 'static {
         on_submit: impl FnOnce(&mut Cursive, Self::Data) + 'static,
         on_dismiss: impl FnOnce(&mut Cursive, Self::Data) + 'static,
     ) -> Dialog {
-        Dialog::around(self)
+        // Dismissing already does something with the data, so there's nothing to discard.
+        Dialog::around(FormGuard::new(self, false))
             .button(submit_label, make_cb::<Self, _>(on_submit))
             .button(dismiss_label, make_cb::<Self, _>(on_dismiss))
     }
@@ -72,6 +198,10 @@ impl Form for EditView {
         assert_eq!(Rc::strong_count(&content), 1);
         Rc::try_unwrap(content).unwrap()
     }
+
+    fn track_changes(&self) -> Option<ChangeCheck<Self>> {
+        compare_with(self, |v| v.get_content())
+    }
 }
 
 impl Form for TextArea {
@@ -80,6 +210,10 @@ impl Form for TextArea {
     fn into_data(self) -> Self::Data {
         String::from(self.get_content())
     }
+
+    fn track_changes(&self) -> Option<ChangeCheck<Self>> {
+        compare_with(self, |v| String::from(v.get_content()))
+    }
 }
 
 // This would be generic across all implementors of ViewWrapper, but rustc complains.
@@ -89,4 +223,9 @@ impl<V: Form> Form for ResizedView<V> {
     fn into_data(self) -> Self::Data {
         self.into_inner().ok().unwrap().into_data()
     }
+
+    fn track_changes(&self) -> Option<ChangeCheck<Self>> {
+        let check = self.get_inner().track_changes()?;
+        Some(Box::new(move |v| check(v.get_inner())))
+    }
 }
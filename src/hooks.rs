@@ -0,0 +1,133 @@
+use async_trait::async_trait;
+use deluge_rpc::{Event, EventKind, InfoHash, Query, Session};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use tokio::time;
+
+use crate::config;
+use crate::views::statusbar;
+use crate::views::thread::ViewThread;
+
+#[derive(Debug, Clone, Deserialize, Query)]
+struct HookInfo {
+    name: String,
+    download_location: String,
+}
+
+fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    }
+}
+
+/// Runs a user's hook command, describing the torrent through environment variables.
+fn run_hook(command: String, event: &'static str, hash: InfoHash, info: HookInfo) {
+    let path = Path::new(&info.download_location).join(&info.name);
+
+    let mut cmd = shell(&command);
+    cmd.env("DTUI_EVENT", event)
+        .env("DTUI_HASH", hash.to_string())
+        .env("DTUI_NAME", &info.name)
+        .env("DTUI_PATH", path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    // Wait on a blocking thread, so the children get reaped without holding up the task.
+    tokio::task::spawn_blocking(move || {
+        if let Ok(mut child) = cmd.spawn() {
+            let _ = child.wait();
+        }
+    });
+}
+
+/// Runs the commands from the `hooks` config section when their events come in.
+pub(crate) struct HookThread {
+    // For mentioning hooks that had to be skipped.
+    toasts: statusbar::Toasts,
+    // The torrents whose trackers are currently failing, so that each failure only counts once,
+    // however many times the tracker reports it.
+    tracker_errors: HashSet<InfoHash>,
+}
+
+impl HookThread {
+    pub(crate) fn new(toasts: statusbar::Toasts) -> Self {
+        Self {
+            toasts,
+            tracker_errors: HashSet::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl ViewThread for HookThread {
+    fn event_interest(&self) -> HashSet<EventKind> {
+        deluge_rpc::events![
+            TorrentAdded,
+            TorrentFinished,
+            TorrentRemoved,
+            TorrentTrackerStatus
+        ]
+    }
+
+    // Whatever was failing before is news again to a new connection.
+    async fn reload(&mut self, _: &Session) -> deluge_rpc::Result<()> {
+        self.tracker_errors.clear();
+        Ok(())
+    }
+
+    async fn update(&mut self, _: &Session) -> deluge_rpc::Result<()> {
+        Ok(())
+    }
+
+    async fn on_event(&mut self, session: &Session, event: Event) -> deluge_rpc::Result<()> {
+        let hooks = config::read().hooks.clone();
+
+        let (hash, name, command) = match event {
+            Event::TorrentFinished(hash) => (hash, "torrent_finished", hooks.torrent_finished),
+            Event::TorrentAdded(hash, false) => (hash, "torrent_added", hooks.torrent_added),
+            Event::TorrentTrackerStatus(hash, status) if status.starts_with("Error") => {
+                if !self.tracker_errors.insert(hash) {
+                    return Ok(());
+                }
+                (hash, "tracker_error", hooks.tracker_error)
+            }
+            Event::TorrentTrackerStatus(hash, _) | Event::TorrentRemoved(hash) => {
+                self.tracker_errors.remove(&hash);
+                return Ok(());
+            }
+            _ => return Ok(()),
+        };
+
+        let command = match command {
+            Some(command) if !command.trim().is_empty() => command,
+            _ => return Ok(()),
+        };
+
+        // The torrent may be gone by now, which is no reason to stop handling events.
+        match session.get_torrent_status::<HookInfo>(hash).await {
+            Ok(info) => run_hook(command, name, hash, info),
+            Err(e) => self
+                .toasts
+                .post(format!("Skipped the {} hook for {}: {:?}", name, hash, e)),
+        }
+
+        Ok(())
+    }
+
+    fn tick(&self) -> time::Duration {
+        time::Duration::from_secs(60)
+    }
+
+    fn clear(&mut self) {
+        self.tracker_errors.clear();
+    }
+}
@@ -0,0 +1,99 @@
+use cursive::traits::Nameable;
+use cursive::view::ViewWrapper;
+use cursive::views::Dialog;
+use cursive::Cursive;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::task::JoinHandle;
+
+/// Background work started from the UI. Dropping this doesn't stop it; `cancel` does.
+pub(crate) struct Job(JoinHandle<()>);
+
+impl Job {
+    pub fn cancel(&self) {
+        self.0.abort();
+    }
+}
+
+/// Runs a future in the background, then hands its output to `on_done` on the UI thread.
+pub(crate) fn spawn<T, Fut, F>(siv: &Cursive, fut: Fut, on_done: F) -> Job
+where
+    T: Send + 'static,
+    Fut: Future<Output = T> + Send + 'static,
+    F: FnOnce(&mut Cursive, T) + Send + 'static,
+{
+    let sink = siv.cb_sink().clone();
+    let handle = tokio::spawn(async move {
+        let output = fut.await;
+        // This only fails if the UI has already gone away, in which case nobody cares.
+        sink.send(Box::new(move |siv| on_done(siv, output))).ok();
+    });
+    Job(handle)
+}
+
+/// A dialog that cancels its job once it's gone, whether that's from its button or from Esc.
+struct ProgressDialog {
+    inner: Dialog,
+    job: Job,
+}
+
+impl ViewWrapper for ProgressDialog {
+    cursive::wrap_impl!(self.inner: Dialog);
+}
+
+impl Drop for ProgressDialog {
+    fn drop(&mut self) {
+        // Harmless if the job is what took the dialog down, since it's done by then.
+        self.job.cancel();
+    }
+}
+
+/// Like `spawn`, but shows `text` in a dialog until the job is done, with a button to cancel it.
+/// If the job gets cancelled, `on_done` isn't called.
+pub(crate) fn spawn_with_progress<T, Fut, F>(
+    siv: &mut Cursive,
+    title: &str,
+    text: String,
+    fut: Fut,
+    on_done: F,
+) where
+    T: Send + 'static,
+    Fut: Future<Output = T> + Send + 'static,
+    F: FnOnce(&mut Cursive, T) + Send + 'static,
+{
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+    let name = format!("job {}", NEXT_ID.fetch_add(1, Ordering::Relaxed));
+
+    // Whoever gets to the dialog first, the job or the cancel button, takes it down.
+    fn take_dialog(siv: &mut Cursive, name: &str) -> bool {
+        let screen = siv.screen_mut();
+        match screen.find_layer_from_name(name) {
+            Some(pos) => {
+                screen.remove_layer(pos);
+                true
+            }
+            None => false,
+        }
+    }
+
+    let job = {
+        let name = name.clone();
+        spawn(siv, fut, move |siv, output| {
+            if take_dialog(siv, &name) {
+                on_done(siv, output);
+            }
+        })
+    };
+
+    let cancel = {
+        let name = name.clone();
+        move |siv: &mut Cursive| {
+            take_dialog(siv, &name);
+        }
+    };
+
+    let inner = Dialog::text(text).button("Cancel", cancel).title(title);
+    let dialog = ProgressDialog { inner, job }.with_name(name);
+
+    siv.add_layer(dialog);
+}
@@ -0,0 +1,262 @@
+use cursive::event::{Event, Key};
+
+use crate::config;
+
+/// Where a keybinding applies, for grouping them in the help overlay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Context {
+    Global,
+    TorrentList,
+    FilesTab,
+    Filters,
+}
+
+impl Context {
+    pub const ALL: [Self; 4] = [
+        Self::Global,
+        Self::TorrentList,
+        Self::FilesTab,
+        Self::Filters,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::Global => "Global",
+            Self::TorrentList => "Torrent list",
+            Self::FilesTab => "Files tab",
+            Self::Filters => "Filters",
+        }
+    }
+}
+
+/// Actions that can be rebound in the config, under `keybindings`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Action {
+    Quit,
+    Help,
+    CommandBar,
+    NextTab,
+    PrevTab,
+    Search,
+    CycleWorkspace,
+    ToggleMark,
+    MarkAll,
+    InvertMarks,
+    ToggleFilters,
+    NarrowFilters,
+    WidenFilters,
+    MaximizeTorrents,
+    MaximizeTabs,
+    Lock,
+}
+
+impl Action {
+    pub const ALL: [Self; 16] = [
+        Self::Quit,
+        Self::Help,
+        Self::CommandBar,
+        Self::NextTab,
+        Self::PrevTab,
+        Self::Search,
+        Self::CycleWorkspace,
+        Self::ToggleMark,
+        Self::MarkAll,
+        Self::InvertMarks,
+        Self::ToggleFilters,
+        Self::NarrowFilters,
+        Self::WidenFilters,
+        Self::MaximizeTorrents,
+        Self::MaximizeTabs,
+        Self::Lock,
+    ];
+
+    /// How the action is referred to in the config.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Quit => "quit",
+            Self::Help => "help",
+            Self::CommandBar => "command_bar",
+            Self::NextTab => "next_tab",
+            Self::PrevTab => "prev_tab",
+            Self::Search => "search",
+            Self::CycleWorkspace => "cycle_workspace",
+            Self::ToggleMark => "toggle_mark",
+            Self::MarkAll => "mark_all",
+            Self::InvertMarks => "invert_marks",
+            Self::ToggleFilters => "toggle_filters",
+            Self::NarrowFilters => "narrow_filters",
+            Self::WidenFilters => "widen_filters",
+            Self::MaximizeTorrents => "maximize_torrents",
+            Self::MaximizeTabs => "maximize_tabs",
+            Self::Lock => "lock",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Quit => "Quit",
+            Self::Help => "Show this help",
+            Self::CommandBar => "Open the command bar",
+            Self::NextTab => "Next torrent tab",
+            Self::PrevTab => "Previous torrent tab",
+            Self::Search => "Search torrents by name",
+            Self::CycleWorkspace => "Next workspace",
+            Self::ToggleMark => "Mark or unmark the selected row",
+            Self::MarkAll => "Mark every visible row",
+            Self::InvertMarks => "Invert which visible rows are marked",
+            Self::ToggleFilters => "Show or hide the filters",
+            Self::NarrowFilters => "Make the filters narrower",
+            Self::WidenFilters => "Make the filters wider",
+            Self::MaximizeTorrents => "Hide the torrent tabs, or bring them back",
+            Self::MaximizeTabs => "Expand the torrent tabs to the full screen, or restore them",
+            Self::Lock => "Lock the screen behind a passphrase",
+        }
+    }
+
+    pub fn context(self) -> Context {
+        match self {
+            Self::ToggleMark | Self::MarkAll | Self::InvertMarks => Context::TorrentList,
+            _ => Context::Global,
+        }
+    }
+
+    fn default_key(self) -> &'static str {
+        match self {
+            Self::Quit => "q",
+            Self::Help => "?",
+            Self::CommandBar => ":",
+            Self::NextTab => "]",
+            Self::PrevTab => "[",
+            Self::Search => "/",
+            Self::CycleWorkspace => "w",
+            Self::ToggleMark => "Space",
+            Self::MarkAll => "Ctrl+a",
+            Self::InvertMarks => "*",
+            Self::ToggleFilters => "Ctrl+b",
+            Self::NarrowFilters => "<",
+            Self::WidenFilters => ">",
+            Self::MaximizeTorrents => "z",
+            Self::MaximizeTabs => "Z",
+            Self::Lock => "Ctrl+l",
+        }
+    }
+
+    /// The key bound to this action, as written in the config.
+    pub fn key(self) -> String {
+        match config::read().keybindings.get(self.name()) {
+            Some(key) if parse(key).is_some() => key.clone(),
+            _ => String::from(self.default_key()),
+        }
+    }
+
+    pub fn event(self) -> Event {
+        parse(&self.key()).expect("default keybindings should parse")
+    }
+}
+
+/// Bindings that can't be changed, but still belong in the help overlay.
+pub(crate) const FIXED_BINDINGS: &[(Context, &str, &str)] = &[
+    (Context::Global, "Esc", "Close the topmost dialog"),
+    (Context::Global, "Alt+1…9", "Switch to a workspace"),
+    (
+        Context::Global,
+        "Tab",
+        "Complete a command, in the command bar",
+    ),
+    (
+        Context::Global,
+        "Tab",
+        "Complete a folder name, in path fields",
+    ),
+    (
+        Context::Global,
+        "Ctrl+t",
+        "Collapse or expand a section, in dialogs",
+    ),
+    (Context::TorrentList, "Right-click", "Torrent actions"),
+    (Context::TorrentList, "Right-click header", "Column options"),
+    (
+        Context::TorrentList,
+        "Shift+click header",
+        "Then sort by that column",
+    ),
+    (
+        Context::TorrentList,
+        "Double-click",
+        "Collapse or expand a group",
+    ),
+    (
+        Context::FilesTab,
+        "Double-click",
+        "Collapse or expand a folder",
+    ),
+    (Context::FilesTab, "Right-click", "File actions"),
+    (Context::Filters, "Up/Down", "Move the highlight"),
+    (Context::Filters, "Enter", "Select the highlighted filter"),
+    (
+        Context::Filters,
+        "Space",
+        "Add or remove the filter from its category",
+    ),
+    (
+        Context::Filters,
+        "Ctrl+click",
+        "Add or remove the filter from its category",
+    ),
+    (Context::Filters, "Drag right edge", "Resize the filters"),
+];
+
+/// Parses keys like "q", "Space", "Ctrl+a", "Alt+1", or "F5".
+pub(crate) fn parse(s: &str) -> Option<Event> {
+    let single = |s: &str| {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => None,
+        }
+    };
+
+    if let Some(rest) = s.strip_prefix("Ctrl+") {
+        return single(rest).map(|c| Event::CtrlChar(c.to_ascii_lowercase()));
+    } else if let Some(rest) = s.strip_prefix("Alt+") {
+        return single(rest).map(Event::AltChar);
+    }
+
+    let key = match s {
+        "Space" => return Some(Event::Char(' ')),
+        "Enter" => Key::Enter,
+        "Tab" => Key::Tab,
+        "Backspace" => Key::Backspace,
+        "Esc" => Key::Esc,
+        "Up" => Key::Up,
+        "Down" => Key::Down,
+        "Left" => Key::Left,
+        "Right" => Key::Right,
+        "Home" => Key::Home,
+        "End" => Key::End,
+        "PageUp" => Key::PageUp,
+        "PageDown" => Key::PageDown,
+        "Insert" => Key::Ins,
+        "Delete" => Key::Del,
+        _ => match s.strip_prefix('F').and_then(|n| n.parse::<u8>().ok()) {
+            Some(n) if (1..=12).contains(&n) => Key::from_f(n),
+            _ => return single(s).map(Event::Char),
+        },
+    };
+    Some(Event::Key(key))
+}
+
+/// Every binding that applies in `context`, as (key, description) pairs.
+pub(crate) fn bindings(context: Context) -> Vec<(String, &'static str)> {
+    let rebindable = Action::ALL
+        .iter()
+        .filter(|action| action.context() == context)
+        .map(|action| (action.key(), action.description()));
+
+    let fixed = FIXED_BINDINGS
+        .iter()
+        .filter(|(c, _, _)| *c == context)
+        .map(|(_, key, desc)| (String::from(*key), *desc));
+
+    rebindable.chain(fixed).collect()
+}
@@ -0,0 +1,178 @@
+//! Talking to Deluge 1.3 daemons.
+//!
+//! deluge-rpc only speaks the 2.x protocol, so a 1.3 daemon gets a bridge:
+//! a local listener for deluge-rpc to connect to instead, which passes messages along
+//! in 1.3's framing, and smooths over the few places where the two versions disagree.
+
+use deluge_rpc::Session;
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io;
+use std::sync::Mutex;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::TlsAcceptor;
+
+use crate::config::Protocol;
+use crate::wire;
+
+const BRIDGE_ADDRESS: &str = "127.0.0.1";
+const RPC_ERROR: u8 = 2;
+
+static ACCEPTOR: Lazy<TlsAcceptor> = Lazy::new(wire::acceptor);
+
+// What each daemon turned out to speak, so that it only has to be worked out once.
+static DETECTED: Lazy<Mutex<HashMap<(String, u16), Protocol>>> = Lazy::new(Default::default);
+
+/// Connects to a daemon using whichever protocol it speaks, working that out first if need be.
+pub(crate) async fn connect(
+    address: &str,
+    port: u16,
+    protocol: Protocol,
+) -> deluge_rpc::Result<Session> {
+    let protocol = match protocol {
+        Protocol::Auto => detect(address, port).await,
+        protocol => protocol,
+    };
+    match protocol {
+        Protocol::Deluge1 => {
+            let port = bridge(address, port).await?;
+            Session::connect((BRIDGE_ADDRESS, port)).await
+        }
+        _ => Session::connect((address, port)).await,
+    }
+}
+
+/// Each version ignores messages framed for the other, so this asks both ways at once,
+/// and goes with whichever gets an answer.
+async fn detect(address: &str, port: u16) -> Protocol {
+    let key = (address.to_owned(), port);
+    if let Some(protocol) = DETECTED.lock().unwrap().get(&key) {
+        return *protocol;
+    }
+
+    let v2 = probe(address, port, wire::DELUGE_2);
+    let v1 = probe(address, port, wire::DELUGE_1);
+    tokio::pin!(v2, v1);
+    let detected = tokio::select! {
+        true = &mut v2 => Protocol::Deluge2,
+        true = &mut v1 => Protocol::Deluge1,
+        // Connecting the usual way will fail too, with a better explanation.
+        else => return Protocol::Deluge2,
+    };
+
+    DETECTED.lock().unwrap().insert(key, detected);
+    detected
+}
+
+/// Asks the daemon for its version, to see whether it answers in the same framing.
+/// Any daemon will say, without logging in first.
+async fn probe(address: &str, port: u16, version: u8) -> bool {
+    let attempt = async {
+        let stream = TcpStream::connect((address, port)).await?;
+        let mut stream = wire::connector()
+            .connect(wire::server_name(), stream)
+            .await?;
+        let request = wire::encode(&json!([[0, "daemon.info", [], {}]]))?;
+        wire::write_frame(&mut stream, version, &request).await?;
+        wire::read_frame(&mut stream, version).await
+    };
+    attempt.await.is_ok()
+}
+
+/// Connects to a 1.3 daemon, and starts a bridge to it for a single connection.
+/// Returns the local port that the bridge is waiting on.
+async fn bridge(address: &str, port: u16) -> io::Result<u16> {
+    let daemon = TcpStream::connect((address, port)).await?;
+    let daemon = wire::connector()
+        .connect(wire::server_name(), daemon)
+        .await?;
+
+    let listener = TcpListener::bind((BRIDGE_ADDRESS, 0)).await?;
+    let local_port = listener.local_addr()?.port();
+
+    tokio::spawn(async move {
+        let client = match listener.accept().await {
+            Ok((client, _)) => client,
+            Err(_) => return,
+        };
+        // Nobody else gets to use it.
+        drop(listener);
+        let client = match ACCEPTOR.accept(client).await {
+            Ok(client) => client,
+            Err(_) => return,
+        };
+
+        let (client_reader, client_writer) = tokio::io::split(client);
+        let (daemon_reader, daemon_writer) = tokio::io::split(daemon);
+        let to_daemon = (wire::DELUGE_2, wire::DELUGE_1);
+        let to_client = (wire::DELUGE_1, wire::DELUGE_2);
+        let mut requests =
+            tokio::spawn(relay(client_reader, daemon_writer, to_daemon, fix_request));
+        let mut replies = tokio::spawn(relay(daemon_reader, client_writer, to_client, fix_reply));
+
+        // Once either side hangs up, so does the other.
+        tokio::select! {
+            _ = &mut requests => replies.abort(),
+            _ = &mut replies => requests.abort(),
+        }
+    });
+
+    Ok(local_port)
+}
+
+/// Passes messages along, reframing them, and rewriting any that `fix` has an opinion on.
+async fn relay(
+    mut from: impl AsyncRead + Unpin,
+    mut to: impl AsyncWrite + Unpin,
+    (from_version, to_version): (u8, u8),
+    fix: fn(&[u8]) -> Option<Value>,
+) -> io::Result<()> {
+    loop {
+        let body = wire::read_frame(&mut from, from_version).await?;
+        let body = match fix(&body) {
+            Some(msg) => wire::encode(&msg)?,
+            None => body,
+        };
+        wire::write_frame(&mut to, to_version, &body).await?;
+    }
+}
+
+/// 2.x clients send their version along with the login, which 1.3 refuses as an extra argument.
+fn fix_request(body: &[u8]) -> Option<Value> {
+    let mut msg = wire::decode(body).ok()?;
+    let mut changed = false;
+    for request in msg.as_array_mut()? {
+        if request.get(1).and_then(Value::as_str) != Some("daemon.login") {
+            continue;
+        }
+        if let Some(kwargs) = request.get_mut(3).and_then(Value::as_object_mut) {
+            changed |= kwargs.remove("client_version").is_some();
+        }
+    }
+    Some(msg).filter(|_| changed)
+}
+
+/// 1.3 describes errors with just a type, a message, and a traceback,
+/// sometimes bundled up in a list of their own. 2.x has args and kwargs instead of a message.
+fn fix_reply(body: &[u8]) -> Option<Value> {
+    let decoded = wire::inflate(body).ok()?;
+    // Every message is a short list, which rencode opens with a single byte,
+    // and then comes the message type, which is small enough to be a single byte too.
+    // Only errors need changing, so nothing else is worth decoding.
+    if decoded.get(1) != Some(&RPC_ERROR) {
+        return None;
+    }
+
+    let msg: Value = rencode::from_bytes(&decoded).ok()?;
+    let (id, kind, message, traceback) = match msg.as_array()?.as_slice() {
+        [_, id, details] => match details.as_array()?.as_slice() {
+            [kind, message, traceback] => (id, kind, message, traceback),
+            _ => return None,
+        },
+        [_, id, kind, message, traceback] => (id, kind, message, traceback),
+        _ => return None,
+    };
+    Some(json!([RPC_ERROR, id, kind, [message], {}, traceback]))
+}
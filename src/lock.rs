@@ -0,0 +1,227 @@
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use cursive::event::{Event, EventResult, EventTrigger};
+use cursive::theme::ColorStyle;
+use cursive::traits::{Nameable, Resizable};
+use cursive::view::{View, ViewWrapper};
+use cursive::views::{Dialog, EditView, LayerPosition, LinearLayout, TextView};
+use cursive::{Cursive, Printer, Vec2};
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::config;
+
+const LAYER_NAME: &str = "lock screen";
+
+static LAST_INPUT: Lazy<Mutex<Instant>> = Lazy::new(|| Mutex::new(Instant::now()));
+
+/// Covers the whole screen, so that nothing behind it can be read,
+/// and keeps every keypress and click from getting past the passphrase prompt.
+struct LockScreen {
+    dialog: Dialog,
+    offset: Vec2,
+    dialog_size: Vec2,
+}
+
+impl ViewWrapper for LockScreen {
+    cursive::wrap_impl!(self.dialog: Dialog);
+
+    fn wrap_draw(&self, printer: &Printer) {
+        printer.with_color(ColorStyle::background(), |printer| {
+            for y in 0..printer.size.y {
+                printer.print_hline((0, y), printer.size.x, " ");
+            }
+        });
+        let printer = printer.offset(self.offset).cropped(self.dialog_size);
+        self.dialog.draw(&printer);
+    }
+
+    fn wrap_required_size(&mut self, constraint: Vec2) -> Vec2 {
+        constraint
+    }
+
+    fn wrap_layout(&mut self, size: Vec2) {
+        self.dialog_size = self.dialog.required_size(size).zip_map(size, usize::min);
+        self.offset = size.saturating_sub(self.dialog_size).map(|n| n / 2);
+        self.dialog.layout(self.dialog_size);
+    }
+
+    fn wrap_on_event(&mut self, event: Event) -> EventResult {
+        match event {
+            // These aren't input, and the rest of the app still relies on them.
+            Event::Refresh | Event::WindowResize => self.dialog.on_event(event),
+            _ => {
+                let result = self.dialog.on_event(event.relativized(self.offset));
+                if result.is_consumed() {
+                    result
+                } else {
+                    // Otherwise, it'd fall through to the global keybindings.
+                    EventResult::Consumed(None)
+                }
+            }
+        }
+    }
+}
+
+fn is_locked(siv: &mut Cursive) -> bool {
+    siv.screen_mut().find_layer_from_name(LAYER_NAME).is_some()
+}
+
+/// Blanks the screen until the passphrase is entered.
+/// If there isn't one yet, asks for one first.
+pub(crate) fn lock(siv: &mut Cursive) {
+    if is_locked(siv) {
+        return;
+    }
+    if config::read().lock.passphrase_hash.is_none() {
+        choose_passphrase(siv);
+        return;
+    }
+
+    let content = LinearLayout::vertical()
+        .child(TextView::new("Enter the passphrase to unlock.").with_name("lock message"))
+        .child(
+            EditView::new()
+                .secret()
+                .on_submit(try_unlock)
+                .with_name("lock passphrase")
+                .fixed_width(30),
+        );
+
+    let dialog = Dialog::around(content)
+        .title("dtui is locked")
+        .button("Unlock", |siv| {
+            let entered = siv
+                .call_on_name("lock passphrase", |v: &mut EditView| v.get_content())
+                .unwrap();
+            try_unlock(siv, &entered);
+        });
+
+    let screen = LockScreen {
+        dialog,
+        offset: Vec2::zero(),
+        dialog_size: Vec2::zero(),
+    };
+
+    // The menubar would otherwise stay on screen, and clickable.
+    siv.set_autohide_menu(true);
+    siv.add_fullscreen_layer(screen.with_name(LAYER_NAME));
+}
+
+/// Salts and hashes a passphrase, for the config to keep instead of the passphrase itself.
+pub(crate) fn hash_passphrase(passphrase: &str) -> String {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(passphrase.as_bytes(), &salt)
+        .expect("a freshly generated salt should be usable")
+        .to_string()
+}
+
+fn passphrase_matches(entered: &str) -> bool {
+    let cfg = config::read();
+    let hash = match cfg.lock.passphrase_hash.as_deref().map(PasswordHash::new) {
+        Some(Ok(hash)) => hash,
+        _ => return false,
+    };
+    Argon2::default()
+        .verify_password(entered.as_bytes(), &hash)
+        .is_ok()
+}
+
+fn try_unlock(siv: &mut Cursive, entered: &str) {
+    if passphrase_matches(entered) {
+        let screen = siv.screen_mut();
+        if let Some(pos) = screen.find_layer_from_name(LAYER_NAME) {
+            screen.remove_layer(pos);
+        }
+        siv.set_autohide_menu(false);
+        touch(siv);
+        return;
+    }
+
+    siv.call_on_name("lock passphrase", |v: &mut EditView| v.set_content(""));
+    siv.call_on_name("lock message", |v: &mut TextView| {
+        v.set_content("Wrong passphrase. Try again.")
+    });
+}
+
+fn choose_passphrase(siv: &mut Cursive) {
+    let field = |name| EditView::new().secret().with_name(name).fixed_width(30);
+    let content = LinearLayout::vertical()
+        .child(TextView::new(
+            "Choose a passphrase for unlocking dtui.\n\
+             Only a salted hash of it is kept in the config file.",
+        ))
+        .child(TextView::new("").with_name("new passphrase message"))
+        .child(TextView::new("Passphrase:"))
+        .child(field("new passphrase"))
+        .child(TextView::new("Again:"))
+        .child(field("new passphrase again"));
+
+    let dialog = Dialog::around(content)
+        .title("Lock")
+        .button("Lock", |siv| {
+            let get = |siv: &mut Cursive, name| {
+                siv.call_on_name(name, |v: &mut EditView| v.get_content())
+                    .unwrap()
+            };
+            let first = get(siv, "new passphrase");
+            let second = get(siv, "new passphrase again");
+
+            let problem = if first.is_empty() {
+                "The passphrase can't be empty."
+            } else if first != second {
+                "The passphrases don't match."
+            } else {
+                let mut cfg = config::write();
+                cfg.lock.passphrase_hash = Some(hash_passphrase(&first));
+                cfg.save();
+                drop(cfg);
+
+                siv.pop_layer();
+                lock(siv);
+                return;
+            };
+            siv.call_on_name("new passphrase message", |v: &mut TextView| {
+                v.set_content(problem)
+            });
+        })
+        .dismiss_button("Cancel");
+
+    siv.add_layer(dialog);
+}
+
+/// Which events count as someone using dtui, for the idle timeout.
+pub(crate) fn input() -> EventTrigger {
+    EventTrigger::from_fn(|event| !matches!(event, Event::Refresh | Event::WindowResize))
+}
+
+pub(crate) fn touch(_: &mut Cursive) {
+    *LAST_INPUT.lock().unwrap() = Instant::now();
+}
+
+/// Locks once nobody's touched anything for long enough.
+/// Runs on every refresh, which also keeps the lock screen above any dialogs
+/// that background work has popped up since.
+pub(crate) fn check_idle(siv: &mut Cursive) {
+    let screen = siv.screen_mut();
+    if let Some(pos) = screen.find_layer_from_name(LAYER_NAME) {
+        if pos != LayerPosition::FromBack(screen.len() - 1) {
+            screen.move_to_front(pos);
+        }
+        return;
+    }
+
+    let cfg = config::read();
+    let minutes = match cfg.lock.idle_minutes {
+        Some(minutes) if minutes > 0 && cfg.lock.passphrase_hash.is_some() => minutes,
+        _ => return,
+    };
+    drop(cfg);
+
+    if LAST_INPUT.lock().unwrap().elapsed() >= Duration::from_secs(minutes * 60) {
+        lock(siv);
+    }
+}
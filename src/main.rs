@@ -1,13 +1,16 @@
 #![feature(async_closure)]
 #![feature(drain_filter)]
 
+use cursive::event::Event;
 use cursive::menu::Tree;
 use cursive::traits::*;
-use cursive::views::Panel;
+use cursive::views::{BoxedView, DummyView, EditView, LinearLayout, Panel, TextView};
 use cursive::Cursive;
-use deluge_rpc::{AuthLevel, FilterDict, InfoHash, Session};
+use deluge_rpc::{AuthLevel, EventKind, InfoHash, Session};
+use std::collections::HashSet;
 use std::sync::{Arc, RwLock};
 use tokio::sync::{watch, Notify};
+use tokio::time;
 use uuid::Uuid;
 
 #[macro_use]
@@ -15,25 +18,106 @@ mod util;
 
 mod views;
 use views::{
-    filters::FiltersView, static_linear_layout::StaticLinearLayout, statusbar::StatusBarView,
-    tabs::TorrentTabsView, torrents::TorrentsView,
+    filters::{FilterSets, FiltersPane, FiltersView},
+    scroll::ScrollWrapper,
+    static_linear_layout::StaticLinearLayout,
+    statusbar::StatusBarView,
+    tabs::TorrentTabsView,
+    thread::ViewThread,
+    torrents::TorrentsView,
+    workspaces::{self, WorkspacesView},
 };
 
+mod backend;
+mod bandwidth;
+mod capabilities;
+use capabilities::Capabilities;
+mod cli;
+mod clipboard;
+mod commands;
 mod config;
+mod demo;
+use config::GroupBy;
+mod event_interest;
+use event_interest::EventInterest;
 mod form;
+mod hooks;
+mod jobs;
+mod keys;
+mod lock;
+use keys::Action;
+mod legacy;
 mod menu;
+mod notifications;
+mod rpc;
+mod scheduler;
+mod shutdown;
 mod themes;
+mod visibility;
+mod watch_dir;
+mod wire;
 
 type Selection = Arc<RwLock<Option<InfoHash>>>;
+/// The torrents marked in the torrents view, for views that act on several at once.
+type Marked = Arc<RwLock<Vec<InfoHash>>>;
 
 #[derive(Debug, Clone)]
 pub(crate) enum SessionHandle {
-    Connected { id: Uuid, session: Arc<Session> },
+    Connected {
+        id: Uuid,
+        session: Arc<Session>,
+        auth_level: AuthLevel,
+        capabilities: Arc<Capabilities>,
+        event_interest: Arc<EventInterest>,
+        responsiveness: Arc<rpc::Responsiveness>,
+    },
     Disconnected,
 }
 impl SessionHandle {
-    fn new(id: Uuid, session: Arc<Session>) -> Self {
-        Self::Connected { id, session }
+    fn new(
+        id: Uuid,
+        session: Arc<Session>,
+        auth_level: AuthLevel,
+        capabilities: Capabilities,
+    ) -> Self {
+        Self::Connected {
+            id,
+            session,
+            auth_level,
+            capabilities: Arc::new(capabilities),
+            event_interest: Arc::default(),
+            responsiveness: Arc::default(),
+        }
+    }
+
+    /// Adds a view's interests to the ones applied to this session.
+    async fn register_event_interest(
+        &self,
+        view: &'static str,
+        kinds: HashSet<EventKind>,
+    ) -> deluge_rpc::Result<()> {
+        match self {
+            Self::Connected {
+                session,
+                event_interest,
+                ..
+            } => event_interest.register(session, view, kinds).await,
+            Self::Disconnected => Ok(()),
+        }
+    }
+
+    /// Notes whether a thread's latest request to the daemon was answered in time.
+    fn record_responding(&self, thread: &'static str, responding: bool) {
+        if let Self::Connected { responsiveness, .. } = self {
+            responsiveness.record(thread, responding);
+        }
+    }
+
+    fn get_responsiveness(&self) -> Option<&Arc<rpc::Responsiveness>> {
+        match self {
+            Self::Connected { responsiveness, .. } => Some(responsiveness),
+            Self::Disconnected => None,
+        }
     }
 
     fn get_id(&self) -> Option<Uuid> {
@@ -49,6 +133,20 @@ impl SessionHandle {
             Self::Disconnected => None,
         }
     }
+
+    fn get_auth_level(&self) -> Option<AuthLevel> {
+        match self {
+            Self::Connected { auth_level, .. } => Some(*auth_level),
+            Self::Disconnected => None,
+        }
+    }
+
+    fn get_capabilities(&self) -> Option<&Arc<Capabilities>> {
+        match self {
+            Self::Connected { capabilities, .. } => Some(capabilities),
+            Self::Disconnected => None,
+        }
+    }
 }
 
 struct AppState {
@@ -62,47 +160,99 @@ impl AppState {
 
     fn replace(&mut self, val: SessionHandle) {
         self.val = val;
-        self.tx.send(self.val.clone()).unwrap();
+        // This only fails if every view thread has already exited, in which case nobody cares.
+        self.tx.send(self.val.clone()).ok();
     }
 }
 
-#[tokio::main]
-async fn main() -> deluge_rpc::Result<()> {
-    let (session_send, session_recv) = watch::channel(SessionHandle::Disconnected);
+async fn autoconnect() -> deluge_rpc::Result<Option<SessionHandle>> {
+    let (id, host) = {
+        let cfg = config::read();
+        let cmgr = &cfg.connection_manager;
+        match cmgr.autoconnect {
+            Some(id) => (id, cmgr.hosts[&id].clone()),
+            None => return Ok(None),
+        }
+    };
+    connect(id, &host).await
+}
+
+/// Connects and logs in, or gives up quietly if the daemon doesn't answer in time.
+async fn connect(id: Uuid, host: &config::Host) -> deluge_rpc::Result<Option<SessionHandle>> {
+    let policy = rpc::Policy::from(host);
+    let connecting = legacy::connect(&host.address, host.port, host.protocol);
 
+    // If the daemon doesn't answer, start up disconnected rather than not at all.
+    let mut ses = match time::timeout(policy.connect_timeout, connecting).await {
+        Ok(ses) => ses?,
+        Err(_) => return Ok(None),
+    };
+
+    let auth_level = match time::timeout(
+        policy.request_timeout,
+        ses.login(&host.username, &host.password),
+    )
+    .await
     {
-        let cfg = config::get_config();
-        let cmgr = &cfg.read().unwrap().connection_manager;
-        if let Some(id) = cmgr.autoconnect {
-            let host = &cmgr.hosts[&id];
-            let endpoint = (host.address.as_str(), host.port);
+        Ok(auth_level) => auth_level?,
+        Err(_) => return Ok(None),
+    };
+    // TODO: be interactive about this
+    assert!(auth_level >= AuthLevel::Normal);
 
-            let mut ses = Session::connect(endpoint).await?;
+    let capabilities = Capabilities::detect(&ses).await;
 
-            let auth_level = ses.login(&host.username, &host.password).await?;
-            // TODO: be interactive about this
-            assert!(auth_level >= AuthLevel::Normal);
+    Ok(Some(SessionHandle::new(
+        id,
+        Arc::new(ses),
+        auth_level,
+        capabilities,
+    )))
+}
 
-            let handle = SessionHandle::new(id, Arc::new(ses));
-            session_send.send(handle).unwrap();
+#[tokio::main]
+async fn main() -> deluge_rpc::Result<()> {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let demo = cli::take_demo(&mut args);
+    cli::take_profile(&mut args);
+    config::init();
+    if let Some(command) = args.first() {
+        if cli::is_command(command) {
+            return cli::run(&args).await;
         }
     }
 
+    let (session_send, session_recv) = watch::channel(SessionHandle::Disconnected);
+
+    let initial = if demo {
+        let port = demo::start()
+            .await
+            .expect("failed to start the demo daemon");
+        connect(demo::HOST_ID, &demo::host(port)).await?
+    } else {
+        autoconnect().await?
+    };
+    if let Some(handle) = initial {
+        session_send.send(handle).unwrap();
+    }
+
     let app_state = AppState {
         tx: session_send,
         val: session_recv.borrow().clone(),
     };
 
-    let (filters_send, filters_recv) = watch::channel(FilterDict::default());
+    let (filters_send, filters_recv) = watch::channel(FilterSets::default());
     let filters_notify = Arc::new(Notify::new());
 
     let selection = Arc::new(RwLock::new(None));
     let selection_notify = Arc::new(Notify::new());
+    let marked = Marked::default();
 
     let torrents = TorrentsView::new(
         session_recv.clone(),
         selection.clone(),
         selection_notify.clone(),
+        marked.clone(),
         filters_recv.clone(),
         filters_notify.clone(),
     )
@@ -114,41 +264,126 @@ async fn main() -> deluge_rpc::Result<()> {
         filters_recv.clone(),
         filters_notify,
     )
-    .with_name("filters")
-    .scrollable();
-
-    let status_bar = StatusBarView::new(session_recv.clone()).with_name("status");
+    .with_name("filters");
+    let filters = ScrollWrapper::new(filters);
+
+    let status_bar = StatusBarView::new(session_recv.clone(), selection.clone());
+    let toasts = status_bar.toasts();
+    let status_bar = status_bar.with_name("status");
+
+    let search = EditView::new()
+        .on_edit(|siv, text, _| {
+            siv.call_on_name("torrents", |v: &mut TorrentsView| v.set_search(text));
+        })
+        .on_submit(|siv, _| {
+            siv.focus_name("torrents").ok();
+        })
+        .with_name("search")
+        .fixed_width(20);
+
+    let top_bar = LinearLayout::horizontal()
+        .child(WorkspacesView)
+        .child(DummyView.full_width())
+        .child(TextView::new("Search: "))
+        .child(search);
 
     let torrents_ui = StaticLinearLayout::horizontal((
-        Panel::new(filters).title("Filters"),
-        Panel::new(torrents).title("Torrents"),
+        FiltersPane::new(filters).with_name("filters_pane"),
+        Panel::new(StaticLinearLayout::vertical((top_bar, torrents)).weight(1, 1))
+            .title("Torrents"),
     ));
 
     let torrent_tabs =
-        TorrentTabsView::new(session_recv.clone(), selection, selection_notify).with_name("tabs");
+        TorrentTabsView::new(session_recv.clone(), selection, selection_notify, marked)
+            .with_name("tabs");
+
+    let watch_dir_recv = session_recv.clone();
+    shutdown::spawn("watch directories", async move {
+        watch_dir::run(watch_dir_recv).await;
+        Ok(())
+    });
+    shutdown::spawn(
+        "notifications",
+        notifications::NotifierThread::new(toasts.clone()).run(session_recv.clone()),
+    );
+    shutdown::spawn(
+        "hooks",
+        hooks::HookThread::new(toasts).run(session_recv.clone()),
+    );
+    shutdown::spawn(
+        "scheduler",
+        scheduler::SchedulerThread::new(session_recv.clone()).run(session_recv.clone()),
+    );
 
     // No more cloning the receiver after this point.
     // It's important to drop so that we can unwrap the Arc<SessionHandle> on close.
     drop(session_recv);
 
-    let main_ui = StaticLinearLayout::vertical((torrents_ui, torrent_tabs, status_bar));
+    // The torrents and the tabs split whatever height the status bar leaves them.
+    let main_ui: menu::MainLayout = StaticLinearLayout::vertical((
+        BoxedView::boxed(torrents_ui),
+        BoxedView::boxed(torrent_tabs),
+        BoxedView::boxed(status_bar),
+    ))
+    .weight(0, 3)
+    .weight(1, 2);
 
     /*
     let mut siv = cursive::Cursive::new(|| {
     });
     */
     let mut siv = cursive::Cursive::new();
-    siv.set_fps(4);
+    siv.set_fps(visibility::FPS);
     siv.set_autohide_menu(false);
-    siv.set_theme(themes::dracula());
+    siv.set_theme(themes::current());
 
-    siv.add_global_callback('q', Cursive::quit);
+    siv.add_global_callback(Action::Quit.event(), shutdown::quit);
     siv.add_global_callback(cursive::event::Key::Esc, |siv| {
         if siv.screen().len() > 1 {
-            siv.pop_layer();
+            form::dismiss(siv);
         }
     });
     siv.add_global_callback(cursive::event::Event::Refresh, Cursive::clear);
+    siv.add_global_callback(cursive::event::Event::Refresh, visibility::update);
+    siv.add_global_callback(Action::NextTab.event(), |siv| {
+        siv.call_on_name("tabs", |v: &mut TorrentTabsView| v.cycle_tab(true));
+    });
+    siv.add_global_callback(Action::PrevTab.event(), |siv| {
+        siv.call_on_name("tabs", |v: &mut TorrentTabsView| v.cycle_tab(false));
+    });
+    siv.add_global_callback(Action::Search.event(), |siv| {
+        siv.focus_name("search").ok();
+    });
+    siv.add_global_callback(Action::CycleWorkspace.event(), workspaces::cycle_workspace);
+    siv.add_global_callback(Action::ToggleFilters.event(), |siv| {
+        let visible = siv
+            .call_on_name("filters_pane", |v: &mut FiltersPane| {
+                v.toggle();
+                v.is_visible()
+            })
+            .unwrap();
+        if !visible {
+            siv.focus_name("torrents").ok();
+        }
+    });
+    siv.add_global_callback(Action::MaximizeTorrents.event(), menu::maximize_torrents);
+    siv.add_global_callback(Action::MaximizeTabs.event(), menu::maximize_tabs);
+    siv.add_global_callback(Action::NarrowFilters.event(), |siv| {
+        siv.call_on_name("filters_pane", |v: &mut FiltersPane| v.resize(-2));
+    });
+    siv.add_global_callback(Action::WidenFilters.event(), |siv| {
+        siv.call_on_name("filters_pane", |v: &mut FiltersPane| v.resize(2));
+    });
+    siv.add_global_callback(Action::Help.event(), menu::show_help);
+    siv.add_global_callback(Action::CommandBar.event(), commands::show_command_bar);
+    siv.add_global_callback(Action::Lock.event(), lock::lock);
+    siv.add_global_callback(cursive::event::Event::Refresh, lock::check_idle);
+    siv.set_on_pre_event(lock::input(), lock::touch);
+    for (i, c) in ('1'..='9').enumerate() {
+        siv.add_global_callback(Event::AltChar(c), move |siv| {
+            workspaces::switch_workspace(siv, i)
+        });
+    }
 
     siv.menubar()
         .add_subtree(
@@ -156,24 +391,54 @@ async fn main() -> deluge_rpc::Result<()> {
             Tree::new()
                 .leaf("Add torrent", menu::add_torrent_dialog)
                 .leaf("Create torrent", |_| ())
+                .subtree("Recently added", Tree::new().delimiter())
+                .delimiter()
+                .leaf("Disconnect", menu::disconnect)
+                .leaf("Switch profile", menu::show_profiles)
+                .leaf("Lock", lock::lock)
                 .delimiter()
                 .leaf("Quit and shutdown daemon", menu::quit_and_shutdown_daemon)
                 .delimiter()
-                .leaf("Quit", Cursive::quit),
+                .leaf("Quit", shutdown::quit),
         )
         .add_subtree(
             "Edit",
             Tree::new()
-                .leaf("Preferences", |_| ())
+                .leaf("Preferences", menu::show_preferences)
+                .leaf("Network", menu::show_network_preferences)
                 .leaf("Connection Manager", menu::show_connection_manager),
-        );
+        )
+        .add_subtree(
+            "View",
+            Tree::new()
+                .leaf("Toggle detailed rows", menu::toggle_detailed_rows)
+                .leaf("Bandwidth history", menu::show_bandwidth_history)
+                .subtree(
+                    "Group by",
+                    Tree::new()
+                        .leaf("Nothing", |siv| menu::set_group_by(siv, GroupBy::None))
+                        .leaf("State", |siv| menu::set_group_by(siv, GroupBy::State))
+                        .leaf("Label", |siv| menu::set_group_by(siv, GroupBy::Label)),
+                ),
+        )
+        .add_subtree(
+            "Torrent",
+            Tree::new()
+                .leaf("Update trackers (all visible)", menu::reannounce_visible)
+                .leaf("Force re-check (selected)", menu::recheck_selected),
+        )
+        .add_subtree("Help", Tree::new().leaf("Keybindings", menu::show_help));
 
-    siv.add_fullscreen_layer(main_ui);
+    siv.add_fullscreen_layer(main_ui.with_name("main"));
+    menu::update_recent_menu(&mut siv);
+    workspaces::apply_active(&mut siv);
+    menu::warn_if_read_only(&mut siv);
 
     siv.set_user_data(app_state);
 
+    let cb_sink = siv.cb_sink().clone();
     siv.run_with(|| {
-        cursive::backends::crossterm::Backend::init()
+        backend::Backend::init(cb_sink)
             .map(cursive_buffered_backend::BufferedBackend::new)
             .map(Box::new)
             .expect("Failed to initialize backend")
@@ -1,31 +1,50 @@
 use cursive::event::Callback;
 use cursive::menu::Tree;
 use cursive::traits::*;
-use cursive::views::{MenuPopup, TextArea};
+use cursive::views::{
+    BoxedView, Dialog, DummyView, EditView, LinearLayout, MenuPopup, SelectView, TextArea, TextView,
+};
 use cursive::Cursive;
 use cursive::Vec2;
 use futures::executor::block_on;
+use globset::{GlobBuilder, GlobMatcher};
 use serde::Deserialize;
 use std::future::Future;
+use std::path::Path;
+use std::process::Stdio;
 use std::rc::Rc;
 use std::sync::Arc;
+use tokio::time;
 use uuid::Uuid;
 
+use crate::capabilities::Capabilities;
+use crate::config::{GroupBy, QuickAction, RecentTorrent};
 use crate::form::Form;
+use crate::jobs;
+use crate::keys;
+use crate::rpc::{self, Policy};
 use crate::{AppState, SessionHandle};
 
 use crate::views::{
-    connection_manager::ConnectionManagerView, remove_torrent::RemoveTorrentPrompt,
+    connection_manager::ConnectionManagerView,
+    labeled_checkbox::LabeledCheckbox,
+    preferences::{NetworkPreferences, NetworkPreferencesView, PreferencesView},
+    remove_torrent::RemoveTorrentPrompt,
+    static_linear_layout::StaticLinearLayout,
+    statusbar,
     tabs::files::FileKey,
+    torrents::TorrentsView,
 };
 
-use deluge_rpc::{FilePriority, InfoHash, Query, Session, TorrentOptions};
+use deluge_rpc::{AuthLevel, FilePriority, InfoHash, Query, Session, TorrentOptions, TorrentState};
 
 trait CursiveWithSession<'a> {
     type Ref: 'a;
 
     fn session(&'a mut self) -> Self::Ref;
 
+    fn policy(&mut self) -> Policy;
+
     fn with_session<T, F>(&'a mut self, f: F) -> T
     where
         F: FnOnce(Self::Ref) -> T,
@@ -33,21 +52,29 @@ trait CursiveWithSession<'a> {
         f(self.session())
     }
 
-    fn with_session_blocking<T, F>(&'a mut self, f: F) -> T::Output
+    fn with_session_blocking<T, U, F>(&'a mut self, f: F) -> Result<U, rpc::Failure>
     where
-        T: Future,
+        T: Future<Output = deluge_rpc::Result<U>>,
         F: FnOnce(Self::Ref) -> T,
     {
-        block_on(self.with_session(f))
+        let policy = self.policy();
+        block_on(rpc::request(&policy, self.with_session(f)))
     }
 }
 
 // "with session blocking + unwrap"
 // Simple macro for more concisely performing RPC inside of Cursive callbacks.
+// If the request fails or times out, an error dialog is shown and the callback returns early.
 macro_rules! wsbu {
     // Invocation A: Using a Cursive object, execute a Session -> Future closure.
     ($siv:expr, $f:expr) => {
-        $siv.with_session_blocking($f).unwrap()
+        match $siv.with_session_blocking($f) {
+            Ok(val) => val,
+            Err(e) => {
+                $siv.add_layer(Dialog::info(e.to_string()).title("Error"));
+                return;
+            }
+        }
     };
 
     // Invocation B: Convert a Session -> Future closure using Invocation A.
@@ -82,13 +109,138 @@ impl<'a> CursiveWithSession<'a> for Cursive {
             .get_session()
             .expect("SessionHandle was unexpectedly empty")
     }
+
+    fn policy(&mut self) -> Policy {
+        let id = self
+            .user_data::<AppState>()
+            .and_then(|app_state| app_state.get().get_id());
+        Policy::for_host(id)
+    }
 }
 
 fn add_torrent(siv: &mut Cursive, text: String) {
     let options = TorrentOptions::default();
     let http_headers = None;
 
-    wsbuf!(@siv; :add_torrent_url, &text, &options, http_headers);
+    let url = text.clone();
+    let hash = if url.starts_with("magnet:") {
+        wsbuf!(@siv; :add_torrent_magnet, &url, &options)
+    } else {
+        wsbuf!(@siv; :add_torrent_url, &url, &options, http_headers)
+    };
+    torrent_added(siv, hash, &text);
+}
+
+/// Says whether the daemon took a torrent, then selects it once it shows up.
+pub(crate) fn torrent_added(siv: &mut Cursive, hash: Option<InfoHash>, source: &str) {
+    match hash {
+        Some(hash) => {
+            statusbar::toast(siv, "Torrent added");
+            siv.call_on_name("torrents", |v: &mut TorrentsView| v.select_when_added(hash));
+            remember_added(siv, hash, source);
+        }
+        None => statusbar::toast(siv, "The daemon did not add the torrent"),
+    }
+}
+
+fn remember_added(siv: &mut Cursive, hash: InfoHash, source: &str) {
+    {
+        let mut cfg = crate::config::write();
+        let recent = &mut cfg.recent_torrents;
+        let hash = hash.to_string();
+        recent.torrents.retain(|t| t.hash != hash);
+        let entry = RecentTorrent {
+            hash,
+            source: source.to_owned(),
+            name: None,
+        };
+        recent.torrents.insert(0, entry);
+        recent.torrents.truncate(recent.limit);
+        cfg.save();
+    }
+    update_recent_menu(siv);
+}
+
+/// What to call a recent torrent that the daemon hasn't named for us yet.
+fn name_from_source(source: &str) -> String {
+    use percent_encoding::percent_decode_str;
+
+    let name = if let Some(query) = source.strip_prefix("magnet:?") {
+        query.split('&').find_map(|pair| pair.strip_prefix("dn="))
+    } else {
+        let path = source.split(|c: char| c == '?' || c == '#').next().unwrap();
+        path.rsplit('/').find(|s| !s.is_empty())
+    };
+    let name = name.unwrap_or(source).replace('+', " ");
+    percent_decode_str(&name).decode_utf8_lossy().into_owned()
+}
+
+/// Rebuilds File → Recently added, picking up the names of any torrents the daemon has listed since.
+pub(crate) fn update_recent_menu(siv: &mut Cursive) {
+    let names = siv
+        .call_on_name("torrents", |v: &mut TorrentsView| v.torrent_names())
+        .unwrap_or_default();
+
+    let entries: Vec<(String, String, String)> = {
+        let mut cfg = crate::config::write();
+        let mut named = false;
+        for recent in &mut cfg.recent_torrents.torrents {
+            let name = names
+                .iter()
+                .find(|(hash, _)| hash.to_string() == recent.hash);
+            if let Some((_, name)) = name {
+                if recent.name.as_ref() != Some(name) {
+                    recent.name = Some(name.clone());
+                    named = true;
+                }
+            }
+        }
+        if named {
+            cfg.save();
+        }
+
+        let recent = &cfg.recent_torrents.torrents;
+        recent
+            .iter()
+            .map(|t| {
+                let label = t
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| name_from_source(&t.source));
+                (label, t.hash.clone(), t.source.clone())
+            })
+            .collect()
+    };
+
+    let mut menu = Tree::new();
+    for (label, hash, source) in entries {
+        menu.add_leaf(label, move |siv| open_recent(siv, &hash, &source));
+    }
+    if menu.is_empty() {
+        menu.add_delimiter();
+    }
+
+    let file_menu = siv.menubar().find_subtree("File");
+    if let Some(recent_menu) = file_menu.and_then(|m| m.find_subtree("Recently added")) {
+        *recent_menu = menu;
+    }
+}
+
+/// Selects a recently added torrent, or adds it again if it's since been removed.
+fn open_recent(siv: &mut Cursive, hash: &str, source: &str) {
+    let names = siv
+        .call_on_name("torrents", |v: &mut TorrentsView| v.torrent_names())
+        .unwrap_or_default();
+
+    match names.iter().find(|(h, _)| h.to_string() == hash) {
+        Some((hash, _)) => {
+            let hash = *hash;
+            siv.call_on_name("torrents", |v: &mut TorrentsView| v.select_when_added(hash));
+            siv.focus_name("torrents").ok();
+        }
+        None => add_torrent(siv, source.to_owned()),
+    }
+    update_recent_menu(siv);
 }
 
 pub fn add_torrent_dialog(siv: &mut Cursive) {
@@ -99,17 +251,88 @@ pub fn add_torrent_dialog(siv: &mut Cursive) {
     siv.add_layer(dialog);
 }
 
-fn replace_session(siv: &mut Cursive, new: Option<(Uuid, Arc<Session>, String, String)>) {
-    let handle = new
-        .map(|(id, mut session, user, pass)| {
-            assert_eq!(Arc::strong_count(&session), 1);
-            let fut = Arc::get_mut(&mut session).unwrap().login(&user, &pass);
-            block_on(fut).unwrap();
-            SessionHandle::new(id, session)
-        })
-        .unwrap_or(SessionHandle::Disconnected);
+pub(crate) fn replace_session(
+    siv: &mut Cursive,
+    new: Option<(Uuid, Arc<Session>, String, String)>,
+) {
+    match new {
+        Some((id, session, user, pass)) => log_in(siv, id, async move { Ok(session) }, user, pass),
+        None => siv
+            .user_data::<AppState>()
+            .unwrap()
+            .replace(SessionHandle::Disconnected),
+    }
+}
+
+/// Connects to a configured host and logs in, without blocking the UI.
+pub(crate) fn connect(siv: &mut Cursive, id: Uuid, host: crate::config::Host) {
+    let (address, port, protocol) = (host.address, host.port, host.protocol);
+    let timeout = Policy::from(&host).connect_timeout;
+    let session = async move {
+        let connecting = crate::legacy::connect(&address, port, protocol);
+        match time::timeout(timeout, connecting).await {
+            Ok(Ok(session)) => Ok(Arc::new(session)),
+            Ok(Err(e)) => Err(rpc::Failure::Rpc(e)),
+            Err(_) => Err(rpc::Failure::NotResponding),
+        }
+    };
+    log_in(siv, id, session, host.username, host.password);
+}
+
+/// Logs in once `session` has connected, switching to it when done.
+/// Meanwhile, a dialog shows what's going on and offers to give up.
+fn log_in<F>(siv: &mut Cursive, id: Uuid, session: F, user: String, pass: String)
+where
+    F: Future<Output = Result<Arc<Session>, rpc::Failure>> + Send + 'static,
+{
+    let address = crate::config::read()
+        .connection_manager
+        .hosts
+        .get(&id)
+        .map(|host| format!("{}:{}", host.address, host.port))
+        .unwrap_or_default();
+    let text = format!("Connecting to {}@{}…", user, address);
+    let policy = Policy::for_host(Some(id));
+
+    let fut = async move {
+        let mut session = session.await?;
+        assert_eq!(Arc::strong_count(&session), 1);
+        let login = Arc::get_mut(&mut session).unwrap().login(&user, &pass);
+        let auth_level = rpc::request(&policy, login).await?;
+        let capabilities = Capabilities::detect(&session).await;
+        Result::<_, rpc::Failure>::Ok(SessionHandle::new(id, session, auth_level, capabilities))
+    };
+
+    jobs::spawn_with_progress(siv, "Connecting", text, fut, |siv, result| match result {
+        Ok(handle) => siv.user_data::<AppState>().unwrap().replace(handle),
+        Err(e) => {
+            let msg = format!("Couldn't connect. {}", e);
+            siv.add_layer(Dialog::info(msg).title("Connection Failed"));
+        }
+    });
+}
 
-    siv.user_data::<AppState>().unwrap().replace(handle);
+pub fn disconnect(siv: &mut Cursive) {
+    replace_session(siv, None);
+}
+
+/// The torrents, the torrent tabs, and the status bar, from top to bottom.
+pub(crate) type MainLayout = StaticLinearLayout<(BoxedView, BoxedView, BoxedView)>;
+
+/// Gives the torrent list the full height by hiding the tabs, or brings the tabs back.
+pub fn maximize_torrents(siv: &mut Cursive) {
+    siv.call_on_name("main", |v: &mut MainLayout| {
+        v.set_visible(0, true);
+        v.toggle_visible(1);
+    });
+}
+
+/// Expands the active tab to the full screen, or restores the usual split.
+pub fn maximize_tabs(siv: &mut Cursive) {
+    siv.call_on_name("main", |v: &mut MainLayout| {
+        v.set_visible(1, true);
+        v.toggle_visible(0);
+    });
 }
 
 pub fn show_connection_manager(siv: &mut Cursive) {
@@ -123,6 +346,131 @@ pub fn show_connection_manager(siv: &mut Cursive) {
     siv.add_layer(dialog);
 }
 
+/// Lists the config profiles to switch between, plus a way to start a new one.
+pub(crate) fn show_profiles(siv: &mut Cursive) {
+    let current = crate::config::profile();
+
+    let mut select = SelectView::new();
+    for name in crate::config::profiles() {
+        let label = if name == current {
+            format!("{} (current)", name)
+        } else {
+            name.clone()
+        };
+        select.add_item(label, Some(name));
+    }
+    select.add_item("New profile…", None);
+
+    select.set_on_submit(|siv, choice: &Option<String>| {
+        siv.pop_layer();
+        match choice {
+            Some(name) => switch_profile(siv, name),
+            None => {
+                let dialog = TextArea::new()
+                    .into_dialog("Cancel", "Create", |siv, name| {
+                        let name = name.trim();
+                        if name.is_empty() || name.contains(['/', '\\']) {
+                            let msg = "Profile names can't be empty or contain slashes.";
+                            siv.add_layer(Dialog::info(msg).title("New Profile"));
+                        } else {
+                            switch_profile(siv, name);
+                        }
+                    })
+                    .title("New Profile");
+                siv.add_layer(dialog);
+            }
+        }
+    });
+
+    siv.add_layer(
+        Dialog::around(select)
+            .dismiss_button("Cancel")
+            .title("Profiles"),
+    );
+}
+
+/// Disconnects, loads another profile's config, then connects to its autoconnect host.
+/// Keybindings and table layouts are only read at startup, so those wait for a restart.
+fn switch_profile(siv: &mut Cursive, name: &str) {
+    if name == crate::config::profile() {
+        return;
+    }
+
+    if let Err(e) = crate::config::switch_profile(name) {
+        let msg = format!("Couldn't load the {} profile: {}", name, e);
+        siv.add_layer(Dialog::info(msg).title("Profiles"));
+        return;
+    }
+    crate::bandwidth::switch_profile();
+    disconnect(siv);
+    siv.set_theme(crate::themes::current());
+
+    let autoconnect = {
+        let cfg = crate::config::read();
+        let cmgr = &cfg.connection_manager;
+        cmgr.autoconnect
+            .and_then(|id| Some((id, cmgr.hosts.get(&id)?.clone())))
+    };
+    if let Some((id, host)) = autoconnect {
+        connect(siv, id, host);
+    }
+
+    update_recent_menu(siv);
+    statusbar::toast(siv, format!("Switched to the {} profile", name));
+    warn_if_read_only(siv);
+}
+
+/// Lets the user know when settings changes are going to be lost.
+pub(crate) fn warn_if_read_only(siv: &mut Cursive) {
+    if crate::config::read().read_only {
+        let msg = "This profile's config is from a newer version of dtui. \
+                   It won't be saved, so any changes last until dtui quits.";
+        siv.add_layer(Dialog::info(msg).title("Read-Only Config"));
+    }
+}
+
+pub fn show_preferences(siv: &mut Cursive) {
+    let dialog = PreferencesView::new()
+        .into_dialog("Cancel", "OK", |siv, prefs| {
+            let mut cfg = crate::config::write();
+            prefs.apply(&mut cfg);
+            cfg.save();
+            drop(cfg);
+            siv.set_theme(crate::themes::current());
+        })
+        .title("Preferences");
+
+    siv.add_layer(dialog);
+}
+
+pub fn show_network_preferences(siv: &mut Cursive) {
+    let app_state = siv.user_data::<AppState>().unwrap();
+    if app_state.get().get_session().is_none() {
+        return;
+    }
+
+    let prefs = wsbu!(siv, async move |ses| ses
+        .get_config_values::<NetworkPreferences>()
+        .await);
+    let dialog = NetworkPreferencesView::new(&prefs)
+        .into_dialog("Cancel", "OK", |siv, prefs| {
+            let config: std::collections::HashMap<&str, serde_json::Value> = [
+                ("dht", prefs.dht),
+                ("lsd", prefs.lsd),
+                ("utpex", prefs.utpex),
+                ("upnp", prefs.upnp),
+                ("natpmp", prefs.natpmp),
+            ]
+            .iter()
+            .map(|&(key, val)| (key, serde_json::Value::Bool(val)))
+            .collect();
+            wsbuf!(@siv; :set_config, &config);
+        })
+        .title("Network");
+
+    siv.add_layer(dialog);
+}
+
 async fn set_single_file_priority(
     session: &Session,
     hash: InfoHash,
@@ -175,6 +523,127 @@ async fn set_multi_file_priority(
     session.set_torrent_options(&[hash], &options).await
 }
 
+async fn set_priorities_by_pattern(
+    session: &Session,
+    hash: InfoHash,
+    rules: &[(GlobMatcher, FilePriority)],
+) -> deluge_rpc::Result<usize> {
+    #[derive(Debug, Clone, Deserialize)]
+    struct File {
+        index: usize,
+        path: String,
+    }
+
+    #[derive(Debug, Clone, Deserialize, Query)]
+    struct Files {
+        files: Vec<File>,
+        file_priorities: Vec<FilePriority>,
+    }
+
+    let Files {
+        files,
+        mut file_priorities,
+    } = session.get_torrent_status::<Files>(hash).await?;
+
+    let mut matched = 0;
+    for file in &files {
+        // Where rules overlap, the last one wins.
+        let rule = rules
+            .iter()
+            .rev()
+            .find(|(glob, _)| glob.is_match(&file.path));
+        if let Some(&(_, priority)) = rule {
+            file_priorities[file.index] = priority;
+            matched += 1;
+        }
+    }
+
+    if matched > 0 {
+        let options = TorrentOptions {
+            file_priorities: Some(file_priorities),
+            ..TorrentOptions::default()
+        };
+        session.set_torrent_options(&[hash], &options).await?;
+    }
+
+    Ok(matched)
+}
+
+/// Parses lines like `*.nfo skip`, ignoring blank ones.
+fn parse_priority_rules(text: &str) -> Result<Vec<(GlobMatcher, FilePriority)>, String> {
+    let mut rules = Vec::new();
+
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let (pattern, priority) = line
+            .rsplit_once(char::is_whitespace)
+            .ok_or_else(|| format!("\"{}\" needs a priority after the pattern.", line))?;
+
+        let priority = match priority.to_lowercase().as_str() {
+            "skip" => FilePriority::Skip,
+            "low" => FilePriority::Low,
+            "normal" => FilePriority::Normal,
+            "high" => FilePriority::High,
+            _ => return Err(format!("\"{}\" isn't a priority.", priority)),
+        };
+
+        let glob = GlobBuilder::new(pattern.trim_end())
+            .case_insensitive(true)
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        rules.push((glob.compile_matcher(), priority));
+    }
+
+    Ok(rules)
+}
+
+fn priority_rules_dialog(siv: &mut Cursive, hash: InfoHash) {
+    let help = "One rule per line: a pattern, then skip, low, normal, or high.\n\
+                Where rules overlap, the last one wins.";
+
+    let rules = TextArea::new()
+        .content("*.nfo skip\n")
+        .with_name("priority rules")
+        .min_size((40, 6));
+
+    let apply = move |siv: &mut Cursive| {
+        let text = siv
+            .call_on_name("priority rules", |v: &mut TextArea| {
+                v.get_content().to_owned()
+            })
+            .unwrap();
+
+        let rules = match parse_priority_rules(&text) {
+            Ok(rules) => rules,
+            Err(msg) => {
+                siv.add_layer(Dialog::info(msg).title("Invalid Rule"));
+                return;
+            }
+        };
+        siv.pop_layer();
+
+        let matched = wsbuf!(@siv; set_priorities_by_pattern, hash, &rules);
+        let msg = match matched {
+            0 => "No files matched".to_owned(),
+            1 => "Set the priority of 1 file".to_owned(),
+            n => format!("Set the priorities of {} files", n),
+        };
+        statusbar::toast(siv, msg);
+    };
+
+    let dialog = Dialog::around(
+        LinearLayout::vertical()
+            .child(TextView::new(help))
+            .child(DummyView)
+            .child(rules),
+    )
+    .button("Apply", apply)
+    .dismiss_button("Cancel")
+    .title("Set Priorities by Pattern");
+
+    siv.add_layer(dialog);
+}
+
 fn rename_file_dialog(siv: &mut Cursive, hash: InfoHash, index: usize, old_name: &str) {
     let dialog = TextArea::new()
         .content(old_name)
@@ -188,7 +657,27 @@ fn rename_file_dialog(siv: &mut Cursive, hash: InfoHash, index: usize, old_name:
     siv.add_layer(dialog);
 }
 
+/// Whether the daemon has an RPC method, telling the user if it doesn't.
+fn check_supported(siv: &mut Cursive, method: &str, title: &str) -> bool {
+    let supported = siv
+        .user_data::<AppState>()
+        .unwrap()
+        .get()
+        .get_capabilities()
+        .map_or(true, |caps| caps.supports(method));
+
+    if !supported {
+        let msg = "The daemon doesn't support this. It may need to be upgraded.";
+        siv.add_layer(Dialog::info(msg).title(title));
+    }
+    supported
+}
+
 fn rename_folder_dialog(siv: &mut Cursive, hash: InfoHash, old_name: Rc<str>) {
+    if !check_supported(siv, "core.rename_folder", "Rename Folder") {
+        return;
+    }
+
     let dialog = TextArea::new()
         .content(old_name.as_ref())
         .with(|v| v.set_cursor(old_name.len()))
@@ -212,8 +701,9 @@ pub fn files_tab_file_menu(
 
     let old_name = Rc::from(old_name);
     let cb = move |siv: &mut Cursive| {
+        let path = Rc::clone(&old_name);
         let old_name = Rc::clone(&old_name);
-        let menu_tree = Tree::new()
+        let mut menu_tree = Tree::new()
             .leaf("Rename", move |siv| {
                 rename_file_dialog(siv, hash, index, &old_name)
             })
@@ -221,7 +711,11 @@ pub fn files_tab_file_menu(
             .leaf("Skip", make_cb(FilePriority::Skip))
             .leaf("Low", make_cb(FilePriority::Low))
             .leaf("Normal", make_cb(FilePriority::Normal))
-            .leaf("High", make_cb(FilePriority::High));
+            .leaf("High", make_cb(FilePriority::High))
+            .leaf("Set Priorities by Pattern…", move |siv| {
+                priority_rules_dialog(siv, hash)
+            });
+        add_open_items(siv, &mut menu_tree, hash, path);
 
         let menu_popup = MenuPopup::new(Rc::new(menu_tree));
 
@@ -249,7 +743,8 @@ pub(crate) fn files_tab_folder_menu(
     let mut name = Some(Rc::from(name));
     let cb = move |siv: &mut Cursive| {
         let name = name.take().unwrap();
-        let menu_tree = Tree::new()
+        let path = Rc::clone(&name);
+        let mut menu_tree = Tree::new()
             .leaf("Rename", move |siv| {
                 rename_folder_dialog(siv, hash, Rc::clone(&name))
             })
@@ -257,7 +752,11 @@ pub(crate) fn files_tab_folder_menu(
             .leaf("Skip", make_cb(FilePriority::Skip))
             .leaf("Low", make_cb(FilePriority::Low))
             .leaf("Normal", make_cb(FilePriority::Normal))
-            .leaf("High", make_cb(FilePriority::High));
+            .leaf("High", make_cb(FilePriority::High))
+            .leaf("Set Priorities by Pattern…", move |siv| {
+                priority_rules_dialog(siv, hash)
+            });
+        add_open_items(siv, &mut menu_tree, hash, path);
 
         let menu_popup = MenuPopup::new(Rc::new(menu_tree));
 
@@ -267,7 +766,215 @@ pub(crate) fn files_tab_folder_menu(
     Callback::from_fn_mut(cb)
 }
 
+pub(crate) fn change_owner_dialog(siv: &mut Cursive, hashes: Vec<InfoHash>) {
+    if hashes.is_empty() {
+        return;
+    }
+
+    let auth_level = siv.user_data::<AppState>().unwrap().get().get_auth_level();
+    if auth_level < Some(AuthLevel::Admin) {
+        let msg = "Changing a torrent's owner requires an admin account.";
+        siv.add_layer(Dialog::info(msg).title("Change Owner"));
+        return;
+    }
+
+    if !check_supported(siv, "core.get_known_accounts", "Change Owner") {
+        return;
+    }
+
+    let accounts = wsbuf!(@siv; :get_known_accounts);
+
+    let mut select = SelectView::new();
+    for account in accounts {
+        select.add_item_str(account.username);
+    }
+    select.set_on_submit(move |siv, owner: &String| {
+        let options = TorrentOptions {
+            owner: Some(owner.clone()),
+            ..TorrentOptions::default()
+        };
+        wsbuf!(@siv; :set_torrent_options, &hashes, &options);
+        siv.pop_layer();
+    });
+
+    let dialog = Dialog::around(select.scrollable())
+        .dismiss_button("Cancel")
+        .title("Change Owner");
+
+    siv.add_layer(dialog);
+}
+
+async fn magnet_uri(session: &Session, hash: InfoHash) -> deluge_rpc::Result<String> {
+    use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+
+    #[derive(Debug, Clone, Deserialize)]
+    struct Tracker {
+        url: String,
+    }
+
+    #[derive(Debug, Clone, Deserialize, Query)]
+    struct MagnetInfo {
+        name: String,
+        trackers: Vec<Tracker>,
+    }
+
+    let info = session.get_torrent_status::<MagnetInfo>(hash).await?;
+
+    let mut uri = format!(
+        "magnet:?xt=urn:btih:{}&dn={}",
+        hash,
+        utf8_percent_encode(&info.name, NON_ALPHANUMERIC),
+    );
+    for tracker in &info.trackers {
+        uri.push_str("&tr=");
+        uri.extend(utf8_percent_encode(&tracker.url, NON_ALPHANUMERIC));
+    }
+    Ok(uri)
+}
+
+pub(crate) fn copy_hash(_: &mut Cursive, hash: InfoHash) {
+    crate::clipboard::copy(&hash.to_string());
+}
+
+pub(crate) fn copy_magnet_uri(siv: &mut Cursive, hash: InfoHash) {
+    let uri = wsbuf!(@siv; magnet_uri, hash);
+    crate::clipboard::copy(&uri);
+}
+
+/// The host we're connected to, if its paths can be opened locally.
+fn openable_host(siv: &mut Cursive) -> Option<crate::config::Host> {
+    let id = siv.user_data::<AppState>()?.get().get_id()?;
+    let cfg = crate::config::read();
+    let host = cfg.connection_manager.hosts.get(&id)?;
+    if host.can_resolve_paths() {
+        Some(host.clone())
+    } else {
+        None
+    }
+}
+
+fn run_command(command: &str, path: &Path) {
+    let mut words = command.split_whitespace();
+    let program = match words.next() {
+        Some(program) => program,
+        None => return,
+    };
+
+    // The command is detached from the terminal so it can't mess with the UI.
+    let mut cmd = std::process::Command::new(program);
+    cmd.args(words)
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    // Wait on a blocking thread, so the child gets reaped without holding up the UI.
+    tokio::task::spawn_blocking(move || {
+        if let Ok(mut child) = cmd.spawn() {
+            let _ = child.wait();
+        }
+    });
+}
+
+async fn download_location(session: &Session, hash: InfoHash) -> deluge_rpc::Result<String> {
+    #[derive(Debug, Clone, Deserialize, Query)]
+    struct DownloadLocation {
+        download_location: String,
+    }
+
+    let response = session.get_torrent_status::<DownloadLocation>(hash).await;
+    Ok(response?.download_location)
+}
+
+/// Opens a path within a torrent's download location.
+/// With `folder`, opens it in a file manager, or if it isn't a folder, the one containing it.
+fn open_torrent_path(siv: &mut Cursive, hash: InfoHash, relative: &str, folder: bool) {
+    let host = match openable_host(siv) {
+        Some(host) => host,
+        None => return,
+    };
+
+    let location = wsbuf!(@siv; download_location, hash);
+    let path = match host.local_path(&location) {
+        Some(dir) => dir.join(relative),
+        None => return,
+    };
+
+    let cfg = crate::config::read();
+    if folder {
+        let dir = if path.is_dir() {
+            &path
+        } else {
+            path.parent().unwrap_or(&path)
+        };
+        run_command(&cfg.commands.open_folder, dir);
+    } else {
+        run_command(&cfg.commands.open_file, &path);
+    }
+}
+
+fn add_open_items(siv: &mut Cursive, menu: &mut Tree, hash: InfoHash, relative: Rc<str>) {
+    if openable_host(siv).is_none() {
+        return;
+    }
+
+    let relative2 = Rc::clone(&relative);
+    menu.add_delimiter();
+    menu.add_leaf("Open File", move |siv| {
+        open_torrent_path(siv, hash, &relative, false)
+    });
+    menu.add_leaf("Open Folder", move |siv| {
+        open_torrent_path(siv, hash, &relative2, true)
+    });
+}
+
+fn rename_torrent_dialog(siv: &mut Cursive, hash: InfoHash, old_name: &str) {
+    let dialog = EditView::new()
+        .content(old_name)
+        .on_submit(move |siv, new_name| {
+            siv.pop_layer();
+            rename_torrent(siv, hash, new_name);
+        })
+        .min_width(40)
+        .into_dialog("Cancel", "Rename", move |siv, new_name| {
+            rename_torrent(siv, hash, &new_name)
+        })
+        .title("Rename Torrent");
+
+    siv.add_layer(dialog);
+}
+
+fn rename_torrent(siv: &mut Cursive, hash: InfoHash, new_name: &str) {
+    let options = TorrentOptions {
+        name: Some(new_name.to_owned()),
+        ..TorrentOptions::default()
+    };
+    wsbuf!(@siv; :set_torrent_options, &[hash], &options);
+    siv.call_on_name("torrents", |v: &mut TorrentsView| {
+        v.rename_torrent(hash, new_name.to_owned())
+    });
+}
+
 fn remove_torrent_dialog(siv: &mut Cursive, hash: InfoHash, name: &str) {
+    // If the torrent is one of several marked ones, offer to remove all of them.
+    let selected = siv
+        .call_on_name("torrents", |v: &mut TorrentsView| {
+            v.selected_torrent_names()
+        })
+        .unwrap_or_default();
+
+    if selected.len() > 1 && selected.iter().any(|(h, _)| *h == hash) {
+        let (hashes, names): (Vec<InfoHash>, Vec<String>) = selected.into_iter().unzip();
+        let dialog = RemoveTorrentPrompt::new_multi(&names)
+            .into_dialog("Cancel", "OK", move |siv, remove_data| {
+                wsbuf!(@siv; :remove_torrents, &hashes, remove_data);
+            })
+            .title("Remove Torrents");
+
+        siv.add_layer(dialog);
+        return;
+    }
+
     let dialog = RemoveTorrentPrompt::new_single(name)
         .into_dialog("Cancel", "OK", move |siv, remove_data| {
             wsbuf!(@siv; :remove_torrent, hash, remove_data);
@@ -277,53 +984,104 @@ fn remove_torrent_dialog(siv: &mut Cursive, hash: InfoHash, name: &str) {
     siv.add_layer(dialog);
 }
 
+fn is_error_muted(hash: InfoHash) -> bool {
+    let hash = hash.to_string();
+    crate::config::read().notifications.muted.contains(&hash)
+}
+
+fn toggle_error_mute(siv: &mut Cursive, hash: InfoHash) {
+    let muted = {
+        let mut cfg = crate::config::write();
+        let list = &mut cfg.notifications.muted;
+        let hash = hash.to_string();
+        let muted = match list.iter().position(|h| *h == hash) {
+            Some(i) => {
+                list.remove(i);
+                false
+            }
+            None => {
+                list.push(hash);
+                true
+            }
+        };
+        cfg.save();
+        muted
+    };
+
+    let msg = if muted {
+        "This torrent's errors are muted"
+    } else {
+        "This torrent's errors are no longer muted"
+    };
+    statusbar::toast(siv, msg);
+}
+
 pub fn torrent_context_menu(hash: InfoHash, name: &str, position: Vec2) -> Callback {
-    let mut name = Some(Box::from(name)); // It's so dumb that this is necessary.
+    let mut name = Some(Rc::<str>::from(name)); // It's so dumb that this is necessary.
     let cb = move |siv: &mut Cursive| {
         let name = name.take().unwrap();
+        let old_name = Rc::clone(&name);
+        let path = Rc::clone(&name);
 
+        // The daemon only has a Label category while the Label plugin is enabled.
         let label_menu = {
             use crate::views::filters::FILTER_CATEGORIES;
             use deluge_rpc::FilterKey;
             let categories = FILTER_CATEGORIES.read().unwrap();
 
-            let mut menu = Tree::new();
-            if let Some(filter_cat) = categories.get(&FilterKey::Label) {
+            categories.get(&FilterKey::Label).map(|filter_cat| {
+                let mut menu = Tree::new();
                 for (label, _) in &filter_cat.filters {
                     let owned_label = label.to_owned();
                     let cb = move |siv: &mut Cursive| {
-                        siv.with_session_blocking(|ses| ses.set_torrent_label(hash, &owned_label))
-                            .unwrap()
+                        wsbu!(siv, |ses| ses.set_torrent_label(hash, &owned_label))
                     };
 
                     let display_label = if label.is_empty() { "No Label" } else { label };
                     menu.add_leaf(display_label, cb);
                 }
-            }
-            if menu.is_empty() {
-                menu.add_delimiter();
-            }
-            menu
+                if menu.is_empty() {
+                    menu.add_delimiter();
+                }
+                menu
+            })
+        };
+
+        let mute_label = if is_error_muted(hash) {
+            "Unmute Errors"
+        } else {
+            "Mute Errors"
         };
 
-        let menu_tree = Tree::new()
+        let mut menu_tree = Tree::new()
             .leaf("Pause", wsbuf!(:pause_torrent, hash))
             .leaf("Resume", wsbuf!(:resume_torrent, hash))
             .delimiter()
+            .leaf("Rename", move |siv| {
+                rename_torrent_dialog(siv, hash, &old_name)
+            })
+            .delimiter()
             .subtree("Options", Tree::new().delimiter())
             .delimiter()
             .subtree("Queue", Tree::new().delimiter())
             .delimiter()
             .leaf("Update Tracker", wsbuf!(:force_reannounce, &[hash]))
             .leaf("Edit Trackers", |_| todo!())
+            .leaf(mute_label, move |siv| toggle_error_mute(siv, hash))
             .delimiter()
             .leaf("Remove Torrent", move |siv| {
                 remove_torrent_dialog(siv, hash, &name)
             })
             .delimiter()
             .leaf("Force Re-check", wsbuf!(:force_recheck, &[hash]))
-            .leaf("Move Download Folder", |_| todo!())
-            .subtree("Label", label_menu);
+            .leaf("Move Download Folder", |_| todo!());
+        if let Some(label_menu) = label_menu {
+            menu_tree.add_subtree("Label", label_menu);
+        }
+        menu_tree.add_delimiter();
+        menu_tree.add_leaf("Copy Hash", move |siv| copy_hash(siv, hash));
+        menu_tree.add_leaf("Copy Magnet URI", move |siv| copy_magnet_uri(siv, hash));
+        add_open_items(siv, &mut menu_tree, hash, path);
 
         let menu_popup = MenuPopup::new(Rc::new(menu_tree));
 
@@ -333,7 +1091,165 @@ pub fn torrent_context_menu(hash: InfoHash, name: &str, position: Vec2) -> Callb
     Callback::from_fn_mut(cb)
 }
 
-pub fn quit_and_shutdown_daemon(siv: &mut Cursive) {
+/// Whatever middle-clicking a torrent is set to do.
+pub fn quick_action(hash: InfoHash, name: &str, state: TorrentState) -> Callback {
+    let name = Rc::<str>::from(name);
+    match crate::config::read().interface.middle_click {
+        QuickAction::Nothing => Callback::dummy(),
+        QuickAction::TogglePause if state == TorrentState::Paused => {
+            Callback::from_fn(wsbuf!(:resume_torrent, hash))
+        }
+        QuickAction::TogglePause => Callback::from_fn(wsbuf!(:pause_torrent, hash)),
+        QuickAction::Reannounce => Callback::from_fn(wsbuf!(:force_reannounce, &[hash])),
+        QuickAction::Recheck => Callback::from_fn(wsbuf!(:force_recheck, &[hash])),
+        QuickAction::Remove => {
+            Callback::from_fn(move |siv| remove_torrent_dialog(siv, hash, &name))
+        }
+    }
+}
+
+pub fn reannounce_visible(siv: &mut Cursive) {
+    let hashes = siv
+        .call_on_name("torrents", |v: &mut TorrentsView| v.visible_torrents())
+        .unwrap_or_default();
+
+    if !hashes.is_empty() {
+        wsbuf!(@siv; :force_reannounce, &hashes);
+    }
+}
+
+pub fn recheck_selected(siv: &mut Cursive) {
+    let hashes = siv
+        .call_on_name("torrents", |v: &mut TorrentsView| v.selected_torrents())
+        .unwrap_or_default();
+
+    if !hashes.is_empty() {
+        wsbuf!(@siv; :force_recheck, &hashes);
+    }
+}
+
+pub(crate) fn show_help(siv: &mut Cursive) {
+    let mut text = String::new();
+    for context in keys::Context::ALL.iter() {
+        let bindings = keys::bindings(*context);
+        if bindings.is_empty() {
+            continue;
+        }
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(context.title());
+        text.push('\n');
+
+        let width = bindings.iter().map(|(key, _)| key.chars().count()).max();
+        for (key, desc) in &bindings {
+            text.push_str(&format!("  {:w$}  {}\n", key, desc, w = width.unwrap_or(0)));
+        }
+    }
+
+    let dialog = Dialog::around(TextView::new(text).scrollable())
+        .title("Keybindings")
+        .dismiss_button("Close");
+    siv.add_layer(dialog);
+}
+
+pub(crate) fn show_bandwidth_history(siv: &mut Cursive) {
+    const WIDTH: usize = 72;
+
+    let buckets = crate::bandwidth::buckets(WIDTH);
+    let (down, down_peak) = crate::bandwidth::sparkline(buckets.iter().map(|b| b.0));
+    let (up, up_peak) = crate::bandwidth::sparkline(buckets.iter().map(|b| b.1));
+
+    let g = crate::util::glyphs::glyphs();
+    let peak = |rate| format!("peak {}/s", crate::util::fmt::bytes(rate));
+    let text = format!(
+        "{} {}\n{}\n\n{} {}\n{}\n\n{:<w$}{:>w$}",
+        g.down,
+        peak(down_peak),
+        down,
+        g.up,
+        peak(up_peak),
+        up,
+        "24h ago",
+        "now",
+        w = WIDTH / 2,
+    );
+
+    siv.add_layer(Dialog::info(text).title("Bandwidth History"));
+}
+
+pub(crate) fn toggle_detailed_rows(siv: &mut Cursive) {
+    let detailed = {
+        let mut cfg = crate::config::write();
+        cfg.interface.detailed_rows = !cfg.interface.detailed_rows;
+        cfg.save();
+        cfg.interface.detailed_rows
+    };
+    siv.call_on_name("torrents", |v: &mut TorrentsView| {
+        v.set_detailed_rows(detailed)
+    });
+}
+
+pub(crate) fn set_group_by(siv: &mut Cursive, group_by: GroupBy) {
+    {
+        let mut cfg = crate::config::write();
+        cfg.interface.group_by = group_by;
+        cfg.save();
+    }
+    siv.call_on_name("torrents", |v: &mut TorrentsView| v.set_group_by(group_by));
+}
+
+fn shutdown_daemon(siv: &mut Cursive) {
     wsbuf!(@siv; :shutdown);
-    siv.quit();
+    crate::shutdown::quit(siv);
+}
+
+pub fn quit_and_shutdown_daemon(siv: &mut Cursive) {
+    if !crate::config::read().interface.confirm_shutdown {
+        shutdown_daemon(siv);
+        return;
+    }
+
+    let host = siv
+        .user_data::<AppState>()
+        .unwrap()
+        .get()
+        .get_id()
+        .and_then(|id| {
+            let cfg = crate::config::read();
+            let host = cfg.connection_manager.hosts.get(&id)?;
+            Some(format!("{}:{}", host.address, host.port))
+        })
+        .unwrap_or_else(|| String::from("the daemon"));
+
+    let active = siv
+        .call_on_name("torrents", |v: &mut TorrentsView| v.active_count())
+        .unwrap_or(0);
+
+    let msg = format!(
+        "Shut down {}?\n{} torrent(s) are currently active.",
+        host, active,
+    );
+
+    let content = LinearLayout::vertical()
+        .child(TextView::new(msg))
+        .child(DummyView)
+        .child(LabeledCheckbox::new("Don't ask again").with_name("dont_ask"));
+
+    let dialog = Dialog::around(content)
+        .title("Quit and Shutdown Daemon")
+        .dismiss_button("Cancel")
+        .button("Shut down", |siv| {
+            let dont_ask = siv
+                .call_on_name("dont_ask", |v: &mut LabeledCheckbox| v.is_checked())
+                .unwrap_or(false);
+            if dont_ask {
+                let mut cfg = crate::config::write();
+                cfg.interface.confirm_shutdown = false;
+                cfg.save();
+            }
+            shutdown_daemon(siv);
+        });
+
+    siv.add_layer(dialog);
 }
@@ -0,0 +1,217 @@
+use async_trait::async_trait;
+use deluge_rpc::{Event, EventKind, InfoHash, Query, Session, TorrentState};
+use serde::Deserialize;
+use std::collections::HashSet;
+
+use crate::config;
+use crate::views::statusbar;
+use crate::views::thread::ViewThread;
+
+#[derive(Debug, Clone, Deserialize, Query)]
+struct TorrentName {
+    name: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Query)]
+struct TorrentHealth {
+    name: String,
+    state: TorrentState,
+    tracker_status: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Problem {
+    TorrentError,
+    TrackerError,
+}
+
+impl Problem {
+    fn summary(self) -> &'static str {
+        match self {
+            Self::TorrentError => "Torrent error",
+            Self::TrackerError => "Tracker error",
+        }
+    }
+}
+
+impl TorrentHealth {
+    fn problems(&self) -> Vec<Problem> {
+        let mut problems = Vec::new();
+        if matches!(self.state, TorrentState::Error) {
+            problems.push(Problem::TorrentError);
+        }
+        if self.tracker_status.starts_with("Error:") {
+            problems.push(Problem::TrackerError);
+        }
+        problems
+    }
+}
+
+fn bell() {
+    crate::backend::write_raw(b"\x07");
+}
+
+pub(crate) fn notify(desktop: bool, summary: &str, body: &str) {
+    if !desktop {
+        return bell();
+    }
+
+    let (summary, body) = (summary.to_owned(), body.to_owned());
+    // Talking to the notification daemon blocks, so keep it off the async workers.
+    tokio::task::spawn_blocking(move || {
+        let result = notify_rust::Notification::new()
+            .appname("dtui")
+            .summary(&summary)
+            .body(&body)
+            .show();
+        if result.is_err() {
+            bell();
+        }
+    });
+}
+
+/// Not a view, but it reacts to session events in exactly the same way as one.
+/// It also keeps an eye out for torrents running into errors, which it only mentions once
+/// per run-in, since the daemon keeps retrying (and reporting) failed announces.
+pub(crate) struct NotifierThread {
+    toasts: statusbar::Toasts,
+    // The torrents whose errors have already been mentioned.
+    errored: HashSet<InfoHash>,
+    tracker_errors: HashSet<InfoHash>,
+}
+
+impl NotifierThread {
+    pub(crate) fn new(toasts: statusbar::Toasts) -> Self {
+        Self {
+            toasts,
+            errored: HashSet::new(),
+            tracker_errors: HashSet::new(),
+        }
+    }
+
+    fn mentioned(&mut self, problem: Problem) -> &mut HashSet<InfoHash> {
+        match problem {
+            Problem::TorrentError => &mut self.errored,
+            Problem::TrackerError => &mut self.tracker_errors,
+        }
+    }
+
+    /// Mentions a problem, unless it's already been mentioned since it started.
+    fn report(&mut self, hash: InfoHash, name: &str, problem: Problem) {
+        if !self.mentioned(problem).insert(hash) {
+            return;
+        }
+
+        let cfg = config::read().notifications.clone();
+        if !cfg.error_alerts || cfg.muted.contains(&hash.to_string()) {
+            return;
+        }
+
+        self.toasts.post(format!("{}: {}", problem.summary(), name));
+
+        let wanted = match problem {
+            Problem::TorrentError => cfg.torrent_error,
+            Problem::TrackerError => cfg.tracker_error,
+        };
+        if cfg.enabled && wanted {
+            notify(cfg.desktop, problem.summary(), name);
+        }
+    }
+
+    /// Looks up the name of a torrent that just ran into a problem, and mentions it.
+    async fn report_hash(
+        &mut self,
+        session: &Session,
+        hash: InfoHash,
+        problem: Problem,
+    ) -> deluge_rpc::Result<()> {
+        if self.mentioned(problem).contains(&hash) {
+            return Ok(());
+        }
+        // It may well have been removed in the meantime, which is nothing to mention.
+        if let Ok(torrent) = session.get_torrent_status::<TorrentName>(hash).await {
+            self.report(hash, &torrent.name, problem);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ViewThread for NotifierThread {
+    fn event_interest(&self) -> HashSet<EventKind> {
+        deluge_rpc::events![
+            TorrentAdded,
+            TorrentFinished,
+            TorrentRemoved,
+            TorrentStateChanged,
+            TorrentTrackerStatus
+        ]
+    }
+
+    // Whatever was wrong before connecting is still worth hearing about, so this looks once.
+    // From then on, the events say which torrents to look at.
+    async fn reload(&mut self, session: &Session) -> deluge_rpc::Result<()> {
+        self.clear();
+        if !config::read().notifications.error_alerts {
+            return Ok(());
+        }
+
+        let torrents = session.get_torrents_status::<TorrentHealth>(None).await?;
+        for (hash, torrent) in torrents {
+            for problem in torrent.problems() {
+                self.report(hash, &torrent.name, problem);
+            }
+        }
+        Ok(())
+    }
+
+    async fn update(&mut self, _: &Session) -> deluge_rpc::Result<()> {
+        Ok(())
+    }
+
+    async fn on_event(&mut self, session: &Session, event: Event) -> deluge_rpc::Result<()> {
+        match event {
+            Event::TorrentStateChanged(hash, TorrentState::Error) => {
+                return self.report_hash(session, hash, Problem::TorrentError).await;
+            }
+            Event::TorrentStateChanged(hash, _) => {
+                self.errored.remove(&hash);
+                return Ok(());
+            }
+            Event::TorrentTrackerStatus(hash, status) if status.starts_with("Error:") => {
+                return self.report_hash(session, hash, Problem::TrackerError).await;
+            }
+            Event::TorrentTrackerStatus(hash, _) => {
+                self.tracker_errors.remove(&hash);
+                return Ok(());
+            }
+            Event::TorrentRemoved(hash) => {
+                self.errored.remove(&hash);
+                self.tracker_errors.remove(&hash);
+                return Ok(());
+            }
+            _ => (),
+        }
+
+        let cfg = config::read().notifications.clone();
+        if !cfg.enabled {
+            return Ok(());
+        }
+
+        let (hash, summary) = match event {
+            Event::TorrentFinished(hash) if cfg.torrent_finished => (hash, "Torrent finished"),
+            Event::TorrentAdded(hash, false) if cfg.torrent_added => (hash, "Torrent added"),
+            _ => return Ok(()),
+        };
+
+        let name = session.get_torrent_status::<TorrentName>(hash).await?.name;
+        notify(cfg.desktop, summary, &name);
+
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.errored.clear();
+        self.tracker_errors.clear();
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::HashSet;
+use std::fmt::{self, Display, Formatter};
+use std::future::Future;
+use std::sync::Mutex;
+use tokio::time::{self, Duration};
+use uuid::Uuid;
+
+use crate::config::{self, Host};
+
+pub(crate) const CONNECT_TIMEOUT: u64 = 10;
+pub(crate) const REQUEST_TIMEOUT: u64 = 30;
+pub(crate) const RETRIES: u64 = 2;
+
+/// How long to wait on the daemon, and how many more times to try before giving up on it.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Policy {
+    pub connect_timeout: Duration,
+    pub request_timeout: Duration,
+    pub retries: u64,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(CONNECT_TIMEOUT),
+            request_timeout: Duration::from_secs(REQUEST_TIMEOUT),
+            retries: RETRIES,
+        }
+    }
+}
+
+impl From<&Host> for Policy {
+    fn from(host: &Host) -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(host.connect_timeout.unwrap_or(CONNECT_TIMEOUT)),
+            request_timeout: Duration::from_secs(host.request_timeout.unwrap_or(REQUEST_TIMEOUT)),
+            retries: host.retries.unwrap_or(RETRIES),
+        }
+    }
+}
+
+impl Policy {
+    pub fn for_host(id: Option<Uuid>) -> Self {
+        let cfg = config::read();
+        id.and_then(|id| cfg.connection_manager.hosts.get(&id))
+            .map_or_else(Self::default, Self::from)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum Failure {
+    Rpc(deluge_rpc::Error),
+    NotResponding,
+}
+
+impl From<deluge_rpc::Error> for Failure {
+    fn from(e: deluge_rpc::Error) -> Self {
+        Self::Rpc(e)
+    }
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Rpc(e) => write!(f, "The daemon returned an error: {:?}", e),
+            Self::NotResponding => f.write_str("The daemon is not responding."),
+        }
+    }
+}
+
+/// Waits on a request for as long as the policy allows. It isn't retried,
+/// since the daemon may have acted on it even if the response never arrived.
+pub(crate) async fn request<T>(
+    policy: &Policy,
+    fut: impl Future<Output = deluge_rpc::Result<T>>,
+) -> Result<T, Failure> {
+    match time::timeout(policy.request_timeout, fut).await {
+        Ok(result) => Ok(result?),
+        Err(_) => Err(Failure::NotResponding),
+    }
+}
+
+/// Which of the threads polling a session last had a request to it time out,
+/// for showing in the status bar. Each thread only clears its own mark,
+/// so that one getting answers can't hide another that isn't.
+#[derive(Debug, Default)]
+pub(crate) struct Responsiveness {
+    stalled: Mutex<HashSet<&'static str>>,
+}
+
+impl Responsiveness {
+    pub fn record(&self, thread: &'static str, responding: bool) {
+        let mut stalled = self.stalled.lock().unwrap();
+        if responding {
+            stalled.remove(thread);
+        } else {
+            stalled.insert(thread);
+        }
+    }
+
+    pub fn is_responding(&self) -> bool {
+        self.stalled.lock().unwrap().is_empty()
+    }
+}
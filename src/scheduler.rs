@@ -0,0 +1,195 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Local, NaiveTime, Weekday};
+use deluge_rpc::{Query, Session};
+use serde::Deserialize;
+use std::collections::HashMap;
+use tokio::sync::watch;
+use tokio::{task, time};
+use uuid::Uuid;
+
+use crate::config::{self, ScheduleAction, ScheduleEffect, ScheduleRule};
+use crate::views::thread::ViewThread;
+use crate::visibility::Visibility;
+use crate::SessionHandle;
+
+#[derive(Debug, Clone, Copy, Deserialize, Query)]
+struct Limits {
+    max_download_speed: f64,
+    max_upload_speed: f64,
+}
+
+fn runs_on(rule: &ScheduleRule, day: Weekday) -> bool {
+    rule.days.is_empty()
+        || rule
+            .days
+            .iter()
+            .any(|d| d.parse::<Weekday>().map_or(false, |d| d == day))
+}
+
+fn is_active(rule: &ScheduleRule, now: DateTime<Local>) -> bool {
+    let parse = |s: &str| NaiveTime::parse_from_str(s.trim(), "%H:%M").ok();
+    let (start, end) = match (parse(&rule.start), parse(&rule.end)) {
+        (Some(start), Some(end)) => (start, end),
+        _ => return false,
+    };
+
+    let time = now.time();
+    let today = now.weekday();
+    if start <= end {
+        start <= time && time < end && runs_on(rule, today)
+    } else if time >= start {
+        runs_on(rule, today)
+    } else if time < end {
+        // This is the tail end of a window that started yesterday.
+        runs_on(rule, today.pred())
+    } else {
+        false
+    }
+}
+
+async fn set_limits(session: &Session, download: f64, upload: f64) -> deluge_rpc::Result<()> {
+    let mut config = HashMap::new();
+    config.insert("max_download_speed", serde_json::Value::from(download));
+    config.insert("max_upload_speed", serde_json::Value::from(upload));
+    session.set_config(&config).await
+}
+
+/// Pauses or throttles the whole session during the hours in the `scheduler` config section.
+/// Like the notifier, it's not a view, but it gets the session the same way one would.
+pub(crate) struct SchedulerThread {
+    session_recv: watch::Receiver<SessionHandle>,
+    host: Option<Uuid>,
+    // Mirrors the host's entry in the config, which is what outlives reconnects and restarts,
+    // so that throttled limits never get mistaken for the ones to go back to.
+    in_effect: Option<ScheduleEffect>,
+    wanted: Option<ScheduleAction>,
+}
+
+impl SchedulerThread {
+    pub fn new(session_recv: watch::Receiver<SessionHandle>) -> Self {
+        Self {
+            session_recv,
+            host: None,
+            in_effect: None,
+            wanted: None,
+        }
+    }
+
+    fn remember(&mut self, in_effect: Option<ScheduleEffect>) {
+        self.in_effect = in_effect;
+        let host = match self.host {
+            Some(host) => host,
+            None => return,
+        };
+
+        let mut cfg = config::write();
+        let saved = &mut cfg.scheduler.in_effect;
+        match in_effect {
+            Some(in_effect) => saved.insert(host, in_effect),
+            None => saved.remove(&host),
+        };
+        cfg.save();
+    }
+
+    async fn undo(&mut self, session: &Session) -> deluge_rpc::Result<()> {
+        let in_effect = match self.in_effect {
+            Some(in_effect) => in_effect,
+            None => return Ok(()),
+        };
+        match in_effect.action {
+            // This also resumes a session that was paused before the rule began, but
+            // there's no telling the two apart.
+            ScheduleAction::Pause => session.resume_session().await?,
+            ScheduleAction::Limit { .. } => {
+                if let Some((download, upload)) = in_effect.saved_limits {
+                    set_limits(session, download, upload).await?;
+                }
+            }
+        }
+        task::block_in_place(|| self.remember(None));
+        Ok(())
+    }
+
+    async fn apply(&mut self, session: &Session, action: ScheduleAction) -> deluge_rpc::Result<()> {
+        let saved_limits = match action {
+            ScheduleAction::Pause => None,
+            ScheduleAction::Limit { .. } => {
+                let limits = session.get_config_values::<Limits>().await?;
+                Some((limits.max_download_speed, limits.max_upload_speed))
+            }
+        };
+        // Remembered first, so that quitting partway through still leaves a way back.
+        let in_effect = ScheduleEffect {
+            action,
+            saved_limits,
+        };
+        task::block_in_place(|| self.remember(Some(in_effect)));
+
+        let result = match action {
+            ScheduleAction::Pause => session.pause_session().await,
+            ScheduleAction::Limit { download, upload } => {
+                set_limits(session, download, upload).await
+            }
+        };
+        if result.is_err() {
+            task::block_in_place(|| self.remember(None));
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl ViewThread for SchedulerThread {
+    // Picks up whatever was left in effect on this host, whether by an earlier connection
+    // or an earlier run of dtui, so that it gets undone if its rule is over.
+    async fn reload(&mut self, session: &Session) -> deluge_rpc::Result<()> {
+        self.host = self.session_recv.borrow().get_id();
+        self.in_effect = self
+            .host
+            .and_then(|host| config::read().scheduler.in_effect.get(&host).copied());
+        self.update(session).await
+    }
+
+    async fn update(&mut self, _session: &Session) -> deluge_rpc::Result<()> {
+        self.wanted = {
+            let cfg = config::read();
+            let now = Local::now();
+            let scheduler = &cfg.scheduler;
+            scheduler
+                .rules
+                .iter()
+                .rev()
+                .find(|rule| scheduler.enabled && is_active(rule, now))
+                .map(|rule| rule.action)
+        };
+        Ok(())
+    }
+
+    async fn send_changes(&mut self, session: &Session) -> deluge_rpc::Result<()> {
+        if self.wanted != self.in_effect.map(|in_effect| in_effect.action) {
+            self.undo(session).await?;
+            if let Some(action) = self.wanted {
+                self.apply(session, action).await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn tick(&self) -> time::Duration {
+        time::Duration::from_secs(30)
+    }
+
+    // The schedule doesn't care what's on screen.
+    fn should_poll(&self, _: &Visibility) -> bool {
+        true
+    }
+
+    // Whatever was applied stays applied on the daemon, even after quitting, so that a rule
+    // like "pause overnight" keeps working with dtui closed. It stays remembered in the config
+    // until the next connection to that host, which undoes it if it's no longer due.
+    fn clear(&mut self) {
+        self.host = None;
+        self.in_effect = None;
+        self.wanted = None;
+    }
+}
@@ -0,0 +1,73 @@
+use cursive::views::Dialog;
+use cursive::Cursive;
+use futures::executor::block_on;
+use once_cell::sync::Lazy;
+use std::future::Future;
+use std::sync::Mutex;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time;
+
+// How long to wait for background tasks to finish whatever they're in the middle of.
+const GRACE_PERIOD: time::Duration = time::Duration::from_secs(2);
+
+type Task = (&'static str, JoinHandle<deluge_rpc::Result<()>>);
+
+static TASKS: Lazy<Mutex<Vec<Task>>> = Lazy::new(Default::default);
+static SIGNAL: Lazy<(watch::Sender<bool>, watch::Receiver<bool>)> =
+    Lazy::new(|| watch::channel(false));
+
+/// Spawns a background task that gets waited on when quitting.
+pub(crate) fn spawn<F>(name: &'static str, fut: F)
+where
+    F: Future<Output = deluge_rpc::Result<()>> + Send + 'static,
+{
+    let handle = tokio::spawn(fut);
+    TASKS.lock().unwrap().push((name, handle));
+}
+
+/// Resolves once it's time for background tasks to stop.
+pub(crate) async fn signaled() {
+    let mut recv = SIGNAL.1.clone();
+    while !*recv.borrow() {
+        if recv.changed().await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Tells every task to stop, then waits briefly for them, collecting their errors.
+fn join_all() -> Vec<String> {
+    SIGNAL.0.send(true).unwrap();
+    let tasks = std::mem::take(&mut *TASKS.lock().unwrap());
+
+    block_on(async {
+        let deadline = time::Instant::now() + GRACE_PERIOD;
+        let mut errors = Vec::new();
+        for (name, handle) in tasks {
+            match time::timeout_at(deadline, handle).await {
+                Ok(Ok(Ok(()))) => (),
+                Ok(Ok(Err(e))) => errors.push(format!("{}: {:?}", name, e)),
+                Ok(Err(e)) => errors.push(format!("{}: {}", name, e)),
+                Err(_) => errors.push(format!("{}: didn't finish in time", name)),
+            }
+        }
+        errors
+    })
+}
+
+/// Quits once the background tasks have wound down, first showing any errors they ran into.
+pub(crate) fn quit(siv: &mut Cursive) {
+    crate::views::torrents::save_last_session(siv);
+
+    let errors = join_all();
+    if errors.is_empty() {
+        siv.quit();
+        return;
+    }
+
+    let dialog = Dialog::text(errors.join("\n"))
+        .title("Errors During Shutdown")
+        .button("Quit", Cursive::quit);
+    siv.add_layer(dialog);
+}
@@ -1,4 +1,50 @@
-use cursive::theme::{Color::Rgb, Palette, PaletteColor::*, Theme};
+use cursive::theme::{
+    BaseColor::{self, *},
+    Color::{self, Dark, Light, Rgb, RgbLowRes},
+    Palette,
+    PaletteColor::{self, *},
+    Theme,
+};
+
+use crate::config::{self, ColorSupport, ThemeName};
+
+const PALETTE_COLORS: [PaletteColor; 11] = [
+    Background,
+    Shadow,
+    View,
+    Primary,
+    Secondary,
+    Tertiary,
+    TitlePrimary,
+    TitleSecondary,
+    Highlight,
+    HighlightInactive,
+    HighlightText,
+];
+
+const BASE_COLORS: [BaseColor; 8] = [Black, Red, Green, Yellow, Blue, Magenta, Cyan, White];
+
+// What the 16 standard colors usually look like (xterm's defaults), dark then light.
+const DARK_RGB: [(u8, u8, u8); 8] = [
+    (0x00, 0x00, 0x00),
+    (0xCD, 0x00, 0x00),
+    (0x00, 0xCD, 0x00),
+    (0xCD, 0xCD, 0x00),
+    (0x00, 0x00, 0xEE),
+    (0xCD, 0x00, 0xCD),
+    (0x00, 0xCD, 0xCD),
+    (0xE5, 0xE5, 0xE5),
+];
+const LIGHT_RGB: [(u8, u8, u8); 8] = [
+    (0x7F, 0x7F, 0x7F),
+    (0xFF, 0x00, 0x00),
+    (0x00, 0xFF, 0x00),
+    (0xFF, 0xFF, 0x00),
+    (0x5C, 0x5C, 0xFF),
+    (0xFF, 0x00, 0xFF),
+    (0x00, 0xFF, 0xFF),
+    (0xFF, 0xFF, 0xFF),
+];
 
 pub fn dracula() -> Theme {
     let mut palette = Palette::default();
@@ -19,3 +65,99 @@ pub fn dracula() -> Theme {
         ..Theme::default()
     }
 }
+
+/// Bright text on black, using only the 16 standard colors.
+pub fn high_contrast() -> Theme {
+    let mut palette = Palette::default();
+
+    palette[Background] = Dark(Black);
+    palette[View] = Dark(Black);
+    palette[Primary] = Light(White);
+    palette[Secondary] = Light(Cyan);
+    palette[Tertiary] = Light(White);
+    palette[Shadow] = Dark(Black);
+    palette[TitlePrimary] = Light(Yellow);
+    palette[TitleSecondary] = Light(Cyan);
+    palette[Highlight] = Light(White);
+    palette[HighlightInactive] = Dark(Blue);
+    palette[HighlightText] = Dark(Black);
+
+    Theme {
+        shadow: false,
+        palette,
+        ..Theme::default()
+    }
+}
+
+/// Guesses how many colors the terminal can show, going by `COLORTERM` and `TERM`.
+fn detect_color_support() -> ColorSupport {
+    let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+    if colorterm == "truecolor" || colorterm == "24bit" {
+        return ColorSupport::TrueColor;
+    }
+
+    match std::env::var("TERM") {
+        Ok(term) if term.contains("256color") => ColorSupport::Colors256,
+        Ok(_) => ColorSupport::Colors16,
+        // Windows consoles don't set TERM, and the modern ones all do truecolor.
+        Err(_) if cfg!(windows) => ColorSupport::TrueColor,
+        Err(_) => ColorSupport::Colors16,
+    }
+}
+
+fn nearest_base_color(r: u8, g: u8, b: u8) -> Color {
+    let distance = |(r2, g2, b2): (u8, u8, u8)| {
+        let d = |x: u8, y: u8| (i32::from(x) - i32::from(y)).pow(2);
+        d(r, r2) + d(g, g2) + d(b, b2)
+    };
+
+    let dark = BASE_COLORS
+        .iter()
+        .zip(&DARK_RGB)
+        .map(|(c, rgb)| (Dark(*c), *rgb));
+    let light = BASE_COLORS
+        .iter()
+        .zip(&LIGHT_RGB)
+        .map(|(c, rgb)| (Light(*c), *rgb));
+    dark.chain(light)
+        .min_by_key(|(_, rgb)| distance(*rgb))
+        .map(|(c, _)| c)
+        .unwrap()
+}
+
+/// Approximates a color with the ones the terminal can actually show.
+fn downgrade(color: Color, support: ColorSupport) -> Color {
+    match (color, support) {
+        (Rgb(r, g, b), ColorSupport::Colors256) => {
+            let scale = |x: u8| ((u16::from(x) * 5 + 127) / 255) as u8;
+            RgbLowRes(scale(r), scale(g), scale(b))
+        }
+        (Rgb(r, g, b), ColorSupport::Colors16) => nearest_base_color(r, g, b),
+        (RgbLowRes(r, g, b), ColorSupport::Colors16) => nearest_base_color(r * 51, g * 51, b * 51),
+        (color, _) => color,
+    }
+}
+
+/// The configured theme, adjusted for what the terminal supports.
+pub fn current() -> Theme {
+    let (name, support) = {
+        let cfg = &config::read().interface;
+        (cfg.theme, cfg.colors)
+    };
+
+    let mut theme = match name {
+        ThemeName::Dracula => dracula(),
+        ThemeName::HighContrast => high_contrast(),
+    };
+
+    let support = match support {
+        ColorSupport::Auto => detect_color_support(),
+        support => support,
+    };
+
+    for color in &PALETTE_COLORS {
+        theme.palette[*color] = downgrade(theme.palette[*color], support);
+    }
+
+    theme
+}
@@ -1,7 +1,74 @@
 pub mod eventual;
 pub mod fmt;
+pub mod glyphs;
 pub mod simple_slab;
 
+use std::cmp::Ordering;
+
+/// Compares names case-insensitively, with runs of digits compared by value,
+/// so that "Episode 2" comes before "Episode 10".
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_chars, mut b_chars) = (a.chars().peekable(), b.chars().peekable());
+
+    loop {
+        let (x, y) = match (a_chars.peek(), b_chars.peek()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => (*x, *y),
+        };
+
+        let ord = if x.is_ascii_digit() && y.is_ascii_digit() {
+            let take_number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                let mut digits = String::new();
+                while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                    digits.push(c);
+                }
+                digits.trim_start_matches('0').to_owned()
+            };
+            let (m, n) = (take_number(&mut a_chars), take_number(&mut b_chars));
+            m.len().cmp(&n.len()).then_with(|| m.cmp(&n))
+        } else {
+            a_chars.next();
+            b_chars.next();
+            x.to_lowercase().cmp(y.to_lowercase())
+        };
+
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+}
+
+/// Compares names the way the user has configured.
+pub fn compare_names(a: &str, b: &str, natural: bool) -> Ordering {
+    if natural {
+        natural_cmp(a, b)
+    } else {
+        a.cmp(b)
+    }
+}
+
+/// An exponential moving average, weighted to roughly cover the last few samples.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Ema(Option<f64>);
+
+impl Ema {
+    pub fn update(&mut self, sample: u64, samples: usize) -> u64 {
+        let alpha = 2.0 / (samples.max(1) as f64 + 1.0);
+        let value = match self.0 {
+            Some(prev) => prev + alpha * (sample as f64 - prev),
+            None => sample as f64,
+        };
+        self.0 = Some(value);
+        self.get()
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.map_or(0, |v| v.round() as u64)
+    }
+}
+
 pub const fn digit_width(mut n: u64) -> usize {
     if n == 0 {
         return 1;
@@ -1,16 +1,64 @@
-use std::fmt::Display;
+use std::fmt::{Display, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 use bytesize::ByteSize;
+use chrono::{Local, TimeZone};
 use pretty_dtoa::FmtFloatConfig;
 
+// Copies of the byte format preferences, since sizes get formatted for every cell drawn.
+static BINARY_UNITS: AtomicBool = AtomicBool::new(true);
+// `usize::MAX` for however many places bytesize picks.
+static BYTE_DECIMALS: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// Takes note of the byte format preferences. Called whenever the config is loaded or saved.
+pub fn set_byte_format(binary: bool, decimals: Option<usize>) {
+    BINARY_UNITS.store(binary, Ordering::Relaxed);
+    BYTE_DECIMALS.store(decimals.unwrap_or(usize::MAX), Ordering::Relaxed);
+}
+
+fn byte_format() -> (bool, Option<usize>) {
+    let decimals = BYTE_DECIMALS.load(Ordering::Relaxed);
+    (
+        BINARY_UNITS.load(Ordering::Relaxed),
+        Some(decimals).filter(|&d| d != usize::MAX),
+    )
+}
+
+fn fixed_bytes(amt: u64, binary: bool, places: usize) -> String {
+    let unit = if binary { 1024.0 } else { 1000.0 };
+    let mut val = amt as f64;
+    let mut prefixes = "KMGTPE".chars();
+    let mut prefix = None;
+
+    while val >= unit {
+        match prefixes.next() {
+            Some(p) => prefix = Some(p),
+            None => break,
+        }
+        val /= unit;
+    }
+
+    match prefix {
+        None => format!("{} B", amt),
+        Some(p) if binary => format!("{:.*} {}iB", places, val, p),
+        Some(p) => format!("{:.*} {}B", places, val, p),
+    }
+}
+
 pub fn bytes(amt: u64) -> String {
-    ByteSize(amt).to_string_as(true)
+    match byte_format() {
+        (binary, None) => ByteSize(amt).to_string_as(binary),
+        (binary, Some(places)) => fixed_bytes(amt, binary, places),
+    }
 }
 
 pub fn bytes_limit(amt: f64) -> String {
-    ByteSize((amt * 1024.0) as u64)
-        .to_string_as(true)
-        .replace(".0", "")
+    // Deluge expresses limits in KiB regardless of how they're displayed.
+    let amt = (amt * 1024.0) as u64;
+    match byte_format() {
+        (binary, None) => ByteSize(amt).to_string_as(binary).replace(".0", ""),
+        (binary, Some(places)) => fixed_bytes(amt, binary, places),
+    }
 }
 
 pub fn speed_pair(val: u64, max: f64) -> String {
@@ -28,6 +76,16 @@ pub fn pair<T, U: Display, F: FnMut(T) -> U>(mut f: F, a: T, b: Option<T>) -> St
     }
 }
 
+/// Shortens `s` to at most `width` characters, marking the cut with an ellipsis.
+pub fn truncate(s: &str, width: usize) -> String {
+    if s.chars().count() <= width {
+        return s.to_owned();
+    }
+    let mut out: String = s.chars().take(width.saturating_sub(1)).collect();
+    out.push_str(super::glyphs::glyphs().ellipsis);
+    out
+}
+
 pub fn percentage(val: f32) -> String {
     if val == 0.0 {
         return String::from("0");
@@ -97,7 +155,31 @@ pub fn time_or_dash(secs: i64) -> String {
 }
 
 pub fn date(t: i64) -> String {
-    epochs::unix(t).unwrap().to_string()
+    let time = match Local.timestamp_opt(t, 0).single() {
+        Some(time) => time,
+        None => return String::from("???"),
+    };
+
+    let format = crate::config::read().interface.date_format.clone();
+    let mut s = String::new();
+    // chrono reports bad format strings as a formatting error rather than up front.
+    if write!(s, "{}", time.format(&format)).is_err() {
+        s.clear();
+        write!(s, "{}", time.format(crate::config::DEFAULT_DATE_FORMAT)).unwrap();
+    }
+    s
+}
+
+/// Formats a timestamp as how long ago it was, e.g. "3d 4h ago".
+pub fn relative_date(t: i64) -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64);
+
+    match now - t {
+        secs if secs <= 0 => String::from("now"),
+        secs => duration(secs as u64) + " ago",
+    }
 }
 
 pub fn date_or_dash(t: i64) -> String {
@@ -0,0 +1,134 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The decorative symbols drawn throughout the UI, with ASCII stand-ins for terminals
+/// and fonts that can't render the fancy ones.
+pub struct Glyphs {
+    pub collapsed: &'static str,
+    pub expanded: &'static str,
+    pub active: &'static str,
+    pub inactive: &'static str,
+    pub bullet: &'static str,
+    pub peers: &'static str,
+    pub down: &'static str,
+    pub up: &'static str,
+    pub overhead: &'static str,
+    pub disk: &'static str,
+    pub check: &'static str,
+    pub cross: &'static str,
+    pub warning: &'static str,
+    pub infinity: &'static str,
+    pub times: &'static str,
+    pub ellipsis: &'static str,
+    pub sort_ascending: &'static str,
+    pub sort_descending: &'static str,
+    pub secondary_ascending: &'static str,
+    pub secondary_descending: &'static str,
+    pub gauge_full: &'static str,
+    pub gauge_empty: &'static str,
+    // Eighths of a cell, from empty to nearly full.
+    pub partial_blocks: [&'static str; 8],
+    // Eighths of a cell's height, from nearly empty to full, for graphs.
+    pub bars: [&'static str; 8],
+    pub hline: &'static str,
+    pub vline: &'static str,
+    pub cross_line: &'static str,
+    pub tee_down: &'static str,
+    pub tee_up: &'static str,
+    pub title_left: &'static str,
+    pub title_right: &'static str,
+    pub hline_start: &'static str,
+    pub hline_end: &'static str,
+    // Top left, top right, bottom left, bottom right.
+    pub corners: [&'static str; 4],
+}
+
+const UNICODE: Glyphs = Glyphs {
+    collapsed: "▸",
+    expanded: "▾",
+    active: "●",
+    inactive: "◌",
+    bullet: "•",
+    peers: "⇄",
+    down: "↓",
+    up: "↑",
+    overhead: "⇵",
+    disk: "💾",
+    check: "✓",
+    cross: "✗",
+    warning: "⚠",
+    infinity: "∞",
+    times: "×",
+    ellipsis: "…",
+    sort_ascending: "▲",
+    sort_descending: "▼",
+    secondary_ascending: "▵",
+    secondary_descending: "▿",
+    gauge_full: "█",
+    gauge_empty: "░",
+    partial_blocks: [" ", "▏", "▎", "▍", "▌", "▋", "▊", "▉"],
+    bars: ["▁", "▂", "▃", "▄", "▅", "▆", "▇", "█"],
+    hline: "─",
+    vline: "│",
+    cross_line: "┼",
+    tee_down: "┬",
+    tee_up: "┴",
+    title_left: "┤",
+    title_right: "├",
+    hline_start: "╶",
+    hline_end: "╴",
+    corners: ["┌", "┐", "└", "┘"],
+};
+
+const ASCII: Glyphs = Glyphs {
+    collapsed: ">",
+    expanded: "v",
+    active: "*",
+    inactive: "o",
+    bullet: "|",
+    peers: "Peers:",
+    down: "D:",
+    up: "U:",
+    overhead: "Overhead:",
+    disk: "Free:",
+    check: "yes",
+    cross: "no",
+    warning: "!",
+    infinity: "inf",
+    times: "x",
+    ellipsis: "~",
+    sort_ascending: "^",
+    sort_descending: "v",
+    secondary_ascending: "'",
+    secondary_descending: ",",
+    gauge_full: "#",
+    gauge_empty: ".",
+    partial_blocks: [" "; 8],
+    bars: ["_", ".", "-", "~", "=", "*", "%", "#"],
+    hline: "-",
+    vline: "|",
+    cross_line: "+",
+    tee_down: "+",
+    tee_up: "+",
+    title_left: "[",
+    title_right: "]",
+    hline_start: "-",
+    hline_end: "-",
+    corners: ["+", "+", "+", "+"],
+};
+
+// A copy of the preference, since glyphs get looked up for every cell drawn.
+static ASCII_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Takes note of the preference. Called whenever the config is loaded or saved.
+pub fn set_ascii_only(ascii_only: bool) {
+    ASCII_ONLY.store(ascii_only, Ordering::Relaxed);
+}
+
+/// The glyphs to draw with, according to the user's preference.
+pub fn glyphs() -> &'static Glyphs {
+    if ASCII_ONLY.load(Ordering::Relaxed) {
+        &ASCII
+    } else {
+        &UNICODE
+    }
+}
@@ -3,6 +3,7 @@
 // real Slab, there is no need to keep track of vacant slots, and since a SimpleSlab cannot be
 // "sparse", a large, mostly-empty SimpleSlab is not slow to iterate.
 
+use std::hash::{Hash, Hasher};
 use std::marker::PhantomData;
 use std::ops::{Index, IndexMut};
 
@@ -44,6 +45,12 @@ impl<T> PartialEq for SlabKey<T> {
 
 impl<T> Eq for SlabKey<T> {}
 
+impl<T> Hash for SlabKey<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
 impl<T> PartialEq<usize> for SlabKey<T> {
     fn eq(&self, other: &usize) -> bool {
         self.0 == *other
@@ -7,12 +7,16 @@ pub(crate) mod table;
 pub(crate) mod filters;
 pub(crate) mod statusbar;
 pub(crate) mod torrents;
+pub(crate) mod workspaces;
 
 pub(crate) mod connection_manager;
 pub(crate) mod edit_host;
 pub(crate) mod labeled_checkbox;
 pub(crate) mod linear_panel;
+pub(crate) mod path_edit;
+pub(crate) mod preferences;
 pub(crate) mod remove_torrent;
+pub(crate) mod scroll;
 pub(crate) mod spin;
 pub(crate) mod static_linear_layout;
 
@@ -2,27 +2,31 @@ use std::cell::Cell;
 use std::cmp::{Ordering, PartialEq};
 use std::rc::Rc;
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
 use super::{
     edit_host::EditHostView,
     labeled_checkbox::LabeledCheckbox,
     static_linear_layout::StaticLinearLayout,
-    table::{TableCallback, TableView, TableViewData},
+    table::{draw_cells, TableCallback, TableView, TableViewData},
 };
 use crate::config;
-use crate::form::Form;
-use crate::util::eventual::Eventual;
+use crate::form::{self, Form};
+use crate::legacy;
+use crate::rpc::Policy;
+use crate::util::{self, eventual::Eventual, glyphs::glyphs};
 use crate::SessionHandle;
 
 use tokio::sync::oneshot;
-use tokio::task;
+use tokio::{task, time};
 
 use deluge_rpc::Session;
 
 use cursive::{
     event::Callback,
+    theme::Effect,
     view::ViewWrapper,
-    views::{Button, DummyView, Panel},
+    views::{Button, Dialog, DummyView, Panel},
     Cursive, Printer,
 };
 use uuid::Uuid;
@@ -34,6 +38,7 @@ pub(crate) enum Column {
     Status,
     Host,
     Version,
+    Latency,
 }
 impl AsRef<str> for Column {
     fn as_ref(&self) -> &'static str {
@@ -41,9 +46,15 @@ impl AsRef<str> for Column {
             Self::Status => "Status",
             Self::Host => "Host",
             Self::Version => "Version",
+            Self::Latency => "Latency",
         }
     }
 }
+impl Default for Column {
+    fn default() -> Self {
+        Self::Host
+    }
+}
 
 pub(crate) struct Connection {
     address: String,
@@ -51,7 +62,10 @@ pub(crate) struct Connection {
     username: String,
     password: String, // ¯\_(ツ)_/¯
     version: Eventual<String>,
+    // How long the daemon took to answer a request, once connected.
+    latency: Eventual<Duration>,
     session: Eventual<Arc<Session>>,
+    group: Option<String>,
 }
 
 // TODO: helper EqByKey trait in util?
@@ -59,7 +73,17 @@ impl Connection {
     fn new(host: &config::Host) -> Self {
         let (session, ses_tx) = Eventual::new();
         let (version, ver_tx) = Eventual::new();
-        let fut = connect(host.address.clone(), host.port, ses_tx, ver_tx);
+        let (latency, lat_tx) = Eventual::new();
+        let timeout = Policy::from(host).connect_timeout;
+        let fut = connect(
+            host.address.clone(),
+            host.port,
+            host.protocol,
+            timeout,
+            ses_tx,
+            ver_tx,
+            lat_tx,
+        );
         task::spawn(fut);
 
         Self {
@@ -68,18 +92,25 @@ impl Connection {
             username: host.username.clone(),
             password: host.password.clone(),
             version,
+            latency,
             session,
+            group: host.group.clone(),
         }
     }
 
     fn existing(host: &config::Host, ses: Arc<Session>) -> Self {
         let (version, mut ver_tx) = Eventual::new();
+        let (latency, lat_tx) = Eventual::new();
         let session = Eventual::ready(ses.clone());
 
         let fut = async move {
+            let start = Instant::now();
             tokio::select! {
                 result = ses.daemon_info() => match result {
-                    Ok(ver) => ver_tx.send(ver).unwrap_or(()),
+                    Ok(ver) => {
+                        lat_tx.send(start.elapsed()).unwrap_or(());
+                        ver_tx.send(ver).unwrap_or(());
+                    }
                     Err(_) => (),
                 },
                 _ = ver_tx.closed() => (),
@@ -93,7 +124,9 @@ impl Connection {
             username: host.username.clone(),
             password: host.password.clone(),
             version,
+            latency,
             session,
+            group: host.group.clone(),
         }
     }
 
@@ -108,61 +141,250 @@ impl PartialEq<Self> for Connection {
     }
 }
 
+/// A line of the connection table: either a host, or the header of a group of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum ConnectionRow {
+    Host(Uuid),
+    Group(usize), // an index into ConnectionTableData::groups
+}
+
+struct Group {
+    name: String,
+    collapsed: bool,
+    count: usize,
+}
+
 #[derive(Default)]
 pub(crate) struct ConnectionTableData {
-    rows: Vec<Uuid>,
+    // Every host, in order. Ungrouped ones come first, then each group alphabetically.
+    hosts: Vec<Uuid>,
+    // What's actually shown: the above, plus group headers and minus collapsed groups.
+    rows: Vec<ConnectionRow>,
     connections: FnvIndexMap<Uuid, Connection>,
+    // Never shrinks, so that a group's index stays put while it's empty.
+    groups: Vec<Group>,
     current_host: Option<Uuid>,
     #[allow(dead_code)]
     autoconnect_host: Option<Uuid>,
+    sort_column: Column,
+    descending_sort: bool,
 }
 
 impl ConnectionTableData {
     fn get_current_host(&self) -> Option<&Connection> {
         Some(&self.connections[&self.current_host?])
     }
+
+    /// Connected, then online, then offline.
+    fn status_rank(&self, connection: &Connection) -> u8 {
+        if self.get_current_host() == Some(connection) {
+            0
+        } else if connection.session.is_ready() {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Adds a host, or replaces it if it's already present.
+    fn insert(&mut self, id: Uuid, connection: Connection) {
+        if self.connections.insert(id, connection).is_none() {
+            self.hosts.push(id);
+        }
+        self.sort_stable();
+    }
+
+    fn remove(&mut self, id: Uuid) {
+        if self.current_host == Some(id) {
+            self.current_host = None;
+        }
+        self.connections
+            .remove(&id)
+            .expect("Tried to remove nonexistent connection");
+        self.hosts.retain(|h| *h != id);
+        self.rebuild_rows();
+    }
+
+    fn compare_hosts(&self, a: &Uuid, b: &Uuid) -> Ordering {
+        let (a, b) = (&self.connections[a], &self.connections[b]);
+
+        let ord = match self.sort_column {
+            Column::Status => self.status_rank(a).cmp(&self.status_rank(b)),
+            Column::Host => {
+                (&a.username, &a.address, a.port).cmp(&(&b.username, &b.address, b.port))
+            }
+            // Compared number by number, so that 2.0.10 comes after 2.0.9.
+            Column::Version => match (a.version.get(), b.version.get()) {
+                (Some(a), Some(b)) => util::natural_cmp(&a, &b),
+                (x, y) => x.is_some().cmp(&y.is_some()),
+            },
+            // Hosts that haven't answered count as the slowest.
+            Column::Latency => match (a.latency.get(), b.latency.get()) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (x, y) => x.is_none().cmp(&y.is_none()),
+            },
+        };
+
+        let ord = if self.descending_sort {
+            ord.reverse()
+        } else {
+            ord
+        };
+
+        // Groups are always in alphabetical order, whichever way their contents are sorted.
+        a.group.cmp(&b.group).then(ord)
+    }
+
+    /// Lays out the rows according to the (already sorted) hosts.
+    fn rebuild_rows(&mut self) {
+        let mut rows = std::mem::take(&mut self.rows);
+        rows.clear();
+
+        for group in &mut self.groups {
+            group.count = 0;
+        }
+
+        let mut current: Option<usize> = None;
+        for id in &self.hosts {
+            let name = match &self.connections[id].group {
+                Some(name) => name,
+                None => {
+                    rows.push(ConnectionRow::Host(*id));
+                    continue;
+                }
+            };
+
+            let group_id = match current {
+                Some(group_id) if self.groups[group_id].name == *name => group_id,
+                _ => {
+                    let group_id = match self.groups.iter().position(|g| g.name == *name) {
+                        Some(group_id) => group_id,
+                        None => {
+                            let collapsed = config::read()
+                                .connection_manager
+                                .collapsed_groups
+                                .contains(name);
+                            self.groups.push(Group {
+                                name: name.clone(),
+                                collapsed,
+                                count: 0,
+                            });
+                            self.groups.len() - 1
+                        }
+                    };
+                    rows.push(ConnectionRow::Group(group_id));
+                    current = Some(group_id);
+                    group_id
+                }
+            };
+
+            let group = &mut self.groups[group_id];
+            group.count += 1;
+            if !group.collapsed {
+                rows.push(ConnectionRow::Host(*id));
+            }
+        }
+
+        self.rows = rows;
+    }
+
+    fn toggle_group(&mut self, id: usize) {
+        let group = &mut self.groups[id];
+        group.collapsed = !group.collapsed;
+
+        let mut cfg = config::write();
+        let collapsed_groups = &mut cfg.connection_manager.collapsed_groups;
+        collapsed_groups.retain(|name| *name != group.name);
+        if group.collapsed {
+            collapsed_groups.push(group.name.clone());
+        }
+        cfg.save();
+        drop(cfg);
+
+        self.rebuild_rows();
+    }
 }
 
 impl TableViewData for ConnectionTableData {
     type Column = Column;
-    // interesting exercise in an un-sorted table
-    type RowIndex = Uuid;
-    type RowValue = Connection;
-    type Rows = Vec<Uuid>;
-
-    fn sort_column(&self) -> Self::Column {
-        Column::Host
+    type RowIndex = ConnectionRow;
+    type RowValue = ConnectionRow;
+    type Rows = Vec<ConnectionRow>;
+
+    impl_table! {
+        sort_column = self.sort_column;
+        rows = self.rows;
+        descending_sort = self.descending_sort;
     }
-    fn descending_sort(&self) -> bool {
-        true
+
+    fn sort_unstable(&mut self) {
+        let mut hosts = std::mem::take(&mut self.hosts);
+        hosts.sort_unstable_by(|a, b| self.compare_hosts(a, b));
+        self.hosts = hosts;
+        self.rebuild_rows();
     }
 
-    fn rows(&self) -> &Self::Rows {
-        &self.rows
+    fn sort_stable(&mut self) {
+        let mut hosts = std::mem::take(&mut self.hosts);
+        hosts.sort_by(|a, b| self.compare_hosts(a, b));
+        self.hosts = hosts;
+        self.rebuild_rows();
     }
-    fn rows_mut(&mut self) -> &mut Self::Rows {
-        &mut self.rows
+
+    fn set_sort_column(&mut self, val: Column) {
+        self.sort_column = val;
+        self.sort_stable();
     }
-    fn set_rows(&mut self, val: Self::Rows) {
-        self.rows = val;
+
+    fn set_descending_sort(&mut self, val: bool) {
+        let old_val = self.descending_sort;
+        self.descending_sort = val;
+        if val != old_val {
+            self.sort_stable();
+        }
     }
 
-    fn set_sort_column(&mut self, _: Self::Column) {}
-    fn set_descending_sort(&mut self, _: bool) {}
+    fn compare_rows(&self, a: &ConnectionRow, b: &ConnectionRow) -> Ordering {
+        // The rows are derived from the sorted hosts, so this only matters for them.
+        match (a, b) {
+            (ConnectionRow::Host(a), ConnectionRow::Host(b)) => self.compare_hosts(a, b),
+            _ => {
+                let position = |row| self.rows.iter().position(|r| r == row);
+                position(a).cmp(&position(b))
+            }
+        }
+    }
 
-    fn compare_rows(&self, a: &Self::RowIndex, b: &Self::RowIndex) -> Ordering {
-        a.cmp(b)
+    fn get_row_value<'a>(&'a self, index: &'a ConnectionRow) -> &'a ConnectionRow {
+        index
     }
 
-    fn get_row_value<'a>(&'a self, index: &'a Self::RowIndex) -> &'a Self::RowValue {
-        &self.connections[index]
+    fn draw_row(&self, printer: &Printer, columns: &[(Column, usize)], row: &ConnectionRow) {
+        match row {
+            ConnectionRow::Host(_) => draw_cells(self, printer, columns, row),
+            ConnectionRow::Group(id) => {
+                let group = &self.groups[*id];
+                let g = glyphs();
+                let marker = if group.collapsed {
+                    g.collapsed
+                } else {
+                    g.expanded
+                };
+                let header = format!("{} {} ({})", marker, group.name, group.count);
+                printer.with_effect(Effect::Bold, |p| p.print((0, 0), &header));
+            }
+        }
     }
 
-    fn draw_cell(&self, printer: &Printer, connection: &Self::RowValue, column: Self::Column) {
+    fn draw_cell(&self, printer: &Printer, row: &ConnectionRow, column: Self::Column) {
+        let connection = match row {
+            ConnectionRow::Host(id) => &self.connections[id],
+            ConnectionRow::Group(_) => return,
+        };
+
         let print = |s| printer.print((0, 0), s);
         match column {
             Column::Status => {
-                // uuuuuuugh, the catch-22 of passing draw-cell a value vs an index
                 if self.get_current_host() == Some(connection) {
                     assert!(connection.session.get().is_some());
                     print("Connected");
@@ -181,12 +403,17 @@ impl TableViewData for ConnectionTableData {
                     print(&s);
                 }
             }
+            Column::Latency => {
+                if let Some(latency) = connection.latency.get() {
+                    print(&format!("{} ms", latency.as_millis()));
+                }
+            }
         }
     }
 }
 
 type ConnectionManagerButtons =
-    StaticLinearLayout<(Button, Button, Button, Button, DummyView, Button)>;
+    StaticLinearLayout<(Button, Button, Button, Button, Button, DummyView, Button)>;
 
 type StartupOptions = StaticLinearLayout<(LabeledCheckbox, LabeledCheckbox)>;
 
@@ -203,21 +430,24 @@ pub(crate) struct ConnectionManagerView {
 async fn connect(
     address: String,
     port: u16,
+    protocol: config::Protocol,
+    timeout: Duration,
     mut session_tx: oneshot::Sender<Arc<Session>>,
     mut version_tx: oneshot::Sender<String>,
+    latency_tx: oneshot::Sender<Duration>,
 ) {
-    let endpoint = (address.as_str(), port);
-
     let info = async {
-        let session = Session::connect(endpoint).await?;
+        let session = legacy::connect(&address, port, protocol).await?;
+        let start = Instant::now();
         let version = session.daemon_info().await?;
-        deluge_rpc::Result::Ok((session, version))
+        deluge_rpc::Result::Ok((session, version, start.elapsed()))
     };
 
-    let (ses, ver) = tokio::select! {
-        result = info => match result {
-            Ok(x) => x,
-            Err(_) => return (),
+    // A host that doesn't answer in time is shown as offline.
+    let (ses, ver, latency) = tokio::select! {
+        result = time::timeout(timeout, info) => match result {
+            Ok(Ok(x)) => x,
+            _ => return (),
         },
         _ = session_tx.closed() => return (),
         _ = version_tx.closed() => return (),
@@ -225,36 +455,103 @@ async fn connect(
 
     session_tx.send(Arc::new(ses)).unwrap_or(());
     version_tx.send(ver).unwrap_or(());
+    latency_tx.send(latency).unwrap_or(());
 }
 
 fn selection_change_cb(
     selected_connection: Rc<Cell<Option<Uuid>>>,
 ) -> impl TableCallback<ConnectionTableData> {
-    move |_: &mut _, id: &Uuid, _, _| {
-        selected_connection.set(Some(*id));
+    move |_: &mut _, row: &ConnectionRow, _, _| {
+        let id = match row {
+            ConnectionRow::Host(id) => Some(*id),
+            ConnectionRow::Group(_) => None,
+        };
+        selected_connection.set(id);
         Callback::dummy()
     }
 }
 
-fn add_button_cb(table_data: Arc<RwLock<ConnectionTableData>>) -> impl Fn(&mut Cursive) {
+fn store_host(table_data: &RwLock<ConnectionTableData>, id: Uuid, host: config::Host) {
+    table_data
+        .write()
+        .unwrap()
+        .insert(id, Connection::new(&host));
+
+    let mut cfg = config::write();
+    cfg.connection_manager.hosts.insert(id, host);
+    cfg.save();
+}
+
+/// The Save button for the host edited in the topmost dialog, under `id`,
+/// or as a new one if `id` is `None`.
+/// If another entry already has the same endpoint, asks whether to overwrite that one instead,
+/// so that there aren't two rows for the same daemon. Saying no goes back to editing.
+fn save_host(
+    table_data: Arc<RwLock<ConnectionTableData>>,
+    id: Option<Uuid>,
+) -> impl Fn(&mut Cursive) {
     move |siv: &mut Cursive| {
+        let peeked = form::peek(siv, |view: &EditHostView| {
+            let (username, address, port) = view.login();
+            let duplicate = config::read()
+                .connection_manager
+                .hosts
+                .iter()
+                .find(|(other, h)| {
+                    Some(**other) != id
+                        && (&h.username, &h.address, h.port) == (&username, &address, port)
+                })
+                .map(|(other, _)| *other);
+            (duplicate, format!("{}@{}:{}", username, address, port))
+        });
+
         let table_data = table_data.clone();
+        let (existing, endpoint) = match peeked {
+            Some((Some(existing), endpoint)) => (existing, endpoint),
+            _ => {
+                let id = id.unwrap_or_else(Uuid::new_v4);
+                return form::submit::<EditHostView>(siv, move |_, host| {
+                    store_host(&table_data, id, host)
+                });
+            }
+        };
 
-        let save_host = move |_: &mut _, host: config::Host| {
-            let id = Uuid::new_v4();
+        let overwrite = move |siv: &mut Cursive| {
+            siv.pop_layer();
 
-            let mut data = table_data.write().unwrap();
+            let table_data = table_data.clone();
+            form::submit::<EditHostView>(siv, move |_, host| {
+                if let Some(id) = id {
+                    table_data.write().unwrap().remove(id);
 
-            data.connections.insert(id, Connection::new(&host));
-            data.rows.push(id);
+                    let mut cfg = config::write();
+                    let cmgr = &mut cfg.connection_manager;
+                    cmgr.hosts.shift_remove(&id);
+                    if cmgr.autoconnect == Some(id) {
+                        cmgr.autoconnect = Some(existing);
+                    }
+                }
 
-            let mut cfg = config::write();
-            cfg.connection_manager.hosts.insert(id, host);
-            cfg.save();
+                store_host(&table_data, existing, host);
+            });
         };
 
+        let msg = format!("{} is already in the list. Overwrite it?", endpoint);
+        let dialog = Dialog::text(msg)
+            .button("Overwrite", overwrite)
+            .dismiss_button("Cancel")
+            .title("Duplicate Host");
+
+        siv.add_layer(dialog);
+    }
+}
+
+fn add_button_cb(table_data: Arc<RwLock<ConnectionTableData>>) -> impl Fn(&mut Cursive) {
+    move |siv: &mut Cursive| {
+        let on_save = save_host(table_data.clone(), None);
+
         let dialog = EditHostView::default()
-            .into_dialog("Cancel", "Save", save_host)
+            .into_dialog_custom_submit("Cancel", "Save", on_save)
             .title("Add Host");
 
         siv.add_layer(dialog)
@@ -266,30 +563,17 @@ fn edit_button_cb(
     selected_connection: Rc<Cell<Option<Uuid>>>,
 ) -> impl Fn(&mut Cursive) {
     let cb = move |siv: &mut Cursive| {
-        let id = selected_connection
-            .get()
-            .expect("No selection; edit button should be disabled");
+        let id = match selected_connection.get() {
+            Some(id) => id,
+            None => return, // a group header is selected
+        };
 
-        let conn = &table_data.read().unwrap().connections[&id];
-        let view = EditHostView::new(&conn.address, conn.port, &conn.username, &conn.password);
-        drop(conn);
+        let view = EditHostView::from(&config::read().connection_manager.hosts[&id]);
 
-        let table_data = table_data.clone();
-
-        let save_host = move |_: &mut _, host: config::Host| {
-            table_data
-                .write()
-                .unwrap()
-                .connections
-                .insert(id, Connection::new(&host));
-
-            let mut cfg = config::write();
-            cfg.connection_manager.hosts.insert(id, host);
-            cfg.save();
-        };
+        let on_save = save_host(table_data.clone(), Some(id));
 
         let dialog = view
-            .into_dialog("Cancel", "Save", save_host)
+            .into_dialog_custom_submit("Cancel", "Save", on_save)
             .title("Edit Host");
 
         siv.add_layer(dialog);
@@ -303,19 +587,40 @@ fn remove_button_cb(
     selected_connection: Rc<Cell<Option<Uuid>>>,
 ) -> impl Fn(&mut Cursive) {
     move |_| {
-        let id = selected_connection
-            .get()
-            .expect("No selection; remove button should be disabled");
+        let id = match selected_connection.get() {
+            Some(id) => id,
+            None => return, // a group header is selected
+        };
 
-        let mut data = table_data.write().unwrap();
+        table_data.write().unwrap().remove(id);
+    }
+}
+
+fn import_button_cb(table_data: Arc<RwLock<ConnectionTableData>>) -> impl Fn(&mut Cursive) {
+    move |siv: &mut Cursive| {
+        let added = {
+            let mut cfg = config::write();
+            let added = cfg
+                .connection_manager
+                .merge_hosts(config::deluge_hostlist());
+            if !added.is_empty() {
+                cfg.save();
+            }
+            added
+        };
 
-        if data.current_host == Some(id) {
-            data.current_host = None;
+        let mut data = table_data.write().unwrap();
+        for (id, host) in &added {
+            data.insert(*id, Connection::new(host));
         }
+        drop(data);
 
-        data.connections
-            .remove(&id)
-            .expect("Tried to remove nonexistent connection");
+        let msg = match added.len() {
+            0 => String::from("No new hosts found in Deluge's hostlist.conf."),
+            1 => String::from("Imported 1 host from Deluge."),
+            n => format!("Imported {} hosts from Deluge.", n),
+        };
+        siv.add_layer(Dialog::info(msg).title("Import from Deluge"));
     }
 }
 
@@ -331,21 +636,30 @@ impl ConnectionManagerView {
 
         let cols = vec![
             (Column::Status, 9),
-            (Column::Host, 50),
+            (Column::Host, 42),
             (Column::Version, 11),
+            (Column::Latency, 8),
         ];
         let mut table = TableView::<ConnectionTableData>::new(cols);
 
         let selected_connection = Rc::new(Cell::new(None));
 
         table.set_on_selection_change(selection_change_cb(Rc::clone(&selected_connection)));
+        table.set_on_double_click(
+            |data: &mut ConnectionTableData, row: &ConnectionRow, _, _| {
+                if let ConnectionRow::Group(id) = row {
+                    data.toggle_group(*id);
+                }
+                Callback::dummy()
+            },
+        );
 
         let table_data = table.get_data();
 
         let mut data = table_data.write().unwrap();
 
-        data.rows = cmgr.hosts.keys().copied().collect();
-        let len = data.rows.len();
+        data.hosts = cmgr.hosts.keys().copied().collect();
+        let len = data.hosts.len();
         data.connections.reserve(len);
         data.autoconnect_host = autoconnect_host;
 
@@ -363,18 +677,22 @@ impl ConnectionManagerView {
             data.connections.insert(*id, conn);
         }
 
-        drop(data);
+        // Laying out the groups reads the config.
         drop(cfg);
+        data.sort_stable();
+        drop(data);
 
         let add_button = add_button_cb(table_data.clone());
         let edit_button = edit_button_cb(table_data.clone(), selected_connection.clone());
-        let remove_button = remove_button_cb(table_data, selected_connection);
+        let remove_button = remove_button_cb(table_data.clone(), selected_connection);
+        let import_button = import_button_cb(table_data);
 
         let buttons = ConnectionManagerButtons::horizontal((
             Button::new("Add", add_button),
             Button::new("Edit", edit_button),
             Button::new("Remove", remove_button),
             Button::new("Refresh", |_| ()),
+            Button::new("Import from Deluge", import_button),
             DummyView,
             Button::new("Stop Daemon", |_| ()),
         ));
@@ -406,7 +724,10 @@ impl Form for ConnectionManagerView {
 
         // TODO: Save prefs BEFORE THIS POINT.
         // Starting now, there will be early returns.
-        let selected: Uuid = table.get_selection().copied()?;
+        let selected: Uuid = match table.get_selection().copied()? {
+            ConnectionRow::Host(id) => id,
+            ConnectionRow::Group(_) => return None,
+        };
 
         drop(table);
         assert_eq!(Arc::strong_count(&data), 1);
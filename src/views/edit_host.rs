@@ -1,12 +1,13 @@
-use crate::config::Host;
-use crate::form::Form;
+use crate::config::{Host, PathMapping, Protocol};
+use crate::form::{self, ChangeCheck, Form};
+use crate::rpc::{self, Policy};
 
 use crate::views::{
     linear_panel::LinearPanel, spin::SpinView, static_linear_layout::StaticLinearLayout,
 };
 
 use cursive::view::ViewWrapper;
-use cursive::views::{TextArea, TextView};
+use cursive::views::{SelectView, TextArea, TextView};
 
 type PortSpinView = SpinView<u16, std::ops::RangeFull>;
 
@@ -16,6 +17,20 @@ pub struct EditHostView {
 
 type HostRow = StaticLinearLayout<(TextView, TextArea, PortSpinView)>;
 
+type SecondsSpinView = SpinView<u64, std::ops::RangeFrom<u64>>;
+type RetriesSpinView = SpinView<u64, std::ops::RangeFull>;
+
+type TimeoutsRow = StaticLinearLayout<(SecondsSpinView, SecondsSpinView, RetriesSpinView)>;
+
+impl Form for TimeoutsRow {
+    type Data = (u64, u64, u64);
+
+    fn into_data(self) -> Self::Data {
+        let (connect, request, retries) = self.into_children();
+        (connect.get_val(), request.get_val(), retries.get_val())
+    }
+}
+
 impl Form for HostRow {
     type Data = (String, u16);
 
@@ -27,6 +42,25 @@ impl Form for HostRow {
 
 type TextRow = StaticLinearLayout<(TextView, TextArea)>;
 
+type ProtocolRow = StaticLinearLayout<(TextView, SelectView<Protocol>)>;
+
+impl Form for ProtocolRow {
+    type Data = Protocol;
+
+    fn into_data(self) -> Self::Data {
+        self.into_children()
+            .1
+            .selection()
+            .map_or_else(Protocol::default, |p| *p)
+    }
+}
+
+const PROTOCOLS: [(&str, Protocol); 3] = [
+    ("Detect automatically", Protocol::Auto),
+    ("Deluge 2.x", Protocol::Deluge2),
+    ("Deluge 1.3", Protocol::Deluge1),
+];
+
 impl Form for TextRow {
     type Data = String;
 
@@ -35,8 +69,43 @@ impl Form for TextRow {
     }
 }
 
+const MAPPING_SEPARATOR: &str = " -> ";
+
+fn format_mappings(mappings: &[PathMapping]) -> String {
+    mappings
+        .iter()
+        .map(|m| format!("{}{}{}", m.remote, MAPPING_SEPARATOR, m.local.display()))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn parse_mappings(text: &str) -> Vec<PathMapping> {
+    text.lines()
+        .filter_map(|line| {
+            let (remote, local) = line.split_once(MAPPING_SEPARATOR.trim())?;
+            let (remote, local) = (remote.trim(), local.trim());
+            if remote.is_empty() || local.is_empty() {
+                return None;
+            }
+            Some(PathMapping {
+                remote: remote.to_owned(),
+                local: local.into(),
+            })
+        })
+        .collect()
+}
+
 impl EditHostView {
-    pub fn new(hostname: &str, port: u16, username: &str, password: &str) -> Self {
+    pub fn new(
+        hostname: &str,
+        port: u16,
+        username: &str,
+        password: &str,
+        path_mappings: &[PathMapping],
+        group: Option<&str>,
+        policy: Policy,
+        protocol: Protocol,
+    ) -> Self {
         let host_row = HostRow::horizontal((
             TextView::new("Hostname: "),
             TextArea::new().content(hostname),
@@ -53,10 +122,39 @@ impl EditHostView {
             TextArea::new().content(password),
         ));
 
+        let group_row = TextRow::horizontal((
+            TextView::new("Group:    "),
+            TextArea::new().content(group.unwrap_or_default()),
+        ));
+
+        let timeouts_row = TimeoutsRow::horizontal((
+            SpinView::new(Some("Connect timeout"), Some("s"), 1..)
+                .with_val(policy.connect_timeout.as_secs()),
+            SpinView::new(Some("Request timeout"), Some("s"), 1..)
+                .with_val(policy.request_timeout.as_secs()),
+            SpinView::new(Some("Retries"), None, ..).with_val(policy.retries),
+        ));
+
+        let selected = PROTOCOLS.iter().position(|(_, p)| *p == protocol);
+        let protocols = SelectView::new()
+            .popup()
+            .with_all(PROTOCOLS.iter().copied())
+            .selected(selected.unwrap_or_default());
+        let protocol_row = ProtocolRow::horizontal((TextView::new("Protocol: "), protocols));
+
+        let mappings_row = TextRow::vertical((
+            TextView::new("Path mappings (one \"remote -> local\" per line):"),
+            TextArea::new().content(format_mappings(path_mappings)),
+        ));
+
         let inner = LinearPanel::vertical()
             .child(host_row, None)
             .child(username_row, None)
-            .child(password_row, None);
+            .child(password_row, None)
+            .child(group_row, None)
+            .child(timeouts_row, None)
+            .child(protocol_row, None)
+            .child(mappings_row, None);
 
         Self { inner }
     }
@@ -64,7 +162,16 @@ impl EditHostView {
 
 impl From<&Host> for EditHostView {
     fn from(value: &Host) -> Self {
-        Self::new(&value.address, value.port, &value.username, &value.password)
+        Self::new(
+            &value.address,
+            value.port,
+            &value.username,
+            &value.password,
+            &value.path_mappings,
+            value.group.as_deref(),
+            Policy::from(value),
+            value.protocol,
+        )
     }
 }
 
@@ -78,30 +185,82 @@ impl ViewWrapper for EditHostView {
     cursive::wrap_impl!(self.inner: LinearPanel);
 }
 
-fn take_row_content<T: Form>(rows: &mut LinearPanel, index: usize) -> T::Data {
-    rows.remove_child(index)
-        .unwrap()
-        .downcast::<T>()
-        .ok()
-        .unwrap()
-        .into_data()
+impl EditHostView {
+    fn text(&self, i: usize) -> String {
+        let row = self.inner.get_child::<TextRow>(i).unwrap();
+        String::from(row.get_children().1.get_content())
+    }
+
+    fn timeouts(&self) -> (u64, u64, u64) {
+        let (connect, request, retries) = self
+            .inner
+            .get_child::<TimeoutsRow>(4)
+            .unwrap()
+            .get_children();
+        (connect.get_val(), request.get_val(), retries.get_val())
+    }
+
+    fn endpoint(&self) -> (String, u16) {
+        let (_, hostname, port) = self.inner.get_child::<HostRow>(0).unwrap().get_children();
+        (String::from(hostname.get_content()), port.get_val())
+    }
+
+    /// The username, address, and port, which together say which daemon account this is.
+    pub fn login(&self) -> (String, String, u16) {
+        let (address, port) = self.endpoint();
+        (self.text(1), address, port)
+    }
+
+    fn protocol(&self) -> Option<Protocol> {
+        let (_, protocols) = self
+            .inner
+            .get_child::<ProtocolRow>(5)
+            .unwrap()
+            .get_children();
+        protocols.selection().map(|p| *p)
+    }
 }
 
 impl Form for EditHostView {
     type Data = Host;
 
+    fn track_changes(&self) -> Option<ChangeCheck<Self>> {
+        form::compare_with(self, |v| {
+            (
+                v.endpoint(),
+                v.text(1),
+                v.text(2),
+                v.text(3),
+                v.timeouts(),
+                v.protocol(),
+                v.text(6),
+            )
+        })
+    }
+
     fn into_data(self) -> Self::Data {
         let mut inner = self.inner;
 
-        let password = take_row_content::<TextRow>(&mut inner, 2);
-        let username = take_row_content::<TextRow>(&mut inner, 1);
-        let (address, port) = take_row_content::<HostRow>(&mut inner, 0);
+        let path_mappings = parse_mappings(&inner.take_child_data::<TextRow>(6));
+        let protocol = inner.take_child_data::<ProtocolRow>(5);
+        let (connect_timeout, request_timeout, retries) = inner.take_child_data::<TimeoutsRow>(4);
+        let group = inner.take_child_data::<TextRow>(3).trim().to_owned();
+        let password = inner.take_child_data::<TextRow>(2);
+        let username = inner.take_child_data::<TextRow>(1);
+        let (address, port) = inner.take_child_data::<HostRow>(0);
 
         Host {
             address,
             port,
             username,
             password,
+            path_mappings,
+            group: Some(group).filter(|g| !g.is_empty()),
+            // Only remembered if they differ from the defaults, so that those can change later.
+            connect_timeout: Some(connect_timeout).filter(|t| *t != rpc::CONNECT_TIMEOUT),
+            request_timeout: Some(request_timeout).filter(|t| *t != rpc::REQUEST_TIMEOUT),
+            retries: Some(retries).filter(|r| *r != rpc::RETRIES),
+            protocol,
         }
     }
 }
@@ -1,21 +1,25 @@
+use super::scroll::RowIds;
 use super::thread::ViewThread;
+use crate::visibility::Visibility;
 use crate::SessionHandle;
 use async_trait::async_trait;
-use cursive::event::{Event, EventResult, MouseButton, MouseEvent};
+use cursive::event::{Event, EventResult, Key, MouseButton, MouseEvent};
 use cursive::traits::*;
 use cursive::vec::Vec2;
-use cursive::view::CannotFocus;
-use cursive::Printer;
-use deluge_rpc::{FilterDict, FilterKey, Session};
+use cursive::view::{CannotFocus, SizeConstraint, ViewWrapper};
+use cursive::views::{BoxedView, HideableView, Panel, ResizedView};
+use cursive::{Printer, Rect};
+use deluge_rpc::{EventKind, FilterKey, Session};
 use fnv::FnvHashMap;
 use once_cell::sync::Lazy;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 use std::sync::{Arc, RwLock};
 use tokio::sync::{watch, Notify};
 
 //use super::scroll::ScrollInner;
 
 use crate::util::digit_width;
+use crate::util::glyphs::glyphs;
 
 #[derive(Debug)]
 pub(crate) struct Category {
@@ -23,33 +27,72 @@ pub(crate) struct Category {
     pub collapsed: bool,
 }
 
+impl Category {
+    /// The number of torrents in this category, not counting the ones that
+    /// are matched by "catch-all" filters like All, Active, or Error.
+    fn total_hits(&self, key: FilterKey) -> u64 {
+        self.filters
+            .iter()
+            .filter(|(val, _)| match (key, val.as_str()) {
+                (FilterKey::State, "Active") | (FilterKey::Tracker, "Error") => false,
+                _ => !FiltersView::is_all(key, val),
+            })
+            .map(|(_, hits)| hits)
+            .sum()
+    }
+
+    fn header(&self, key: FilterKey) -> String {
+        let g = glyphs();
+        if self.collapsed {
+            format!("{} {} ({})", g.collapsed, key, self.total_hits(key))
+        } else {
+            format!("{} {}", g.expanded, key)
+        }
+    }
+}
+
 pub(crate) type Categories = BTreeMap<FilterKey, Category>;
 
+/// The active filters. A torrent must match at least one value for every key.
+pub(crate) type FilterSets = FnvHashMap<FilterKey, Vec<String>>;
+
 enum Row {
     Parent(FilterKey),
     Child(FilterKey, usize),
 }
 
+/// A row, identified in a way that survives filters coming and going.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum RowId {
+    Parent(FilterKey),
+    Child(FilterKey, String),
+}
+
 pub(crate) struct FiltersView {
     // TODO: figure out how to remove filters that vanish.
-    active_filters: FilterDict,
+    active_filters: FilterSets,
     categories: &'static RwLock<Categories>,
-    filters_send: watch::Sender<FilterDict>,
+    filters_send: watch::Sender<FilterSets>,
     filters_notify: Arc<Notify>,
+    highlighted: usize,
+    // The highlighted row as of the last layout, and where it was back then.
+    highlighted_id: Option<(RowId, usize)>,
+    // Whether the Label plugin was enabled, as of the last layout.
+    had_labels: bool,
 }
 
 pub(crate) static FILTER_CATEGORIES: Lazy<RwLock<Categories>> = Lazy::new(Default::default);
 
 struct FiltersViewThread {
     categories: &'static RwLock<Categories>,
-    filters_recv: watch::Receiver<FilterDict>,
+    filters_recv: watch::Receiver<FilterSets>,
     update_notifier: Arc<Notify>,
 }
 
 impl FiltersViewThread {
     fn new(
         categories: &'static RwLock<Categories>,
-        filters_recv: watch::Receiver<FilterDict>,
+        filters_recv: watch::Receiver<FilterSets>,
     ) -> Self {
         let update_notifier = Arc::new(Notify::new());
         Self {
@@ -62,11 +105,14 @@ impl FiltersViewThread {
     fn should_show(&self, key: FilterKey, filter: &(String, u64)) -> bool {
         let (val, hits) = filter;
 
-        if *hits > 0 || false
-        /* TODO: "show zero hits" pref */
-        {
+        if *hits > 0 || crate::config::read().filters.show_zero_hits {
             true
-        } else if self.filters_recv.borrow().get(&key) == Some(val) {
+        } else if self
+            .filters_recv
+            .borrow()
+            .get(&key)
+            .map_or(false, |vals| vals.contains(val))
+        {
             true
         } else {
             false
@@ -119,12 +165,22 @@ impl FiltersViewThread {
 
 #[async_trait]
 impl ViewThread for FiltersViewThread {
-    async fn reload(&mut self, session: &Session) -> deluge_rpc::Result<()> {
-        let interested = deluge_rpc::events![TorrentAdded, TorrentRemoved, TorrentStateChanged];
-        session.set_event_interest(&interested).await?;
+    // The first update is enough.
+    async fn reload(&mut self, _: &Session) -> deluge_rpc::Result<()> {
         Ok(())
     }
 
+    fn event_interest(&self) -> HashSet<EventKind> {
+        // Plugins like Label add their own categories to the filter tree.
+        deluge_rpc::events![
+            TorrentAdded,
+            TorrentRemoved,
+            TorrentStateChanged,
+            PluginEnabled,
+            PluginDisabled,
+        ]
+    }
+
     async fn update(&mut self, session: &Session) -> deluge_rpc::Result<()> {
         let new_tree = session.get_filter_tree(true, &[]).await?;
         self.replace_tree(new_tree);
@@ -133,7 +189,9 @@ impl ViewThread for FiltersViewThread {
 
     async fn on_event(&mut self, _: &Session, event: deluge_rpc::Event) -> deluge_rpc::Result<()> {
         use deluge_rpc::EventKind::*;
-        if let TorrentAdded | TorrentRemoved | TorrentStateChanged = event.into() {
+        if let TorrentAdded | TorrentRemoved | TorrentStateChanged | PluginEnabled
+        | PluginDisabled = event.into()
+        {
             self.update_notifier.notify_one();
         }
         Ok(())
@@ -143,6 +201,11 @@ impl ViewThread for FiltersViewThread {
         self.update_notifier.clone()
     }
 
+    // The filters are beside the torrents, so they come and go together.
+    fn should_poll(&self, ui: &Visibility) -> bool {
+        !ui.covered && ui.torrents
+    }
+
     fn clear(&mut self) {
         self.replace_tree(Default::default());
     }
@@ -151,34 +214,57 @@ impl ViewThread for FiltersViewThread {
 impl FiltersView {
     pub(crate) fn new(
         session_recv: watch::Receiver<SessionHandle>,
-        filters_send: watch::Sender<FilterDict>,
-        filters_recv: watch::Receiver<FilterDict>,
+        filters_send: watch::Sender<FilterSets>,
+        filters_recv: watch::Receiver<FilterSets>,
         filters_notify: Arc<Notify>,
     ) -> Self {
         let categories = &*FILTER_CATEGORIES;
         let thread_obj = FiltersViewThread::new(categories, filters_recv);
-        tokio::spawn(thread_obj.run(session_recv));
+        crate::shutdown::spawn("filters", thread_obj.run(session_recv));
         Self {
-            active_filters: FilterDict::default(),
+            active_filters: FilterSets::default(),
             categories,
             filters_send,
             filters_notify,
+            highlighted: 0,
+            highlighted_id: None,
+            had_labels: false,
+        }
+    }
+
+    fn is_all(key: FilterKey, val: &str) -> bool {
+        match (key, val) {
+            (FilterKey::Owner, "") => true,
+            (FilterKey::Owner, "All") => false, // in case All is the name of a user
+            (_, "All") => true,
+            _ => false,
         }
     }
 
-    fn get_active_filters(&self) -> FilterDict {
+    fn get_active_filters(&self) -> FilterSets {
         self.active_filters
             .iter()
-            .filter(|(key, val)| match (key, val.as_str()) {
-                (FilterKey::Owner, "") => false,
-                (FilterKey::Owner, "All") => true, // in case All is the name of a user
-                (_, "All") => false,
-                _ => true,
+            .map(|(key, vals)| {
+                let vals = vals.iter().filter(|val| !Self::is_all(*key, val));
+                (*key, vals.cloned().collect::<Vec<String>>())
             })
-            .map(|(k, v)| (*k, v.clone()))
+            .filter(|(_, vals)| !vals.is_empty())
             .collect()
     }
 
+    /// The active filters, including "all" values, for restoring later.
+    pub(crate) fn active_filters(&self) -> FilterSets {
+        self.active_filters.clone()
+    }
+
+    pub(crate) fn set_active_filters(&mut self, filters: FilterSets) {
+        self.active_filters = filters;
+        self.filters_send
+            .send(self.get_active_filters())
+            .expect("Couldn't send new view filters");
+        self.filters_notify.notify_one();
+    }
+
     fn get_row(categories: &Categories, mut y: usize) -> Option<Row> {
         for (key, category) in categories.iter() {
             if y == 0 {
@@ -198,7 +284,57 @@ impl FiltersView {
         None
     }
 
-    fn click(&mut self, y: usize) {
+    fn get_row_id(categories: &Categories, y: usize) -> Option<RowId> {
+        match Self::get_row(categories, y)? {
+            Row::Parent(key) => Some(RowId::Parent(key)),
+            Row::Child(key, idx) => {
+                let filter = categories[&key].filters[idx].0.clone();
+                Some(RowId::Child(key, filter))
+            }
+        }
+    }
+
+    fn find_row_id(categories: &Categories, id: &RowId) -> Option<usize> {
+        let mut y = 0;
+        for (key, category) in categories.iter() {
+            if *id == RowId::Parent(*key) {
+                return Some(y);
+            }
+            y += 1;
+
+            if category.collapsed {
+                continue;
+            }
+            if let RowId::Child(k, filter) = id {
+                if k == key {
+                    let idx = category.filters.iter().position(|(f, _)| f == filter)?;
+                    return Some(y + idx);
+                }
+            }
+            y += category.filters.len();
+        }
+        None
+    }
+
+    /// Keeps the highlight on the same filter when others appear or disappear above it.
+    fn follow_highlight(&mut self) {
+        let categories = self.categories.read().unwrap();
+
+        if let Some((id, y)) = self.highlighted_id.take() {
+            if y == self.highlighted {
+                if let Some(y) = Self::find_row_id(&categories, &id) {
+                    self.highlighted = y;
+                }
+            }
+        }
+
+        self.highlighted_id =
+            Self::get_row_id(&categories, self.highlighted).map(|id| (id, self.highlighted));
+    }
+
+    /// Selects the filter on row `y`, or with `toggle`, adds or removes it from its category's set.
+    fn click(&mut self, y: usize, toggle: bool) {
+        self.highlighted = y;
         let mut categories = self.categories.write().unwrap();
 
         match Self::get_row(&categories, y) {
@@ -210,18 +346,29 @@ impl FiltersView {
                 let filters = &mut categories.get_mut(&key).unwrap().filters;
 
                 let filter = filters[idx].0.clone();
-                let old = self.active_filters.insert(key, filter);
-
-                // Remove the empty category immediately, rather than waiting for the next update.
-                // TODO: "show zero hits" pref
-                if let Some(val) = old {
-                    if (key, val.as_str()) != (FilterKey::Owner, "") {
-                        for i in 0..filters.len() {
-                            if filters[i].0 == val {
-                                if filters[i].1 == 0 {
-                                    filters.remove(i);
-                                }
-                                break;
+                let active = self.active_filters.entry(key).or_default();
+                let old = std::mem::take(active);
+
+                if toggle && !Self::is_all(key, &filter) {
+                    active.extend(old.iter().filter(|val| !Self::is_all(key, val)).cloned());
+                    if let Some(i) = active.iter().position(|val| *val == filter) {
+                        active.remove(i);
+                    } else {
+                        active.push(filter);
+                    }
+                } else {
+                    active.push(filter);
+                }
+
+                // Remove emptied filters immediately, rather than waiting for the next update.
+                if !crate::config::read().filters.show_zero_hits {
+                    for val in old.iter().filter(|val| !active.contains(val)) {
+                        if Self::is_all(key, val) {
+                            continue;
+                        }
+                        if let Some(i) = filters.iter().position(|(v, _)| v == val) {
+                            if filters[i].1 == 0 {
+                                filters.remove(i);
                             }
                         }
                     }
@@ -241,7 +388,7 @@ impl FiltersView {
     fn content_width(categories: &Categories) -> usize {
         let mut w = 0;
         for (key, category) in categories.iter() {
-            w = w.max(2 + key.as_str().len());
+            w = w.max(category.header(*key).chars().count());
             for (filter, hits) in category.filters.iter() {
                 w = w.max(3 + filter.len() + 1 + digit_width(*hits));
             }
@@ -260,24 +407,42 @@ impl FiltersView {
         h
     }
 
+    fn move_highlight(&mut self, up: bool) -> EventResult {
+        let height = Self::content_height(&self.categories.read().unwrap());
+        let y = self.highlighted.min(height.saturating_sub(1));
+
+        self.highlighted = if up {
+            y.saturating_sub(1)
+        } else {
+            (y + 1).min(height.saturating_sub(1))
+        };
+
+        if self.highlighted == y {
+            EventResult::Ignored
+        } else {
+            EventResult::Consumed(None)
+        }
+    }
+
     fn draw_row(&self, printer: &Printer, y: usize) {
         let categories = self.categories.read().unwrap();
+        let printer = &printer.focused(printer.focused && y == self.highlighted);
 
         match Self::get_row(&categories, y) {
             Some(Row::Parent(key)) => {
-                let c = if categories[&key].collapsed {
-                    '▸'
-                } else {
-                    '▾'
-                };
-                printer.print((0, 0), &format!("{} {}", c, key));
+                let header = categories[&key].header(key);
+                printer.with_selection(printer.focused, |p| p.print((0, 0), &header));
             }
             Some(Row::Child(key, idx)) => {
                 let (filter, hits) = &categories[&key].filters[idx];
-                let c = if self.active_filters.get(&key) == Some(filter) {
-                    '●'
+                let is_active = self
+                    .active_filters
+                    .get(&key)
+                    .map(|vals| vals.contains(filter));
+                let c = if is_active == Some(true) {
+                    glyphs().active
                 } else {
-                    '◌'
+                    glyphs().inactive
                 };
                 let filter = match (key, filter.as_str()) {
                     (FilterKey::Owner, "") => "All",
@@ -290,13 +455,27 @@ impl FiltersView {
                     .x
                     .saturating_sub(3 + filter.len() + digit_width(*hits));
                 let spaces = " ".repeat(nspaces);
-                printer.print((0, 0), &format!(" {} {}{}{}", c, filter, spaces, hits));
+                printer.with_selection(printer.focused, |p| {
+                    p.print((0, 0), &format!(" {} {}{}{}", c, filter, spaces, hits));
+                });
             }
             None => (),
         }
     }
 }
 
+impl RowIds for FiltersView {
+    type Id = RowId;
+
+    fn row_id(&self, y: usize) -> Option<RowId> {
+        Self::get_row_id(&self.categories.read().unwrap(), y)
+    }
+
+    fn find_row(&self, id: &RowId) -> Option<usize> {
+        Self::find_row_id(&self.categories.read().unwrap(), id)
+    }
+}
+
 impl View for FiltersView {
     fn draw(&self, printer: &Printer) {
         for y in 0..printer.output_size.y {
@@ -306,6 +485,27 @@ impl View for FiltersView {
         }
     }
 
+    fn layout(&mut self, _: Vec2) {
+        let (has_labels, connected) = {
+            let categories = self.categories.read().unwrap();
+            (
+                categories.contains_key(&FilterKey::Label),
+                !categories.is_empty(),
+            )
+        };
+
+        // A label filter would hide everything once the plugin is disabled.
+        if self.had_labels && !has_labels && connected {
+            let mut filters = self.active_filters();
+            if filters.remove(&FilterKey::Label).is_some() {
+                self.set_active_filters(filters);
+            }
+        }
+        self.had_labels = has_labels;
+
+        self.follow_highlight();
+    }
+
     fn required_size(&mut self, _: Vec2) -> Vec2 {
         let categories = self.categories.read().unwrap();
         (
@@ -319,15 +519,30 @@ impl View for FiltersView {
         Ok(EventResult::Consumed(None))
     }
 
+    fn important_area(&self, view_size: Vec2) -> Rect {
+        Rect::from_size((0, self.highlighted), (view_size.x, 1))
+    }
+
     fn on_event(&mut self, event: Event) -> EventResult {
         match event {
+            Event::Key(Key::Up) => self.move_highlight(true),
+            Event::Key(Key::Down) => self.move_highlight(false),
+            Event::Key(Key::Enter) => {
+                self.click(self.highlighted, false);
+                EventResult::Consumed(None)
+            }
+            Event::Char(' ') => {
+                self.click(self.highlighted, true);
+                EventResult::Consumed(None)
+            }
             Event::Mouse {
                 offset,
                 position,
                 event,
             } => match event {
                 MouseEvent::Press(MouseButton::Left) => {
-                    self.click(position.y.saturating_sub(offset.y));
+                    let toggle = crate::backend::mouse_ctrl();
+                    self.click(position.y.saturating_sub(offset.y), toggle);
                     EventResult::Consumed(None)
                 }
                 _ => EventResult::Ignored,
@@ -336,3 +551,110 @@ impl View for FiltersView {
         }
     }
 }
+
+/// The Filters panel, which can be hidden, and resized either with the keyboard or by
+/// dragging its right edge. Both are remembered in the config.
+pub(crate) struct FiltersPane {
+    inner: HideableView<ResizedView<Panel<BoxedView>>>,
+    width: usize,
+    dragging: bool,
+}
+
+impl FiltersPane {
+    const MIN_WIDTH: usize = 12;
+    const MAX_WIDTH: usize = 80;
+
+    pub fn new(filters: impl View) -> Self {
+        let (visible, width) = {
+            let cfg = &crate::config::read().filters;
+            (cfg.visible, cfg.width)
+        };
+
+        let panel = Panel::new(BoxedView::boxed(filters)).title("Filters");
+        let constraint = match width {
+            Some(w) => SizeConstraint::Fixed(w),
+            None => SizeConstraint::Free,
+        };
+        let resized = ResizedView::new(constraint, SizeConstraint::Free, panel);
+
+        Self {
+            inner: HideableView::new(resized).visible(visible),
+            width: 0,
+            dragging: false,
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.inner.is_visible()
+    }
+
+    pub fn toggle(&mut self) {
+        let visible = !self.inner.is_visible();
+        self.inner.set_visible(visible);
+
+        let mut cfg = crate::config::write();
+        cfg.filters.visible = visible;
+        cfg.save();
+    }
+
+    /// Widens (or, for negative `delta`, narrows) the pane, starting from its current width.
+    pub fn resize(&mut self, delta: isize) {
+        let width = (self.width as isize + delta).max(0) as usize;
+        self.set_width(width);
+        self.save_width();
+    }
+
+    fn set_width(&mut self, width: usize) {
+        self.width = width.max(Self::MIN_WIDTH).min(Self::MAX_WIDTH);
+        self.inner
+            .get_inner_mut()
+            .set_width(SizeConstraint::Fixed(self.width));
+    }
+
+    fn save_width(&self) {
+        let mut cfg = crate::config::write();
+        cfg.filters.width = Some(self.width);
+        cfg.save();
+    }
+}
+
+impl ViewWrapper for FiltersPane {
+    cursive::wrap_impl!(self.inner: HideableView<ResizedView<Panel<BoxedView>>>);
+
+    fn wrap_layout(&mut self, size: Vec2) {
+        if self.inner.is_visible() {
+            self.width = size.x;
+        }
+        self.inner.layout(size);
+    }
+
+    fn wrap_on_event(&mut self, event: Event) -> EventResult {
+        if let Event::Mouse {
+            offset,
+            position,
+            event,
+        } = event
+        {
+            let x = position.checked_sub(offset).map(|pos| pos.x);
+            match event {
+                MouseEvent::Press(MouseButton::Left) if x == Some(self.width.saturating_sub(1)) => {
+                    self.dragging = true;
+                    return EventResult::Consumed(None);
+                }
+                MouseEvent::Hold(MouseButton::Left) if self.dragging => {
+                    if let Some(x) = x {
+                        self.set_width(x + 1);
+                    }
+                    return EventResult::Consumed(None);
+                }
+                MouseEvent::Release(MouseButton::Left) if self.dragging => {
+                    self.dragging = false;
+                    self.save_width();
+                    return EventResult::Consumed(None);
+                }
+                _ => (),
+            }
+        }
+        self.inner.on_event(event)
+    }
+}
@@ -1,23 +1,44 @@
-use cursive::event::EventResult;
-use cursive::view::ViewWrapper;
+use cursive::event::{Event, EventResult};
+use cursive::theme::ColorStyle;
+use cursive::view::{View, ViewWrapper};
 use cursive::views::{Checkbox, PaddedView};
 use cursive::Cursive;
 use cursive::Printer;
 
-use crate::form::Form;
+use crate::form::{self, ChangeCheck, Form};
 
 pub struct LabeledCheckbox {
     inner: PaddedView<Checkbox>,
     label: String,
+    // Neither checked nor unchecked, for a value that differs between several torrents.
+    // Shown as [-] until the user toggles it, after which it's checked.
+    indeterminate: bool,
 }
 
 impl ViewWrapper for LabeledCheckbox {
     cursive::wrap_impl!(self.inner: PaddedView<Checkbox>);
 
     fn wrap_draw(&self, printer: &Printer) {
-        self.inner.wrap_draw(printer);
+        if self.indeterminate {
+            // Mirrors how Checkbox draws itself.
+            if self.is_enabled() && printer.enabled {
+                printer.with_selection(printer.focused, |p| p.print((0, 0), "[-]"));
+            } else {
+                printer.with_color(ColorStyle::secondary(), |p| p.print((0, 0), "[-]"));
+            }
+        } else {
+            self.inner.wrap_draw(printer);
+        }
         printer.print((4, 0), &self.label);
     }
+
+    fn wrap_on_event(&mut self, event: Event) -> EventResult {
+        let result = self.inner.on_event(event);
+        if result.is_consumed() {
+            self.indeterminate = false;
+        }
+        result
+    }
 }
 
 #[allow(unused)]
@@ -25,7 +46,11 @@ impl LabeledCheckbox {
     pub fn new(label: impl Into<String>) -> Self {
         let label: String = label.into();
         let inner = PaddedView::lrtb(0, label.len() + 1, 0, 0, Checkbox::new());
-        Self { inner, label }
+        Self {
+            inner,
+            label,
+            indeterminate: false,
+        }
     }
 
     pub fn disable(&mut self) {
@@ -69,10 +94,12 @@ impl LabeledCheckbox {
     }
 
     pub fn toggle(&mut self) -> EventResult {
+        self.indeterminate = false;
         self.inner.get_inner_mut().check()
     }
 
     pub fn check(&mut self) -> EventResult {
+        self.indeterminate = false;
         self.inner.get_inner_mut().check()
     }
 
@@ -86,6 +113,7 @@ impl LabeledCheckbox {
     }
 
     pub fn uncheck(&mut self) -> EventResult {
+        self.indeterminate = false;
         self.inner.get_inner_mut().uncheck()
     }
 
@@ -95,6 +123,7 @@ impl LabeledCheckbox {
     }
 
     pub fn set_checked(&mut self, checked: bool) -> EventResult {
+        self.indeterminate = false;
         self.inner.get_inner_mut().set_checked(checked)
     }
 
@@ -102,6 +131,33 @@ impl LabeledCheckbox {
         self.set_checked(checked);
         self
     }
+
+    /// Shows the checkbox as [-] until it's toggled. Doesn't call the change callback.
+    pub fn set_indeterminate(&mut self, indeterminate: bool) {
+        if indeterminate {
+            // So that the first toggle checks it.
+            let _ = self.inner.get_inner_mut().set_checked(false);
+        }
+        self.indeterminate = indeterminate;
+    }
+
+    pub fn indeterminate(mut self) -> Self {
+        self.set_indeterminate(true);
+        self
+    }
+
+    pub fn is_indeterminate(&self) -> bool {
+        self.indeterminate
+    }
+
+    /// The checked state, or `None` while indeterminate.
+    pub fn state(&self) -> Option<bool> {
+        if self.indeterminate {
+            None
+        } else {
+            Some(self.is_checked())
+        }
+    }
 }
 
 impl Form for LabeledCheckbox {
@@ -110,4 +166,8 @@ impl Form for LabeledCheckbox {
     fn into_data(self) -> Self::Data {
         self.is_checked()
     }
+
+    fn track_changes(&self) -> Option<ChangeCheck<Self>> {
+        form::compare_with(self, Self::state)
+    }
 }
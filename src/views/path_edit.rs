@@ -0,0 +1,222 @@
+use cursive::direction::Direction;
+use cursive::event::{Event, EventResult, Key};
+use cursive::view::{CannotFocus, View, ViewWrapper};
+use cursive::views::EditView;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+use crate::form::{self, ChangeCheck, Form};
+
+/// Splits a path into the directory part (including its trailing separator) and the
+/// partial name after it.
+fn split_path(path: &str) -> Option<(&str, &str)> {
+    let i = path.rfind(|c| c == '/' || c == '\\')?;
+    Some(path.split_at(i + 1))
+}
+
+fn common_prefix<'a>(names: &'a [String]) -> &'a str {
+    let first = match names.first() {
+        Some(first) => first.as_str(),
+        None => return "",
+    };
+    let len = names[1..].iter().fold(first.len(), |len, name| {
+        first[..len]
+            .char_indices()
+            .zip(name.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(len.min(name.len()), |((i, _), _)| i)
+    });
+    &first[..len]
+}
+
+/// Lists the names of a local directory's subdirectories.
+fn list_dirs(local: &Path) -> Vec<String> {
+    let entries = match std::fs::read_dir(local) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut names: Vec<String> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+
+    names.sort_unstable();
+    names
+}
+
+struct Cycle {
+    dir: String,
+    names: Vec<String>,
+    index: usize,
+}
+
+/// An `EditView` for paths on the daemon's machine, with Tab completion of directory names.
+/// Completion only works when the host's paths can be resolved locally (see `Host::local_path`).
+pub(crate) struct PathEdit {
+    inner: EditView,
+    // The host whose paths these are.
+    host: Option<Uuid>,
+    // Set when the completion was ambiguous, so pressing Tab again steps through the candidates.
+    cycle: Option<Cycle>,
+    // The directory being typed in, and its subdirectories once they've been listed.
+    // Listing happens in the background as soon as the directory changes, so that
+    // completing never has to wait on the disk.
+    listing_dir: Option<String>,
+    listing: Arc<Mutex<Option<(String, Vec<String>)>>>,
+}
+
+impl PathEdit {
+    pub fn new(host: Option<Uuid>) -> Self {
+        Self {
+            inner: EditView::new(),
+            host,
+            cycle: None,
+            listing_dir: None,
+            listing: Arc::default(),
+        }
+    }
+
+    pub fn set_host(&mut self, host: Option<Uuid>) {
+        if host != self.host {
+            self.host = host;
+            self.listing_dir = None;
+        }
+    }
+
+    fn local_path(&self, dir: &str) -> Option<PathBuf> {
+        let cfg = crate::config::read();
+        cfg.connection_manager
+            .hosts
+            .get(&self.host?)?
+            .local_path(dir)
+    }
+
+    /// Starts listing the directory being typed in, unless it already has been.
+    fn prefetch(&mut self) {
+        let content = self.inner.get_content();
+        let dir = match split_path(&content) {
+            Some((dir, _)) => dir,
+            None => return,
+        };
+        if self.listing_dir.as_deref() == Some(dir) {
+            return;
+        }
+        self.listing_dir = Some(dir.to_owned());
+
+        let local = match self.local_path(dir) {
+            Some(local) => local,
+            None => return,
+        };
+        let (listing, dir) = (self.listing.clone(), dir.to_owned());
+        tokio::task::spawn_blocking(move || {
+            let names = list_dirs(&local);
+            *listing.lock().unwrap() = Some((dir, names));
+        });
+    }
+
+    pub fn on_edit<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&mut cursive::Cursive, &str, usize) + 'static,
+    {
+        self.inner.set_on_edit(callback);
+        self
+    }
+
+    pub fn get_inner_mut(&mut self) -> &mut EditView {
+        &mut self.inner
+    }
+
+    fn complete(&mut self) -> EventResult {
+        if let Some(cycle) = &mut self.cycle {
+            cycle.index = (cycle.index + 1) % cycle.names.len();
+            let path = format!("{}{}", cycle.dir, cycle.names[cycle.index]);
+            return EventResult::Consumed(Some(self.inner.set_content(path)));
+        }
+
+        let content = self.inner.get_content();
+        let (dir, prefix) = match split_path(&content) {
+            Some(parts) => parts,
+            None => return EventResult::Ignored,
+        };
+        if self.local_path(dir).is_none() {
+            return EventResult::Ignored;
+        }
+        let names: Vec<String> = match &*self.listing.lock().unwrap() {
+            Some((listed, names)) if listed == dir => names
+                .iter()
+                .filter(|name| name.starts_with(prefix))
+                // Dotfiles only show up if asked for.
+                .filter(|name| prefix.starts_with('.') || !name.starts_with('.'))
+                .cloned()
+                .collect(),
+            // Still being listed.
+            _ => return EventResult::Consumed(None),
+        };
+        if names.is_empty() {
+            return EventResult::Consumed(None);
+        }
+
+        let path = if names.len() == 1 {
+            // Finish the name with whichever separator the path already uses.
+            let sep = &dir[dir.len() - 1..];
+            format!("{}{}{}", dir, names[0], sep)
+        } else {
+            let common = common_prefix(&names);
+            if common.len() == prefix.len() {
+                // Start just before the first candidate so it's the one shown.
+                self.cycle = Some(Cycle {
+                    dir: dir.to_owned(),
+                    index: names.len() - 1,
+                    names,
+                });
+                return self.complete();
+            }
+            format!("{}{}", dir, common)
+        };
+
+        EventResult::Consumed(Some(self.inner.set_content(path)))
+    }
+}
+
+impl ViewWrapper for PathEdit {
+    cursive::wrap_impl!(self.inner: EditView);
+
+    fn wrap_on_event(&mut self, event: Event) -> EventResult {
+        let result = if event == Event::Key(Key::Tab) && self.inner.is_enabled() {
+            match self.complete() {
+                EventResult::Ignored => self.inner.on_event(event),
+                result => result,
+            }
+        } else {
+            if event != Event::Refresh {
+                self.cycle = None;
+            }
+            self.inner.on_event(event)
+        };
+        self.prefetch();
+        result
+    }
+
+    fn wrap_take_focus(&mut self, source: Direction) -> Result<EventResult, CannotFocus> {
+        let result = self.inner.take_focus(source);
+        if result.is_ok() {
+            self.prefetch();
+        }
+        result
+    }
+}
+
+impl Form for PathEdit {
+    type Data = String;
+
+    fn into_data(self) -> Self::Data {
+        self.inner.into_data()
+    }
+
+    fn track_changes(&self) -> Option<ChangeCheck<Self>> {
+        form::compare_with(self, |v| v.inner.get_content())
+    }
+}
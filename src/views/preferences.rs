@@ -0,0 +1,271 @@
+use cursive::traits::Resizable;
+use cursive::view::ViewWrapper;
+use cursive::views::{EditView, ResizedView, TextView};
+
+use deluge_rpc::Query;
+use serde::Deserialize;
+
+use crate::config::{
+    self, FiltersConfig, InterfaceConfig, NotificationsConfig, StatusBarConfig, ThemeName,
+};
+use crate::form::Form;
+use crate::views::{
+    labeled_checkbox::LabeledCheckbox, linear_panel::LinearPanel,
+    static_linear_layout::StaticLinearLayout,
+};
+
+type NotificationsSection = StaticLinearLayout<(
+    LabeledCheckbox,
+    LabeledCheckbox,
+    LabeledCheckbox,
+    LabeledCheckbox,
+    LabeledCheckbox,
+    LabeledCheckbox,
+    LabeledCheckbox,
+)>;
+
+impl Form for NotificationsSection {
+    type Data = NotificationsConfig;
+
+    fn into_data(self) -> Self::Data {
+        let (enabled, desktop, finished, added, tracker_error, torrent_error, error_alerts) =
+            self.into_children();
+        NotificationsConfig {
+            enabled: enabled.into_data(),
+            desktop: desktop.into_data(),
+            torrent_finished: finished.into_data(),
+            torrent_added: added.into_data(),
+            tracker_error: tracker_error.into_data(),
+            torrent_error: torrent_error.into_data(),
+            error_alerts: error_alerts.into_data(),
+            ..config::read().notifications.clone()
+        }
+    }
+}
+
+type StatusBarSection = StaticLinearLayout<(LabeledCheckbox, LabeledCheckbox)>;
+
+impl Form for StatusBarSection {
+    type Data = StatusBarConfig;
+
+    fn into_data(self) -> Self::Data {
+        let (show_overhead, overhead_percentage) = self.into_children();
+        StatusBarConfig {
+            show_overhead: show_overhead.into_data(),
+            overhead_percentage: overhead_percentage.into_data(),
+            ..config::read().status_bar.clone()
+        }
+    }
+}
+
+/// The daemon's own settings for how it finds peers and accepts incoming connections.
+#[derive(Debug, Clone, Deserialize, Query)]
+pub(crate) struct NetworkPreferences {
+    pub dht: bool,
+    pub lsd: bool,
+    pub utpex: bool,
+    pub upnp: bool,
+    pub natpmp: bool,
+}
+
+pub(crate) type NetworkPreferencesView = StaticLinearLayout<(
+    LabeledCheckbox,
+    LabeledCheckbox,
+    LabeledCheckbox,
+    LabeledCheckbox,
+    LabeledCheckbox,
+)>;
+
+impl NetworkPreferencesView {
+    pub fn new(prefs: &NetworkPreferences) -> Self {
+        Self::vertical((
+            LabeledCheckbox::new("DHT").with_checked(prefs.dht),
+            LabeledCheckbox::new("Local Service Discovery").with_checked(prefs.lsd),
+            LabeledCheckbox::new("Peer Exchange").with_checked(prefs.utpex),
+            LabeledCheckbox::new("UPnP").with_checked(prefs.upnp),
+            LabeledCheckbox::new("NAT-PMP").with_checked(prefs.natpmp),
+        ))
+    }
+}
+
+impl Form for NetworkPreferencesView {
+    type Data = NetworkPreferences;
+
+    fn into_data(self) -> Self::Data {
+        let (dht, lsd, utpex, upnp, natpmp) = self.into_children();
+        NetworkPreferences {
+            dht: dht.into_data(),
+            lsd: lsd.into_data(),
+            utpex: utpex.into_data(),
+            upnp: upnp.into_data(),
+            natpmp: natpmp.into_data(),
+        }
+    }
+}
+
+type DateFormatRow = StaticLinearLayout<(TextView, ResizedView<EditView>)>;
+
+impl Form for DateFormatRow {
+    type Data = String;
+
+    fn into_data(self) -> Self::Data {
+        self.into_children().1.into_data()
+    }
+}
+
+type InterfaceSection = StaticLinearLayout<(
+    LabeledCheckbox,
+    LabeledCheckbox,
+    LabeledCheckbox,
+    LabeledCheckbox,
+    LabeledCheckbox,
+    LabeledCheckbox,
+    LabeledCheckbox,
+    DateFormatRow,
+)>;
+
+impl Form for InterfaceSection {
+    type Data = InterfaceConfig;
+
+    fn into_data(self) -> Self::Data {
+        let (
+            confirm_shutdown,
+            relative_dates,
+            natural_sort,
+            binary_units,
+            smooth_speeds,
+            ascii_only,
+            high_contrast,
+            date_format,
+        ) = self.into_children();
+
+        let mut date_format = date_format.into_data();
+        if date_format.trim().is_empty() {
+            date_format = String::from(config::DEFAULT_DATE_FORMAT);
+        }
+
+        InterfaceConfig {
+            confirm_shutdown: confirm_shutdown.into_data(),
+            relative_dates: relative_dates.into_data(),
+            natural_sort: natural_sort.into_data(),
+            binary_units: binary_units.into_data(),
+            smooth_speeds: smooth_speeds.into_data(),
+            ascii_only: ascii_only.into_data(),
+            theme: if high_contrast.into_data() {
+                ThemeName::HighContrast
+            } else {
+                ThemeName::Dracula
+            },
+            date_format,
+            ..config::read().interface.clone()
+        }
+    }
+}
+
+pub(crate) struct Preferences {
+    pub notifications: NotificationsConfig,
+    pub filters: FiltersConfig,
+    pub interface: InterfaceConfig,
+    pub status_bar: StatusBarConfig,
+}
+
+impl Preferences {
+    pub fn apply(self, cfg: &mut config::Config) {
+        cfg.notifications = self.notifications;
+        cfg.filters = self.filters;
+        cfg.interface = self.interface;
+        cfg.status_bar = self.status_bar;
+    }
+}
+
+pub(crate) struct PreferencesView {
+    inner: LinearPanel,
+}
+
+impl PreferencesView {
+    pub fn new() -> Self {
+        let cfg = config::read();
+
+        let notifications = {
+            let n = &cfg.notifications;
+            NotificationsSection::vertical((
+                LabeledCheckbox::new("Enable notifications").with_checked(n.enabled),
+                LabeledCheckbox::new("Desktop notifications (otherwise, ring the bell)")
+                    .with_checked(n.desktop),
+                LabeledCheckbox::new("Torrent finished").with_checked(n.torrent_finished),
+                LabeledCheckbox::new("Torrent added").with_checked(n.torrent_added),
+                LabeledCheckbox::new("Tracker error").with_checked(n.tracker_error),
+                LabeledCheckbox::new("Torrent error").with_checked(n.torrent_error),
+                LabeledCheckbox::new("Watch for new torrent and tracker errors")
+                    .with_checked(n.error_alerts),
+            ))
+        };
+
+        let filters = LabeledCheckbox::new("Show filters with zero hits")
+            .with_checked(cfg.filters.show_zero_hits);
+
+        let interface = {
+            let i = &cfg.interface;
+            InterfaceSection::vertical((
+                LabeledCheckbox::new("Confirm before shutting down the daemon")
+                    .with_checked(i.confirm_shutdown),
+                LabeledCheckbox::new("Show dates relative to now").with_checked(i.relative_dates),
+                LabeledCheckbox::new("Sort names naturally (\"2\" before \"10\", ignoring case)")
+                    .with_checked(i.natural_sort),
+                LabeledCheckbox::new("Binary size units (KiB, MiB) rather than decimal (KB, MB)")
+                    .with_checked(i.binary_units),
+                LabeledCheckbox::new("Smooth out transfer speeds").with_checked(i.smooth_speeds),
+                LabeledCheckbox::new("Only draw ASCII symbols").with_checked(i.ascii_only),
+                LabeledCheckbox::new("High-contrast theme")
+                    .with_checked(i.theme == ThemeName::HighContrast),
+                DateFormatRow::horizontal((
+                    TextView::new("Date format: "),
+                    EditView::new().content(&i.date_format).min_width(20),
+                )),
+            ))
+        };
+
+        let status_bar = StatusBarSection::vertical((
+            LabeledCheckbox::new("Show protocol overhead")
+                .with_checked(cfg.status_bar.show_overhead),
+            LabeledCheckbox::new("Show overhead as a percentage of total traffic")
+                .with_checked(cfg.status_bar.overhead_percentage),
+        ));
+
+        let inner = LinearPanel::vertical()
+            .child(notifications, Some("Notifications"))
+            .child(filters, Some("Filters"))
+            .child(interface, Some("Interface"))
+            .child(status_bar, Some("Status Bar"));
+
+        Self { inner }
+    }
+}
+
+impl ViewWrapper for PreferencesView {
+    cursive::wrap_impl!(self.inner: LinearPanel);
+}
+
+impl Form for PreferencesView {
+    type Data = Preferences;
+
+    fn into_data(self) -> Self::Data {
+        let mut inner = self.inner;
+
+        let notifications = inner.take_child_data::<NotificationsSection>(0);
+        let filters = FiltersConfig {
+            show_zero_hits: inner.take_child_data::<LabeledCheckbox>(0),
+            ..config::read().filters.clone()
+        };
+
+        let interface = inner.take_child_data::<InterfaceSection>(0);
+        let status_bar = inner.take_child_data::<StatusBarSection>(0);
+
+        Preferences {
+            notifications,
+            filters,
+            interface,
+            status_bar,
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use cursive::traits::*;
 use cursive::view::ViewWrapper;
 use cursive::views::{DummyView, LinearLayout, TextView};
 
@@ -17,19 +18,31 @@ const WARNING_TRIANGLE: &str = concat!(
 );
 
 impl RemoveTorrentPrompt {
-    pub fn new_single(name: impl AsRef<str>) -> Self {
+    fn new(question: &str, names: impl View) -> Self {
         let top = LinearLayout::horizontal()
             .child(TextView::new(WARNING_TRIANGLE))
             .child(DummyView)
-            .child(TextView::new("\nRemove the selected torrent?").center());
+            .child(TextView::new(format!("\n{}", question)).center());
 
         let content = LinearLayout::vertical()
             .child(top)
-            .child(TextView::new(name.as_ref()).center())
+            .child(names)
             .child(LabeledCheckbox::new("Include downloaded files"));
 
         Self { inner: content }
     }
+
+    pub fn new_single(name: impl AsRef<str>) -> Self {
+        let names = TextView::new(name.as_ref()).center();
+        Self::new("Remove the selected torrent?", names)
+    }
+
+    pub fn new_multi(names: &[impl AsRef<str>]) -> Self {
+        let question = format!("Remove the {} selected torrents?", names.len());
+        let names = names.iter().map(AsRef::as_ref).collect::<Vec<&str>>();
+        let names = TextView::new(names.join("\n")).scrollable().max_height(10);
+        Self::new(&question, names)
+    }
 }
 
 impl ViewWrapper for RemoveTorrentPrompt {
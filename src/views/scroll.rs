@@ -0,0 +1,66 @@
+use cursive::traits::*;
+use cursive::vec::Vec2;
+use cursive::view::ViewWrapper;
+use cursive::views::{NamedView, ScrollView};
+
+/// A view made of one-line rows that can be told apart across updates.
+pub(crate) trait RowIds {
+    type Id: PartialEq;
+
+    fn row_id(&self, y: usize) -> Option<Self::Id>;
+    fn find_row(&self, id: &Self::Id) -> Option<usize>;
+}
+
+impl<V: RowIds + View> RowIds for NamedView<V> {
+    type Id = V::Id;
+
+    fn row_id(&self, y: usize) -> Option<Self::Id> {
+        self.with_view(|v| v.row_id(y)).flatten()
+    }
+
+    fn find_row(&self, id: &Self::Id) -> Option<usize> {
+        self.with_view(|v| v.find_row(id)).flatten()
+    }
+}
+
+/// A `ScrollView` that keeps the same row at the top when the content changes underneath it.
+pub(crate) struct ScrollWrapper<V: RowIds> {
+    inner: ScrollView<V>,
+    // The row at the top of the view as of the last layout, and the scroll offset back then.
+    anchor: Option<(V::Id, usize)>,
+}
+
+impl<V: RowIds + View> ScrollWrapper<V> {
+    pub fn new(view: V) -> Self {
+        Self {
+            inner: ScrollView::new(view),
+            anchor: None,
+        }
+    }
+}
+
+impl<V: RowIds + View> ViewWrapper for ScrollWrapper<V> {
+    cursive::wrap_impl!(self.inner: ScrollView<V>);
+
+    fn wrap_layout(&mut self, size: Vec2) {
+        self.inner.layout(size);
+
+        let viewport = self.inner.content_viewport();
+
+        // If the offset moved since the last layout, the user scrolled, so leave it be.
+        if let Some((id, offset)) = self.anchor.take() {
+            if offset == viewport.top() {
+                match self.inner.get_inner().find_row(&id) {
+                    Some(y) if y != offset => self.inner.set_offset((viewport.left(), y)),
+                    _ => (),
+                }
+            }
+        }
+
+        let top = self.inner.content_viewport().top();
+        self.anchor = match top {
+            0 => None,
+            _ => self.inner.get_inner().row_id(top).map(|id| (id, top)),
+        };
+    }
+}
@@ -2,12 +2,13 @@ use crate::form::Form;
 use crate::util::digit_width;
 use crate::views::static_linear_layout::StaticLinearLayout;
 
-use cursive::event::{AnyCb, Callback, Event, EventResult};
+use cursive::event::{AnyCb, Callback, Event, EventResult, Key, MouseButton, MouseEvent};
 use cursive::traits::*;
 use cursive::view::{Selector, SizeConstraint, ViewWrapper};
 use cursive::views::{Button, DummyView, EditView, ResizedView, TextView};
-use cursive::Cursive;
+use cursive::{Cursive, Vec2};
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
 use std::{
@@ -30,20 +31,20 @@ pub trait Spinnable:
         None
     }
 
-    fn checked_incr(self) -> Option<Self>;
-    fn checked_decr(self) -> Option<Self>;
+    fn checked_incr(self, step: Self) -> Option<Self>;
+    fn checked_decr(self, step: Self) -> Option<Self>;
 
     fn allows_negative(bounds: &impl RangeBounds<Self>) -> bool {
         let zero = Self::from(0u8);
 
         match bounds.start_bound() {
             Bound::Excluded(min) | Bound::Included(min) => *min < zero,
-            Bound::Unbounded => zero.checked_decr().is_some(),
+            Bound::Unbounded => zero.checked_decr(Self::from(1u8)).is_some(),
         }
     }
 
-    fn clamped_incr(self, bounds: &impl RangeBounds<Self>) -> Self {
-        match (self.checked_incr(), bounds.end_bound()) {
+    fn clamped_incr(self, step: Self, bounds: &impl RangeBounds<Self>) -> Self {
+        match (self.checked_incr(step), bounds.end_bound()) {
             // If incrementing overflows, but the end bound is inclusive, use that.
             (None, Bound::Included(max)) => *max,
 
@@ -56,9 +57,9 @@ pub trait Spinnable:
         }
     }
 
-    fn clamped_decr(self, bounds: &impl RangeBounds<Self>) -> Self {
+    fn clamped_decr(self, step: Self, bounds: &impl RangeBounds<Self>) -> Self {
         // See above
-        match (self.checked_decr(), bounds.start_bound()) {
+        match (self.checked_decr(step), bounds.start_bound()) {
             (None, Bound::Included(min)) => *min,
             (Some(v), _) if bounds.contains(&v) => v,
             _ => self,
@@ -72,11 +73,11 @@ impl Spinnable for u64 {
     fn is_float() -> bool {
         false
     }
-    fn checked_incr(self) -> Option<Self> {
-        self.checked_add(1)
+    fn checked_incr(self, step: Self) -> Option<Self> {
+        self.checked_add(step)
     }
-    fn checked_decr(self) -> Option<Self> {
-        self.checked_sub(1)
+    fn checked_decr(self, step: Self) -> Option<Self> {
+        self.checked_sub(step)
     }
     fn allows_negative(_: &impl RangeBounds<Self>) -> bool {
         false
@@ -89,11 +90,11 @@ impl Spinnable for i64 {
     fn is_float() -> bool {
         false
     }
-    fn checked_incr(self) -> Option<Self> {
-        self.checked_add(1)
+    fn checked_incr(self, step: Self) -> Option<Self> {
+        self.checked_add(step)
     }
-    fn checked_decr(self) -> Option<Self> {
-        self.checked_sub(1)
+    fn checked_decr(self, step: Self) -> Option<Self> {
+        self.checked_sub(step)
     }
 }
 
@@ -103,11 +104,11 @@ impl Spinnable for u16 {
     fn is_float() -> bool {
         false
     }
-    fn checked_incr(self) -> Option<Self> {
-        self.checked_add(1)
+    fn checked_incr(self, step: Self) -> Option<Self> {
+        self.checked_add(step)
     }
-    fn checked_decr(self) -> Option<Self> {
-        self.checked_sub(1)
+    fn checked_decr(self, step: Self) -> Option<Self> {
+        self.checked_sub(step)
     }
     fn allows_negative(_: &impl RangeBounds<Self>) -> bool {
         false
@@ -120,14 +121,26 @@ impl Spinnable for f64 {
     fn is_float() -> bool {
         true
     }
-    fn checked_incr(self) -> Option<Self> {
-        Some(self + 1.0).filter(|x| x.is_finite())
+    // Rounded, so that stepping by 0.1 doesn't show 0.30000000000000004.
+    fn checked_incr(self, step: Self) -> Option<Self> {
+        Some(((self + step) * 1e6).round() / 1e6).filter(|x| x.is_finite())
     }
-    fn checked_decr(self) -> Option<Self> {
-        Some(self - 1.0).filter(|x| x.is_finite())
+    fn checked_decr(self, step: Self) -> Option<Self> {
+        Some(((self - step) * 1e6).round() / 1e6).filter(|x| x.is_finite())
     }
 }
 
+/// How long the - and + buttons have to be held before they start repeating.
+const REPEAT_DELAY: Duration = Duration::from_millis(500);
+
+/// A - or + button that's being held down.
+#[derive(Clone, Copy)]
+struct Held {
+    up: bool,
+    since: Instant,
+    repeated: bool,
+}
+
 type SpinViewInner = StaticLinearLayout<(
     TextView,
     ResizedView<ResizedView<EditView>>,
@@ -140,6 +153,13 @@ type SpinViewInner = StaticLinearLayout<(
 pub(crate) struct SpinView<T: Spinnable, B: RangeBounds<T>> {
     bounds: B,
     val: T,
+    step: T,
+    // For PageUp and PageDown. Ten steps if unset.
+    page_step: Option<T>,
+    // A special value shown as text instead, like Deluge's -1 for "Unlimited".
+    sentinel: Option<(T, &'static str)>,
+    held: Option<Held>,
+    size: Vec2,
     own_id: String,
     inner: SpinViewInner,
     on_modify: Option<Rc<dyn Fn(&mut Cursive, T)>>,
@@ -194,6 +214,11 @@ where
         Self {
             bounds,
             val,
+            step: T::from(1u8),
+            page_step: None,
+            sentinel: None,
+            held: None,
+            size: Vec2::zero(),
             own_id,
             inner,
             on_modify: None,
@@ -206,7 +231,8 @@ where
 
     pub fn set_val(&mut self, new_val: T) -> Callback {
         self.val = new_val;
-        let cb = self.get_edit_view_mut().set_content(new_val.to_string());
+        let content = self.display(new_val);
+        let cb = self.get_edit_view_mut().set_content(content);
         if let Some(f) = self.on_modify.as_ref() {
             let f = f.clone();
             let val = self.val;
@@ -224,6 +250,32 @@ where
         self
     }
 
+    pub fn step(mut self, step: T) -> Self {
+        self.step = step;
+        self
+    }
+
+    pub fn page_step(mut self, page_step: T) -> Self {
+        self.page_step = Some(page_step);
+        self
+    }
+
+    /// Shows `val` as `text`, which (or an empty field) can also be typed in to mean `val`.
+    pub fn sentinel(mut self, val: T, text: &'static str) -> Self {
+        self.sentinel = Some((val, text));
+        if self.val == val {
+            let _: Callback = self.set_val(val);
+        }
+        self
+    }
+
+    fn display(&self, val: T) -> String {
+        match self.sentinel {
+            Some((sentinel, text)) if sentinel == val => String::from(text),
+            _ => val.to_string(),
+        }
+    }
+
     pub fn set_on_modify<F: Fn(&mut Cursive, T) + 'static>(&mut self, cb: F) {
         self.on_modify = Some(Rc::new(cb));
     }
@@ -250,6 +302,14 @@ where
     }
 
     fn parse_content(&mut self, content: &str) {
+        if let Some((sentinel, text)) = self.sentinel {
+            let content = content.trim();
+            if content.is_empty() || content.eq_ignore_ascii_case(text) {
+                self.val = sentinel;
+                return;
+            }
+        }
+
         if let Ok(v) = content.parse::<T>() {
             if self.bounds.contains(&v) {
                 self.val = v;
@@ -265,15 +325,94 @@ where
     }
 
     fn decr(&mut self) -> Callback {
-        let new_val = self.val.clamped_decr(&self.bounds);
+        let mut new_val = self.val.clamped_decr(self.step, &self.bounds);
+        // Stepping down past the lowest real value lands on a lower sentinel, if any.
+        if let Some((sentinel, _)) = self.sentinel {
+            if new_val == self.val && sentinel < self.val {
+                new_val = sentinel;
+            }
+        }
         self.set_val(new_val)
     }
 
     fn incr(&mut self) -> Callback {
-        let new_val = self.val.clamped_incr(&self.bounds);
+        let new_val = match self.sentinel {
+            // -1 + 50 would be a strange place to end up; start counting from zero instead.
+            Some((sentinel, _)) if sentinel == self.val && sentinel < T::default() => T::default(),
+            _ => self.val.clamped_incr(self.step, &self.bounds),
+        };
         self.set_val(new_val)
     }
 
+    fn page(&mut self, up: bool) -> Callback {
+        let mut new_val = self.val;
+        match self.page_step {
+            Some(step) if up => new_val = new_val.clamped_incr(step, &self.bounds),
+            Some(step) => new_val = new_val.clamped_decr(step, &self.bounds),
+            None => {
+                for _ in 0..10 {
+                    new_val = if up {
+                        new_val.clamped_incr(self.step, &self.bounds)
+                    } else {
+                        new_val.clamped_decr(self.step, &self.bounds)
+                    };
+                }
+            }
+        }
+        self.set_val(new_val)
+    }
+
+    /// Whether a position is over the + button (true) or the - button (false), if either.
+    fn button_at(&self, pos: Vec2) -> Option<bool> {
+        // The buttons are the last thing in the row, three columns each.
+        let x = self.size.x;
+        if pos.x >= x.saturating_sub(3) {
+            Some(true)
+        } else if pos.x >= x.saturating_sub(6) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    fn on_mouse(&mut self, pos: Option<Vec2>, event: MouseEvent) -> EventResult {
+        let inside = pos.map_or(false, |p| p.x < self.size.x && p.y < self.size.y);
+
+        match event {
+            MouseEvent::WheelUp if inside => EventResult::Consumed(Some(self.incr())),
+            MouseEvent::WheelDown if inside => EventResult::Consumed(Some(self.decr())),
+            MouseEvent::Press(MouseButton::Left) => {
+                self.held = pos
+                    .filter(|_| inside)
+                    .and_then(|p| self.button_at(p))
+                    .map(|up| Held {
+                        up,
+                        since: Instant::now(),
+                        repeated: false,
+                    });
+                EventResult::Ignored
+            }
+            // The button would step once more on release, which is one too many after repeating.
+            MouseEvent::Release(_) => match self.held.take() {
+                Some(held) if held.repeated => EventResult::Consumed(None),
+                _ => EventResult::Ignored,
+            },
+            _ => EventResult::Ignored,
+        }
+    }
+
+    /// Steps again if a button has been held long enough. Relies on the periodic refresh.
+    fn repeat(&mut self) -> EventResult {
+        match &mut self.held {
+            Some(held) if held.since.elapsed() >= REPEAT_DELAY => {
+                held.repeated = true;
+                let cb = if held.up { self.incr() } else { self.decr() };
+                EventResult::Consumed(Some(cb))
+            }
+            _ => EventResult::Ignored,
+        }
+    }
+
     fn submit(&mut self) -> Callback {
         self.set_val(self.val)
     }
@@ -285,12 +424,40 @@ where
 {
     cursive::wrap_impl!(self.inner: SpinViewInner);
 
+    fn wrap_layout(&mut self, size: Vec2) {
+        self.size = size;
+        self.inner.layout(size);
+    }
+
     fn wrap_on_event(&mut self, event: Event) -> EventResult {
+        match event {
+            Event::Key(Key::PageUp) => return EventResult::Consumed(Some(self.page(true))),
+            Event::Key(Key::PageDown) => return EventResult::Consumed(Some(self.page(false))),
+            Event::Refresh => {
+                if let result @ EventResult::Consumed(_) = self.repeat() {
+                    return result;
+                }
+            }
+            Event::Mouse {
+                offset,
+                position,
+                event,
+            } => {
+                let result = self.on_mouse(position.checked_sub(offset), event);
+                if result.is_consumed() {
+                    return result;
+                }
+            }
+            _ => self.held = None,
+        }
+
         if self.inner.get_focus_index() == 0 {
             if let Event::Char(ch) = event {
                 match ch {
                     '0'..='9' => (),
 
+                    c if c.is_ascii_alphabetic() && self.sentinel.is_some() => (),
+
                     '.' if T::is_float() && !self.get_content().contains('.') => (),
 
                     '-' if T::allows_negative(&self.bounds)
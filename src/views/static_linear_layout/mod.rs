@@ -24,8 +24,10 @@ pub struct StaticLinearLayout<T> {
 struct ChildMetadata {
     required_size: Vec2,
     last_size: Vec2,
-    #[allow(dead_code)]
+    // Children with a nonzero weight split whatever space the others leave, in proportion.
     weight: usize,
+    // Hidden children take up no space, and can't be focused.
+    visible: bool,
 }
 
 impl Default for ChildMetadata {
@@ -34,6 +36,7 @@ impl Default for ChildMetadata {
             required_size: Vec2::zero(),
             last_size: Vec2::zero(),
             weight: 0,
+            visible: true,
         }
     }
 }
@@ -85,6 +88,23 @@ impl<'a, I: Iterator<Item = (usize, &'a ChildMetadata)>> Iterator for ChildRefIt
     }
 }
 
+/// Splits `space` in proportion to `weights`, handing out any remainder from the front.
+fn distribute(weights: &[usize], space: usize) -> Vec<usize> {
+    let total: usize = weights.iter().sum();
+    let mut lengths: Vec<usize> = weights.iter().map(|w| space * w / total).collect();
+
+    let mut leftover = space - lengths.iter().sum::<usize>();
+    for (length, _) in lengths.iter_mut().zip(weights).filter(|(_, &w)| w > 0) {
+        if leftover == 0 {
+            break;
+        }
+        *length += 1;
+        leftover -= 1;
+    }
+
+    lengths
+}
+
 fn cap<'a, I: Iterator<Item = &'a mut usize>>(iter: I, max: usize) {
     let mut available = max;
     for item in iter {
@@ -116,12 +136,40 @@ impl<T: ViewTuple> StaticLinearLayout<T> {
         self
     }
 
+    pub fn set_visible(&mut self, i: usize, visible: bool) {
+        if self.child_metadata[i].visible == visible {
+            return;
+        }
+        self.child_metadata[i].visible = visible;
+        self.invalidate();
+
+        if !visible && self.focus == i {
+            // Don't leave focus stranded on something that isn't there.
+            if let EventResult::Ignored = self.move_focus(direction::Direction::front()) {
+                self.move_focus(direction::Direction::back());
+            }
+        }
+    }
+
+    pub fn visible(mut self, i: usize, visible: bool) -> Self {
+        self.set_visible(i, visible);
+        self
+    }
+
+    pub fn is_visible(&self, i: usize) -> bool {
+        self.child_metadata[i].visible
+    }
+
+    pub fn toggle_visible(&mut self, i: usize) {
+        self.set_visible(i, !self.is_visible(i));
+    }
+
     pub fn len(&self) -> usize {
         T::LEN
     }
 
     pub fn set_focus_index(&mut self, index: usize) -> Result<(), ()> {
-        if index >= self.len() {
+        if index >= self.len() || !self.is_visible(index) {
             Err(())
         } else if let Ok(_) = self
             .children
@@ -207,6 +255,9 @@ impl<T: ViewTuple> StaticLinearLayout<T> {
                     break EventResult::Ignored;
                 }
                 focus -= 1;
+                if !self.is_visible(focus) {
+                    continue;
+                }
                 if let Ok(x) = self.children.take_focus(focus, source) {
                     self.focus = focus;
                     break x;
@@ -217,6 +268,9 @@ impl<T: ViewTuple> StaticLinearLayout<T> {
                 if focus == self.len() {
                     break EventResult::Ignored;
                 }
+                if !self.is_visible(focus) {
+                    continue;
+                }
                 if let Ok(x) = self.children.take_focus(focus, source) {
                     self.focus = focus;
                     break x;
@@ -250,6 +304,9 @@ impl<T: ViewTuple> StaticLinearLayout<T> {
                 // TODO: get actual width (not super important)
                 usize::MAX,
             ) {
+                if !item.child.visible {
+                    continue;
+                }
                 let child_size = item.child.last_size.get(self.orientation);
                 if item.offset + child_size > position {
                     if let Ok(_) = self
@@ -273,6 +330,9 @@ impl<T: ViewTuple + 'static> View for StaticLinearLayout<T> {
             self.orientation,
             *printer.size.get(self.orientation),
         ) {
+            if !item.child.visible {
+                continue;
+            }
             let printer = &printer
                 .offset(self.orientation.make_vec(item.offset, 0))
                 .cropped(item.child.last_size)
@@ -296,6 +356,10 @@ impl<T: ViewTuple + 'static> View for StaticLinearLayout<T> {
         let mut sizes = Vec::with_capacity(self.len());
 
         for item in ChildRefIter::new(self.child_metadata.iter().enumerate(), o, *size.get(o)) {
+            if !item.child.visible {
+                sizes.push(Vec2::zero());
+                continue;
+            }
             let size = size.with_axis(o, item.length);
             self.children.layout(item.index, size);
             sizes.push(size);
@@ -316,12 +380,52 @@ impl<T: ViewTuple + 'static> View for StaticLinearLayout<T> {
         let mut metadata = std::mem::take(&mut self.child_metadata);
 
         let ideal_sizes = self.children.with_each_mut(|t, i| {
+            if !metadata[i].visible {
+                metadata[i].required_size = Vec2::zero();
+                return Vec2::zero();
+            }
             let required_size = t.required_size(i, req);
             metadata[i].required_size = required_size;
             required_size
         });
         let ideal = o.stack(ideal_sizes.iter().copied());
 
+        let weights: Vec<usize> = metadata
+            .iter()
+            .map(|c| if c.visible { c.weight } else { 0 })
+            .collect();
+        if weights.iter().any(|&w| w > 0) {
+            let fixed: usize = ideal_sizes
+                .iter()
+                .zip(&weights)
+                .filter(|(_, &w)| w == 0)
+                .map(|(v, _)| *v.get(o))
+                .sum();
+
+            if fixed <= *req.get(o) {
+                let shares = distribute(&weights, req.get(o) - fixed);
+
+                for i in 0..self.len() {
+                    if !metadata[i].visible {
+                        continue;
+                    }
+                    let length = if weights[i] == 0 {
+                        *ideal_sizes[i].get(o)
+                    } else {
+                        shares[i]
+                    };
+                    let size = self.children.required_size(i, req.with_axis(o, length));
+                    metadata[i].required_size = size.with_axis(o, length);
+                }
+
+                let weighted = o.stack(metadata.iter().map(|c| c.required_size));
+
+                self.cache = Some(SizeCache::build(weighted, req));
+                self.child_metadata = metadata;
+                return weighted;
+            }
+        }
+
         if ideal.fits_in(req) {
             self.cache = Some(SizeCache::build(ideal, req));
             self.child_metadata = metadata;
@@ -331,6 +435,9 @@ impl<T: ViewTuple + 'static> View for StaticLinearLayout<T> {
         let budget_req = req.with_axis(o, 1);
 
         let min_sizes = self.children.with_each_mut(|t, i| {
+            if !metadata[i].visible {
+                return Vec2::zero();
+            }
             let required_size = t.required_size(i, budget_req);
             metadata[i].required_size = required_size;
             required_size
@@ -378,6 +485,9 @@ impl<T: ViewTuple + 'static> View for StaticLinearLayout<T> {
             .collect();
 
         for i in 0..self.len() {
+            if !metadata[i].visible {
+                continue;
+            }
             let size = self.children.required_size(i, final_lengths[i]);
             metadata[i].required_size = size;
         }
@@ -395,6 +505,9 @@ impl<T: ViewTuple + 'static> View for StaticLinearLayout<T> {
             Ok(self.move_focus(source))
         } else {
             for i in 0..self.len() {
+                if !self.is_visible(i) {
+                    continue;
+                }
                 if let Ok(x) = self.children.take_focus(i, source) {
                     return Ok(x);
                 }
@@ -458,6 +571,9 @@ impl<T: ViewTuple + 'static> View for StaticLinearLayout<T> {
 
     fn focus_view(&mut self, selector: &Selector<'_>) -> Result<EventResult, ViewNotFound> {
         for i in 0..self.len() {
+            if !self.is_visible(i) {
+                continue;
+            }
             if let Ok(x) = self.children.focus_view(i, selector) {
                 return Ok(x);
             }
@@ -1,8 +1,11 @@
 use super::StaticLinearLayout;
+use crate::util::glyphs::glyphs;
+use crate::views::linear_panel::TOGGLE_EVENT;
 
 use cursive::{
-    direction::Orientation,
-    view::{View, ViewWrapper},
+    direction::{Direction, Orientation},
+    event::{Event, EventResult, Key, MouseButton, MouseEvent},
+    view::{CannotFocus, View, ViewWrapper},
     views::PaddedView,
     Printer, Vec2,
 };
@@ -11,6 +14,8 @@ pub struct Child<T> {
     inner: PaddedView<T>,
     orientation: Orientation,
     title: Option<String>,
+    // Only titled children in a vertical panel can be collapsed, down to just their title.
+    collapsed: bool,
 }
 
 impl<T: View> Child<T> {
@@ -24,6 +29,33 @@ impl<T: View> Child<T> {
             inner,
             orientation,
             title,
+            collapsed: false,
+        }
+    }
+
+    fn is_collapsible(&self) -> bool {
+        self.orientation == Orientation::Vertical && self.title.is_some()
+    }
+
+    fn toggle_collapsed(&mut self) -> EventResult {
+        self.collapsed = !self.collapsed;
+        EventResult::Consumed(None)
+    }
+
+    fn is_title_click(&self, event: &Event) -> bool {
+        match (event, &self.title) {
+            (
+                Event::Mouse {
+                    offset,
+                    position,
+                    event: MouseEvent::Press(MouseButton::Left),
+                },
+                Some(title),
+            ) => match position.checked_sub(*offset) {
+                Some(pos) => pos.y == 0 && (1..title.chars().count() + 5).contains(&pos.x),
+                None => false,
+            },
+            _ => false,
         }
     }
 
@@ -48,41 +80,86 @@ impl<T: View> ViewWrapper for Child<T> {
     fn wrap_draw(&self, printer: &Printer) {
         let Vec2 { x: px, y: py } = printer.size;
         let (px1, py1) = (px.saturating_sub(1), py.saturating_sub(1));
+        let g = glyphs();
         match self.orientation {
             Orientation::Vertical => {
-                printer.print_vline((0, 0), px, "│");
-                printer.print_vline((px1, 0), py, "│");
+                printer.print_vline((0, 0), px, g.vline);
+                printer.print_vline((px1, 0), py, g.vline);
                 printer.print_hdelim((0, 0), px);
             }
             Orientation::Horizontal => {
-                printer.print_hline((0, 0), px, "─");
-                printer.print_hline((0, py1), px, "─");
-                printer.print_vline((0, 0), py, "│");
-                printer.print((0, 0), "┬");
-                printer.print((0, py), "┴");
+                printer.print_hline((0, 0), px, g.hline);
+                printer.print_hline((0, py1), px, g.hline);
+                printer.print_vline((0, 0), py, g.vline);
+                printer.print((0, 0), g.tee_down);
+                printer.print((0, py), g.tee_up);
             }
         }
 
         let shrinkage = self.orientation.make_vec(0, 1);
 
         if let Some(title) = &self.title {
-            let text = format!("┤{}├", title);
+            let text = if self.is_collapsible() {
+                let marker = if self.collapsed {
+                    g.collapsed
+                } else {
+                    g.expanded
+                };
+                format!("{}{} {}{}", g.title_left, marker, title, g.title_right)
+            } else {
+                format!("{}{}{}", g.title_left, title, g.title_right)
+            };
+            let focused = self.collapsed && printer.focused;
             printer
                 .offset((1, 0))
                 .shrinked(shrinkage)
-                .print((0, 0), &text);
+                .with_selection(focused, |p| p.print((0, 0), &text));
         }
 
-        self.inner.draw(printer)
+        if !self.collapsed {
+            self.inner.draw(printer)
+        }
     }
 
     fn wrap_required_size(&mut self, req: Vec2) -> Vec2 {
         let mut req = self.inner.required_size(req);
         if let Some(title) = &self.title {
-            req.x = req.x.max(title.len() + 4);
+            req.x = req.x.max(title.chars().count() + 6);
+        }
+        if self.collapsed {
+            req.y = 1;
         }
         req
     }
+
+    fn wrap_layout(&mut self, size: Vec2) {
+        if !self.collapsed {
+            self.inner.layout(size);
+        }
+    }
+
+    fn wrap_take_focus(&mut self, source: Direction) -> Result<EventResult, CannotFocus> {
+        if self.collapsed {
+            // Still focusable, so that it can be expanded from the keyboard.
+            Ok(EventResult::Consumed(None))
+        } else {
+            self.inner.take_focus(source)
+        }
+    }
+
+    fn wrap_on_event(&mut self, event: Event) -> EventResult {
+        if self.is_collapsible() {
+            if event == TOGGLE_EVENT || self.is_title_click(&event) {
+                return self.toggle_collapsed();
+            } else if self.collapsed {
+                return match event {
+                    Event::Key(Key::Enter) => self.toggle_collapsed(),
+                    _ => EventResult::Ignored,
+                };
+            }
+        }
+        self.inner.on_event(event)
+    }
 }
 
 pub struct StaticLinearPanel<T> {
@@ -153,11 +230,12 @@ macro_rules! impls {
 
                     let (x, y) = printer.size.saturating_sub((1, 1)).pair();
 
-                    printer.print_hline((0, y), x, "─");
+                    let g = glyphs();
+                    printer.print_hline((0, y), x, g.hline);
 
                     for (pos, ch) in Iterator::zip(
                         [(0, 0), (x, 0), (0, y), (x, y)].iter(),
-                        ["┌", "┐", "└", "┘"].iter(),
+                        g.corners.iter(),
                     ) {
                         printer.print(*pos, ch);
                     }
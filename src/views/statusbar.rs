@@ -1,18 +1,30 @@
+use super::path_edit::PathEdit;
 use super::thread::ViewThread;
-use crate::util;
-use crate::SessionHandle;
+use crate::form::Form;
+use crate::menu;
+use crate::rpc;
+use crate::util::glyphs::glyphs;
+use crate::util::{self, Ema};
+use crate::{AppState, Selection, SessionHandle};
 use async_trait::async_trait;
+use cursive::direction::Direction;
+use cursive::event::{Event, EventResult, MouseButton, MouseEvent};
+use cursive::theme::{BaseColor, ColorStyle, Effect};
 use cursive::traits::*;
-use cursive::Printer;
-use deluge_rpc::{Query, Session};
+use cursive::utils::markup::StyledString;
+use cursive::view::CannotFocus;
+use cursive::views::{Dialog, SelectView};
+use cursive::{Cursive, Printer, Vec2};
+use deluge_rpc::{InfoHash, Query, Session};
 use serde::Deserialize;
+use std::collections::{BTreeSet, HashSet, VecDeque};
 use std::fmt::{self, Display, Formatter};
 use std::net::IpAddr;
 use std::sync::{Arc, RwLock};
 use tokio::sync::watch;
 use tokio::time;
 
-#[derive(Default, Debug, Clone, Copy)]
+#[derive(Default, Debug, Clone)]
 struct StatusBarData {
     connected: bool,
 
@@ -22,10 +34,27 @@ struct StatusBarData {
     max_download_rate: f64,
     upload_rate: u64,
     max_upload_rate: f64,
+    total_traffic: (u64, u64),
     protocol_traffic: (u64, u64),
+    show_overhead: bool,
+    overhead_percentage: bool,
     free_space: u64,
+    // The selected torrent's download location and its free space, if it's somewhere else.
+    selected_space: Option<(String, u64)>,
+    // Download locations that are running out of room, and how much room they have left.
+    low_space: Vec<(String, u64)>,
     ip: Option<IpAddr>,
     dht_nodes: u64,
+    has_incoming_connections: bool,
+    dht: bool,
+    lsd: bool,
+    utpex: bool,
+    // The parts that failed to update last time, and are showing old values.
+    stale: Vec<&'static str>,
+    // Shared with the session handle, so that it's current whichever thread stalled.
+    responsiveness: Arc<rpc::Responsiveness>,
+    // Toasts waiting their turn, oldest first.
+    toasts: VecDeque<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Query)]
@@ -38,6 +67,7 @@ struct StatusQuery {
     payload_upload_rate: f64,
     #[serde(rename = "dht.dht_nodes")]
     dht_nodes: u64,
+    has_incoming_connections: bool,
 }
 
 #[derive(Debug, Clone, Deserialize, Query)]
@@ -45,6 +75,22 @@ struct ConfigQuery {
     max_connections_global: i64,
     max_download_speed: f64,
     max_upload_speed: f64,
+    dht: bool,
+    lsd: bool,
+    utpex: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Query)]
+struct LocationQuery {
+    download_location: String,
+}
+
+/// The parts of the status bar that do something when clicked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Segment {
+    FreeSpace,
+    LowSpace,
+    Network,
 }
 
 impl Display for StatusBarData {
@@ -53,92 +99,400 @@ impl Display for StatusBarData {
             return write!(f, "Not Connected");
         }
 
-        f.write_str(" ⇄ ")?;
+        let g = glyphs();
+
+        write!(f, " {} ", g.peers)?;
         f.write_str(&util::fmt::pair(|x| x, self.num_peers, self.max_peers))?;
         f.write_str(" ")?;
 
-        f.write_str(" ↓ ")?;
+        write!(f, " {} ", g.down)?;
         f.write_str(&util::fmt::speed_pair(
             self.download_rate,
             self.max_download_rate,
         ))?;
         f.write_str(" ")?;
 
-        f.write_str(" ↑ ")?;
+        write!(f, " {} ", g.up)?;
         f.write_str(&util::fmt::speed_pair(
             self.upload_rate,
             self.max_upload_rate,
         ))?;
         f.write_str(" ")?;
 
-        write!(
-            f,
-            " ⇵ {}:{} B/s ",
-            self.protocol_traffic.0, self.protocol_traffic.1
-        )?;
+        if self.show_overhead {
+            let (down, up) = self.protocol_traffic;
+            if self.overhead_percentage {
+                let total = self.total_traffic.0 + self.total_traffic.1;
+                let percentage = if total == 0 {
+                    0.0
+                } else {
+                    (down + up) as f64 / total as f64 * 100.0
+                };
+                write!(f, " {} {:.1}% ", g.overhead, percentage)?;
+            } else {
+                write!(
+                    f,
+                    " {} {}{}/s {}{}/s ",
+                    g.overhead,
+                    g.down,
+                    util::fmt::bytes(down),
+                    g.up,
+                    util::fmt::bytes(up)
+                )?;
+            }
+        }
 
-        write!(f, " 💾 {} ", util::fmt::bytes(self.free_space))?;
+        Ok(())
+    }
+}
 
-        if let Some(ip) = self.ip {
-            write!(f, " IP: {} ", ip)?;
-        } else {
-            write!(f, " IP: N/A ")?;
+impl StatusBarData {
+    fn segments(&self) -> Vec<(Option<Segment>, String)> {
+        let mut segments = vec![(None, self.to_string())];
+        if !self.connected {
+            return segments;
+        }
+
+        if !self.responsiveness.is_responding() {
+            segments.push((
+                None,
+                format!(" {} Daemon not responding ", glyphs().warning),
+            ));
         }
 
-        write!(f, " DHT: {}", self.dht_nodes)?;
+        segments.push((Some(Segment::FreeSpace), self.free_space()));
 
-        Ok(())
+        if let Some(warning) = self.low_space_warning() {
+            segments.push((Some(Segment::LowSpace), warning));
+        }
+
+        let ip = match self.ip {
+            Some(ip) => format!(" IP: {} ", ip),
+            None => String::from(" IP: N/A "),
+        };
+        segments.push((None, ip));
+
+        segments.push((Some(Segment::Network), self.network_indicators()));
+
+        if !self.stale.is_empty() {
+            segments.push((
+                None,
+                format!("  {} stale: {}", glyphs().warning, self.stale.join(", ")),
+            ));
+        }
+        segments
+    }
+
+    fn segment_at(&self, x: usize) -> Option<Segment> {
+        let mut start = 0;
+        for (segment, text) in self.segments() {
+            let end = start + StyledString::plain(text).width();
+            if (start..end).contains(&x) {
+                return segment;
+            }
+            start = end;
+        }
+        None
+    }
+
+    /// Free space in the monitored path, plus in the selected torrent's download location.
+    /// Clicking on this picks which path to monitor.
+    fn free_space(&self) -> String {
+        let mut s = format!(" {} {} ", glyphs().disk, util::fmt::bytes(self.free_space));
+        if let Some((path, space)) = &self.selected_space {
+            let path = util::fmt::truncate(path, 24);
+            s += &format!("({}: {}) ", path, util::fmt::bytes(*space));
+        }
+        s
+    }
+
+    /// Clicking on this lists every download location that's running low.
+    fn low_space_warning(&self) -> Option<String> {
+        let warning = glyphs().warning;
+        match self.low_space.as_slice() {
+            [] => None,
+            [(path, space)] => Some(format!(
+                " {} Low space: {} ({}) ",
+                warning,
+                util::fmt::truncate(path, 24),
+                util::fmt::bytes(*space),
+            )),
+            all => Some(format!(" {} Low space: {} locations ", warning, all.len())),
+        }
+    }
+
+    /// Whether the daemon can be reached from outside, and how it's finding peers.
+    /// Clicking on these opens the daemon's network preferences.
+    fn network_indicators(&self) -> String {
+        if !self.connected {
+            return String::new();
+        }
+
+        let g = glyphs();
+        let check = |b: bool| if b { g.check } else { g.cross };
+
+        let dht = if self.dht {
+            self.dht_nodes.to_string()
+        } else {
+            String::from("off")
+        };
+
+        format!(
+            " Port: {}  DHT: {}  LSD: {}  PEX: {}",
+            check(self.has_incoming_connections),
+            dht,
+            check(self.lsd),
+            check(self.utpex),
+        )
     }
 }
 
 pub(crate) struct StatusBarView {
     data: Arc<RwLock<StatusBarData>>,
+    // A short message shown at the right end of the bar, and when it was posted.
+    toast: Option<(String, time::Instant)>,
+}
+
+const TOAST_DURATION: time::Duration = time::Duration::from_secs(5);
+
+// The external IP and free space rarely change, so they're fetched less often than the rates.
+const IP_INTERVAL: time::Duration = time::Duration::from_secs(5 * 60);
+const SPACE_INTERVAL: time::Duration = time::Duration::from_secs(30);
+// Checking every download location means a request per location, so it's done even less often.
+const LOW_SPACE_INTERVAL: time::Duration = time::Duration::from_secs(2 * 60);
+
+fn is_due(last: Option<time::Instant>, interval: time::Duration) -> bool {
+    last.map_or(true, |t| t.elapsed() >= interval)
 }
 
 struct StatusBarViewThread {
     data: Arc<RwLock<StatusBarData>>,
+    session_recv: watch::Receiver<SessionHandle>,
+    selection: Selection,
+    download_avg: Ema,
+    upload_avg: Ema,
+    ip_fetched: Option<time::Instant>,
+    space_fetched: Option<time::Instant>,
+    // What the free space was last fetched for: the monitored path and the selected torrent.
+    space_key: (Option<String>, Option<InfoHash>),
+    low_space_fetched: Option<time::Instant>,
+    // The locations that have already been toasted about, until they have room again.
+    low_space_warned: HashSet<String>,
 }
 
 impl StatusBarViewThread {
-    pub(crate) fn new(data: Arc<RwLock<StatusBarData>>) -> Self {
-        Self { data }
+    pub(crate) fn new(
+        data: Arc<RwLock<StatusBarData>>,
+        session_recv: watch::Receiver<SessionHandle>,
+        selection: Selection,
+    ) -> Self {
+        Self {
+            data,
+            session_recv,
+            selection,
+            download_avg: Ema::default(),
+            upload_avg: Ema::default(),
+            ip_fetched: None,
+            space_fetched: None,
+            space_key: (None, None),
+            low_space_fetched: None,
+            low_space_warned: HashSet::new(),
+        }
     }
-}
 
-#[async_trait]
-impl ViewThread for StatusBarViewThread {
-    async fn update(&mut self, session: &Session) -> deluge_rpc::Result<()> {
-        let (status, config, ip, space) = tokio::try_join!(
-            session.get_session_status::<StatusQuery>(),
-            session.get_config_values::<ConfigQuery>(),
-            session.get_external_ip(),
-            session.get_free_space(None),
-        )?;
+    fn apply_status(&mut self, status: StatusQuery) {
+        let (smooth_speeds, samples, show_overhead, overhead_percentage) = {
+            let cfg = crate::config::read();
+            (
+                cfg.interface.smooth_speeds,
+                cfg.interface.speed_samples,
+                cfg.status_bar.show_overhead,
+                cfg.status_bar.overhead_percentage,
+            )
+        };
+        let download_avg = self
+            .download_avg
+            .update(status.payload_download_rate as u64, samples);
+        let upload_avg = self
+            .upload_avg
+            .update(status.payload_upload_rate as u64, samples);
+        crate::bandwidth::record(
+            status.payload_download_rate as u64,
+            status.payload_upload_rate as u64,
+        );
+
+        let mut data = self.data.write().unwrap();
+
+        data.num_peers = status.num_peers_connected;
+        if smooth_speeds {
+            data.download_rate = download_avg;
+            data.upload_rate = upload_avg;
+        } else {
+            data.download_rate = status.payload_download_rate as u64;
+            data.upload_rate = status.payload_upload_rate as u64;
+        }
+        data.dht_nodes = status.dht_nodes;
+        data.has_incoming_connections = status.has_incoming_connections;
+
+        data.total_traffic = (status.download_rate as u64, status.upload_rate as u64);
+        data.protocol_traffic = (
+            (status.download_rate - status.payload_download_rate).max(0.0) as u64,
+            (status.upload_rate - status.payload_upload_rate).max(0.0) as u64,
+        );
+        data.show_overhead = show_overhead;
+        data.overhead_percentage = overhead_percentage;
+    }
+
+    fn apply_config(&mut self, config: ConfigQuery) {
+        let mut data = self.data.write().unwrap();
+
+        data.max_peers = match config.max_connections_global {
+            n if n > 0 => Some(n as u64),
+            _ => None,
+        };
+        data.max_download_rate = config.max_download_speed;
+        data.max_upload_rate = config.max_upload_speed;
+        data.dht = config.dht;
+        data.lsd = config.lsd;
+        data.utpex = config.utpex;
+    }
+
+    async fn update_ip(&mut self, session: &Session) -> deluge_rpc::Result<()> {
+        if !is_due(self.ip_fetched, IP_INTERVAL) {
+            return Ok(());
+        }
+
+        let ip = session.get_external_ip().await?;
+        self.data.write().unwrap().ip = Some(ip);
+        self.ip_fetched = Some(time::Instant::now());
+        Ok(())
+    }
+
+    async fn update_free_space(&mut self, session: &Session) -> deluge_rpc::Result<()> {
+        let path = crate::config::read().status_bar.free_space_path.clone();
+        let selected = *self.selection.read().unwrap();
+        let key = (path, selected);
+
+        // Picking a different path or torrent shouldn't have to wait for the next round.
+        if !is_due(self.space_fetched, SPACE_INTERVAL) && key == self.space_key {
+            return Ok(());
+        }
+
+        let space = session.get_free_space(key.0.as_deref()).await?;
+
+        let selected_space = match selected {
+            Some(hash) => {
+                let location = session
+                    .get_torrent_status::<LocationQuery>(hash)
+                    .await?
+                    .download_location;
+                if Some(&location) == key.0.as_ref() {
+                    None
+                } else {
+                    let space = session.get_free_space(Some(location.as_str())).await?;
+                    Some((location, space))
+                }
+            }
+            None => None,
+        };
 
-        /* stupid async borrow checker */
         {
             let mut data = self.data.write().unwrap();
+            data.free_space = space;
+            data.selected_space = selected_space;
+        }
+        self.space_fetched = Some(time::Instant::now());
+        self.space_key = key;
+        Ok(())
+    }
 
-            data.connected = true;
+    /// Checks every torrent's download location against the low space threshold,
+    /// since a full disk quietly stalls everything downloading to it.
+    async fn update_low_space(&mut self, session: &Session) -> deluge_rpc::Result<()> {
+        if !is_due(self.low_space_fetched, LOW_SPACE_INTERVAL) {
+            return Ok(());
+        }
+        // Even if this goes wrong partway, it waits for the next interval to try again.
+        self.low_space_fetched = Some(time::Instant::now());
 
-            data.ip = Some(ip);
-            data.free_space = space;
+        let threshold = crate::config::read().status_bar.low_space_threshold;
+        let mut low_space = Vec::new();
 
-            data.num_peers = status.num_peers_connected;
-            data.download_rate = status.payload_download_rate as u64;
-            data.upload_rate = status.payload_upload_rate as u64;
-            data.dht_nodes = status.dht_nodes;
+        if threshold > 0 {
+            let locations: BTreeSet<String> = session
+                .get_torrents_status::<LocationQuery>(None)
+                .await?
+                .into_values()
+                .map(|q| q.download_location)
+                .collect();
+
+            for location in locations {
+                // A location that's gone missing, or that the daemon can't check, is no reason
+                // to stop checking the rest.
+                let space = match session.get_free_space(Some(location.as_str())).await {
+                    Ok(space) => space,
+                    Err(_) => continue,
+                };
+                if space < threshold {
+                    low_space.push((location, space));
+                }
+            }
+        }
+
+        self.low_space_warned
+            .retain(|path| low_space.iter().any(|(p, _)| p == path));
+        for (path, space) in &low_space {
+            if self.low_space_warned.insert(path.clone()) {
+                let msg = format!("Only {} left in {}", util::fmt::bytes(*space), path);
+                self.data.write().unwrap().toasts.push_back(msg);
+            }
+        }
+
+        self.data.write().unwrap().low_space = low_space;
+        Ok(())
+    }
+}
 
-            data.protocol_traffic.0 = (status.download_rate - status.payload_download_rate) as u64;
-            data.protocol_traffic.0 = (status.upload_rate - status.payload_upload_rate) as u64;
+#[async_trait]
+impl ViewThread for StatusBarViewThread {
+    async fn reload(&mut self, session: &Session) -> deluge_rpc::Result<()> {
+        if let Some(responsiveness) = self.session_recv.borrow().get_responsiveness() {
+            self.data.write().unwrap().responsiveness = responsiveness.clone();
+        }
+        self.update(session).await
+    }
+
+    async fn update(&mut self, session: &Session) -> deluge_rpc::Result<()> {
+        // Each part of the status bar gets by on its own, keeping its last known values
+        // if its query fails, so that e.g. a failed IP lookup doesn't blank everything.
+        let (status, config) = tokio::join!(
+            session.get_session_status::<StatusQuery>(),
+            session.get_config_values::<ConfigQuery>(),
+        );
 
-            data.max_peers = match config.max_connections_global {
-                n if n > 0 => Some(n as u64),
-                _ => None,
-            };
-            data.max_download_rate = config.max_download_speed;
-            data.max_upload_rate = config.max_upload_speed;
+        let mut stale = Vec::new();
+
+        match status {
+            Ok(status) => self.apply_status(status),
+            Err(_) => stale.push("rates"),
+        }
+        match config {
+            Ok(config) => self.apply_config(config),
+            Err(_) => stale.push("limits"),
+        }
+        if self.update_ip(session).await.is_err() {
+            stale.push("IP");
+        }
+        if self.update_free_space(session).await.is_err() {
+            stale.push("free space");
         }
+        if self.update_low_space(session).await.is_err() {
+            stale.push("low space check");
+        }
+
+        let mut data = self.data.write().unwrap();
+        data.connected = true;
+        data.stale = stale;
 
         Ok(())
     }
@@ -148,21 +502,170 @@ impl ViewThread for StatusBarViewThread {
     }
 
     fn clear(&mut self) {
-        self.data.write().unwrap().connected = false;
+        let mut data = self.data.write().unwrap();
+        data.connected = false;
+        data.stale.clear();
+        data.responsiveness = Arc::default();
+        data.low_space.clear();
+        self.download_avg = Ema::default();
+        self.upload_avg = Ema::default();
+        self.ip_fetched = None;
+        self.space_fetched = None;
+        self.low_space_fetched = None;
+        self.low_space_warned.clear();
     }
 }
 
 impl StatusBarView {
-    pub fn new(session_recv: watch::Receiver<SessionHandle>) -> Self {
+    pub fn new(session_recv: watch::Receiver<SessionHandle>, selection: Selection) -> Self {
         let data = Arc::new(RwLock::new(StatusBarData::default()));
-        let thread_obj = StatusBarViewThread::new(data.clone());
-        tokio::spawn(thread_obj.run(session_recv));
-        Self { data }
+        let thread_obj = StatusBarViewThread::new(data.clone(), session_recv.clone(), selection);
+        crate::shutdown::spawn("status bar", thread_obj.run(session_recv));
+        Self { data, toast: None }
     }
 }
 
+impl StatusBarView {
+    /// For posting toasts from outside the UI thread.
+    pub fn toasts(&self) -> Toasts {
+        Toasts(self.data.clone())
+    }
+}
+
+/// Queues up toasts for a status bar. They're shown one after another, as of its next layout.
+#[derive(Clone)]
+pub(crate) struct Toasts(Arc<RwLock<StatusBarData>>);
+
+impl Toasts {
+    pub fn post(&self, msg: impl Into<String>) {
+        self.0.write().unwrap().toasts.push_back(msg.into());
+    }
+}
+
+/// Briefly shows a message in the status bar, for things that don't need a dialog.
+pub(crate) fn toast(siv: &mut Cursive, msg: impl Into<String>) {
+    let msg = msg.into();
+    siv.call_on_name("status", |v: &mut StatusBarView| v.toasts().post(msg));
+}
+
 impl View for StatusBarView {
     fn draw(&self, printer: &Printer) {
-        printer.print((0, 0), &self.data.read().unwrap().to_string());
+        let mut x = 0;
+        for (segment, text) in self.data.read().unwrap().segments() {
+            if segment == Some(Segment::LowSpace) {
+                let style = ColorStyle::front(BaseColor::Red.light());
+                printer.with_color(style, |p| {
+                    p.with_effect(Effect::Reverse, |p| p.print((x, 0), &text))
+                });
+            } else {
+                printer.print((x, 0), &text);
+            }
+            x += StyledString::plain(text).width();
+        }
+
+        if let Some((msg, posted)) = &self.toast {
+            if posted.elapsed() < TOAST_DURATION {
+                let text = format!(" {} ", msg);
+                let width = StyledString::plain(text.as_str()).width();
+                let x = printer.size.x.saturating_sub(width);
+                printer.print((x, 0), &text);
+            }
+        }
+    }
+
+    fn layout(&mut self, _: Vec2) {
+        let showing = matches!(&self.toast, Some((_, posted)) if posted.elapsed() < TOAST_DURATION);
+        if !showing {
+            let next = self.data.write().unwrap().toasts.pop_front();
+            self.toast = next.map(|msg| (msg, time::Instant::now()));
+        }
+    }
+
+    fn take_focus(&mut self, _: Direction) -> Result<EventResult, CannotFocus> {
+        Err(CannotFocus)
     }
+
+    fn on_event(&mut self, event: Event) -> EventResult {
+        match event {
+            Event::Mouse {
+                offset,
+                position,
+                event: MouseEvent::Press(MouseButton::Left),
+            } => {
+                let x = match position.checked_sub(offset) {
+                    Some(pos) if pos.y == 0 => pos.x,
+                    _ => return EventResult::Ignored,
+                };
+
+                let data = self.data.read().unwrap();
+                match data.segment_at(x) {
+                    Some(Segment::FreeSpace) => {
+                        let selected = data.selected_space.as_ref().map(|(p, _)| p.clone());
+                        EventResult::with_cb(move |siv| pick_free_space_path(siv, selected.clone()))
+                    }
+                    Some(Segment::LowSpace) => {
+                        let low_space = data.low_space.clone();
+                        EventResult::with_cb(move |siv| show_low_space(siv, &low_space))
+                    }
+                    Some(Segment::Network) => EventResult::with_cb(menu::show_network_preferences),
+                    None => EventResult::Ignored,
+                }
+            }
+            _ => EventResult::Ignored,
+        }
+    }
+}
+
+fn show_low_space(siv: &mut Cursive, low_space: &[(String, u64)]) {
+    let threshold = crate::config::read().status_bar.low_space_threshold;
+    let mut text = format!(
+        "These download locations have less than {} free:\n",
+        util::fmt::bytes(threshold)
+    );
+    for (path, space) in low_space {
+        text += &format!("\n{}: {}", path, util::fmt::bytes(*space));
+    }
+    siv.add_layer(Dialog::info(text).title("Low Disk Space"));
+}
+
+/// Lets the user choose which path's free space the status bar shows.
+fn pick_free_space_path(siv: &mut Cursive, selected: Option<String>) {
+    let current = crate::config::read().status_bar.free_space_path.clone();
+
+    let mut select = SelectView::new().item("Default download location", Some(None));
+    if let Some(path) = selected {
+        select.add_item(format!("Selected torrent: {}", path), Some(Some(path)));
+    }
+    if let Some(path) = current {
+        select.add_item(format!("Current: {}", path), Some(Some(path)));
+    }
+    select.add_item("Other...", None);
+
+    select.set_on_submit(|siv, choice: &Option<Option<String>>| {
+        siv.pop_layer();
+        match choice {
+            Some(path) => set_free_space_path(path.clone()),
+            None => {
+                let host = siv.user_data::<AppState>().unwrap().get().get_id();
+                let dialog = PathEdit::new(host)
+                    .into_dialog("Cancel", "OK", |_, path| {
+                        set_free_space_path(Some(path).filter(|p| !p.trim().is_empty()))
+                    })
+                    .title("Free space path");
+                siv.add_layer(dialog);
+            }
+        }
+    });
+
+    siv.add_layer(
+        Dialog::around(select)
+            .dismiss_button("Cancel")
+            .title("Show free space for"),
+    );
+}
+
+fn set_free_space_path(path: Option<String>) {
+    let mut cfg = crate::config::write();
+    cfg.status_bar.free_space_path = path;
+    cfg.save();
 }
@@ -1,21 +1,38 @@
+use std::cell::RefCell;
 use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::hash::Hash;
 use std::ops::DerefMut;
+use std::rc::Rc;
 use std::sync::{Arc, RwLock};
 
 use cursive::direction::Direction;
 use cursive::event::{Callback, Event, EventResult, MouseButton, MouseEvent};
+use cursive::menu::Tree;
+use cursive::theme::ColorStyle;
 use cursive::view::{scroll, CannotFocus};
+use cursive::views::MenuPopup;
+use cursive::Cursive;
 use cursive::Printer;
+use cursive::Rect;
 use cursive::Vec2;
 use cursive::View;
 
+use crate::config;
+use crate::keys::Action;
+use crate::util::glyphs::glyphs;
+
+const MIN_COLUMN_WIDTH: usize = 3;
+
 pub(crate) trait TableViewData: Default {
     type Column: Copy + Eq + AsRef<str>;
-    type RowIndex: Copy + Eq;
+    type RowIndex: Copy + Eq + Hash;
     type RowValue;
     type Rows: DerefMut<Target = [Self::RowIndex]> + Default;
 
-    const SHOULD_GROW_TO_FIT: bool = false;
+    const SUPPORTS_SECONDARY_SORT: bool = false;
+    /// Whether rows can be marked for batch operations, beyond just being selected.
+    const SUPPORTS_MARKING: bool = false;
 
     fn sort_column(&self) -> Self::Column;
     fn set_sort_column(&mut self, val: Self::Column);
@@ -23,6 +40,13 @@ pub(crate) trait TableViewData: Default {
     fn descending_sort(&self) -> bool;
     fn set_descending_sort(&mut self, val: bool);
 
+    /// The column used to break ties in the sort column, and whether it's descending.
+    /// Tables that don't support a secondary sort can leave this alone.
+    fn secondary_sort(&self) -> Option<(Self::Column, bool)> {
+        None
+    }
+    fn set_secondary_sort(&mut self, _val: Option<(Self::Column, bool)>) {}
+
     fn reverse_rows(&mut self) {
         self.set_descending_sort(!self.descending_sort());
     }
@@ -55,14 +79,52 @@ pub(crate) trait TableViewData: Default {
 
     fn get_row_value<'a>(&'a self, index: &'a Self::RowIndex) -> &'a Self::RowValue;
 
+    /// How many lines each row takes up. `draw_row` gets a printer this tall.
+    fn row_height(&self) -> usize {
+        1
+    }
+
     fn draw_cell(&self, printer: &Printer, row: &Self::RowValue, column: Self::Column);
 
     fn draw_row(&self, printer: &Printer, columns: &[(Self::Column, usize)], row: &Self::RowValue) {
-        let mut x = 0;
-        for (column, width) in columns {
-            let printer = printer.offset((x, 0)).cropped((*width, 1));
-            self.draw_cell(&printer, row, *column);
-            x += width + 1;
+        draw_cells(self, printer, columns, row);
+    }
+}
+
+pub(crate) fn draw_cells<T: TableViewData>(
+    data: &T,
+    printer: &Printer,
+    columns: &[(T::Column, usize)],
+    row: &T::RowValue,
+) {
+    let mut x = 0;
+    for (column, width) in columns {
+        let printer = printer.offset((x, 0)).cropped((*width, 1));
+        data.draw_cell(&printer, row, *column);
+        x += width + 1;
+    }
+}
+
+/// Draws a bar across the whole cell, `fraction` of the way full, with `label` centered on it.
+pub(crate) fn draw_progress(printer: &Printer, fraction: f32, label: &str) {
+    let width = printer.size.x;
+    let eighths = (fraction.max(0.0).min(1.0) * (width * 8) as f32).round() as usize;
+    let (filled, partial) = (eighths / 8, eighths % 8);
+
+    let filled_style = ColorStyle::highlight();
+    printer.with_color(filled_style, |p| p.print_hline((0, 0), filled, " "));
+    if partial > 0 {
+        printer.print((filled, 0), glyphs().partial_blocks[partial]);
+    }
+
+    // The label covers up the partial block, if they overlap; that's fine.
+    let start = width.saturating_sub(label.chars().count()) / 2;
+    for (x, (i, c)) in (start..width).zip(label.char_indices()) {
+        let s = &label[i..i + c.len_utf8()];
+        if x < filled {
+            printer.with_color(filled_style, |p| p.print((x, 0), s));
+        } else {
+            printer.print((x, 0), s);
         }
     }
 }
@@ -101,15 +163,41 @@ impl<T: TableViewData, F: Fn(&mut T, &T::RowIndex, Vec2, Vec2) -> Callback + 'st
 }
 type BoxedTableCallback<T> = Box<dyn TableCallback<T>>;
 
+// Given the data and the marked rows, produces a line of text to show under the table.
+type BoxedFooter<T> = Box<dyn Fn(&T, &[<T as TableViewData>::RowIndex]) -> String>;
+
+// Chosen from the header's context menu, which can't borrow the table.
+// They're queued up and applied on the next layout.
+#[derive(Debug, Clone, Copy)]
+enum HeaderAction<C> {
+    MoveLeft(C),
+    MoveRight(C),
+    ToggleVisible(C),
+}
+
 pub(crate) struct TableView<T: TableViewData> {
     data: Arc<RwLock<T>>,
     columns: Vec<(T::Column, usize)>,
+    hidden_columns: Vec<(T::Column, usize)>,
     scroll_core: scroll::Core,
     selected: Option<T::RowIndex>,
+    // Rows marked for batch operations, in addition to the selected row.
+    marked: Vec<T::RowIndex>,
+    // Where the selected row was as of the last layout, if it was present at all.
+    selected_index: Option<usize>,
+    // The row at the top of the view as of the last layout, and the scroll offset back then.
+    scroll_anchor: Option<(T::RowIndex, usize)>,
     double_click_primed: bool,
     on_selection_change: Option<BoxedTableCallback<T>>,
     on_double_click: Option<BoxedTableCallback<T>>,
     on_right_click: Option<BoxedTableCallback<T>>,
+    on_middle_click: Option<BoxedTableCallback<T>>,
+    footer: Option<BoxedFooter<T>>,
+    empty_message: Option<&'static str>,
+    config_key: Option<&'static str>,
+    resizing: Option<usize>,
+    last_width: usize,
+    header_actions: Rc<RefCell<Vec<HeaderAction<T::Column>>>>,
 }
 
 impl<T: TableViewData> TableView<T> {
@@ -117,12 +205,23 @@ impl<T: TableViewData> TableView<T> {
         Self {
             data: Arc::new(RwLock::new(T::default())),
             columns,
+            hidden_columns: Vec::new(),
             scroll_core: scroll::Core::default(),
             selected: None,
+            marked: Vec::new(),
+            selected_index: None,
+            scroll_anchor: None,
             double_click_primed: false,
             on_selection_change: None,
             on_double_click: None,
             on_right_click: None,
+            on_middle_click: None,
+            footer: None,
+            empty_message: None,
+            config_key: None,
+            resizing: None,
+            last_width: 0,
+            header_actions: Rc::default(),
         }
     }
 
@@ -134,6 +233,56 @@ impl<T: TableViewData> TableView<T> {
         self.selected.as_ref()
     }
 
+    /// Selects a row, scrolling to it on the next layout. The selection callback isn't run.
+    pub fn set_selection(&mut self, row: T::RowIndex) {
+        self.selected = Some(row);
+    }
+
+    /// How far down the rows are scrolled, in lines.
+    pub fn scroll_offset(&self) -> usize {
+        self.scroll_core.content_viewport().top()
+    }
+
+    /// Scrolls to a line. This only goes as far as the last layout allows.
+    pub fn set_scroll_offset(&mut self, y: usize) {
+        let x = self.scroll_core.content_viewport().left();
+        self.scroll_core.set_offset((x, y));
+    }
+
+    pub fn get_marked(&self) -> &[T::RowIndex] {
+        &self.marked
+    }
+
+    pub fn set_marked(&mut self, marked: Vec<T::RowIndex>) {
+        self.marked = marked;
+    }
+
+    fn toggle_mark(&mut self) -> EventResult {
+        let row = match self.selected {
+            Some(row) => row,
+            None => return EventResult::Ignored,
+        };
+        if let Some(i) = self.marked.iter().position(|r| *r == row) {
+            self.marked.remove(i);
+        } else {
+            self.marked.push(row);
+        }
+        EventResult::Consumed(None)
+    }
+
+    fn mark_all(&mut self) -> EventResult {
+        self.marked = self.data.read().unwrap().rows().to_vec();
+        EventResult::Consumed(None)
+    }
+
+    fn invert_marks(&mut self) -> EventResult {
+        let data = self.data.read().unwrap();
+        let marked: HashSet<T::RowIndex> = self.marked.iter().copied().collect();
+        let unmarked = data.rows().iter().filter(|row| !marked.contains(row));
+        self.marked = unmarked.copied().collect();
+        EventResult::Consumed(None)
+    }
+
     pub(super) fn set_on_selection_change(&mut self, f: impl TableCallback<T>) {
         self.on_selection_change = Some(Box::new(f));
     }
@@ -146,13 +295,317 @@ impl<T: TableViewData> TableView<T> {
         self.on_right_click = Some(Box::new(f));
     }
 
-    fn click_header(&mut self, mut x: usize) -> EventResult {
-        for (column, width) in &self.columns {
+    pub(super) fn set_on_middle_click(&mut self, f: impl TableCallback<T>) {
+        self.on_middle_click = Some(Box::new(f));
+    }
+
+    pub(super) fn set_footer(&mut self, f: impl Fn(&T, &[T::RowIndex]) -> String + 'static) {
+        self.footer = Some(Box::new(f));
+    }
+
+    /// Shown in the middle of the table while it has no rows.
+    pub(super) fn set_empty_message(&mut self, msg: &'static str) {
+        self.empty_message = Some(msg);
+    }
+
+    /// Rows taken up by the footer, if any: a separator, then the text.
+    /// It's dropped when the table is too short to show any rows alongside it.
+    fn footer_height(&self, height: usize) -> usize {
+        if self.footer.is_some() && height > 2 + 2 {
+            2
+        } else {
+            0
+        }
+    }
+
+    /// Persist this table's column layout in the config, under the given key.
+    /// Hides a column unless the saved layout says otherwise. Call before `set_config_key`.
+    pub(super) fn hide_by_default(&mut self, column: T::Column) {
+        if let Some(i) = self.columns.iter().position(|(c, _)| *c == column) {
+            let entry = self.columns.remove(i);
+            self.hidden_columns.push(entry);
+        }
+    }
+
+    pub(super) fn set_config_key(&mut self, key: &'static str) {
+        self.config_key = Some(key);
+
+        let cfg = config::read();
+        if let Some(table_cfg) = cfg.tables.get(key) {
+            // Columns that were hidden by default stay that way if the saved layout predates them.
+            let default_hidden: Vec<T::Column> =
+                self.hidden_columns.iter().map(|(c, _)| *c).collect();
+            self.columns.append(&mut self.hidden_columns);
+
+            // Columns missing from the saved order keep their relative order, at the end.
+            let position = |column: &T::Column| {
+                table_cfg
+                    .order
+                    .iter()
+                    .position(|name| name == column.as_ref())
+                    .unwrap_or(usize::MAX)
+            };
+            self.columns.sort_by_key(|(column, _)| position(column));
+
+            let is_hidden = |column: &T::Column| {
+                let name = column.as_ref();
+                let is_known = table_cfg.order.iter().any(|n| n == name);
+                table_cfg.hidden.iter().any(|n| n == name)
+                    || (!is_known && default_hidden.contains(column))
+            };
+            // Never hide every single column.
+            if !self.columns.iter().all(|(column, _)| is_hidden(column)) {
+                let (hidden, visible) = self
+                    .columns
+                    .drain(..)
+                    .partition(|(column, _)| is_hidden(column));
+                self.columns = visible;
+                self.hidden_columns = hidden;
+            }
+
+            for (column, width) in &mut self.columns {
+                if let Some(w) = table_cfg.widths.get(column.as_ref()) {
+                    *width = (*w).max(MIN_COLUMN_WIDTH);
+                }
+            }
+        }
+    }
+
+    fn save_layout(&self) {
+        let key = match self.config_key {
+            Some(key) => key,
+            None => return,
+        };
+
+        let mut cfg = config::write();
+        let table_cfg = cfg.tables.entry(key.to_owned()).or_default();
+        // The first column's width is whatever's left over, so there's no point saving it.
+        table_cfg.widths = self.columns[1..]
+            .iter()
+            .map(|(column, width)| (column.as_ref().to_owned(), *width))
+            .collect();
+        table_cfg.order = self
+            .columns
+            .iter()
+            .map(|(column, _)| column.as_ref().to_owned())
+            .collect();
+        table_cfg.hidden = self
+            .hidden_columns
+            .iter()
+            .map(|(column, _)| column.as_ref().to_owned())
+            .collect();
+        cfg.save();
+    }
+
+    fn column_at(&self, mut x: usize) -> Option<usize> {
+        for (i, (_, width)) in self.columns.iter().enumerate() {
             if x < *width {
-                self.data.write().unwrap().click_column(*column);
+                return Some(i);
+            }
+            x = x.checked_sub(width + 1)?;
+        }
+        None
+    }
+
+    fn header_menu(&self, index: usize, position: Vec2) -> Callback {
+        let column = self.columns[index].0;
+        let can_move_left = index > 0;
+        let can_move_right = index + 1 < self.columns.len();
+        let actions = Rc::clone(&self.header_actions);
+
+        let visible = self.columns.iter().map(|(c, _)| (*c, true));
+        let hidden = self.hidden_columns.iter().map(|(c, _)| (*c, false));
+        let toggles: Vec<(T::Column, bool)> = visible.chain(hidden).collect();
+        let can_hide = self.columns.len() > 1;
+
+        let cb = move |siv: &mut Cursive| {
+            let push = |action| {
+                let actions = Rc::clone(&actions);
+                move |_: &mut Cursive| actions.borrow_mut().push(action)
+            };
+
+            let mut menu_tree = Tree::new();
+            if can_move_left {
+                menu_tree.add_leaf("Move left", push(HeaderAction::MoveLeft(column)));
+            }
+            if can_move_right {
+                menu_tree.add_leaf("Move right", push(HeaderAction::MoveRight(column)));
+            }
+            if !menu_tree.is_empty() {
+                menu_tree.add_delimiter();
+            }
+
+            for (column, is_visible) in &toggles {
+                let label = format!(
+                    "[{}] {}",
+                    if *is_visible { 'x' } else { ' ' },
+                    column.as_ref()
+                );
+                if *is_visible && !can_hide {
+                    menu_tree.add_leaf(label, |_| ());
+                } else {
+                    menu_tree.add_leaf(label, push(HeaderAction::ToggleVisible(*column)));
+                }
+            }
+
+            let menu_popup = MenuPopup::new(Rc::new(menu_tree));
+
+            siv.screen_mut()
+                .add_layer_at(cursive::XY::absolute(position), menu_popup);
+        };
+        Callback::from_fn(cb)
+    }
+
+    /// Ascending, then descending, then off again.
+    fn cycle_secondary_sort(data: &mut T, column: T::Column) {
+        let next = match data.secondary_sort() {
+            Some((c, false)) if c == column => Some((column, true)),
+            Some((c, true)) if c == column => None,
+            _ => Some((column, false)),
+        };
+        data.set_secondary_sort(next);
+    }
+
+    fn toggle_column(&mut self, column: T::Column) {
+        if let Some(i) = self.hidden_columns.iter().position(|(c, _)| *c == column) {
+            let entry = self.hidden_columns.remove(i);
+            self.columns.push(entry);
+        } else if self.columns.len() > 1 {
+            if let Some(i) = self.columns.iter().position(|(c, _)| *c == column) {
+                let mut entry = self.columns.remove(i);
+                if i == 0 {
+                    // Its width was just whatever was left over. Give the new first column that.
+                    std::mem::swap(&mut entry.1, &mut self.columns[0].1);
+                }
+                self.hidden_columns.push(entry);
+            }
+        }
+    }
+
+    /// Lines per row. Never zero, so it's safe to divide by.
+    fn row_height(&self) -> usize {
+        self.data.read().unwrap().row_height().max(1)
+    }
+
+    /// Total height of the rows, in lines.
+    fn content_height(&self) -> usize {
+        let data = self.data.read().unwrap();
+        data.rows().len() * data.row_height().max(1)
+    }
+
+    /// Which row is at a given line of the visible data area.
+    fn row_at(&self, y: usize) -> usize {
+        (y + self.scroll_core.content_viewport().top()) / self.row_height()
+    }
+
+    /// If sorting or filtering moved the selected row, scroll it back into view.
+    fn follow_selection(&mut self) {
+        let index = self.selected.and_then(|sel| {
+            let data = self.data.read().unwrap();
+            data.rows().iter().position(|row| *row == sel)
+        });
+
+        if index != self.selected_index {
+            if let Some(i) = index {
+                let h = self.row_height();
+                self.scroll_core
+                    .scroll_to_rect(Rect::from_size((0, i * h), (1, h)));
+            }
+            self.selected_index = index;
+        }
+    }
+
+    /// If the rows changed underneath us, keep the same row at the top of the view.
+    /// Scrolling since the last layout means the user moved on, so that's left alone.
+    fn restore_scroll_anchor(&mut self) {
+        let viewport = self.scroll_core.content_viewport();
+        let h = self.row_height();
+
+        if let Some((row, offset)) = self.scroll_anchor.take() {
+            if offset != viewport.top() {
+                return;
+            }
+            let data = self.data.read().unwrap();
+            if let Some(i) = data.rows().iter().position(|r| *r == row) {
+                let y = i * h + offset % h;
+                if y != offset {
+                    self.scroll_core.set_offset((viewport.left(), y));
+                }
+            }
+        }
+    }
+
+    fn save_scroll_anchor(&mut self) {
+        let top = self.scroll_core.content_viewport().top();
+        let h = self.row_height();
+
+        // Nothing to anchor at the very top; new rows there should come into view.
+        self.scroll_anchor = if top == 0 {
+            None
+        } else {
+            let data = self.data.read().unwrap();
+            data.rows().get(top / h).map(|row| (*row, top))
+        };
+    }
+
+    fn apply_header_actions(&mut self) {
+        let actions: Vec<_> = self.header_actions.borrow_mut().drain(..).collect();
+        if actions.is_empty() {
+            return;
+        }
+
+        for action in actions {
+            let (column, forward) = match action {
+                HeaderAction::MoveLeft(column) => (column, false),
+                HeaderAction::MoveRight(column) => (column, true),
+                HeaderAction::ToggleVisible(column) => {
+                    self.toggle_column(column);
+                    continue;
+                }
+            };
+
+            let i = match self.columns.iter().position(|(c, _)| *c == column) {
+                Some(i) => i,
+                None => continue,
+            };
+            let j = match forward {
+                true if i + 1 < self.columns.len() => i + 1,
+                false if i > 0 => i - 1,
+                _ => continue,
+            };
+
+            self.columns.swap(i, j);
+            if i == 0 || j == 0 {
+                // Whichever column ends up first gets the leftover space, so leave the widths put.
+                let (wi, wj) = (self.columns[i].1, self.columns[j].1);
+                self.columns[i].1 = wj;
+                self.columns[j].1 = wi;
+            }
+        }
+
+        self.save_layout();
+    }
+
+    fn click_header(&mut self, mut x: usize) -> EventResult {
+        for (i, &(column, width)) in self.columns.iter().enumerate() {
+            if x < width {
+                // Shift+clicking any other column sorts by it too, after the main one.
+                let mut data = self.data.write().unwrap();
+                let secondary = T::SUPPORTS_SECONDARY_SORT
+                    && crate::backend::mouse_shift()
+                    && column != data.sort_column();
+                if secondary {
+                    Self::cycle_secondary_sort(&mut data, column);
+                } else {
+                    data.click_column(column);
+                }
                 return EventResult::Consumed(None);
-            } else if x == *width {
-                // a column separator was clicked; do nothing
+            } else if x == width {
+                // a column separator was clicked; start dragging it
+                if i + 1 < self.columns.len() {
+                    self.resizing = Some(i);
+                    return EventResult::Consumed(None);
+                }
                 return EventResult::Ignored;
             }
             x -= width + 1;
@@ -160,6 +613,30 @@ impl<T: TableViewData> TableView<T> {
         return EventResult::Ignored;
     }
 
+    fn drag_separator(&mut self, separator: usize, x: usize) {
+        // The first column takes up whatever space is left over.
+        // Its right edge is moved by resizing its neighbor instead.
+        let resized = separator.max(1);
+
+        if separator == 0 {
+            let end = self.columns[0].1 + 1 + self.columns[1].1;
+            self.columns[1].1 = end.saturating_sub(x + 1);
+        } else {
+            let start: usize = self.columns[..separator].iter().map(|(_, w)| w + 1).sum();
+            self.columns[separator].1 = x.saturating_sub(start);
+        }
+        self.columns[resized].1 = self.columns[resized].1.max(MIN_COLUMN_WIDTH);
+
+        // Don't let the first column get squeezed below the minimum, either.
+        let others: usize = self.columns[1..].iter().map(|(_, w)| w + 1).sum();
+        let excess = (others + MIN_COLUMN_WIDTH).saturating_sub(self.last_width);
+        let w = &mut self.columns[resized].1;
+        *w = w.saturating_sub(excess).max(MIN_COLUMN_WIDTH);
+
+        let others: usize = self.columns[1..].iter().map(|(_, w)| w + 1).sum();
+        self.columns[0].1 = self.last_width.saturating_sub(others);
+    }
+
     fn width(&self) -> usize {
         self.columns
             .iter()
@@ -201,38 +678,90 @@ where
 {
     fn draw(&self, printer: &Printer) {
         let Vec2 { x: w, y: h } = printer.size;
+        let footer_height = self.footer_height(h);
+        let footer = self.footer.as_ref().filter(|_| footer_height > 0);
 
         let data = self.data.read().unwrap();
 
+        let g = glyphs();
         let mut x = 0;
         for (column, width) in &self.columns {
             let mut name = String::from(column.as_ref());
 
             if *column == data.sort_column() {
                 let c = if data.descending_sort() {
-                    " ▼"
+                    g.sort_descending
                 } else {
-                    " ▲"
+                    g.sort_ascending
                 };
+                name.push(' ');
                 name.push_str(c);
+            } else if let Some((_, descending)) = data.secondary_sort().filter(|(c, _)| c == column)
+            {
+                name.push(' ');
+                name.push_str(if descending {
+                    g.secondary_descending
+                } else {
+                    g.secondary_ascending
+                });
             }
 
             printer.cropped((x + width, 1)).print((x, 0), &name);
-            printer.print_hline((x, 1), *width, "─");
+            printer.print_hline((x, 1), *width, g.hline);
             x += width;
             if x == w {
-                printer.print((0, 1), "╶");
-                printer.print((x - 1, 1), "╴");
+                printer.print((0, 1), g.hline_start);
+                printer.print((x - 1, 1), g.hline_end);
                 break;
             }
-            printer.print_vline((x, 0), h, "│");
-            printer.print((x, 1), "┼");
+            printer.print_vline((x, 0), h.saturating_sub(footer_height), g.vline);
+            printer.print((x, 1), g.cross_line);
+            if footer.is_some() {
+                printer.print((x, h - 2), g.tee_up);
+            }
             x += 1;
         }
 
-        scroll::draw_lines(self, &printer.offset((0, 2)), |this, p, i| {
-            if let Some(row) = data.rows().get(i) {
-                p.with_selection(this.selected == Some(*row), |p| {
+        if let Some(footer) = footer {
+            let text = footer(&data, &self.marked);
+            let mut x = 0;
+            for (_, width) in &self.columns {
+                printer.print_hline((x, h - 2), *width, g.hline);
+                x += width + 1;
+            }
+            printer.print((0, h - 1), &text);
+        }
+
+        let rows_printer = printer
+            .offset((0, 2))
+            .cropped((w, h.saturating_sub(2 + footer_height)));
+
+        if let (Some(msg), true) = (self.empty_message, data.rows().is_empty()) {
+            let Vec2 { x: w, y: h } = rows_printer.size;
+            let msg_width = msg.chars().count();
+            let pos = (w.saturating_sub(msg_width) / 2, h / 2);
+            // Clear out the column separators so the message isn't cut up.
+            rows_printer.print_hline((0, pos.1), w, " ");
+            rows_printer.print(pos, msg);
+            return;
+        }
+
+        let row_height = data.row_height().max(1);
+        scroll::draw(self, &rows_printer, |this, p| {
+            let top = p.content_offset.y;
+            let first = top / row_height;
+            let last = (top + p.output_size.y + row_height - 1) / row_height;
+
+            for (i, row) in data.rows().iter().enumerate().take(last).skip(first) {
+                let is_selected = this.selected == Some(*row);
+                let is_marked = this.marked.contains(row);
+
+                let p = p
+                    .offset((0, i * row_height))
+                    .cropped((p.size.x, row_height));
+                // Marked rows get the "inactive" highlight, so the selected one still stands out.
+                let p = &p.focused(p.focused && is_selected);
+                p.with_selection(is_selected || is_marked, |p| {
                     data.draw_row(p, &this.columns, data.get_row_value(row))
                 });
             }
@@ -240,25 +769,16 @@ where
     }
 
     fn required_size(&mut self, constraint: Vec2) -> Vec2 {
-        let data_constraint = constraint.saturating_sub((0, 2));
+        let chrome = 2 + self.footer_height(constraint.y);
+        let data_constraint = constraint.saturating_sub((0, chrome));
         let data_requirement = scroll::required_size(
             self,
             data_constraint,
             true, // TODO: figure out what's up with this
-            |this, constraint| (constraint.x, this.data.read().unwrap().rows().len()).into(),
+            |this, constraint| (constraint.x, this.content_height()).into(),
         );
-        let mut requirement = data_requirement + (0, 2);
-
-        if T::SHOULD_GROW_TO_FIT {
-            // Take up all available vertical space.
-            // Flexbox is hard and this seems to work for what I need.
-            //
-            // BUG: If a tab is tall enough (easy to achieve for the Files and Peers tabs),
-            // then the pane at the bottom will somehow crowd out the status bar.
-            // TODO: Find to find a way to fix this.
-            requirement.y = requirement.y.max(constraint.y);
-        };
-        requirement
+        // Tables that should fill their pane get a weight in the enclosing layout instead.
+        data_requirement + (0, chrome)
     }
 
     fn layout(&mut self, size: Vec2) {
@@ -267,17 +787,29 @@ where
         // because it doesn't extend into the header.
         // Other code might need to be changed accordingly,
         // but if you have spare space in your last column, you're fine.
+        self.apply_header_actions();
+
         let others_width = self.columns[1..].iter().map(|(_, w)| w + 1).sum::<usize>();
-        self.columns[0].1 = size.x - others_width;
+        self.columns[0].1 = size.x.saturating_sub(others_width);
+        self.last_width = size.x;
 
-        let data_size = size.checked_sub((0, 2)).expect("bar");
+        let data_size = size.saturating_sub((0, 2 + self.footer_height(size.y)));
         scroll::layout(
             self,
             data_size,
             true, // TODO: when do we need to relayout?
             |_this, _size| (),
-            |this, constraint| (constraint.x, this.data.read().unwrap().rows().len()).into(),
+            |this, constraint| (constraint.x, this.content_height()).into(),
         );
+
+        if !self.marked.is_empty() {
+            let data = self.data.read().unwrap();
+            self.marked.retain(|row| data.rows().contains(row));
+        }
+
+        self.restore_scroll_anchor();
+        self.follow_selection();
+        self.save_scroll_anchor();
     }
 
     fn take_focus(&mut self, _: Direction) -> Result<EventResult, CannotFocus> {
@@ -327,6 +859,7 @@ where
 
                     pos.y = pos.y.saturating_sub(2);
 
+                    let i = self.row_at(pos.y);
                     let core = &mut self.scroll_core;
 
                     if core.inner_size().y > core.last_outer_size().y
@@ -337,7 +870,6 @@ where
                     }
 
                     if pos.y < core.last_outer_size().y {
-                        let i = pos.y + core.content_viewport().top();
                         let mut data = self.data.write().unwrap();
                         if let Some(&row) = data.rows().get(i) {
                             let mut res = EventResult::Consumed(None);
@@ -372,9 +904,21 @@ where
                         }
                     }
                 }
-                MouseEvent::Press(MouseButton::Right) if position.y >= offset.y + 2 => {
+                MouseEvent::Press(MouseButton::Right) if position.y < offset.y + 2 => {
+                    let pos = position.saturating_sub(offset);
+                    if let Some(index) = self.column_at(pos.x) {
+                        return EventResult::Consumed(Some(self.header_menu(index, position)));
+                    }
+                }
+                MouseEvent::Press(button @ (MouseButton::Right | MouseButton::Middle))
+                    if position.y >= offset.y + 2 =>
+                {
                     let pos = position.saturating_sub(offset + (0, 2));
-                    let i = pos.y + self.scroll_core.content_viewport().top();
+                    if pos.y >= self.scroll_core.last_outer_size().y {
+                        // That's the footer.
+                        return EventResult::Ignored;
+                    }
+                    let i = self.row_at(pos.y);
                     let mut data = self.data.write().unwrap();
                     if let Some(&row) = data.rows().get(i) {
                         let mut res = EventResult::Consumed(None);
@@ -389,16 +933,24 @@ where
                                 offset,
                             );
                         }
-                        return Self::run_cb(
-                            res,
-                            &self.on_right_click,
-                            &mut data,
-                            &row,
-                            position,
-                            offset,
-                        );
+                        let cb = if button == MouseButton::Right {
+                            &self.on_right_click
+                        } else {
+                            &self.on_middle_click
+                        };
+                        return Self::run_cb(res, cb, &mut data, &row, position, offset);
                     }
                 }
+                MouseEvent::Hold(MouseButton::Left) if self.resizing.is_some() => {
+                    let x = position.saturating_sub(offset).x;
+                    self.drag_separator(self.resizing.unwrap(), x);
+                    return EventResult::Consumed(None);
+                }
+                MouseEvent::Release(MouseButton::Left) if self.resizing.is_some() => {
+                    self.resizing = None;
+                    self.save_layout();
+                    return EventResult::Consumed(None);
+                }
                 MouseEvent::Hold(MouseButton::Left) if position.y >= offset.y + 2 => {
                     let pos = position.saturating_sub(offset + (0, 2));
                     self.scroll_core.drag(pos);
@@ -411,7 +963,7 @@ where
                         self.double_click_primed = false;
                     } else {
                         let pos = position.saturating_sub(offset + (0, 2));
-                        let i = pos.y + self.scroll_core.content_viewport().top();
+                        let i = self.row_at(pos.y);
                         let data = self.data.read().unwrap();
                         self.double_click_primed &= self.selected.as_ref() == data.rows().get(i);
                     }
@@ -420,6 +972,13 @@ where
                 }
                 _ => (),
             },
+            e if T::SUPPORTS_MARKING && e == Action::ToggleMark.event() => {
+                return self.toggle_mark()
+            }
+            e if T::SUPPORTS_MARKING && e == Action::MarkAll.event() => return self.mark_all(),
+            e if T::SUPPORTS_MARKING && e == Action::InvertMarks.event() => {
+                return self.invert_marks()
+            }
             _ => (),
         }
 
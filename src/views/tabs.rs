@@ -1,18 +1,24 @@
 use super::thread::ViewThread;
 use async_trait::async_trait;
 use cursive::align::HAlign;
+use cursive::theme::ColorStyle;
 use cursive::traits::*;
 use cursive::vec::Vec2;
 use cursive::view::ViewWrapper;
 use cursive::views::{DummyView, LinearLayout, TextContent, TextView};
-use cursive_tabs::TabPanel;
-use deluge_rpc::{InfoHash, Session};
+use cursive::Printer;
+use cursive_tabs::TabView;
+use deluge_rpc::{AuthLevel, EventKind, InfoHash, Session};
 use futures::FutureExt;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, RwLock};
 use tokio::sync::{watch, Notify};
 use tokio::task;
+use uuid::Uuid;
 
-use crate::{Selection, SessionHandle};
+use crate::capabilities::Capabilities;
+use crate::visibility::Visibility;
+use crate::{Marked, Selection, SessionHandle};
 
 fn column(rows: &[&str], h_align: HAlign) -> (LinearLayout, TextContent) {
     let labels = TextView::new(rows.join("\n")).style(cursive::theme::Effect::Bold);
@@ -38,6 +44,24 @@ pub(crate) enum Tab {
     Trackers,
 }
 
+impl Tab {
+    const ALL: [Self; 6] = [
+        Self::Status,
+        Self::Details,
+        Self::Options,
+        Self::Files,
+        Self::Peers,
+        Self::Trackers,
+    ];
+
+    fn cycle(self, forward: bool) -> Self {
+        let n = Self::ALL.len();
+        let i = Self::ALL.iter().position(|&tab| tab == self).unwrap();
+        let i = if forward { i + 1 } else { i + n - 1 };
+        Self::ALL[i % n]
+    }
+}
+
 impl AsRef<str> for Tab {
     fn as_ref(&self) -> &str {
         match self {
@@ -76,6 +100,10 @@ trait TabData: ViewThread {
     fn set_selection(&mut self, selection: InfoHash);
 }
 
+/// The counts shown in the tab bar, like "Files (384)". They're for the selected torrent,
+/// and all come from the shared status, so they're current whichever tab is shown.
+type TabCounts = HashMap<Tab, u64>;
+
 trait BuildableTabData: TabData + Sized {
     type V: View;
     fn view() -> (Self::V, Self);
@@ -85,6 +113,7 @@ mod details;
 pub(crate) mod files;
 mod options;
 mod peers;
+mod shared;
 mod status;
 mod trackers;
 
@@ -95,6 +124,8 @@ struct TorrentTabsViewThread {
     active_tab_recv: watch::Receiver<Tab>,
     active_tab: Tab,
     should_reload: bool,
+    counts: TabCounts,
+    counts_send: watch::Sender<TabCounts>,
 
     status_data: status::StatusData,
     details_data: details::DetailsData,
@@ -105,7 +136,13 @@ struct TorrentTabsViewThread {
 }
 
 pub(crate) struct TorrentTabsView {
-    view: TabPanel,
+    view: TabView,
+    // cursive_tabs titles each tab after its name, which has to stay put, so this draws its own bar.
+    bar: Vec<(Tab, String)>,
+    counts_recv: watch::Receiver<TabCounts>,
+    // Whether the bar has the focus, rather than the active tab. It does whenever the tab
+    // has nothing to focus, and Left and Right switch tabs while it does.
+    bar_focused: bool,
     active_tab: Tab,
     active_tab_send: watch::Sender<Tab>,
     // TODO: name all these Notify structs based on who's being notified
@@ -114,6 +151,12 @@ pub(crate) struct TorrentTabsView {
 
     current_options_recv: watch::Receiver<options::OptionsQuery>,
     pending_options: Arc<RwLock<Option<options::OptionsQuery>>>,
+    options_mixed: Arc<RwLock<options::MixedOptions>>,
+
+    session_recv: watch::Receiver<SessionHandle>,
+    capabilities: Option<Arc<Capabilities>>,
+    auth_level: Option<AuthLevel>,
+    host: Option<Uuid>,
 }
 
 impl TorrentTabsViewThread {
@@ -128,6 +171,34 @@ impl TorrentTabsViewThread {
         }
     }
 
+    fn active_tab_is_shared(&self) -> bool {
+        matches!(self.active_tab, Tab::Status | Tab::Details | Tab::Trackers)
+    }
+
+    async fn update_shared(&mut self, session: &Session, hash: InfoHash) -> deluge_rpc::Result<()> {
+        let status = session
+            .get_torrent_status::<shared::SharedStatus>(hash)
+            .await?;
+        self.status_data.apply(&status);
+        self.details_data.apply(&status);
+        self.trackers_data.apply(&status);
+
+        let mut counts = TabCounts::new();
+        counts.insert(Tab::Files, status.num_files);
+        counts.insert(Tab::Peers, status.num_peers + status.num_seeds);
+        counts.insert(Tab::Trackers, status.trackers.len() as u64);
+        self.set_counts(counts);
+        Ok(())
+    }
+
+    fn set_counts(&mut self, counts: TabCounts) {
+        if counts != self.counts {
+            self.counts = counts.clone();
+            // This only fails once the view is gone, at which point nobody cares.
+            self.counts_send.send(counts).ok();
+        }
+    }
+
     fn get_active_tab_mut(&mut self) -> &mut dyn TabData {
         match self.active_tab {
             Tab::Status => &mut self.status_data,
@@ -142,17 +213,31 @@ impl TorrentTabsViewThread {
 
 #[async_trait]
 impl ViewThread for TorrentTabsViewThread {
-    async fn reload(&mut self, session: &Session) -> deluge_rpc::Result<()> {
-        let evs = deluge_rpc::events![TorrentFileRenamed, TorrentFolderRenamed];
-        session.set_event_interest(&evs).await?;
+    async fn reload(&mut self, _: &Session) -> deluge_rpc::Result<()> {
         Ok(())
     }
 
+    fn event_interest(&self) -> HashSet<EventKind> {
+        deluge_rpc::events![
+            TorrentFileRenamed,
+            TorrentFolderRenamed,
+            TorrentTrackerStatus
+        ]
+    }
+
     async fn on_event(
         &mut self,
         session: &Session,
         event: deluge_rpc::Event,
     ) -> deluge_rpc::Result<()> {
+        if let deluge_rpc::Event::TorrentTrackerStatus(hash, status) = &event {
+            // The history is worth keeping even while some other tab is shown.
+            if Some(*hash) == self.last_selection {
+                self.trackers_data.record_status(*hash, status);
+            }
+            return Ok(());
+        }
+
         if self.selection.read().unwrap().is_some() {
             self.get_active_tab_mut().on_event(session, event).await?;
         }
@@ -160,41 +245,83 @@ impl ViewThread for TorrentTabsViewThread {
     }
 
     async fn update(&mut self, session: &Session) -> deluge_rpc::Result<()> {
-        {
-            let lock = self.selection.read().unwrap();
-            if *lock != self.last_selection {
-                self.last_selection = *lock;
-                self.should_reload = true;
-            }
+        let selection = *self.selection.read().unwrap();
+        if selection != self.last_selection {
+            self.last_selection = selection;
+            self.should_reload = true;
+            self.set_counts(TabCounts::new());
         }
 
         if let Some(Ok(())) = self.active_tab_recv.changed().now_or_never() {
             self.active_tab = self.active_tab_recv.borrow().clone();
-            self.should_reload = true;
+            // The shared tabs are filled in on every tick, so moving to one needs no fetch
+            // of its own.
+            self.should_reload |= !self.active_tab_is_shared();
         }
 
-        let selection = self.last_selection;
-        if self.should_reload {
+        let reload = self.should_reload;
+        if reload {
             self.clear();
-            if let Some(sel) = selection {
-                let tab = self.get_active_tab_mut();
+        }
+        let sel = match self.last_selection {
+            Some(sel) => sel,
+            None => {
+                self.should_reload = false;
+                return Ok(());
+            }
+        };
+
+        if reload {
+            self.status_data.set_selection(sel);
+            self.details_data.set_selection(sel);
+            self.trackers_data.set_selection(sel);
+        }
+        // Whichever tab is shown, so that the counts stay current.
+        self.update_shared(session, sel).await?;
+
+        if !self.active_tab_is_shared() {
+            let tab = self.get_active_tab_mut();
+            if reload {
                 tab.set_selection(sel);
                 tab.reload(session).await?;
+            } else {
+                tab.update(session).await?;
             }
-            self.should_reload = false;
-        } else if selection.is_some() {
-            self.get_active_tab_mut().update(session).await?;
         }
+        self.should_reload = false;
 
         Ok(())
     }
 
+    async fn send_changes(&mut self, session: &Session) -> deluge_rpc::Result<()> {
+        if self.last_selection.is_some() && !self.active_tab_is_shared() {
+            self.get_active_tab_mut().send_changes(session).await?;
+        }
+        Ok(())
+    }
+
     fn update_notifier(&self) -> Arc<Notify> {
         self.selection_notify.clone()
     }
 
     fn tick(&self) -> tokio::time::Duration {
-        self.get_active_tab().tick()
+        let configured = task::block_in_place(|| {
+            let cfg = crate::config::read();
+            cfg.torrent_tabs
+                .ticks
+                .get(self.active_tab.as_ref())
+                .copied()
+        });
+
+        match configured.filter(|secs| secs.is_finite() && *secs > 0.0) {
+            Some(secs) => tokio::time::Duration::from_secs_f64(secs),
+            None => self.get_active_tab().tick(),
+        }
+    }
+
+    // Only the active tab gets polled in the first place.
+    fn should_poll(&self, ui: &Visibility) -> bool {
+        !ui.covered && ui.tabs
     }
 
     fn clear(&mut self) {
@@ -209,21 +336,30 @@ impl TorrentTabsView {
         session_recv: watch::Receiver<SessionHandle>,
         selection: Selection,
         selection_notify: Arc<Notify>,
+        marked: Marked,
     ) -> Self {
         let (status_tab, status_data) = status::StatusData::view();
         let (details_tab, details_data) = details::DetailsData::view();
-        let (options_tab, options_data) = options::OptionsData::view();
+        let (options_tab, mut options_data) = options::OptionsData::view();
+        options_data.marked = marked;
         let (files_tab, files_data) = files::FilesData::view();
         let (peers_tab, peers_data) = peers::PeersData::view();
         let (trackers_tab, trackers_data) = trackers::TrackersData::view();
 
         let current_options_recv = options_data.current_options_recv.clone();
         let pending_options = options_data.pending_options.clone();
-
-        let active_tab = Tab::Status;
+        let options_mixed = options_data.mixed.clone();
+
+        let active_tab = crate::config::read()
+            .torrent_tabs
+            .active_tab
+            .as_deref()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(Tab::Status);
         let (active_tab_send, active_tab_recv) = watch::channel(active_tab);
 
         let thread_notifier = selection_notify.clone();
+        let (counts_send, counts_recv) = watch::channel(TabCounts::new());
 
         let thread_obj = TorrentTabsViewThread {
             last_selection: None,
@@ -232,6 +368,8 @@ impl TorrentTabsView {
             active_tab_recv,
             active_tab,
             should_reload: true,
+            counts: TabCounts::new(),
+            counts_send,
             status_data,
             details_data,
             options_data,
@@ -239,59 +377,226 @@ impl TorrentTabsView {
             peers_data,
             trackers_data,
         };
-        task::spawn(thread_obj.run(session_recv));
+        crate::shutdown::spawn("torrent tabs", thread_obj.run(session_recv.clone()));
 
-        let view = TabPanel::new()
+        let view = TabView::new()
             .with_tab(status_tab.with_name("Status"))
             .with_tab(details_tab.with_name("Details"))
             .with_tab(options_tab.with_name("Options"))
             .with_tab(files_tab.with_name("Files"))
             .with_tab(peers_tab.with_name("Peers"))
             .with_tab(trackers_tab.with_name("Trackers"))
-            //.with_bar_placement(cursive_tabs::Placement::VerticalLeft)
             .with_active_tab(active_tab.as_ref())
             .unwrap_or_else(|x| x);
 
-        Self {
+        let mut this = Self {
             view,
+            bar: Vec::new(),
+            counts_recv,
+            bar_focused: false,
             active_tab,
             active_tab_send,
             thread_notifier,
             current_options_recv,
             pending_options,
-        }
+            options_mixed,
+            capabilities: session_recv.borrow().get_capabilities().cloned(),
+            auth_level: session_recv.borrow().get_auth_level(),
+            host: session_recv.borrow().get_id(),
+            session_recv,
+        };
+        this.bar = this.bar_labels();
+        this
     }
 }
 
-use cursive::event::{Event, EventResult};
-
-impl ViewWrapper for TorrentTabsView {
-    cursive::wrap_impl!(self.view: TabPanel);
-
-    fn wrap_on_event(&mut self, event: Event) -> EventResult {
-        let old_tab = self.active_tab;
-        let result = self.view.on_event(event);
+impl TorrentTabsView {
+    fn sync_active_tab(&mut self) {
         if let Some(new_tab) = self.view.active_tab() {
             let new_tab: Tab = new_tab.parse().expect("bad tab name");
-            if new_tab != old_tab {
+            if new_tab != self.active_tab {
                 self.active_tab = new_tab;
                 self.active_tab_send.send(new_tab).unwrap();
                 self.thread_notifier.notify_one();
+
+                let mut cfg = crate::config::write();
+                cfg.torrent_tabs.active_tab = Some(new_tab.to_string());
+                cfg.save();
+            }
+        }
+    }
+
+    fn bar_labels(&self) -> Vec<(Tab, String)> {
+        let counts = self.counts_recv.borrow();
+        Tab::ALL
+            .iter()
+            .map(|&tab| {
+                let label = match counts.get(&tab) {
+                    Some(count) => format!(" {} ({}) ", tab, count),
+                    None => format!(" {} ", tab),
+                };
+                (tab, label)
+            })
+            .collect()
+    }
+
+    /// Which tab's title is at column `x` of the bar.
+    fn tab_at(&self, x: usize) -> Option<Tab> {
+        let mut start = 0;
+        for (tab, label) in &self.bar {
+            let end = start + label.len();
+            if (start..end).contains(&x) {
+                return Some(*tab);
             }
+            // Skip the separator.
+            start = end + 1;
         }
+        None
+    }
 
-        result
+    /// Switches to the next (or previous) tab, wrapping around at either end.
+    pub(crate) fn cycle_tab(&mut self, forward: bool) {
+        let tab = self.active_tab.cycle(forward);
+        self.view
+            .set_active_tab(tab.as_ref())
+            .expect("tab should exist");
+        self.sync_active_tab();
+    }
+
+    /// Moves the focus from the bar into the active tab, if it has anything to focus.
+    fn focus_tab(&mut self) -> EventResult {
+        match self.view.take_focus(Direction::up()) {
+            Ok(result) => {
+                self.bar_focused = false;
+                result
+            }
+            Err(CannotFocus) => EventResult::Consumed(None),
+        }
+    }
+
+    fn on_bar_event(&mut self, event: Event) -> EventResult {
+        match event {
+            Event::Key(Key::Left) => self.cycle_tab(false),
+            Event::Key(Key::Right) => self.cycle_tab(true),
+            Event::Key(Key::Down) | Event::Key(Key::Enter) => return self.focus_tab(),
+            _ => return EventResult::Ignored,
+        }
+        EventResult::Consumed(None)
+    }
+}
+
+use cursive::direction::Direction;
+use cursive::event::{Event, EventResult, Key, MouseButton, MouseEvent};
+use cursive::view::CannotFocus;
+
+impl ViewWrapper for TorrentTabsView {
+    cursive::wrap_impl!(self.view: TabView);
+
+    fn wrap_draw(&self, printer: &Printer) {
+        let mut x = 0;
+        for (tab, label) in &self.bar {
+            let style = match (*tab == self.active_tab, printer.focused && self.bar_focused) {
+                (true, true) => ColorStyle::highlight(),
+                (true, false) => ColorStyle::highlight_inactive(),
+                (false, _) => ColorStyle::primary(),
+            };
+            printer.with_color(style, |p| p.print((x, 0), label));
+            x += label.len();
+            printer.print((x, 0), "│");
+            x += 1;
+        }
+        printer.print_hline((x, 0), printer.size.x.saturating_sub(x), "─");
+
+        self.view
+            .draw(&printer.offset((0, 1)).focused(!self.bar_focused));
+    }
+
+    fn wrap_required_size(&mut self, req: Vec2) -> Vec2 {
+        self.view.required_size(req.saturating_sub((0, 1))) + (0, 1)
+    }
+
+    fn wrap_on_event(&mut self, event: Event) -> EventResult {
+        if let Event::Mouse {
+            offset,
+            position,
+            event: MouseEvent::Press(MouseButton::Left),
+        } = event
+        {
+            if let Some(pos) = position.checked_sub(offset).filter(|pos| pos.y == 0) {
+                if let Some(tab) = self.tab_at(pos.x) {
+                    self.view
+                        .set_active_tab(tab.as_ref())
+                        .expect("tab should exist");
+                    self.sync_active_tab();
+                }
+                self.bar_focused = true;
+                return EventResult::Consumed(None);
+            }
+            self.bar_focused = false;
+        } else if self.bar_focused && !matches!(event, Event::Mouse { .. }) {
+            return self.on_bar_event(event);
+        }
+
+        let result = self.view.on_event(event.relativized((0, 1)));
+        self.sync_active_tab();
+        match result {
+            // Going up past the top of the tab lands on the bar.
+            EventResult::Ignored if event == Event::Key(Key::Up) => {
+                self.bar_focused = true;
+                EventResult::Consumed(None)
+            }
+            result => result,
+        }
+    }
+
+    fn wrap_take_focus(&mut self, source: Direction) -> Result<EventResult, CannotFocus> {
+        // Coming from above, the bar is the first thing there is.
+        if source != Direction::up() {
+            if let Ok(result) = self.view.take_focus(source) {
+                self.bar_focused = false;
+                return Ok(result);
+            }
+        }
+        self.bar_focused = true;
+        Ok(EventResult::Consumed(None))
     }
 
     fn wrap_layout(&mut self, size: Vec2) {
+        if let Some(Ok(())) = self.counts_recv.changed().now_or_never() {
+            self.bar = self.bar_labels();
+        }
+        let size = size.saturating_sub((0, 1));
+
+        if let Some(Ok(())) = self.session_recv.changed().now_or_never() {
+            let handle = self.session_recv.borrow();
+            self.capabilities = handle.get_capabilities().cloned();
+            self.auth_level = handle.get_auth_level();
+            self.host = handle.get_id();
+        }
+
         if self.active_tab == Tab::Options {
+            let host = self.host;
+            self.view
+                .call_on_name("Options", |view: &mut options::OptionsView| {
+                    view.set_host(host)
+                })
+                .unwrap();
+            if let Some(caps) = &self.capabilities {
+                let auth_level = self.auth_level;
+                self.view
+                    .call_on_name("Options", |view: &mut options::OptionsView| {
+                        view.set_capabilities(caps, auth_level)
+                    })
+                    .unwrap();
+            }
+
             if let Some(opts) =
                 task::block_in_place(|| self.pending_options.read().unwrap().clone())
             {
                 self.view
                     .call_on_name("Options", |view: &mut options::OptionsView| {
                         view.second_column().2.set_enabled(opts.stop_at_ratio);
-                        view.apply_button().get_inner_mut().enable();
+                        view.set_dirty(true);
                         view.move_completed_path().set_enabled(opts.move_completed);
                     })
                     .unwrap();
@@ -299,6 +604,7 @@ impl ViewWrapper for TorrentTabsView {
                 return;
             } else if let Some(Ok(())) = self.current_options_recv.changed().now_or_never() {
                 let opts = self.current_options_recv.borrow().clone();
+                let mixed = task::block_in_place(|| *self.options_mixed.read().unwrap());
 
                 // Intentionally ignoring the callbacks returned here.
                 // In this case, those callbacks will update the pending options.
@@ -307,7 +613,7 @@ impl ViewWrapper for TorrentTabsView {
 
                 self.view
                     .call_on_name("Options", |view: &mut options::OptionsView| {
-                        view.update(opts);
+                        view.update(opts, mixed);
                     })
                     .unwrap();
             }
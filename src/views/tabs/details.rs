@@ -1,27 +1,15 @@
-use super::{column, BuildableTabData, TabData};
+use super::{column, shared::SharedStatus, BuildableTabData, TabData};
 use crate::util;
 use crate::views::thread::ViewThread;
+use crate::views::torrents::TorrentsView;
 use async_trait::async_trait;
 use cursive::align::HAlign;
-use cursive::views::{LinearLayout, TextContent, TextView};
-use deluge_rpc::{InfoHash, Query, Session};
-use serde::Deserialize;
+use cursive::traits::Resizable;
+use cursive::views::{Button, DummyView, LinearLayout, TextContent, TextView};
+use cursive::Cursive;
+use deluge_rpc::{InfoHash, Session};
 use static_assertions::const_assert_eq;
 
-#[derive(Debug, Clone, Deserialize, Query)]
-struct TorrentDetails {
-    name: String,
-    download_location: String,
-    total_size: u64,
-    num_files: u64,
-    creator: String,
-    comment: String,
-    time_added: i64,
-    completed_time: i64,
-    num_pieces: u64,
-    piece_length: u64,
-}
-
 pub(super) struct DetailsData {
     selection: InfoHash,
 
@@ -31,15 +19,12 @@ pub(super) struct DetailsData {
     bottom: TextContent,
 }
 
-#[async_trait]
-impl ViewThread for DetailsData {
-    async fn update(&mut self, session: &Session) -> deluge_rpc::Result<()> {
+impl DetailsData {
+    pub(super) fn apply(&mut self, details: &SharedStatus) {
         let hash = self.selection;
 
-        let details = session.get_torrent_status::<TorrentDetails>(hash).await?;
-
         self.top
-            .set_content([details.name, details.download_location].join("\n"));
+            .set_content([details.name.as_str(), details.download_location.as_str()].join("\n"));
 
         self.left.set_content(
             [
@@ -64,8 +49,14 @@ impl ViewThread for DetailsData {
         );
 
         self.bottom
-            .set_content([details.creator, details.comment].join("\n"));
+            .set_content([details.creator.as_str(), details.comment.as_str()].join("\n"));
+    }
+}
 
+#[async_trait]
+impl ViewThread for DetailsData {
+    // The tabs thread fetches for this along with the other shared tabs, and calls `apply`.
+    async fn update(&mut self, _: &Session) -> deluge_rpc::Result<()> {
         Ok(())
     }
 
@@ -83,6 +74,16 @@ impl TabData for DetailsData {
     }
 }
 
+fn with_selected_torrent(f: fn(&mut Cursive, InfoHash)) -> impl Fn(&mut Cursive) {
+    move |siv| {
+        if let Some(Some(hash)) =
+            siv.call_on_name("torrents", |v: &mut TorrentsView| v.selected_torrent())
+        {
+            f(siv, hash);
+        }
+    }
+}
+
 impl BuildableTabData for DetailsData {
     type V = LinearLayout;
 
@@ -105,10 +106,23 @@ impl BuildableTabData for DetailsData {
             .child(TextView::new(" ╷ \n │ \n ╵ "))
             .child(right_view);
 
+        let buttons = LinearLayout::horizontal()
+            .child(Button::new(
+                "Copy Hash",
+                with_selected_torrent(crate::menu::copy_hash),
+            ))
+            .child(DummyView.fixed_width(1))
+            .child(Button::new(
+                "Copy Magnet URI",
+                with_selected_torrent(crate::menu::copy_magnet_uri),
+            ));
+
         let view = LinearLayout::vertical()
             .child(top_view)
             .child(middle_view)
-            .child(bottom_view);
+            .child(bottom_view)
+            .child(DummyView.fixed_height(1))
+            .child(buttons);
 
         let data = Self {
             selection: InfoHash::default(),
@@ -2,20 +2,23 @@ use super::{BuildableTabData, TabData};
 use crate::menu;
 use crate::util::{
     self,
+    glyphs::glyphs,
     simple_slab::{SimpleSlab, SlabKey},
 };
 use crate::views::table::{TableView, TableViewData};
 use crate::views::thread::ViewThread;
 use async_trait::async_trait;
 use cursive::event::Callback;
+use cursive::traits::Resizable;
 use cursive::view::ViewWrapper;
+use cursive::views::{EditView, LinearLayout, TextView};
 use cursive::Printer;
 use cursive::Vec2;
 use deluge_rpc::{FilePriority, InfoHash, Query, Session};
 use itertools::Itertools;
 use serde::Deserialize;
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, RwLock};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -68,7 +71,7 @@ pub(crate) struct Dir {
 pub(crate) type FileKey = SlabKey<File>;
 pub(crate) type DirKey = SlabKey<Dir>;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub(crate) enum DirEntry {
     File(FileKey), // an index into a Vec<File>
     Dir(DirKey),   // an index into a Slab<Dir>
@@ -107,6 +110,13 @@ pub(crate) struct FilesState {
     root_dir: DirKey,
     sort_column: Column,
     descending_sort: bool,
+    natural_sort: bool,
+    // Lowercase. Only entries whose paths contain this are shown.
+    filter: String,
+    // The entries matching the filter, and their ancestors. None if there's no filter.
+    visible: Option<HashSet<DirEntry>>,
+    // Each folder's collapsed-ness from before the filter expanded things.
+    saved_collapse: Option<Vec<bool>>,
 }
 
 macro_rules! getter {
@@ -304,6 +314,59 @@ impl FilesState {
         self.dirs_info.shrink_to_fit();
 
         self.update_dir_values();
+
+        // These were for the old tree.
+        self.saved_collapse = None;
+        self.refilter();
+    }
+
+    /// Narrows the tree down to entries whose paths contain `filter`, ignoring case.
+    pub fn set_filter(&mut self, filter: &str) {
+        self.filter = filter.to_lowercase();
+        self.refilter();
+        self.rebuild_rows();
+    }
+
+    /// Works out which entries the filter leaves visible, expanding every folder with a match
+    /// in it. Once the filter is cleared, the folders go back to how they were.
+    fn refilter(&mut self) {
+        if self.filter.is_empty() {
+            if let Some(saved) = self.saved_collapse.take() {
+                for (dir, collapsed) in self.dirs_info.iter_mut().zip(saved) {
+                    dir.collapsed = collapsed;
+                }
+            }
+            self.visible = None;
+            return;
+        }
+
+        if self.saved_collapse.is_none() {
+            self.saved_collapse = Some(self.dirs_info.iter().map(|dir| dir.collapsed).collect());
+        }
+
+        let mut visible = HashSet::new();
+        for i in 0..self.files_info.len() {
+            let file = DirEntry::File(FileKey::from(i));
+            if !self
+                .get_full_path(file)
+                .to_lowercase()
+                .contains(&self.filter)
+            {
+                continue;
+            }
+            visible.insert(file);
+
+            let mut parent = self.get_parent(file);
+            while let Some(id) = parent {
+                self.dirs_info[id].collapsed = false;
+                if !visible.insert(DirEntry::Dir(id)) {
+                    // Another match already took care of the rest.
+                    break;
+                }
+                parent = self.dirs_info[id].parent;
+            }
+        }
+        self.visible = Some(visible);
     }
 
     fn update_dir_values(&mut self) {
@@ -350,7 +413,12 @@ impl FilesState {
         // or something
         // I don't really know
         // this is a really complicated problem
-        let mut children: Vec<DirEntry> = self.dirs_info[id].children.values().copied().collect();
+        let mut children: Vec<DirEntry> = self.dirs_info[id]
+            .children
+            .values()
+            .copied()
+            .filter(|child| self.visible.as_ref().map_or(true, |v| v.contains(child)))
+            .collect();
 
         children.sort_unstable_by(|a, b| self.compare_rows(a, b));
 
@@ -372,7 +440,7 @@ impl FilesState {
         let (a, b) = (&self.dirs_info[a], &self.dirs_info[b]);
 
         match self.sort_column {
-            Column::Filename => a.name.cmp(&b.name).reverse(),
+            Column::Filename => util::compare_names(&a.name, &b.name, self.natural_sort).reverse(),
             Column::Size => a.size.cmp(&b.size),
             Column::Progress => a
                 .progress
@@ -386,7 +454,7 @@ impl FilesState {
         let (a, b) = (&self.files_info[a], &self.files_info[b]);
 
         match self.sort_column {
-            Column::Filename => a.name.cmp(&b.name).reverse(),
+            Column::Filename => util::compare_names(&a.name, &b.name, self.natural_sort).reverse(),
             Column::Size => a.size.cmp(&b.size),
             Column::Progress => a
                 .progress
@@ -469,7 +537,12 @@ impl TableViewData for FilesState {
         match (col, *entry) {
             (Column::Filename, DirEntry::Dir(id)) => {
                 let dir = &self.dirs_info[id];
-                let c = if dir.collapsed { '▸' } else { '▾' };
+                let g = glyphs();
+                let c = if dir.collapsed {
+                    g.collapsed
+                } else {
+                    g.expanded
+                };
                 let text = format!("{} {}", c, dir.name);
                 printer.print((dir.depth, 0), &text);
             }
@@ -531,11 +604,11 @@ impl TableViewData for FilesState {
 }
 
 pub(super) struct FilesView {
-    inner: TableView<FilesState>,
+    inner: LinearLayout,
 }
 
 impl ViewWrapper for FilesView {
-    cursive::wrap_impl!(self.inner: TableView<FilesState>);
+    cursive::wrap_impl!(self.inner: LinearLayout);
 }
 
 pub(super) struct FilesData {
@@ -547,6 +620,16 @@ pub(super) struct FilesData {
 impl ViewThread for FilesData {
     async fn update(&mut self, session: &Session) -> deluge_rpc::Result<()> {
         let hash = self.selection;
+
+        {
+            let natural_sort = crate::config::read().interface.natural_sort;
+            let mut state = self.state.write().unwrap();
+            if natural_sort != state.natural_sort {
+                state.natural_sort = natural_sort;
+                state.sort_stable();
+            }
+        }
+
         let mut query = session.get_torrent_status_diff::<FilesQuery>(hash).await?;
 
         // Deluge is dumb, so this is always Some.
@@ -628,6 +711,8 @@ impl ViewThread for FilesData {
         let old = std::mem::take(&mut *data);
         data.sort_column = old.sort_column;
         data.descending_sort = old.descending_sort;
+        // It's still in the filter box.
+        data.filter = old.filter;
     }
 }
 
@@ -673,13 +758,25 @@ impl BuildableTabData for FilesData {
             (Column::Progress, 10),
             (Column::Priority, 10),
         ];
-        let mut view = FilesView {
-            inner: TableView::new(columns),
+        let mut table = TableView::new(columns);
+        table.set_config_key("files");
+        table.set_empty_message("No files (the torrent's metadata may not be known yet)");
+        table.set_on_double_click(on_double_click);
+        table.set_on_right_click(on_right_click);
+
+        let state = table.get_data();
+
+        let filter = {
+            let state = state.clone();
+            EditView::new().on_edit(move |_, text, _| state.write().unwrap().set_filter(text))
         };
-        view.inner.set_on_double_click(on_double_click);
-        view.inner.set_on_right_click(on_right_click);
+        let filter_row = LinearLayout::horizontal()
+            .child(TextView::new("Filter: "))
+            .child(filter.full_width());
 
-        let state = view.inner.get_data();
+        let view = FilesView {
+            inner: LinearLayout::vertical().child(filter_row).child(table),
+        };
         let data = FilesData {
             state,
             selection: InfoHash::default(),
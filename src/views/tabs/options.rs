@@ -1,25 +1,31 @@
 use super::{BuildableTabData, TabData};
+use crate::capabilities::Capabilities;
 use crate::views::spin::SpinView;
 use crate::views::thread::ViewThread;
+use crate::views::torrents::TorrentsView;
 use crate::views::{
     labeled_checkbox::LabeledCheckbox,
+    path_edit::PathEdit,
     static_linear_layout::{
         panel::{Child, StaticLinearPanel},
         StaticLinearLayout,
     },
 };
+use crate::Marked;
 use async_trait::async_trait;
 use cursive::traits::Resizable;
 use cursive::views::{
-    Button, DummyView, EditView, EnableableView, Panel, ResizedView, TextContent, TextView,
+    Button, Dialog, DummyView, EditView, EnableableView, Panel, ResizedView, TextContent, TextView,
 };
-use deluge_rpc::{InfoHash, Query, Session};
+use deluge_rpc::{AuthLevel, InfoHash, Query, Session};
+use futures::future;
 use serde::Deserialize;
 use std::sync::{Arc, RwLock};
 use tokio::sync::watch;
 use tokio::sync::Notify;
 use tokio::task;
 use tokio::time;
+use uuid::Uuid;
 
 #[derive(Default, Debug, Clone, Deserialize, Query)]
 pub(super) struct OptionsQuery {
@@ -40,6 +46,129 @@ pub(super) struct OptionsQuery {
     pub super_seeding: bool,
     pub move_completed: bool,
     pub move_completed_path: String,
+
+    pub queue: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Query)]
+struct QueueQuery {
+    queue: i64,
+}
+
+/// Moves a queued torrent from `old` to `new` in the queue.
+/// Deluge can only move torrents one step at a time, or all the way to either end,
+/// so this jumps to whichever end is closer to `new` when that takes fewer steps.
+/// The steps are sent all at once rather than waiting on each one in turn.
+async fn move_in_queue(
+    session: &Session,
+    hash: InfoHash,
+    old: i64,
+    new: i64,
+) -> deluge_rpc::Result<()> {
+    let hashes = [hash];
+
+    if new < old {
+        if new < old - new {
+            session.queue_top(&hashes).await?;
+            future::try_join_all((0..new).map(|_| session.queue_down(&hashes))).await?;
+        } else {
+            future::try_join_all((new..old).map(|_| session.queue_up(&hashes))).await?;
+        }
+    } else if new > old {
+        // Moving past the end of the queue just lands at the end.
+        let last = session
+            .get_torrents_status::<QueueQuery>(None)
+            .await?
+            .values()
+            .filter(|q| q.queue >= 0)
+            .count() as i64
+            - 1;
+        let new = new.min(last);
+
+        if last - new < new - old {
+            session.queue_bottom(&hashes).await?;
+            future::try_join_all((new..last).map(|_| session.queue_up(&hashes))).await?;
+        } else {
+            future::try_join_all((old..new).map(|_| session.queue_down(&hashes))).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Which options differ between the marked torrents. The checkboxes among them are shown as [-].
+/// Applying leaves those fields alone unless they were changed.
+#[derive(Debug, Default, Clone, Copy)]
+pub(super) struct MixedOptions {
+    max_download_speed: bool,
+    max_upload_speed: bool,
+    max_connections: bool,
+    max_upload_slots: bool,
+    stop_ratio: bool,
+    move_completed_path: bool,
+
+    auto_managed: bool,
+    stop_at_ratio: bool,
+    remove_at_ratio: bool,
+    shared: bool,
+    prioritize_first_last_pieces: bool,
+    sequential_download: bool,
+    super_seeding: bool,
+    move_completed: bool,
+}
+
+macro_rules! mixed_fields {
+    ($m:ident) => {
+        $m! {
+            max_download_speed,
+            max_upload_speed,
+            max_connections,
+            max_upload_slots,
+            stop_ratio,
+            move_completed_path,
+            auto_managed,
+            stop_at_ratio,
+            remove_at_ratio,
+            shared,
+            prioritize_first_last_pieces,
+            sequential_download,
+            super_seeding,
+            move_completed,
+        }
+    };
+}
+
+impl MixedOptions {
+    fn of(all: &[OptionsQuery]) -> Self {
+        macro_rules! compare {
+            ($($field:ident),+$(,)?) => {
+                match all.split_first() {
+                    Some((first, rest)) => Self {
+                        $($field: rest.iter().any(|o| o.$field != first.$field)),+
+                    },
+                    None => Self::default(),
+                }
+            };
+        }
+        mixed_fields!(compare)
+    }
+
+    /// Leaves out whatever is mixed and wasn't changed from what was shown.
+    fn apply_to(
+        &self,
+        options: &mut deluge_rpc::TorrentOptions,
+        new: &OptionsQuery,
+        shown: &OptionsQuery,
+    ) {
+        macro_rules! clear {
+            ($($field:ident),+$(,)?) => {
+                $(if self.$field && new.$field == shown.$field {
+                    options.$field = None;
+                })+
+            };
+        }
+        mixed_fields!(clear)
+    }
 }
 
 pub(super) struct OptionsData {
@@ -49,20 +178,31 @@ pub(super) struct OptionsData {
     owner: TextContent,
     pub current_options_recv: watch::Receiver<OptionsQuery>,
     pub pending_options: Arc<RwLock<Option<OptionsQuery>>>,
+    // The torrents that the next Apply should affect, which needn't include the selection.
+    apply_targets: Arc<RwLock<Vec<InfoHash>>>,
+    apply_due: bool,
+    pub mixed: Arc<RwLock<MixedOptions>>,
+    // The marked torrents that `mixed` was worked out for, so it's only redone when they change.
+    mixed_among: Vec<InfoHash>,
+    pub marked: Marked,
 }
 
 impl OptionsData {
     async fn apply(&mut self, session: &Session) -> deluge_rpc::Result<()> {
-        let new_options = task::block_in_place(|| {
-            let mut opts = self.pending_options.write().unwrap();
-            assert!(opts.is_some());
-            opts.take().unwrap()
-        });
+        // The edits stay pending until the daemon has them, so that they aren't lost
+        // if it never answers. Applying again is up to the user.
+        let new_options = task::block_in_place(|| self.pending_options.read().unwrap().clone());
+        let new_options = match new_options {
+            Some(new_options) => new_options,
+            // Reverted in the meantime.
+            None => return Ok(()),
+        };
 
-        self.current_options_send.send(new_options).unwrap();
+        let shown = self.current_options_recv.borrow().clone();
+        let (old_queue, new_queue) = (shown.queue, new_options.queue);
 
-        let options = {
-            let c = self.current_options_recv.borrow();
+        let mut options = {
+            let c = &new_options;
             // Not sure whether I made a mistake with this interface.
             deluge_rpc::TorrentOptions {
                 max_download_speed: Some(c.max_download_speed),
@@ -83,9 +223,59 @@ impl OptionsData {
             }
         };
 
-        session
-            .set_torrent_options(&[self.selection], &options)
-            .await
+        let targets = task::block_in_place(|| self.apply_targets.read().unwrap().clone());
+        let mixed = task::block_in_place(|| *self.mixed.read().unwrap());
+        mixed.apply_to(&mut options, &new_options, &shown);
+        session.set_torrent_options(&targets, &options).await?;
+
+        // The queue position shown is only known to be right for the selection itself.
+        if targets == [self.selection] && old_queue >= 0 && new_queue >= 0 {
+            move_in_queue(session, self.selection, old_queue, new_queue).await?;
+        }
+
+        task::block_in_place(|| {
+            self.pending_options.write().unwrap().take();
+            self.apply_targets.write().unwrap().clear();
+        });
+        self.current_options_send.send(new_options).unwrap();
+        // Whatever differed among the marked torrents may not anymore.
+        self.mixed_among.clear();
+        Ok(())
+    }
+}
+
+impl OptionsData {
+    /// Gets the selected torrent's options, and which ones differ among the marked torrents.
+    async fn fetch(&mut self, session: &Session) -> deluge_rpc::Result<()> {
+        let hash = self.selection;
+        let options = session.get_torrent_status::<OptionsQuery>(hash).await?;
+
+        // The selection's options are the ones shown, so they count even if it isn't marked.
+        let mut marked = task::block_in_place(|| self.marked.read().unwrap().clone());
+        if !marked.is_empty() && !marked.contains(&hash) {
+            marked.push(hash);
+        }
+        if marked.len() < 2 {
+            marked.clear();
+        }
+        if marked != self.mixed_among {
+            let mixed = if marked.is_empty() {
+                MixedOptions::default()
+            } else {
+                let requests = marked
+                    .iter()
+                    .map(|&h| session.get_torrent_status::<OptionsQuery>(h));
+                let marked_options = future::try_join_all(requests).await?;
+                MixedOptions::of(&marked_options)
+            };
+            task::block_in_place(|| *self.mixed.write().unwrap() = mixed);
+            self.mixed_among = marked;
+        }
+
+        self.owner.set_content(&options.owner);
+        self.current_options_send.send(options).unwrap();
+
+        Ok(())
     }
 }
 
@@ -95,29 +285,29 @@ impl ViewThread for OptionsData {
         let deadline = time::Instant::now() + time::Duration::from_secs(1);
 
         if task::block_in_place(|| self.pending_options.read().unwrap().is_none()) {
-            let hash = self.selection;
-            let options = session.get_torrent_status::<OptionsQuery>(hash).await?;
-            self.owner.set_content(&options.owner);
-            self.current_options_send.send(options).unwrap();
+            self.fetch(session).await?;
         } else {
             let timeout = time::timeout_at(deadline, self.apply_notify.notified());
             if let Ok(()) = timeout.await {
-                self.apply(session).await?;
+                self.apply_due = true;
             }
         }
 
         Ok(())
     }
 
+    async fn send_changes(&mut self, session: &Session) -> deluge_rpc::Result<()> {
+        if std::mem::take(&mut self.apply_due) {
+            self.apply(session).await?;
+        }
+        Ok(())
+    }
+
     async fn reload(&mut self, session: &Session) -> deluge_rpc::Result<()> {
+        self.apply_due = false;
+        self.mixed_among.clear();
         task::block_in_place(|| self.pending_options.write().unwrap().take());
-
-        let hash = self.selection;
-        let options = session.get_torrent_status::<OptionsQuery>(hash).await?;
-        self.owner.set_content(&options.owner);
-        self.current_options_send.send(options).unwrap();
-
-        Ok(())
+        self.fetch(session).await
     }
 
     fn clear(&mut self) {
@@ -149,15 +339,18 @@ type BandwidthLimitsColumn = StaticLinearLayout<(TextView, BandwidthLimitsPanel)
 
 pub(super) type RatioLimitControls = StaticLinearLayout<(FloatSpinView, LabeledCheckbox)>;
 
+type ApplyButtons = StaticLinearLayout<(Button, ResizedView<DummyView>, Button)>;
+
 type SecondColumnElements = (
     LabeledCheckbox,
     LabeledCheckbox,
     EnableableView<Panel<RatioLimitControls>>,
-    Panel<Button>,
+    IntSpinView,
+    Panel<ApplyButtons>,
 );
 type SecondColumn = StaticLinearLayout<SecondColumnElements>;
 
-type OwnerTextView = StaticLinearLayout<(TextView, TextView)>;
+type OwnerTextView = StaticLinearLayout<(TextView, TextView, ResizedView<DummyView>, Button)>;
 
 type ThirdColumnElements = (
     OwnerTextView,
@@ -166,7 +359,7 @@ type ThirdColumnElements = (
     LabeledCheckbox,
     LabeledCheckbox,
     LabeledCheckbox,
-    ResizedView<EditView>,
+    ResizedView<PathEdit>,
 );
 type ThirdColumn = StaticLinearLayout<ThirdColumnElements>;
 
@@ -191,8 +384,18 @@ impl OptionsView {
         self.get_children_mut().2.get_children_mut()
     }
 
-    pub fn apply_button(&mut self) -> &mut Panel<Button> {
-        &mut self.second_column().3
+    pub fn apply_panel(&mut self) -> &mut Panel<ApplyButtons> {
+        &mut self.second_column().4
+    }
+
+    /// Marks whether there are pending edits, enabling the Apply and Revert buttons if so.
+    pub fn set_dirty(&mut self, dirty: bool) {
+        let panel = self.apply_panel();
+        panel.set_title(if dirty { "* Modified" } else { "" });
+
+        let (apply, _, revert) = panel.get_inner_mut().get_children_mut();
+        apply.set_enabled(dirty);
+        revert.set_enabled(dirty);
     }
 
     pub fn third_column(&mut self) -> &mut ThirdColumnElements {
@@ -200,10 +403,47 @@ impl OptionsView {
     }
 
     pub fn move_completed_path(&mut self) -> &mut EditView {
-        self.third_column().6.get_inner_mut()
+        self.third_column().6.get_inner_mut().get_inner_mut()
+    }
+
+    /// Says which host the move completed path is on, for completing it.
+    pub fn set_host(&mut self, host: Option<Uuid>) {
+        self.third_column().6.get_inner_mut().set_host(host);
+    }
+
+    /// Greys out the options that the daemon doesn't know about,
+    /// and changing the owner unless the session is allowed to.
+    pub fn set_capabilities(&mut self, caps: &Capabilities, auth_level: Option<AuthLevel>) {
+        let v2 = caps.supports_v2_options();
+        let admin = auth_level >= Some(AuthLevel::Admin);
+        let col3 = self.third_column();
+        col3.0.get_children_mut().3.set_enabled(v2 && admin);
+        col3.1.set_enabled(v2);
+        col3.4.set_enabled(v2);
+    }
+
+    /// Narrows down what was mixed to the checkboxes that still haven't been given a value.
+    pub fn mixed_options(&mut self, fetched: MixedOptions) -> MixedOptions {
+        let col2 = self.second_column();
+        let (auto_managed, stop_at_ratio) = (col2.0.is_indeterminate(), col2.1.is_indeterminate());
+        let ratio_limit_panel = col2.2.get_inner_mut().get_inner_mut().get_children_mut();
+        let remove_at_ratio = ratio_limit_panel.1.is_indeterminate();
+
+        let col3 = self.third_column();
+        MixedOptions {
+            auto_managed,
+            stop_at_ratio,
+            remove_at_ratio,
+            shared: col3.1.is_indeterminate(),
+            prioritize_first_last_pieces: col3.2.is_indeterminate(),
+            sequential_download: col3.3.is_indeterminate(),
+            super_seeding: col3.4.is_indeterminate(),
+            move_completed: col3.5.is_indeterminate(),
+            ..fetched
+        }
     }
 
-    pub(super) fn update(&mut self, opts: OptionsQuery) {
+    pub(super) fn update(&mut self, opts: OptionsQuery, mixed: MixedOptions) {
         let col1 = self.bandwidth_limits();
         col1.0.get_inner_mut().set_val(opts.max_download_speed);
         col1.1.get_inner_mut().set_val(opts.max_upload_speed);
@@ -212,24 +452,43 @@ impl OptionsView {
 
         let col2 = self.second_column();
         col2.0.set_checked(opts.auto_managed);
+        col2.0.set_indeterminate(mixed.auto_managed);
         col2.1.set_checked(opts.stop_at_ratio);
+        col2.1.set_indeterminate(mixed.stop_at_ratio);
         col2.2.set_enabled(opts.stop_at_ratio);
-        col2.3.get_inner_mut().disable();
+        col2.3.set_val(opts.queue);
 
         let ratio_limit_panel = col2.2.get_inner_mut().get_inner_mut().get_children_mut();
         ratio_limit_panel.0.set_val(opts.stop_ratio);
         ratio_limit_panel.1.set_checked(opts.remove_at_ratio);
+        ratio_limit_panel.1.set_indeterminate(mixed.remove_at_ratio);
 
         let col3 = self.third_column();
         col3.1.set_checked(opts.shared);
+        col3.1.set_indeterminate(mixed.shared);
         col3.2.set_checked(opts.prioritize_first_last_pieces);
+        col3.2.set_indeterminate(mixed.prioritize_first_last_pieces);
         col3.3.set_checked(opts.sequential_download);
+        col3.3.set_indeterminate(mixed.sequential_download);
         col3.4.set_checked(opts.super_seeding);
+        col3.4.set_indeterminate(mixed.super_seeding);
         col3.5.set_checked(opts.move_completed);
+        col3.5.set_indeterminate(mixed.move_completed);
 
         let path = self.move_completed_path();
         path.set_enabled(opts.move_completed);
         path.set_content(&opts.move_completed_path);
+
+        self.set_dirty(false);
+    }
+}
+
+/// Takes the checkboxes that were given a value out of what counts as mixed, just before applying.
+fn narrow_mixed(siv: &mut cursive::Cursive, mixed: &RwLock<MixedOptions>) {
+    let fetched = *mixed.read().unwrap();
+    let narrowed = siv.call_on_name("Options", |v: &mut OptionsView| v.mixed_options(fetched));
+    if let Some(m) = narrowed {
+        *mixed.write().unwrap() = m;
     }
 }
 
@@ -255,15 +514,23 @@ impl BuildableTabData for OptionsData {
 
         let bandwidth_limits = {
             let down = SpinView::new(Some("Download Speed"), Some("kiB/s"), -1.0f64..)
+                .step(50.0)
+                .page_step(500.0)
+                .sentinel(-1.0, "Unlimited")
                 .on_modify(set!(pending_options.max_download_speed));
 
             let up = SpinView::new(Some("Upload Speed"), Some("kiB/s"), -1.0f64..)
+                .step(50.0)
+                .page_step(500.0)
+                .sentinel(-1.0, "Unlimited")
                 .on_modify(set!(pending_options.max_upload_speed));
 
             let peers = SpinView::new(Some("Connections"), None, -1i64..)
+                .sentinel(-1, "Unlimited")
                 .on_modify(set!(pending_options.max_connections));
 
             let slots = SpinView::new(Some("Upload Slots"), None, -1i64..)
+                .sentinel(-1, "Unlimited")
                 .on_modify(set!(pending_options.max_upload_slots));
 
             BandwidthLimitsPanel::vertical((down, up, peers, slots))
@@ -273,6 +540,8 @@ impl BuildableTabData for OptionsData {
             BandwidthLimitsColumn::vertical((TextView::new("Bandwidth Limits"), bandwidth_limits));
 
         let apply_notify = Arc::new(Notify::new());
+        let apply_targets = Arc::new(RwLock::new(Vec::new()));
+        let mixed = Arc::new(RwLock::new(MixedOptions::default()));
 
         let col2 = {
             let auto_managed =
@@ -282,8 +551,10 @@ impl BuildableTabData for OptionsData {
                 .on_change(set!(pending_options.stop_at_ratio));
 
             let ratio_limit_panel = {
-                let spinner =
-                    SpinView::new(None, None, 0.0f64..).on_modify(set!(pending_options.stop_ratio));
+                let spinner = SpinView::new(None, None, 0.0f64..)
+                    .step(0.1)
+                    .page_step(1.0)
+                    .on_modify(set!(pending_options.stop_ratio));
 
                 let checkbox = LabeledCheckbox::new("Remove at ratio")
                     .on_change(set!(pending_options.remove_at_ratio));
@@ -292,11 +563,68 @@ impl BuildableTabData for OptionsData {
                 EnableableView::new(Panel::new(layout))
             };
 
-            let apply_notify = apply_notify.clone();
-            let apply = Button::new("Apply", move |_| apply_notify.notify_one());
-            let apply_panel = Panel::new(apply);
+            let queue = SpinView::new(Some("Queue Position"), None, -1i64..)
+                .on_modify(set!(pending_options.queue));
+
+            let apply = {
+                let apply_notify = apply_notify.clone();
+                let apply_targets = apply_targets.clone();
+                let mixed = mixed.clone();
+                Button::new("Apply", move |siv| {
+                    let targets = siv
+                        .call_on_name("torrents", |v: &mut TorrentsView| v.selected_torrents())
+                        .unwrap_or_default();
+
+                    match targets.len() {
+                        0 => return,
+                        1 => {
+                            narrow_mixed(siv, &mixed);
+                            *apply_targets.write().unwrap() = targets;
+                            apply_notify.notify_one();
+                            return;
+                        }
+                        _ => (),
+                    }
+
+                    let apply_notify = apply_notify.clone();
+                    let apply_targets = apply_targets.clone();
+                    let mixed = mixed.clone();
+                    let msg = format!("Apply these options to {} torrents?", targets.len());
+                    let dialog = Dialog::text(msg)
+                        .title("Apply Options")
+                        .dismiss_button("Cancel")
+                        .button("Apply", move |siv| {
+                            narrow_mixed(siv, &mixed);
+                            *apply_targets.write().unwrap() = targets.clone();
+                            apply_notify.notify_one();
+                            siv.pop_layer();
+                        });
+                    siv.add_layer(dialog);
+                })
+            };
 
-            SecondColumn::vertical((auto_managed, stop_at_ratio, ratio_limit_panel, apply_panel))
+            let revert = {
+                let pending_options = pending_options.clone();
+                let current_options_recv = current_options_recv.clone();
+                let mixed = mixed.clone();
+                Button::new("Revert", move |siv| {
+                    pending_options.write().unwrap().take();
+                    let opts = current_options_recv.borrow().clone();
+                    let mixed = *mixed.read().unwrap();
+                    siv.call_on_name("Options", |view: &mut OptionsView| view.update(opts, mixed));
+                })
+            };
+
+            let buttons = ApplyButtons::horizontal((apply, DummyView.fixed_width(1), revert));
+            let apply_panel = Panel::new(buttons);
+
+            SecondColumn::vertical((
+                auto_managed,
+                stop_at_ratio,
+                ratio_limit_panel,
+                queue,
+                apply_panel,
+            ))
         };
 
         let owner_content = TextContent::new("");
@@ -304,7 +632,19 @@ impl BuildableTabData for OptionsData {
         let col3 = {
             let owner_text = TextView::new_with_content(owner_content.clone());
 
-            let owner = OwnerTextView::horizontal((TextView::new("Owner: "), owner_text));
+            let change_owner = Button::new("Change", |siv| {
+                let hashes = siv
+                    .call_on_name("torrents", |v: &mut TorrentsView| v.selected_torrents())
+                    .unwrap_or_default();
+                crate::menu::change_owner_dialog(siv, hashes);
+            });
+
+            let owner = OwnerTextView::horizontal((
+                TextView::new("Owner: "),
+                owner_text,
+                DummyView.fixed_width(1),
+                change_owner,
+            ));
 
             let shared = LabeledCheckbox::new("Shared").on_change(set!(pending_options.shared));
 
@@ -332,7 +672,7 @@ impl BuildableTabData for OptionsData {
                 }
             };
 
-            let move_completed_path = EditView::new().on_edit(edit_cb).min_width(25);
+            let move_completed_path = PathEdit::new(None).on_edit(edit_cb).min_width(25);
 
             ThirdColumn::vertical((
                 owner,
@@ -360,6 +700,11 @@ impl BuildableTabData for OptionsData {
             owner: owner_content,
             apply_notify,
             pending_options,
+            apply_targets,
+            apply_due: false,
+            mixed,
+            mixed_among: Vec::new(),
+            marked: Marked::default(),
         };
         (view, data)
     }
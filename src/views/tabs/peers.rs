@@ -1,11 +1,15 @@
 use super::{BuildableTabData, TabData};
 use crate::util;
+use crate::util::glyphs::glyphs;
 use crate::views::table::{TableView, TableViewData};
 use crate::views::thread::ViewThread;
 use async_trait::async_trait;
+use cursive::traits::{Nameable, Resizable};
+use cursive::views::{Button, LinearLayout, TextContent, TextView};
 use cursive::Printer;
 use deluge_rpc::{InfoHash, Query, Session};
 use fnv::{FnvHashMap, FnvHashSet};
+use itertools::Itertools;
 use serde::Deserialize;
 use std::cmp::Ordering;
 use std::net::SocketAddr;
@@ -66,6 +70,45 @@ impl Default for Column {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SeedFilter {
+    All,
+    HideSeeds,
+    HideLeeches,
+}
+
+impl SeedFilter {
+    fn next(self) -> Self {
+        match self {
+            Self::All => Self::HideSeeds,
+            Self::HideSeeds => Self::HideLeeches,
+            Self::HideLeeches => Self::All,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::All => "Showing: All",
+            Self::HideSeeds => "Showing: Leeches",
+            Self::HideLeeches => "Showing: Seeds",
+        }
+    }
+
+    fn shows(self, peer: &Peer) -> bool {
+        match self {
+            Self::All => true,
+            Self::HideSeeds => !peer.seed,
+            Self::HideLeeches => peer.seed,
+        }
+    }
+}
+
+impl Default for SeedFilter {
+    fn default() -> Self {
+        Self::All
+    }
+}
+
 // TODO: establish a consistent naming convention for the various view-related structs
 #[derive(Default)]
 pub(super) struct PeersTableData {
@@ -73,6 +116,7 @@ pub(super) struct PeersTableData {
     peers: FnvHashMap<SocketAddr, Peer>,
     sort_column: Column,
     descending_sort: bool,
+    seed_filter: SeedFilter,
 }
 
 impl PeersTableData {
@@ -88,20 +132,40 @@ impl PeersTableData {
         self.peers.reserve(peers.len());
 
         for peer in peers.into_iter() {
-            self.rows.push(peer.addr);
+            if self.seed_filter.shows(&peer) {
+                self.rows.push(peer.addr);
+            }
             self.peers.insert(peer.addr, peer);
         }
 
         self.sort_unstable();
     }
 
+    fn set_seed_filter(&mut self, seed_filter: SeedFilter) {
+        self.seed_filter = seed_filter;
+
+        let rows = self
+            .peers
+            .values()
+            .filter(|peer| seed_filter.shows(peer))
+            .map(|peer| peer.addr)
+            .collect();
+
+        self.rows = rows;
+        self.sort_unstable();
+    }
+
     fn update(&mut self, peers: Vec<Peer>) {
         self.peers.clear();
         self.peers.reserve(peers.len());
 
         // TODO: store things more persistently...?
         let old_addrs: FnvHashSet<_> = self.rows.iter().copied().collect();
-        let new_addrs: FnvHashSet<_> = peers.iter().map(|peer| peer.addr).collect();
+        let new_addrs: FnvHashSet<_> = peers
+            .iter()
+            .filter(|peer| self.seed_filter.shows(peer))
+            .map(|peer| peer.addr)
+            .collect();
 
         self.rows.retain(|addr| new_addrs.contains(addr));
         self.rows.extend(new_addrs.difference(&old_addrs));
@@ -114,6 +178,59 @@ impl PeersTableData {
     }
 }
 
+// How many clients/countries to list in the summary.
+const SUMMARY_GROUPS: usize = 5;
+
+#[derive(Default)]
+struct PeerGroup {
+    count: usize,
+    down_speed: u64,
+    up_speed: u64,
+}
+
+fn summarize<'a>(
+    peers: impl Iterator<Item = &'a Peer>,
+    key: impl Fn(&'a Peer) -> &'a str,
+) -> String {
+    let mut groups = FnvHashMap::<&str, PeerGroup>::default();
+    for peer in peers {
+        let group = groups.entry(key(peer)).or_default();
+        group.count += 1;
+        group.down_speed += peer.down_speed;
+        group.up_speed += peer.up_speed;
+    }
+
+    let speed = |n| util::fmt::bytes(n) + "/s";
+
+    groups
+        .into_iter()
+        .sorted_by(|(a_name, a), (b_name, b)| b.count.cmp(&a.count).then(a_name.cmp(b_name)))
+        .take(SUMMARY_GROUPS)
+        .map(|(name, group)| {
+            let name = if name.is_empty() { "?" } else { name };
+            let g = glyphs();
+            format!(
+                "{} {}{} ({}{} {}{})",
+                name,
+                g.times,
+                group.count,
+                g.down,
+                speed(group.down_speed),
+                g.up,
+                speed(group.up_speed),
+            )
+        })
+        .join(", ")
+}
+
+impl PeersTableData {
+    fn summary(&self) -> String {
+        let clients = summarize(self.peers.values(), |peer| &peer.client);
+        let countries = summarize(self.peers.values(), |peer| &peer.country);
+        format!("Clients: {}\nCountries: {}", clients, countries)
+    }
+}
+
 impl TableViewData for PeersTableData {
     type Column = Column;
     type RowIndex = SocketAddr;
@@ -195,6 +312,7 @@ impl TableViewData for PeersTableData {
 
 pub(super) struct PeersData {
     state: Arc<RwLock<PeersTableData>>,
+    summary: TextContent,
     was_empty: bool,
     selection: InfoHash,
 }
@@ -209,11 +327,13 @@ impl ViewThread for PeersData {
         if query.peers.is_empty() {
             if !self.was_empty {
                 self.was_empty = true;
-                self.state.write().unwrap().clear();
+                self.clear();
             }
         } else {
             self.was_empty = false;
-            self.state.write().unwrap().update(query.peers);
+            let mut state = self.state.write().unwrap();
+            state.update(query.peers);
+            self.summary.set_content(state.summary());
         }
 
         Ok(())
@@ -225,7 +345,7 @@ impl ViewThread for PeersData {
         // Get two different locks, so that we can have a moment of empty data.
         // The alternative is a moment of data for the old torrent.
         // I'd like to do this for the other tabs as well.
-        self.state.write().unwrap().clear();
+        self.clear();
 
         let query = session.get_torrent_status::<PeersQuery>(hash).await?;
 
@@ -233,14 +353,22 @@ impl ViewThread for PeersData {
             self.was_empty = true;
         } else {
             self.was_empty = false;
-            self.state.write().unwrap().populate(query.peers);
+            let mut state = self.state.write().unwrap();
+            state.populate(query.peers);
+            self.summary.set_content(state.summary());
         }
 
         Ok(())
     }
 
+    // Peers come and go quickly, and their speeds are the whole point of the tab.
+    fn tick(&self) -> tokio::time::Duration {
+        tokio::time::Duration::from_secs(1)
+    }
+
     fn clear(&mut self) {
         self.state.write().unwrap().clear();
+        self.summary.set_content("");
     }
 }
 
@@ -251,7 +379,7 @@ impl TabData for PeersData {
 }
 
 impl BuildableTabData for PeersData {
-    type V = TableView<PeersTableData>;
+    type V = LinearLayout;
 
     fn view() -> (Self::V, Self) {
         let columns = vec![
@@ -264,10 +392,35 @@ impl BuildableTabData for PeersData {
             (Column::UpSpeed, 10),
         ];
 
-        let view = TableView::new(columns);
-        let state = view.get_data();
+        let mut table = TableView::new(columns);
+        table.set_config_key("peers");
+        table.set_empty_message("No peers connected");
+        let state = table.get_data();
+
+        let seed_filter_button = {
+            let state = state.clone();
+            Button::new(SeedFilter::default().label(), move |siv| {
+                let label = {
+                    let mut state = state.write().unwrap();
+                    let seed_filter = state.seed_filter.next();
+                    state.set_seed_filter(seed_filter);
+                    seed_filter.label()
+                };
+                siv.call_on_name("peers_seed_filter", |b: &mut Button| b.set_label(label));
+            })
+            .with_name("peers_seed_filter")
+        };
+
+        let summary = TextContent::new("");
+        let footer = LinearLayout::horizontal()
+            .child(TextView::new_with_content(summary.clone()).full_width())
+            .child(seed_filter_button);
+
+        let view = LinearLayout::vertical().child(table).child(footer);
+
         let data = PeersData {
             state,
+            summary,
             selection: InfoHash::default(),
             was_empty: true,
         };
@@ -0,0 +1,59 @@
+use deluge_rpc::{Query, TorrentState};
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+pub(super) struct Tracker {/* we don't actually need any of this */}
+
+// The union of everything the Status, Details, and Trackers tabs display.
+// One fetch per tick fills in all three tabs, along with the counts in the tab bar,
+// so switching between them shows up-to-date data without another round-trip.
+#[derive(Debug, Clone, Deserialize, Query)]
+pub(super) struct SharedStatus {
+    // Status
+    pub state: TorrentState,
+    pub progress: f32,
+
+    pub download_payload_rate: u64,
+    pub max_download_speed: f64,
+    pub upload_payload_rate: u64,
+    pub max_upload_speed: f64,
+    #[serde(rename = "all_time_download")] // wtf
+    pub total_downloaded: u64,
+    pub total_payload_download: u64,
+    pub total_uploaded: u64,
+    pub total_payload_upload: u64,
+
+    pub num_seeds: u64,
+    pub total_seeds: i64,
+    pub num_peers: u64,
+    pub total_peers: i64,
+    pub ratio: f64,
+    #[serde(rename = "distributed_copies")]
+    pub availability: f64,
+    pub seed_rank: u64,
+
+    pub eta: i64,
+    pub active_time: i64,
+    pub seeding_time: i64,
+    pub time_since_transfer: i64,
+    pub last_seen_complete: i64,
+
+    // Details
+    pub name: String,
+    pub download_location: String,
+    pub total_size: u64,
+    pub num_files: u64,
+    pub creator: String,
+    pub comment: String,
+    pub time_added: i64,
+    pub completed_time: i64,
+    pub num_pieces: u64,
+    pub piece_length: u64,
+
+    // Trackers
+    pub trackers: Vec<Tracker>,
+    pub tracker_host: String,
+    pub tracker_status: String,
+    pub next_announce: i64,
+    pub private: bool,
+}
@@ -1,4 +1,4 @@
-use super::{column, BuildableTabData, TabData};
+use super::{column, shared::SharedStatus, BuildableTabData, TabData};
 use crate::util;
 use crate::views::thread::ViewThread;
 use async_trait::async_trait;
@@ -6,57 +6,19 @@ use cursive::align::HAlign;
 use cursive::traits::Resizable;
 use cursive::utils::Counter;
 use cursive::views::{DummyView, LinearLayout, ProgressBar, TextContent};
-use deluge_rpc::{InfoHash, Query, Session, TorrentState};
-use serde::Deserialize;
+use deluge_rpc::{InfoHash, Session};
 use std::convert::TryInto;
 use tokio::sync::watch;
 
-#[derive(Debug, Clone, Deserialize, Query)]
-struct TorrentStatus {
-    state: TorrentState,
-    progress: f32,
-
-    download_payload_rate: u64,
-    max_download_speed: f64,
-    upload_payload_rate: u64,
-    max_upload_speed: f64,
-    #[serde(rename = "all_time_download")] // wtf
-    total_downloaded: u64,
-    total_payload_download: u64,
-    total_uploaded: u64,
-    total_payload_upload: u64,
-
-    num_seeds: u64,
-    total_seeds: i64,
-    num_peers: u64,
-    total_peers: i64,
-    ratio: f64,
-    #[serde(rename = "distributed_copies")]
-    availability: f64,
-    seed_rank: u64,
-
-    eta: i64,
-    active_time: i64,
-    seeding_time: i64,
-    time_since_transfer: i64,
-    last_seen_complete: i64,
-}
-
 pub(super) struct StatusData {
-    selection: InfoHash,
-
     progress_label_send: watch::Sender<String>,
     progress_val: Counter,
 
     columns: [TextContent; 3],
 }
 
-#[async_trait]
-impl ViewThread for StatusData {
-    async fn update(&mut self, session: &Session) -> deluge_rpc::Result<()> {
-        let hash = self.selection;
-        let status = session.get_torrent_status::<TorrentStatus>(hash).await?;
-
+impl StatusData {
+    pub(super) fn apply(&mut self, status: &SharedStatus) {
         self.progress_val.set((status.progress * 100.0) as usize);
         let label = format!(
             "{} {}%",
@@ -107,7 +69,13 @@ impl ViewThread for StatusData {
             ]
             .join("\n"),
         );
+    }
+}
 
+#[async_trait]
+impl ViewThread for StatusData {
+    // The tabs thread fetches for this along with the other shared tabs, and calls `apply`.
+    async fn update(&mut self, _: &Session) -> deluge_rpc::Result<()> {
         Ok(())
     }
 
@@ -119,9 +87,8 @@ impl ViewThread for StatusData {
 }
 
 impl TabData for StatusData {
-    fn set_selection(&mut self, selection: InfoHash) {
-        self.selection = selection;
-    }
+    // Everything shown comes from the shared fetch, which knows the selection already.
+    fn set_selection(&mut self, _: InfoHash) {}
 }
 
 impl BuildableTabData for StatusData {
@@ -169,7 +136,6 @@ impl BuildableTabData for StatusData {
         let view = LinearLayout::vertical().child(progress_bar).child(status);
 
         let data = StatusData {
-            selection: InfoHash::default(),
             progress_label_send,
             progress_val,
             columns: [col1_content, col2_content, col3_content],
@@ -1,51 +1,79 @@
-use super::{column, BuildableTabData, TabData};
+use super::{column, shared::SharedStatus, BuildableTabData, TabData};
 use crate::util;
 use crate::views::thread::ViewThread;
 use async_trait::async_trait;
 use cursive::align::HAlign;
-use cursive::traits::Resizable;
-use cursive::views::{Button, DummyView, LinearLayout, TextContent};
-use deluge_rpc::{InfoHash, Query, Session};
-use serde::Deserialize;
-
-#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
-struct Tracker {/* we don't actually need any of this */}
-
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Query)]
-struct TrackersQuery {
-    trackers: Vec<Tracker>,
-    tracker_host: String,
-    tracker_status: String,
-    next_announce: i64,
-    private: bool,
-}
+use cursive::theme::Effect;
+use cursive::traits::{Resizable, Scrollable};
+use cursive::views::{Button, DummyView, LinearLayout, TextContent, TextView};
+use deluge_rpc::{InfoHash, Session};
+use std::collections::VecDeque;
+
+const HISTORY_LEN: usize = 20;
 
 pub(super) struct TrackersData {
     selection: InfoHash,
     content: TextContent,
+    // When the tracker status changed, and to what, for the torrent in `history_hash`.
+    // This only covers the time it's been selected, since the daemon doesn't keep any history,
+    // but it's kept up to date whichever tab is shown.
+    history: VecDeque<(i64, String)>,
+    history_hash: InfoHash,
+    history_content: TextContent,
 }
 
-#[async_trait]
-impl ViewThread for TrackersData {
-    async fn update(&mut self, session: &Session) -> deluge_rpc::Result<()> {
-        let hash = self.selection;
-        let query = session.get_torrent_status::<TrackersQuery>(hash).await?;
+impl TrackersData {
+    /// Notes a torrent's tracker status, starting the history over if it's for a different one.
+    pub(super) fn record_status(&mut self, hash: InfoHash, status: &str) {
+        if self.history_hash != hash {
+            self.history.clear();
+            self.history_hash = hash;
+        }
+
+        if self.history.back().map(|(_, s)| s.as_str()) == Some(status) {
+            return;
+        }
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        let now = chrono::Local::now().timestamp();
+        self.history.push_back((now, status.to_owned()));
+
+        let lines = self
+            .history
+            .iter()
+            .rev()
+            .map(|(t, status)| format!("{}  {}", util::fmt::date(*t), status));
+        self.history_content
+            .set_content(lines.collect::<Vec<_>>().join("\n"));
+    }
+
+    pub(super) fn apply(&mut self, query: &SharedStatus) {
+        self.record_status(self.selection, &query.tracker_status);
 
         self.content.set_content(
             [
                 query.trackers.len().to_string(),
-                query.tracker_host,
-                query.tracker_status,
+                query.tracker_host.clone(),
+                query.tracker_status.clone(),
                 util::fmt::time_or_dash(query.next_announce),
                 String::from(if query.private { "Yes" } else { "No" }),
             ]
             .join("\n"),
         );
+    }
+}
 
+#[async_trait]
+impl ViewThread for TrackersData {
+    // The tabs thread fetches for this along with the other shared tabs, and calls `apply`.
+    async fn update(&mut self, _: &Session) -> deluge_rpc::Result<()> {
         Ok(())
     }
 
     fn clear(&mut self) {
+        // This also happens when switching tabs, so the history stays until a status
+        // for some other torrent comes in.
         self.content.set_content("");
     }
 }
@@ -78,11 +106,21 @@ impl BuildableTabData for TrackersData {
 
         col_view.insert_child(0, left_col);
 
+        let history_content = TextContent::new("");
+        let view = LinearLayout::vertical()
+            .child(col_view)
+            .child(DummyView.fixed_height(1))
+            .child(TextView::new("Status History:").style(Effect::Bold))
+            .child(TextView::new_with_content(history_content.clone()).scrollable());
+
         let data = TrackersData {
             selection: InfoHash::default(),
             content: col_content,
+            history: VecDeque::with_capacity(HISTORY_LEN),
+            history_hash: InfoHash::default(),
+            history_content,
         };
 
-        (col_view, data)
+        (view, data)
     }
 }
@@ -1,12 +1,38 @@
+use crate::rpc::Policy;
+use crate::shutdown;
+use crate::visibility::{self, Visibility};
 use crate::SessionHandle;
 use async_trait::async_trait;
-use deluge_rpc::{Event, Session};
+use deluge_rpc::{Event, EventKind, Session};
+use std::collections::HashSet;
 use std::sync::Arc;
 use tokio::sync::{broadcast, watch, Notify};
-use tokio::time;
+use tokio::{task, time};
 
 type Result = deluge_rpc::Result<()>;
 
+/// Awaits a request, starting it over each time it times out, up to the policy's retry limit.
+/// Evaluates to `None` if it never finishes, after marking the daemon as not responding
+/// to this thread.
+macro_rules! retrying {
+    ($handle:expr, $thread:expr, $policy:expr, $fut:expr) => {{
+        let mut attempts = 0;
+        loop {
+            match time::timeout($policy.request_timeout, $fut).await {
+                Ok(result) => {
+                    $handle.record_responding($thread, true);
+                    break Some(result);
+                }
+                Err(_) if attempts < $policy.retries => attempts += 1,
+                Err(_) => {
+                    $handle.record_responding($thread, false);
+                    break None;
+                }
+            }
+        }
+    }};
+}
+
 #[async_trait]
 pub(crate) trait ViewThread: Send {
     async fn reload(&mut self, session: &Session) -> Result {
@@ -15,10 +41,22 @@ pub(crate) trait ViewThread: Send {
 
     async fn update(&mut self, _session: &Session) -> Result;
 
+    /// The events `on_event` wants to see. They're registered with the session handle
+    /// before each reload, along with every other view's.
+    fn event_interest(&self) -> HashSet<EventKind> {
+        HashSet::new()
+    }
+
     async fn on_event(&mut self, _session: &Session, _event: Event) -> Result {
         Ok(())
     }
 
+    /// Sends along whatever `update` found needs changing on the daemon. Unlike `update`,
+    /// it's never retried, since the daemon may have acted on it even if it never answered.
+    async fn send_changes(&mut self, _session: &Session) -> Result {
+        Ok(())
+    }
+
     fn tick(&self) -> time::Duration {
         time::Duration::from_secs(5)
     }
@@ -27,6 +65,12 @@ pub(crate) trait ViewThread: Send {
         Arc::new(Notify::new())
     }
 
+    /// Whether periodic updates are worth doing, given what's on screen.
+    /// Events still get handled either way.
+    fn should_poll(&self, ui: &Visibility) -> bool {
+        !ui.covered
+    }
+
     fn clear(&mut self);
 
     async fn run(mut self, mut session_recv: watch::Receiver<SessionHandle>) -> Result
@@ -34,31 +78,61 @@ pub(crate) trait ViewThread: Send {
         Self: Sized,
     {
         let mut handle = session_recv.borrow().clone();
+        let view = std::any::type_name::<Self>();
 
         let mut events = broadcast::channel(1).1;
         let update_notifier = self.update_notifier();
+        let mut ui_recv = visibility::subscribe();
 
         let mut should_reload = true;
         let mut should_check = true;
+        let mut policy = Policy::default();
 
         'main: loop {
             if should_reload {
                 should_reload = false;
 
                 if let Some(session) = handle.get_session() {
+                    let id = handle.get_id();
+                    policy = task::block_in_place(|| Policy::for_host(id));
                     events = session.subscribe_events();
-                    self.reload(session).await?;
+                    match retrying!(handle, view, policy, async {
+                        handle
+                            .register_event_interest(view, self.event_interest())
+                            .await?;
+                        self.reload(session).await
+                    }) {
+                        Some(result) => result?,
+                        // Try again after the next tick.
+                        None => should_reload = true,
+                    }
                 } else {
                     self.clear();
                 }
             }
 
             if let Some(session) = handle.get_session() {
-                let tick = time::Instant::now() + self.tick();
-
-                // Assuming this will be reasonably fast.
-                // If not for that assumption, I'd select between this, shutdown, and new_session.
-                self.update(session).await?;
+                let (polling, focused) = {
+                    let ui = ui_recv.borrow();
+                    (self.should_poll(&ui), ui.focused)
+                };
+                let tick = if focused {
+                    self.tick()
+                } else {
+                    self.tick() * visibility::UNFOCUSED_SLOWDOWN
+                };
+                let tick = time::Instant::now() + tick;
+
+                // A daemon that stops responding is left alone until the next tick.
+                if polling {
+                    if let Some(result) = retrying!(handle, view, policy, self.update(session)) {
+                        result?;
+                    }
+                }
+                match time::timeout(policy.request_timeout, self.send_changes(session)).await {
+                    Ok(result) => result?,
+                    Err(_) => handle.record_responding(view, false),
+                }
 
                 'idle: loop {
                     // The select macro isn't gonna let us call self.on_event().
@@ -68,6 +142,19 @@ pub(crate) trait ViewThread: Send {
 
                         _ = update_notifier.notified() => break 'idle,
                         _ = time::sleep_until(tick) => break 'idle,
+                        _ = shutdown::signaled() => return Ok(()),
+
+                        // Catch up as soon as there's something to see again.
+                        Ok(()) = ui_recv.changed(), if !polling || !focused => {
+                            let caught_up = {
+                                let ui = ui_recv.borrow();
+                                self.should_poll(&ui) && ui.focused
+                            };
+                            if caught_up {
+                                break 'idle;
+                            }
+                            continue 'idle;
+                        },
 
                         x = session_recv.changed() => match x {
                             Ok(()) => {
@@ -82,10 +169,18 @@ pub(crate) trait ViewThread: Send {
                         },
                     };
 
-                    self.on_event(session, event).await?;
+                    match time::timeout(policy.request_timeout, self.on_event(session, event)).await
+                    {
+                        Ok(result) => result?,
+                        Err(_) => handle.record_responding(view, false),
+                    }
                 }
             } else if should_check {
-                match session_recv.changed().await {
+                let changed = tokio::select! {
+                    x = session_recv.changed() => x,
+                    _ = shutdown::signaled() => return Ok(()),
+                };
+                match changed {
                     Ok(()) => {
                         handle = session_recv.borrow().clone();
                         should_reload = true;
@@ -101,3 +196,31 @@ pub(crate) trait ViewThread: Send {
         }
     }
 }
+
+/// Hooks a thread up to a session like `run` does, without the retrying or the UI,
+/// and returns the events it asked for, for handing to `deliver_event`.
+#[cfg(test)]
+pub(crate) async fn attach<T: ViewThread>(
+    thread: &mut T,
+    session: &Session,
+) -> deluge_rpc::Result<broadcast::Receiver<Event>> {
+    let events = session.subscribe_events();
+    session.set_event_interest(&thread.event_interest()).await?;
+    thread.reload(session).await?;
+    Ok(events)
+}
+
+/// Waits for the session's next event and hands it to the thread.
+/// Panics if none shows up, rather than leaving a test hanging.
+#[cfg(test)]
+pub(crate) async fn deliver_event<T: ViewThread>(
+    thread: &mut T,
+    session: &Session,
+    events: &mut broadcast::Receiver<Event>,
+) -> Result {
+    let event = time::timeout(time::Duration::from_secs(5), events.recv())
+        .await
+        .expect("the daemon never sent an event")
+        .unwrap();
+    thread.on_event(session, event).await
+}
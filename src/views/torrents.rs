@@ -1,21 +1,31 @@
 use super::thread::ViewThread;
+use crate::config::{GroupBy, LastSession};
 use crate::menu;
-use crate::{Selection, SessionHandle};
+use crate::visibility::Visibility;
+use crate::{AppState, Marked, Selection, SessionHandle};
 use async_trait::async_trait;
-use cursive::traits::*;
-use cursive::utils::Counter;
+use cursive::event::Callback;
+use cursive::theme::{BaseColor, ColorStyle, Effect, PaletteColor};
 use cursive::view::ViewWrapper;
-use cursive::views::ProgressBar;
-use cursive::Printer;
-use deluge_rpc::{FilterDict, FilterKey, InfoHash, InfoHashMap, Query, Session, TorrentState};
+use cursive::{Cursive, Printer, Vec2, View};
+use deluge_rpc::{EventKind, FilterKey, InfoHash, InfoHashMap, Query, Session, TorrentState};
 use futures::FutureExt;
+use std::collections::HashSet;
 use std::sync::{Arc, RwLock};
+use std::time::Instant;
 use tokio::sync::{watch, Notify};
 use tokio::time;
 
-use super::table::{TableView, TableViewData};
+use super::filters::FilterSets;
 
-use crate::util;
+mod search;
+#[cfg(test)]
+mod tests;
+use super::table::{draw_cells, draw_progress, TableView, TableViewData};
+use search::Search;
+
+use crate::util::glyphs::glyphs;
+use crate::util::{self, Ema};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum Column {
@@ -23,6 +33,11 @@ pub(crate) enum Column {
     State,
     Size,
     Speed,
+    Health,
+    Ratio,
+    Tracker,
+    Added,
+    Eta,
 }
 impl AsRef<str> for Column {
     fn as_ref(&self) -> &'static str {
@@ -31,10 +46,33 @@ impl AsRef<str> for Column {
             Self::State => "State",
             Self::Size => "Size",
             Self::Speed => "Speed",
+            Self::Health => "Health",
+            Self::Ratio => "Ratio",
+            Self::Tracker => "Tracker Status",
+            Self::Added => "Added",
+            Self::Eta => "ETA",
         }
     }
 }
 
+impl Column {
+    const ALL: [Self; 9] = [
+        Self::Name,
+        Self::State,
+        Self::Size,
+        Self::Speed,
+        Self::Health,
+        Self::Ratio,
+        Self::Tracker,
+        Self::Added,
+        Self::Eta,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|c| c.as_ref() == name)
+    }
+}
+
 impl Default for Column {
     fn default() -> Self {
         Self::Name
@@ -50,44 +88,67 @@ pub(crate) struct Torrent {
     progress: f32,
     upload_payload_rate: u64,
     download_payload_rate: u64,
+    // Missing if the Label plugin isn't enabled.
+    #[serde(default)]
     label: String,
     owner: String,
     tracker_host: String,
     tracker_status: String,
+    num_seeds: u64,
+    num_peers: u64,
+    distributed_copies: f64,
+    ratio: f64,
+    time_added: i64,
+    eta: i64,
 }
 
 type TorrentDiff = <Torrent as Query>::Diff;
 
 impl Torrent {
-    pub fn matches_filters(&self, filters: &FilterDict) -> bool {
-        for (key, val) in filters.iter() {
-            let cmp_val = match key {
-                FilterKey::State if val == "Active" => {
-                    if self.is_active() {
-                        continue;
-                    } else {
-                        return false;
-                    }
-                }
+    /// Whether the torrent matches the filters and the search, if any.
+    pub fn matches(&self, filters: &FilterSets, search: &Search) -> bool {
+        self.matches_filters(filters) && search.matches(self)
+    }
 
-                FilterKey::Tracker if val == "Error" => {
-                    if self.has_tracker_error() {
-                        continue;
-                    } else {
-                        return false;
-                    }
-                }
+    pub fn matches_filters(&self, filters: &FilterSets) -> bool {
+        filters
+            .iter()
+            .all(|(key, vals)| vals.iter().any(|val| self.matches_filter(*key, val)))
+    }
 
-                FilterKey::State => self.state.as_str(),
-                FilterKey::Owner => self.owner.as_str(),
-                FilterKey::Label => self.label.as_str(),
-                FilterKey::Tracker => self.tracker_host.as_str(),
-            };
-            if val != cmp_val {
-                return false;
-            }
+    fn matches_filter(&self, key: FilterKey, val: &str) -> bool {
+        match key {
+            FilterKey::State if val == "Active" => self.is_active(),
+            FilterKey::Tracker if val == "Error" => self.has_tracker_error(),
+            FilterKey::State => self.state.as_str() == val,
+            FilterKey::Owner => self.owner == val,
+            FilterKey::Label => self.label == val,
+            FilterKey::Tracker => self.tracker_host == val,
+        }
+    }
+
+    /// The second line of the torrent's row, in detailed mode.
+    fn details(&self, (down, up): (u64, u64), eta: i64) -> String {
+        let g = glyphs();
+        let mut parts = vec![
+            format!("{}%", util::fmt::percentage(self.progress)),
+            format!("{} {}/s", g.down, util::fmt::bytes(down)),
+            format!("{} {}/s", g.up, util::fmt::bytes(up)),
+            format!("ETA {}", util::fmt::time_or_dash(eta)),
+        ];
+        if !self.tracker_status.is_empty() {
+            parts.push(self.tracker_status.clone());
+        }
+        parts.join(&format!(" {} ", g.bullet))
+    }
+
+    fn group_name(&self, group_by: GroupBy) -> Option<&str> {
+        match group_by {
+            GroupBy::None => None,
+            GroupBy::State => Some(self.state.as_str()),
+            GroupBy::Label if self.label.is_empty() => Some("No Label"),
+            GroupBy::Label => Some(&self.label),
         }
-        true
     }
 
     pub fn has_tracker_error(&self) -> bool {
@@ -97,67 +158,209 @@ impl Torrent {
     pub fn is_active(&self) -> bool {
         self.download_payload_rate > 0 || self.upload_payload_rate > 0
     }
+
+    /// Roughly how likely the torrent is to be completable, from 0 to 1.
+    /// Any connected seed makes it healthy; otherwise, it depends on what the peers have.
+    pub fn health(&self) -> f64 {
+        if self.num_seeds > 0 || self.progress >= 100.0 {
+            1.0
+        } else if self.num_peers == 0 {
+            0.0
+        } else {
+            self.distributed_copies.min(1.0)
+        }
+    }
+}
+
+/// A line of the torrents table: either a torrent, or the header of a group of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum TorrentRow {
+    Torrent(InfoHash),
+    Group(usize), // an index into TorrentsState::groups
+}
+
+impl TorrentRow {
+    fn hash(self) -> Option<InfoHash> {
+        match self {
+            Self::Torrent(hash) => Some(hash),
+            Self::Group(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct Group {
+    name: String,
+    collapsed: bool,
+    count: usize,
+    total_size: u64,
+    download_rate: u64,
+    upload_rate: u64,
+}
+
+impl Group {
+    fn header(&self) -> String {
+        let g = glyphs();
+        format!(
+            "{} {} ({}) {b} {} {b} {} {}/s {b} {} {}/s",
+            if self.collapsed {
+                g.collapsed
+            } else {
+                g.expanded
+            },
+            self.name,
+            self.count,
+            util::fmt::bytes(self.total_size),
+            g.down,
+            util::fmt::bytes(self.download_rate),
+            g.up,
+            util::fmt::bytes(self.upload_rate),
+            b = g.bullet,
+        )
+    }
 }
 
 #[derive(Debug, Default, Clone)]
 pub(crate) struct TorrentsState {
+    // The torrents matching the filters, in order. This is what gets binary-searched.
     rows: Vec<InfoHash>,
+    // What's actually shown: the above, plus group headers and minus collapsed groups.
+    display_rows: Vec<TorrentRow>,
     torrents: InfoHashMap<Torrent>,
+    group_by: GroupBy,
+    // Never shrinks, so that a group's index stays put while it's empty.
+    groups: Vec<Group>,
     sort_column: Column,
     descending_sort: bool,
+    secondary_sort: Option<(Column, bool)>,
+    natural_sort: bool,
+    detailed_rows: bool,
+    // Set by the UI, then picked up by the thread.
+    search: String,
+    // When the ETAs were last fetched, so they can count down until the next update.
+    updated_at: Option<Instant>,
+    connected: bool,
+    smooth_speeds: bool,
+    // Moving averages of each torrent's download and upload rates.
+    speeds: InfoHashMap<(Ema, Ema)>,
 }
 
+const HEALTH_GAUGE_WIDTH: usize = 5;
+
 impl TableViewData for TorrentsState {
     type Column = Column;
-    type RowIndex = InfoHash;
-    type RowValue = Torrent;
-    type Rows = Vec<InfoHash>;
+    type RowIndex = TorrentRow;
+    type RowValue = TorrentRow;
+    type Rows = Vec<TorrentRow>;
     impl_table! {
         sort_column = self.sort_column;
-        rows = self.rows;
+        rows = self.display_rows;
         descending_sort = self.descending_sort;
     }
 
-    const SHOULD_GROW_TO_FIT: bool = true;
+    const SUPPORTS_SECONDARY_SORT: bool = true;
+    const SUPPORTS_MARKING: bool = true;
+
+    fn get_row_value<'a>(&'a self, index: &'a TorrentRow) -> &'a TorrentRow {
+        index
+    }
+
+    fn sort_unstable(&mut self) {
+        let mut rows = std::mem::take(&mut self.rows);
+        rows.sort_unstable_by(|a, b| self.compare_torrents(a, b));
+        self.rows = rows;
+        self.rebuild_display_rows();
+    }
 
-    fn get_row_value<'a>(&'a self, index: &'a InfoHash) -> &'a Torrent {
-        &self.torrents[index]
+    fn sort_stable(&mut self) {
+        let mut rows = std::mem::take(&mut self.rows);
+        rows.sort_by(|a, b| self.compare_torrents(a, b));
+        self.rows = rows;
+        self.rebuild_display_rows();
     }
 
     fn set_sort_column(&mut self, val: Column) {
         self.sort_column = val;
+        if matches!(self.secondary_sort, Some((c, _)) if c == val) {
+            self.secondary_sort = None;
+        }
+        self.sort_stable();
+    }
+
+    fn secondary_sort(&self) -> Option<(Column, bool)> {
+        self.secondary_sort
+    }
+
+    fn set_secondary_sort(&mut self, val: Option<(Column, bool)>) {
+        self.secondary_sort = val.filter(|(c, _)| *c != self.sort_column);
         self.sort_stable();
     }
 
     fn set_descending_sort(&mut self, val: bool) {
-        if val != self.descending_sort {
-            self.rows.reverse();
+        if val == self.descending_sort {
+            return;
         }
         self.descending_sort = val;
+        if self.secondary_sort.is_some() || self.group_by != GroupBy::None {
+            // The secondary sort and the groups keep their own direction,
+            // so a plain reversal won't do.
+            self.sort_stable();
+        } else {
+            self.rows.reverse();
+            self.rebuild_display_rows();
+        }
     }
 
-    fn compare_rows(&self, a: &InfoHash, b: &InfoHash) -> std::cmp::Ordering {
-        let (ta, tb) = (&self.torrents[a], &self.torrents[b]);
+    fn compare_rows(&self, a: &TorrentRow, b: &TorrentRow) -> std::cmp::Ordering {
+        // The display rows are derived from the sorted torrents, so this only matters for them.
+        match (a, b) {
+            (TorrentRow::Torrent(a), TorrentRow::Torrent(b)) => self.compare_torrents(a, b),
+            _ => {
+                let position = |row| self.display_rows.iter().position(|r| r == row);
+                position(a).cmp(&position(b))
+            }
+        }
+    }
 
-        let mut ord = match self.sort_column {
-            Column::Name => ta.name.cmp(&tb.name).reverse(),
-            Column::State => ta.state.cmp(&tb.state),
-            Column::Size => ta.total_size.cmp(&tb.total_size),
-            Column::Speed => ta.upload_payload_rate.cmp(&tb.upload_payload_rate),
-        };
+    fn row_height(&self) -> usize {
+        if self.detailed_rows {
+            2
+        } else {
+            1
+        }
+    }
 
-        // If the field used for comparison is identical, fall back to comparing infohashes
-        // Arbitrary, but consistent and domain-appropriate.
-        ord = ord.then(a.cmp(b));
+    fn draw_row(&self, printer: &Printer, columns: &[(Column, usize)], row: &TorrentRow) {
+        let hash = match row {
+            TorrentRow::Torrent(hash) => hash,
+            TorrentRow::Group(id) => {
+                let header = self.groups[*id].header();
+                printer.with_effect(Effect::Bold, |p| p.print((0, 0), &header));
+                return;
+            }
+        };
+        let tor = &self.torrents[hash];
 
-        if self.descending_sort {
-            ord = ord.reverse();
+        if tor.has_tracker_error() {
+            let style = ColorStyle::front(BaseColor::Red.light());
+            printer.with_color(style, |p| draw_cells(self, p, columns, row));
+        } else {
+            draw_cells(self, printer, columns, row);
         }
 
-        ord
+        if self.detailed_rows {
+            let style = ColorStyle::front(PaletteColor::Secondary);
+            let details = tor.details(self.rates(&tor.hash), self.eta(tor));
+            printer.with_color(style, |p| p.print((2, 1), &details));
+        }
     }
 
-    fn draw_cell(&self, printer: &Printer, tor: &Torrent, column: Column) {
+    fn draw_cell(&self, printer: &Printer, row: &TorrentRow, column: Column) {
+        let tor = match row {
+            TorrentRow::Torrent(hash) => &self.torrents[hash],
+            TorrentRow::Group(_) => return,
+        };
+
         match column {
             Column::Name => printer.print((0, 0), &tor.name),
             Column::State => {
@@ -171,26 +374,334 @@ impl TableViewData for TorrentsState {
                     TorrentState::Error => "ERROR",
                     TorrentState::Queued => "QUEUE",
                 };
-                let progress = util::fmt::percentage(tor.progress);
-                // TODO: draw my own damn progress bar
-                let status_msg = format!("{} {}%", status, progress);
-                ProgressBar::new()
-                    .with_value(Counter::new(tor.progress as usize))
-                    .with_label(move |_, _| status_msg.to_owned())
-                    .draw(printer);
+                let label = format!("{} {}%", status, util::fmt::percentage(tor.progress));
+                draw_progress(printer, tor.progress / 100.0, &label);
             }
             Column::Size => printer.print((0, 0), &util::fmt::bytes(tor.total_size)),
             Column::Speed => {
-                printer.print((0, 0), &(util::fmt::bytes(tor.upload_payload_rate) + "/s"))
+                let (_, up) = self.rates(&tor.hash);
+                printer.print((0, 0), &(util::fmt::bytes(up) + "/s"))
+            }
+            Column::Health => {
+                let health = tor.health();
+                let color = if health >= 1.0 {
+                    BaseColor::Green
+                } else if health >= 0.5 {
+                    BaseColor::Yellow
+                } else {
+                    BaseColor::Red
+                };
+
+                let filled = (health * HEALTH_GAUGE_WIDTH as f64).round() as usize;
+                let g = glyphs();
+                let gauge = g.gauge_full.repeat(filled)
+                    + &g.gauge_empty.repeat(HEALTH_GAUGE_WIDTH - filled);
+                printer.with_color(ColorStyle::front(color.dark()), |p| p.print((0, 0), &gauge));
+            }
+            Column::Ratio => {
+                let (low, high) = {
+                    let cfg = &crate::config::read().interface;
+                    (cfg.ratio_low, cfg.ratio_high)
+                };
+                // Deluge reports -1 for torrents that haven't downloaded anything.
+                let ratio = if tor.ratio < 0.0 {
+                    f64::INFINITY
+                } else {
+                    tor.ratio
+                };
+
+                let color = if ratio < low {
+                    BaseColor::Red
+                } else if ratio < high {
+                    BaseColor::Yellow
+                } else {
+                    BaseColor::Green
+                };
+
+                let text = if ratio.is_infinite() {
+                    String::from(glyphs().infinity)
+                } else {
+                    format!("{:.2}", ratio)
+                };
+                printer.with_color(ColorStyle::front(color.dark()), |p| p.print((0, 0), &text));
             }
+            Column::Tracker => {
+                let status = util::fmt::truncate(&tor.tracker_status, printer.size.x);
+                printer.print((0, 0), &status);
+            }
+            Column::Added => {
+                let date = if crate::config::read().interface.relative_dates {
+                    util::fmt::relative_date(tor.time_added)
+                } else {
+                    util::fmt::date(tor.time_added)
+                };
+                printer.print((0, 0), &date);
+            }
+            Column::Eta => printer.print((0, 0), &util::fmt::time_or_dash(self.eta(tor))),
         };
     }
 }
 
 impl TorrentsState {
+    fn compare_torrents(&self, a: &InfoHash, b: &InfoHash) -> std::cmp::Ordering {
+        let (ta, tb) = (&self.torrents[a], &self.torrents[b]);
+
+        let mut ord = self.compare_by(ta, tb, self.sort_column);
+
+        if let Some((column, descending)) = self.secondary_sort {
+            // The whole ordering gets flipped below if the primary sort is descending,
+            // so pre-flip the secondary one to keep its own direction.
+            let secondary = self.compare_by(ta, tb, column);
+            let secondary = if descending != self.descending_sort {
+                secondary.reverse()
+            } else {
+                secondary
+            };
+            ord = ord.then(secondary);
+        }
+
+        // If the field used for comparison is identical, fall back to comparing infohashes
+        // Arbitrary, but consistent and domain-appropriate.
+        ord = ord.then(a.cmp(b));
+
+        if self.descending_sort {
+            ord = ord.reverse();
+        }
+
+        // Groups are always in alphabetical order, whichever way their contents are sorted.
+        let group_ord = match (ta.group_name(self.group_by), tb.group_name(self.group_by)) {
+            (Some(ga), Some(gb)) => ga.cmp(gb),
+            _ => std::cmp::Ordering::Equal,
+        };
+
+        group_ord.then(ord)
+    }
+
+    /// Lays out the display rows according to the (already sorted) torrent rows.
+    fn rebuild_display_rows(&mut self) {
+        let mut display_rows = std::mem::take(&mut self.display_rows);
+        display_rows.clear();
+
+        if self.group_by == GroupBy::None {
+            display_rows.extend(self.rows.iter().copied().map(TorrentRow::Torrent));
+            self.display_rows = display_rows;
+            return;
+        }
+
+        for group in &mut self.groups {
+            group.count = 0;
+            group.total_size = 0;
+            group.download_rate = 0;
+            group.upload_rate = 0;
+        }
+
+        let mut current: Option<usize> = None;
+        for hash in &self.rows {
+            let tor = &self.torrents[hash];
+            let name = tor.group_name(self.group_by).unwrap_or_default();
+
+            let id = match current {
+                Some(id) if self.groups[id].name == name => id,
+                _ => {
+                    let id = match self.groups.iter().position(|g| g.name == name) {
+                        Some(id) => id,
+                        None => {
+                            self.groups.push(Group {
+                                name: name.to_owned(),
+                                ..Group::default()
+                            });
+                            self.groups.len() - 1
+                        }
+                    };
+                    display_rows.push(TorrentRow::Group(id));
+                    current = Some(id);
+                    id
+                }
+            };
+
+            let group = &mut self.groups[id];
+            group.count += 1;
+            group.total_size += tor.total_size;
+            group.download_rate += tor.download_payload_rate;
+            group.upload_rate += tor.upload_payload_rate;
+
+            if !group.collapsed {
+                display_rows.push(TorrentRow::Torrent(*hash));
+            }
+        }
+
+        self.display_rows = display_rows;
+    }
+
+    fn set_group_by(&mut self, group_by: GroupBy) {
+        self.group_by = group_by;
+        // Group names mean different things now.
+        self.groups.clear();
+        self.sort_stable();
+    }
+
+    fn toggle_group(&mut self, id: usize) {
+        let group = &mut self.groups[id];
+        group.collapsed = !group.collapsed;
+        self.rebuild_display_rows();
+    }
+
+    /// Whether an update could move the torrent into a different group.
+    fn group_changed(&self, diff: &TorrentDiff) -> bool {
+        match self.group_by {
+            GroupBy::None => false,
+            GroupBy::State => diff.state.is_some(),
+            GroupBy::Label => diff.label.is_some(),
+        }
+    }
+
+    fn compare_by(&self, ta: &Torrent, tb: &Torrent, column: Column) -> std::cmp::Ordering {
+        match column {
+            Column::Name => util::compare_names(&ta.name, &tb.name, self.natural_sort).reverse(),
+            Column::State => ta.state.cmp(&tb.state),
+            Column::Size => ta.total_size.cmp(&tb.total_size),
+            Column::Speed => self.rates(&ta.hash).1.cmp(&self.rates(&tb.hash).1),
+            Column::Health => ta.health().total_cmp(&tb.health()),
+            // Deluge reports -1 when nothing's been downloaded, which is shown as infinite.
+            Column::Ratio => {
+                let key = |t: &Torrent| {
+                    if t.ratio < 0.0 {
+                        f64::INFINITY
+                    } else {
+                        t.ratio
+                    }
+                };
+                key(ta).total_cmp(&key(tb))
+            }
+            Column::Tracker => ta.tracker_status.cmp(&tb.tracker_status),
+            Column::Added => ta.time_added.cmp(&tb.time_added),
+            // Deluge reports 0 when there's no ETA, which should sort after any actual ETA.
+            Column::Eta => {
+                let key = |t: &Torrent| if t.eta > 0 { t.eta } else { i64::MAX };
+                key(ta).cmp(&key(tb))
+            }
+        }
+    }
+
+    /// Whether an update could move the torrent's position when sorting by `column`.
+    fn sort_key_changed(diff: &TorrentDiff, column: Column) -> bool {
+        match column {
+            Column::Name => diff.name.is_some(),
+            Column::State => diff.state.is_some(),
+            Column::Size => diff.total_size.is_some(),
+            Column::Speed => diff.upload_payload_rate.is_some(),
+            Column::Health => {
+                diff.num_seeds.is_some()
+                    || diff.num_peers.is_some()
+                    || diff.distributed_copies.is_some()
+                    || diff.progress.is_some()
+            }
+            Column::Ratio => diff.ratio.is_some(),
+            Column::Tracker => diff.tracker_status.is_some(),
+            Column::Added => diff.time_added.is_some(),
+            Column::Eta => diff.eta.is_some(),
+        }
+    }
+
+    /// The torrent's ETA, counted down locally from the last time it was fetched.
+    fn eta(&self, tor: &Torrent) -> i64 {
+        if tor.eta <= 0 {
+            return tor.eta;
+        }
+        let elapsed = self.updated_at.map_or(0, |t| t.elapsed().as_secs() as i64);
+        // Don't count all the way down to "no ETA" just because the next update is late.
+        (tor.eta - elapsed).max(1)
+    }
+
+    /// The torrent's download and upload rates, smoothed out if the user wants that.
+    fn rates(&self, hash: &InfoHash) -> (u64, u64) {
+        match self.speeds.get(hash) {
+            Some((down, up)) if self.smooth_speeds => (down.get(), up.get()),
+            _ => {
+                let tor = &self.torrents[hash];
+                (tor.download_payload_rate, tor.upload_payload_rate)
+            }
+        }
+    }
+
+    /// Feeds every torrent's current rates into its moving averages.
+    fn sample_speeds(&mut self) {
+        let (smooth_speeds, samples) = {
+            let cfg = &crate::config::read().interface;
+            (cfg.smooth_speeds, cfg.speed_samples)
+        };
+        self.smooth_speeds = smooth_speeds;
+
+        let (torrents, speeds) = (&self.torrents, &mut self.speeds);
+        speeds.retain(|hash, _| torrents.contains_key(hash));
+        for (hash, tor) in torrents {
+            let (down, up) = speeds.entry(*hash).or_default();
+            down.update(tor.download_payload_rate, samples);
+            up.update(tor.upload_payload_rate, samples);
+        }
+
+        // The averages keep moving between updates, so the order has to keep up with them.
+        let by_speed = self.sort_column == Column::Speed
+            || matches!(self.secondary_sort, Some((Column::Speed, _)));
+        if smooth_speeds && by_speed {
+            self.sort_stable();
+        }
+    }
+
+    /// Picks up changes to the name sorting preference, re-sorting if needed.
+    fn sync_natural_sort(&mut self) {
+        let natural_sort = crate::config::read().interface.natural_sort;
+        if natural_sort != self.natural_sort {
+            self.natural_sort = natural_sort;
+            self.sort_stable();
+        }
+    }
+
+    /// A one-line overview of the listed torrents, for under the table.
+    fn summary(&self, marked: &[TorrentRow]) -> String {
+        let torrents = self.rows.iter().map(|hash| &self.torrents[hash]);
+
+        let mut count = 0;
+        let mut downloading = 0;
+        let mut total_size = 0;
+        for tor in torrents {
+            count += 1;
+            total_size += tor.total_size;
+            if tor.state == TorrentState::Downloading {
+                downloading += 1;
+            }
+        }
+
+        let mut parts = vec![
+            format!("{} torrent{}", count, if count == 1 { "" } else { "s" }),
+            format!("{} downloading", downloading),
+        ];
+        let selected = marked.iter().filter_map(|row| row.hash()).count();
+        if selected > 0 {
+            parts.push(format!("{} selected", selected));
+        }
+        parts.push(format!("{} total", util::fmt::bytes(total_size)));
+
+        parts.join(&format!(" {} ", glyphs().bullet))
+    }
+
     fn binary_search(&self, hash: &InfoHash) -> std::result::Result<usize, usize> {
         self.rows
-            .binary_search_by(|hash2| self.compare_rows(hash2, hash))
+            .binary_search_by(|hash2| self.compare_torrents(hash2, hash))
+    }
+
+    fn remove_row(&mut self, hash: InfoHash) {
+        let idx = self
+            .binary_search(&hash)
+            .expect("infohash not found in rows despite torrent matching filters");
+        self.rows.remove(idx);
+    }
+
+    fn insert_row(&mut self, hash: InfoHash) {
+        let idx = self
+            .binary_search(&hash)
+            .expect_err("rows vec already contained infohash");
+        self.rows.insert(idx, hash);
     }
 
     fn toggle_visibility(&mut self, hash: InfoHash) {
@@ -205,14 +716,47 @@ impl TorrentsState {
     }
 }
 
+/// Saves the selected torrent and how far the list is scrolled, for the next launch.
+pub(crate) fn save_last_session(siv: &mut Cursive) {
+    let host = siv
+        .user_data::<AppState>()
+        .and_then(|state| state.get().get_id());
+
+    let last = siv.call_on_name("torrents", |v: &mut TorrentsView| LastSession {
+        host,
+        selected: v.selected_torrent().map(|hash| hash.to_string()),
+        scroll: v.inner.scroll_offset(),
+    });
+
+    if let Some(last) = last {
+        let mut cfg = crate::config::write();
+        cfg.last_session = last;
+        cfg.save();
+    }
+}
+
 pub(crate) struct TorrentsView {
     inner: TableView<TorrentsState>,
+    filters_notify: Arc<Notify>,
+    selection: Selection,
+    selection_notify: Arc<Notify>,
+    marked: Marked,
+    // A torrent that was just added from the UI, to select once it shows up.
+    pending_selection: Option<InfoHash>,
+    session_recv: watch::Receiver<SessionHandle>,
+    // Where the list was left last time, until the torrents it refers to have loaded.
+    last_session: Option<LastSession>,
+    pending_scroll: Option<usize>,
 }
 
 struct TorrentsViewThread {
     data: Arc<RwLock<TorrentsState>>,
-    filters: FilterDict,
-    filters_recv: watch::Receiver<FilterDict>,
+    filters: FilterSets,
+    search: Search,
+    // What `search` was parsed from, for noticing when it changes.
+    search_text: String,
+    search_binary_units: bool,
+    filters_recv: watch::Receiver<FilterSets>,
     filters_notify: Arc<Notify>,
     missed_torrents: Vec<InfoHash>,
     selection: Selection,
@@ -224,13 +768,17 @@ impl TorrentsViewThread {
         data: Arc<RwLock<TorrentsState>>,
         selection: Selection,
         selection_notify: Arc<Notify>,
-        filters_recv: watch::Receiver<FilterDict>,
+        marked: Marked,
+        filters_recv: watch::Receiver<FilterSets>,
         filters_notify: Arc<Notify>,
     ) -> Self {
         let filters = filters_recv.borrow().clone();
         Self {
             data,
             filters,
+            search: Search::default(),
+            search_text: String::new(),
+            search_binary_units: true,
             filters_recv,
             filters_notify,
             missed_torrents: Vec::new(),
@@ -240,55 +788,78 @@ impl TorrentsViewThread {
     }
 
     fn apply_delta(&mut self, delta: InfoHashMap<TorrentDiff>) {
-        let mut toggled_rows = Vec::new();
-        let mut should_sort = false;
-
         let mut data = self.data.write().unwrap();
 
         for (hash, diff) in delta {
-            let sorting_changed = match data.sort_column {
-                Column::Name => diff.name.is_some(),
-                Column::State => diff.state.is_some(),
-                Column::Size => diff.total_size.is_some(),
-                Column::Speed => diff.upload_payload_rate.is_some(),
+            if diff == TorrentDiff::default() {
+                continue;
+            }
+
+            let sorting_changed = TorrentsState::sort_key_changed(&diff, data.sort_column)
+                || data
+                    .secondary_sort
+                    .map_or(false, |(c, _)| TorrentsState::sort_key_changed(&diff, c))
+                || data.group_changed(&diff);
+
+            let did_match = match data.torrents.get(&hash) {
+                Some(torrent) => torrent.matches(&self.filters, &self.search),
+                None => {
+                    self.missed_torrents.push(hash);
+                    continue;
+                }
             };
 
-            if let Some(torrent) = data.torrents.get_mut(&hash) {
-                if diff != TorrentDiff::default() {
-                    let did_match = torrent.matches_filters(&self.filters);
-                    torrent.update(diff);
-                    let does_match = torrent.matches_filters(&self.filters);
+            // Rather than re-sorting everything, take the row out while the rows are still
+            // sorted according to its old values, then binary-insert it once it's updated.
+            let mut listed = did_match;
+            if listed && sorting_changed {
+                data.remove_row(hash);
+                listed = false;
+            }
 
-                    if did_match != does_match {
-                        toggled_rows.push(hash);
-                    }
+            data.torrents.get_mut(&hash).unwrap().update(diff);
+            let does_match = data.torrents[&hash].matches(&self.filters, &self.search);
 
-                    should_sort |= does_match && sorting_changed;
-                }
-            } else {
-                self.missed_torrents.push(hash);
+            if listed && !does_match {
+                data.remove_row(hash);
+            } else if !listed && does_match {
+                data.insert_row(hash);
             }
         }
 
-        for hash in toggled_rows.into_iter() {
-            data.toggle_visibility(hash);
-        }
-
-        if should_sort {
-            data.sort_stable();
-        }
+        data.rebuild_display_rows();
     }
 
-    fn replace_filters(&mut self, new_filters: FilterDict) {
+    fn replace_filters(&mut self, new_filters: FilterSets) {
         self.filters = new_filters;
+        self.refilter();
+    }
+
+    /// Picks up a new search from the UI, if there is one.
+    /// Sizes in it are read the way they're shown, so a change to that counts too.
+    fn sync_search(&mut self) {
+        let binary_units = crate::config::read().interface.binary_units;
+        let search = {
+            let data = self.data.read().unwrap();
+            if data.search == self.search_text && binary_units == self.search_binary_units {
+                return;
+            }
+            data.search.clone()
+        };
+        self.search = Search::parse(&search, binary_units);
+        self.search_text = search;
+        self.search_binary_units = binary_units;
+        self.refilter();
+    }
 
+    fn refilter(&mut self) {
         let mut data = self.data.write().unwrap();
 
         let torrents = std::mem::take(&mut data.torrents);
 
         let iter = torrents
             .iter()
-            .filter(|(_hash, torrent)| torrent.matches_filters(&self.filters))
+            .filter(|(_hash, torrent)| torrent.matches(&self.filters, &self.search))
             .map(|(hash, _torrent)| *hash);
 
         data.rows.clear();
@@ -305,23 +876,25 @@ impl TorrentsViewThread {
             // This was actually an update rather than an addition.
             // Toggle visibility if appropriate, then return.
 
-            let did_match = old_torrent.matches_filters(&self.filters);
-            let does_match = data.torrents[&hash].matches_filters(&self.filters);
+            let did_match = old_torrent.matches(&self.filters, &self.search);
+            let does_match = data.torrents[&hash].matches(&self.filters, &self.search);
 
             if did_match != does_match {
                 data.toggle_visibility(hash);
+                data.rebuild_display_rows();
             }
 
             return;
         }
 
-        if data.torrents[&hash].matches_filters(&self.filters) {
+        if data.torrents[&hash].matches(&self.filters, &self.search) {
             let idx = data
                 .binary_search(&hash)
                 .expect_err("rows vec contained infohash, but torrents hashmap didn't");
 
             data.rows.insert(idx, hash);
         }
+        data.rebuild_display_rows();
     }
 
     fn remove_torrent(&mut self, hash: InfoHash) {
@@ -334,7 +907,7 @@ impl TorrentsViewThread {
         let mut data = self.data.write().unwrap();
         let tor = &data.torrents[&hash];
 
-        if tor.matches_filters(&self.filters) {
+        if tor.matches(&self.filters, &self.search) {
             let idx = data
                 .binary_search(&hash)
                 .expect("infohash not found in rows despite torrent matching filters");
@@ -343,25 +916,33 @@ impl TorrentsViewThread {
         }
 
         data.torrents.remove(&hash);
+        data.rebuild_display_rows();
     }
 }
 
 #[async_trait]
 impl ViewThread for TorrentsViewThread {
+    fn event_interest(&self) -> HashSet<EventKind> {
+        deluge_rpc::events![TorrentAdded, TorrentRemoved, TorrentStateChanged]
+    }
+
     async fn reload(&mut self, session: &Session) -> deluge_rpc::Result<()> {
-        let interested = deluge_rpc::events![TorrentAdded, TorrentRemoved, TorrentStateChanged];
-        session.set_event_interest(&interested).await?;
+        self.data.write().unwrap().connected = true;
 
         let initial_torrents = session.get_torrents_status::<Torrent>(None).await?;
         // TODO: do this more efficiently
         for (hash, torrent) in initial_torrents.into_iter() {
             self.add_torrent(hash, torrent);
         }
+        self.data.write().unwrap().updated_at = Some(Instant::now());
 
         Ok(())
     }
 
     async fn update(&mut self, session: &Session) -> deluge_rpc::Result<()> {
+        self.data.write().unwrap().sync_natural_sort();
+        self.sync_search();
+
         if let Some(Ok(())) = self.filters_recv.changed().now_or_never() {
             let new_filters = self.filters_recv.borrow().clone();
             self.replace_filters(new_filters);
@@ -369,6 +950,11 @@ impl ViewThread for TorrentsViewThread {
 
         let delta = session.get_torrents_status_diff::<Torrent>(None).await?;
         self.apply_delta(delta);
+        {
+            let mut data = self.data.write().unwrap();
+            data.sample_speeds();
+            data.updated_at = Some(Instant::now());
+        }
 
         while let Some(hash) = self.missed_torrents.pop() {
             let new_torrent = session.get_torrent_status::<Torrent>(hash).await?;
@@ -413,10 +999,18 @@ impl ViewThread for TorrentsViewThread {
         time::Duration::from_secs(1)
     }
 
+    fn should_poll(&self, ui: &Visibility) -> bool {
+        !ui.covered && ui.torrents
+    }
+
     fn clear(&mut self) {
         let mut data = self.data.write().unwrap();
         data.torrents.clear();
         data.rows.clear();
+        data.display_rows.clear();
+        data.updated_at = None;
+        data.connected = false;
+        data.speeds.clear();
         self.selection.write().unwrap().take();
         self.selection_notify.notify_one();
         self.missed_torrents.clear();
@@ -429,7 +1023,8 @@ impl TorrentsView {
         session_recv: watch::Receiver<SessionHandle>,
         selection: Selection,
         selection_notify: Arc<Notify>,
-        filters_recv: watch::Receiver<FilterDict>,
+        marked: Marked,
+        filters_recv: watch::Receiver<FilterSets>,
         filters_notify: Arc<Notify>,
     ) -> Self {
         let columns = vec![
@@ -437,32 +1032,271 @@ impl TorrentsView {
             (Column::State, 15),
             (Column::Size, 15),
             (Column::Speed, 15),
+            (Column::Health, HEALTH_GAUGE_WIDTH + 1),
+            (Column::Ratio, 7),
+            (Column::Tracker, 20),
+            (Column::Added, 12),
+            (Column::Eta, 10),
         ];
         let selection_clone = Arc::clone(&selection);
         let selection_notify_clone = Arc::clone(&selection_notify);
         let mut inner = TableView::new(columns);
-        inner.set_on_selection_change(move |_: &mut _, sel: &InfoHash, _, _| {
-            selection_clone.write().unwrap().replace(*sel);
-            selection_notify_clone.notify_one();
-            cursive::event::Callback::dummy()
+        {
+            let data = inner.get_data();
+            let mut data = data.write().unwrap();
+            let cfg = &crate::config::read().interface;
+            data.detailed_rows = cfg.detailed_rows;
+            data.group_by = cfg.group_by;
+        }
+        inner.hide_by_default(Column::Tracker);
+        inner.hide_by_default(Column::Added);
+        inner.hide_by_default(Column::Eta);
+        inner.set_config_key("torrents");
+        inner.set_footer(TorrentsState::summary);
+        inner.set_empty_message("No torrents match the current filters");
+        inner.set_on_selection_change(move |_: &mut _, sel: &TorrentRow, _, _| {
+            if let TorrentRow::Torrent(hash) = sel {
+                selection_clone.write().unwrap().replace(*hash);
+                selection_notify_clone.notify_one();
+            }
+            Callback::dummy()
         });
-        inner.set_on_right_click(|data: &mut TorrentsState, sel: &InfoHash, position, _| {
-            let name = &data.torrents[sel].name;
-            menu::torrent_context_menu(*sel, name, position)
+        inner.set_on_double_click(|data: &mut TorrentsState, sel: &TorrentRow, _, _| {
+            if let TorrentRow::Group(id) = sel {
+                data.toggle_group(*id);
+            }
+            Callback::dummy()
         });
+        inner.set_on_right_click(
+            |data: &mut TorrentsState, sel: &TorrentRow, position, _| match sel {
+                TorrentRow::Torrent(hash) => {
+                    let name = &data.torrents[hash].name;
+                    menu::torrent_context_menu(*hash, name, position)
+                }
+                TorrentRow::Group(_) => Callback::dummy(),
+            },
+        );
+        inner.set_on_middle_click(
+            |data: &mut TorrentsState, sel: &TorrentRow, _, _| match sel {
+                TorrentRow::Torrent(hash) => {
+                    let torrent = &data.torrents[hash];
+                    menu::quick_action(*hash, &torrent.name, torrent.state)
+                }
+                TorrentRow::Group(_) => Callback::dummy(),
+            },
+        );
 
         let thread_obj = TorrentsViewThread::new(
             inner.get_data(),
-            selection,
-            selection_notify,
+            Arc::clone(&selection),
+            Arc::clone(&selection_notify),
             filters_recv,
-            filters_notify,
+            Arc::clone(&filters_notify),
         );
-        tokio::spawn(thread_obj.run(session_recv));
-        Self { inner }
+        crate::shutdown::spawn("torrents", thread_obj.run(session_recv.clone()));
+        Self {
+            inner,
+            filters_notify,
+            selection,
+            selection_notify,
+            marked,
+            pending_selection: None,
+            session_recv,
+            last_session: Some(crate::config::read().last_session.clone()),
+            pending_scroll: None,
+        }
+    }
+}
+
+impl TorrentsView {
+    pub(crate) fn selected_torrent(&self) -> Option<InfoHash> {
+        self.inner.get_selection().and_then(|row| row.hash())
+    }
+
+    /// Selects a torrent that was just added, as soon as its `TorrentAdded` event comes in.
+    pub(crate) fn select_when_added(&mut self, hash: InfoHash) {
+        self.pending_selection = Some(hash);
+        self.select_pending();
+    }
+
+    fn select_pending(&mut self) {
+        let hash = match self.pending_selection {
+            Some(hash) => hash,
+            None => return,
+        };
+
+        let listed = {
+            let data = self.inner.get_data();
+            let data = data.read().unwrap();
+            if !data.torrents.contains_key(&hash) {
+                return;
+            }
+            data.binary_search(&hash).is_ok()
+        };
+        self.pending_selection = None;
+
+        // If the filters hide it, there's nothing to select.
+        if listed {
+            self.inner.set_selection(TorrentRow::Torrent(hash));
+            self.selection.write().unwrap().replace(hash);
+            self.selection_notify.notify_one();
+        }
+    }
+
+    /// Picks up where the last run left off, once connected to the same host as back then
+    /// and the torrents have loaded.
+    fn restore_last_session(&mut self) {
+        let host = match &self.last_session {
+            Some(last) => last.host,
+            None => return,
+        };
+
+        let id = self.session_recv.borrow().get_id();
+        match id {
+            None => return,
+            Some(id) if Some(id) != host => {
+                self.last_session = None;
+                return;
+            }
+            Some(_) => {}
+        }
+        if self.inner.get_data().read().unwrap().torrents.is_empty() {
+            return;
+        }
+
+        let last = self.last_session.take().unwrap();
+        if let Some(hash) = last.selected.and_then(|s| s.parse().ok()) {
+            self.select_when_added(hash);
+        }
+        self.pending_scroll = Some(last.scroll);
+    }
+
+    /// Renames a torrent without waiting for the next update, keeping the rows sorted.
+    pub(crate) fn rename_torrent(&mut self, hash: InfoHash, name: String) {
+        let data = self.inner.get_data();
+        let mut data = data.write().unwrap();
+
+        let listed = data.binary_search(&hash).is_ok();
+        if listed {
+            data.remove_row(hash);
+        }
+        if let Some(torrent) = data.torrents.get_mut(&hash) {
+            torrent.name = name;
+        }
+        if listed {
+            data.insert_row(hash);
+        }
+        data.rebuild_display_rows();
+    }
+
+    pub(crate) fn search(&self) -> String {
+        self.inner.get_data().read().unwrap().search.clone()
+    }
+
+    /// Only shows torrents matching `search`. See `Search` for what it can contain.
+    pub(crate) fn set_search(&mut self, search: &str) {
+        self.inner.get_data().write().unwrap().search = search.to_owned();
+        self.filters_notify.notify_one();
+    }
+
+    pub(crate) fn sort(&self) -> (Column, bool) {
+        let data = self.inner.get_data();
+        let data = data.read().unwrap();
+        (data.sort_column, data.descending_sort)
+    }
+
+    pub(crate) fn set_sort(&mut self, column: Column, descending: bool) {
+        let data = self.inner.get_data();
+        let mut data = data.write().unwrap();
+        data.set_sort_column(column);
+        data.set_descending_sort(descending);
+    }
+
+    pub(crate) fn set_group_by(&mut self, group_by: GroupBy) {
+        self.inner
+            .get_data()
+            .write()
+            .unwrap()
+            .set_group_by(group_by);
+    }
+
+    pub(crate) fn set_detailed_rows(&mut self, detailed: bool) {
+        self.inner.get_data().write().unwrap().detailed_rows = detailed;
+    }
+
+    pub(crate) fn active_count(&self) -> usize {
+        let data = self.inner.get_data();
+        let data = data.read().unwrap();
+        data.torrents.values().filter(|t| t.is_active()).count()
+    }
+
+    /// Every torrent, whether or not it matches the filters.
+    pub(crate) fn torrent_names(&self) -> Vec<(InfoHash, String)> {
+        let data = self.inner.get_data();
+        let data = data.read().unwrap();
+        data.torrents
+            .iter()
+            .map(|(hash, tor)| (*hash, tor.name.clone()))
+            .collect()
+    }
+
+    /// Every torrent that currently matches the filters.
+    pub(crate) fn visible_torrents(&self) -> Vec<InfoHash> {
+        self.inner.get_data().read().unwrap().rows.clone()
+    }
+
+    /// Like `selected_torrents`, but with their names.
+    pub(crate) fn selected_torrent_names(&self) -> Vec<(InfoHash, String)> {
+        let data = self.inner.get_data();
+        let data = data.read().unwrap();
+        self.selected_torrents()
+            .into_iter()
+            .map(|hash| (hash, data.torrents[&hash].name.clone()))
+            .collect()
+    }
+
+    /// The marked torrents, or if none are marked, the selected one.
+    pub(crate) fn selected_torrents(&self) -> Vec<InfoHash> {
+        match self.inner.get_marked() {
+            [] => self.selected_torrent().into_iter().collect(),
+            marked => marked.iter().filter_map(|row| row.hash()).collect(),
+        }
     }
 }
 
 impl ViewWrapper for TorrentsView {
     cursive::wrap_impl!(self.inner: TableView<TorrentsState>);
+
+    fn wrap_layout(&mut self, size: Vec2) {
+        self.restore_last_session();
+        self.select_pending();
+        self.inner.layout(size);
+        if let Some(y) = self.pending_scroll.take() {
+            self.inner.set_scroll_offset(y);
+        }
+
+        let marked: Vec<InfoHash> = self
+            .inner
+            .get_marked()
+            .iter()
+            .filter_map(|row| row.hash())
+            .collect();
+        if *self.marked.read().unwrap() != marked {
+            *self.marked.write().unwrap() = marked;
+            // The tabs care about the marks as much as about the selection.
+            self.selection_notify.notify_one();
+        }
+    }
+
+    fn wrap_draw(&self, printer: &Printer) {
+        if self.inner.get_data().read().unwrap().connected {
+            self.inner.draw(printer);
+            return;
+        }
+
+        // Rather than an empty table, which looks like a daemon without any torrents.
+        let msg = "Not connected. Use Edit > Connection Manager to connect.";
+        let x = printer.size.x.saturating_sub(msg.len()) / 2;
+        printer.print((x, printer.size.y / 2), msg);
+    }
 }
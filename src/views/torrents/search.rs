@@ -0,0 +1,154 @@
+use regex::{Regex, RegexBuilder};
+use std::cmp::Ordering;
+
+use super::Torrent;
+
+/// A comparison like `>` or `<=`, from a search word like `size>10G`.
+#[derive(Debug, Clone, Copy)]
+struct Comparison {
+    ordering: Ordering,
+    or_equal: bool,
+}
+
+impl Comparison {
+    /// Splits the operator off the front of `s`.
+    fn parse(s: &str) -> Option<(Self, &str)> {
+        let split = s
+            .find(|c: char| !"<>=".contains(c))
+            .unwrap_or_else(|| s.len());
+        let (op, rest) = s.split_at(split);
+        let (ordering, or_equal) = match op {
+            ">" => (Ordering::Greater, false),
+            ">=" => (Ordering::Greater, true),
+            "<" => (Ordering::Less, false),
+            "<=" => (Ordering::Less, true),
+            "=" => (Ordering::Equal, false),
+            _ => return None,
+        };
+        Some((Self { ordering, or_equal }, rest))
+    }
+
+    fn holds<T: PartialOrd>(self, val: T, target: T) -> bool {
+        match val.partial_cmp(&target) {
+            Some(Ordering::Equal) => self.or_equal || self.ordering == Ordering::Equal,
+            Some(ordering) => ordering == self.ordering,
+            None => false,
+        }
+    }
+}
+
+/// Sizes like "700M", "1.5GiB", or "100".
+/// Whether a plain "G" means GB or GiB is up to `binary_units`.
+fn parse_size(s: &str, binary_units: bool) -> Option<u64> {
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or_else(|| s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number.parse().ok()?;
+
+    let unit = unit.to_ascii_uppercase();
+    let unit = unit.strip_suffix('B').unwrap_or(&unit);
+    let (prefix, binary) = match unit.strip_suffix('I') {
+        Some(prefix) => (prefix, true),
+        None => (unit, binary_units),
+    };
+    let power = match prefix {
+        "" => 0,
+        "K" => 1,
+        "M" => 2,
+        "G" => 3,
+        "T" => 4,
+        _ => return None,
+    };
+    let base: f64 = if binary { 1024.0 } else { 1000.0 };
+    Some((number * base.powi(power)) as u64)
+}
+
+/// One word of a search.
+#[derive(Debug, Clone)]
+enum Term {
+    Name(String),
+    Regex(Regex),
+    Label(String),
+    Tracker(String),
+    State(String),
+    Owner(String),
+    Size(Comparison, u64),
+    Ratio(Comparison, f64),
+}
+
+impl Term {
+    /// Comparisons that are missing their number, like `size>`, aren't terms at all.
+    fn parse(word: &str, binary_units: bool) -> Option<Self> {
+        if let Some(rest) = word.strip_prefix("size") {
+            if let Some((cmp, size)) = Comparison::parse(rest) {
+                return Some(Self::Size(cmp, parse_size(size, binary_units)?));
+            }
+        } else if let Some(rest) = word.strip_prefix("ratio") {
+            if let Some((cmp, ratio)) = Comparison::parse(rest) {
+                return Some(Self::Ratio(cmp, ratio.parse().ok()?));
+            }
+        }
+
+        let (key, val) = match word.split_once(':') {
+            Some(pair) => pair,
+            None => return Some(Self::Name(word.to_lowercase())),
+        };
+        if key == "re" {
+            let re = RegexBuilder::new(val).case_insensitive(true).build();
+            return re.ok().map(Self::Regex);
+        }
+
+        let val = val.to_lowercase();
+        let term = match key {
+            "label" => Self::Label(val),
+            "tracker" => Self::Tracker(val),
+            "state" => Self::State(val),
+            "owner" => Self::Owner(val),
+            _ => Self::Name(word.to_lowercase()),
+        };
+        Some(term)
+    }
+
+    fn matches(&self, torrent: &Torrent) -> bool {
+        let contains = |field: &str, val: &str| field.to_lowercase().contains(val);
+        match self {
+            Self::Name(val) => contains(&torrent.name, val),
+            Self::Regex(re) => re.is_match(&torrent.name),
+            Self::Label(val) => contains(&torrent.label, val),
+            Self::Tracker(val) => contains(&torrent.tracker_host, val),
+            Self::State(val) => contains(torrent.state.as_str(), val),
+            Self::Owner(val) => contains(&torrent.owner, val),
+            Self::Size(cmp, size) => cmp.holds(torrent.total_size, *size),
+            Self::Ratio(cmp, ratio) => cmp.holds(torrent.ratio, *ratio),
+        }
+    }
+}
+
+/// The search bar's contents, parsed. Every word has to match.
+/// Most words just look for themselves in the name, ignoring case, but some are more specific:
+///
+/// - `re:<regex>` matches the name against a regex, ignoring case.
+/// - `label:`, `tracker:`, `state:`, and `owner:` look in those fields instead.
+/// - `size>10G`, `size<=700M`, `ratio>=1.5`, and so on compare numbers.
+///
+/// Words that don't make sense yet, like a half-typed regex or `size>`, are ignored.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Search {
+    terms: Vec<Term>,
+}
+
+impl Search {
+    /// Sizes without an "i", like "10G", are binary if `binary_units` says so.
+    pub fn parse(text: &str, binary_units: bool) -> Self {
+        let terms = text
+            .split_whitespace()
+            .filter_map(|word| Term::parse(word, binary_units))
+            .collect();
+        Self { terms }
+    }
+
+    pub fn matches(&self, torrent: &Torrent) -> bool {
+        self.terms.iter().all(|term| term.matches(torrent))
+    }
+}
@@ -0,0 +1,209 @@
+use deluge_rpc::{FilterKey, InfoHash, InfoHashMap, TorrentOptions};
+use std::sync::{Arc, RwLock};
+use tokio::sync::watch;
+
+use super::{FilterSets, TorrentDiff, TorrentsState, TorrentsViewThread};
+use crate::demo;
+use crate::views::thread::{self, ViewThread};
+
+fn hash(n: u8) -> InfoHash {
+    format!("{:040x}", n).parse().unwrap()
+}
+
+fn magnet(n: u8, name: &str) -> String {
+    format!("magnet:?xt=urn:btih:{:040x}&dn={}", n, name)
+}
+
+/// A thread with no UI on the other end, and the state it fills in.
+fn detached() -> (TorrentsViewThread, Arc<RwLock<TorrentsState>>) {
+    let data = Arc::new(RwLock::new(TorrentsState::default()));
+    let filters_recv = watch::channel(FilterSets::default()).1;
+    let thread = TorrentsViewThread::new(
+        Arc::clone(&data),
+        Arc::default(),
+        Arc::default(),
+        filters_recv,
+        Arc::default(),
+    );
+    (thread, data)
+}
+
+/// The rows should be exactly the torrents that match, sorted the way the state sorts them,
+/// however they got there.
+fn assert_rows_consistent(thread: &TorrentsViewThread, data: &TorrentsState) {
+    let mut expected: Vec<InfoHash> = data
+        .torrents
+        .iter()
+        .filter(|(_, torrent)| torrent.matches(&thread.filters, &thread.search))
+        .map(|(hash, _)| *hash)
+        .collect();
+    expected.sort_by(|a, b| data.compare_torrents(a, b));
+    assert_eq!(data.rows, expected);
+}
+
+fn position(data: &TorrentsState, name: &str) -> usize {
+    data.rows
+        .iter()
+        .position(|hash| data.torrents[hash].name == name)
+        .unwrap_or_else(|| panic!("{} isn't listed", name))
+}
+
+#[tokio::test]
+async fn reload_lists_everything_in_order() {
+    let session =
+        demo::session_with(&[magnet(1, "bravo"), magnet(2, "charlie"), magnet(3, "alpha")]).await;
+    let (mut thread, data) = detached();
+    thread.reload(&session).await.unwrap();
+
+    let data = data.read().unwrap();
+    assert_eq!(data.rows.len(), 3);
+    assert_rows_consistent(&thread, &data);
+
+    // Whichever way names sort, "bravo" lands between the others.
+    assert_eq!(position(&data, "bravo"), 1);
+}
+
+#[tokio::test]
+async fn renaming_moves_the_row() {
+    let session = demo::session_with(&[magnet(1, "a"), magnet(2, "b"), magnet(3, "c")]).await;
+    let (mut thread, data) = detached();
+    thread.reload(&session).await.unwrap();
+    let before = position(&data.read().unwrap(), "a");
+
+    let mut delta = InfoHashMap::default();
+    let diff = TorrentDiff {
+        name: Some(String::from("d")),
+        ..TorrentDiff::default()
+    };
+    delta.insert(hash(1), diff);
+    thread.apply_delta(delta);
+
+    let data = data.read().unwrap();
+    assert_rows_consistent(&thread, &data);
+    // It sorted before the others, and now it sorts after them, so it's at the opposite end.
+    assert_eq!(position(&data, "d"), 2 - before);
+}
+
+#[tokio::test]
+async fn empty_diffs_change_nothing() {
+    let session = demo::session_with(&[magnet(1, "a"), magnet(2, "b")]).await;
+    let (mut thread, data) = detached();
+    thread.reload(&session).await.unwrap();
+    let before = data.read().unwrap().rows.clone();
+
+    let mut delta = InfoHashMap::default();
+    delta.insert(hash(1), TorrentDiff::default());
+    delta.insert(hash(9), TorrentDiff::default());
+    thread.apply_delta(delta);
+
+    assert_eq!(data.read().unwrap().rows, before);
+    assert!(thread.missed_torrents.is_empty());
+}
+
+#[tokio::test]
+async fn updates_fetch_torrents_whose_events_were_missed() {
+    let session = demo::session_with(&[magnet(1, "a")]).await;
+    let (mut thread, data) = detached();
+    thread.reload(&session).await.unwrap();
+
+    // Nobody's listening for the event this sends.
+    let options = TorrentOptions::default();
+    session
+        .add_torrent_magnet(&magnet(2, "b"), &options)
+        .await
+        .unwrap();
+    thread.update(&session).await.unwrap();
+
+    let data = data.read().unwrap();
+    assert!(data.rows.contains(&hash(2)));
+    assert_rows_consistent(&thread, &data);
+    assert!(thread.missed_torrents.is_empty());
+}
+
+#[tokio::test]
+async fn events_add_and_remove_rows() {
+    let session = demo::session_with(&[magnet(1, "b"), magnet(3, "d")]).await;
+    let (mut thread, data) = detached();
+    let mut events = thread::attach(&mut thread, &session).await.unwrap();
+
+    let options = TorrentOptions::default();
+    for (n, name) in [(2, "c"), (4, "a")] {
+        session
+            .add_torrent_magnet(&magnet(n, name), &options)
+            .await
+            .unwrap();
+        thread::deliver_event(&mut thread, &session, &mut events)
+            .await
+            .unwrap();
+        assert_rows_consistent(&thread, &data.read().unwrap());
+    }
+    assert_eq!(data.read().unwrap().rows.len(), 4);
+
+    session.remove_torrents(&[hash(2)], false).await.unwrap();
+    thread::deliver_event(&mut thread, &session, &mut events)
+        .await
+        .unwrap();
+
+    let data = data.read().unwrap();
+    assert!(!data.rows.contains(&hash(2)));
+    assert!(!data.torrents.contains_key(&hash(2)));
+    assert_rows_consistent(&thread, &data);
+}
+
+#[tokio::test]
+async fn state_changes_respect_the_filters() {
+    let session = demo::session_with(&[magnet(1, "a"), magnet(2, "b"), magnet(3, "c")]).await;
+    let (mut thread, data) = detached();
+    let mut filters = FilterSets::default();
+    filters.insert(FilterKey::State, vec![String::from("Downloading")]);
+    thread.replace_filters(filters);
+    let mut events = thread::attach(&mut thread, &session).await.unwrap();
+    assert_eq!(data.read().unwrap().rows.len(), 3);
+
+    session.pause_torrent(hash(2)).await.unwrap();
+    thread::deliver_event(&mut thread, &session, &mut events)
+        .await
+        .unwrap();
+    {
+        let data = data.read().unwrap();
+        assert!(!data.rows.contains(&hash(2)));
+        assert_rows_consistent(&thread, &data);
+    }
+
+    session.resume_torrent(hash(2)).await.unwrap();
+    thread::deliver_event(&mut thread, &session, &mut events)
+        .await
+        .unwrap();
+
+    let data = data.read().unwrap();
+    assert!(data.rows.contains(&hash(2)));
+    assert_rows_consistent(&thread, &data);
+}
+
+#[tokio::test]
+async fn searches_narrow_the_rows() {
+    let session = demo::session_with(&[
+        magnet(1, "Debian+netinst"),
+        magnet(2, "Fedora+Workstation"),
+        magnet(3, "debian+live"),
+    ])
+    .await;
+    let (mut thread, data) = detached();
+    thread.reload(&session).await.unwrap();
+
+    data.write().unwrap().search = String::from("debian");
+    thread.update(&session).await.unwrap();
+    {
+        let data = data.read().unwrap();
+        assert_eq!(data.rows.len(), 2);
+        assert!(!data.rows.contains(&hash(2)));
+        assert_rows_consistent(&thread, &data);
+    }
+
+    data.write().unwrap().search.clear();
+    thread.update(&session).await.unwrap();
+
+    let data = data.read().unwrap();
+    assert_eq!(data.rows.len(), 3);
+    assert_rows_consistent(&thread, &data);
+}
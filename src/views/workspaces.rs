@@ -0,0 +1,171 @@
+use cursive::event::{Event, EventResult, MouseButton, MouseEvent};
+use cursive::view::CannotFocus;
+use cursive::views::EditView;
+use cursive::{Cursive, Printer, Vec2, View};
+use deluge_rpc::FilterKey;
+
+use crate::config::{self, Workspace};
+use crate::util::glyphs::glyphs;
+use crate::views::filters::{FilterSets, FiltersView};
+use crate::views::torrents::{Column, TorrentsView};
+
+const FILTER_KEYS: [FilterKey; 4] = [
+    FilterKey::State,
+    FilterKey::Owner,
+    FilterKey::Label,
+    FilterKey::Tracker,
+];
+
+/// A strip of tabs for switching between workspaces, drawn above the torrents list.
+pub(crate) struct WorkspacesView;
+
+impl WorkspacesView {
+    fn labels() -> Vec<String> {
+        config::read()
+            .workspaces
+            .workspaces
+            .iter()
+            .enumerate()
+            .map(|(i, ws)| format!(" {}:{} ", i + 1, ws.name))
+            .collect()
+    }
+
+    fn tab_at(x: usize) -> Option<usize> {
+        let mut start = 0;
+        for (i, label) in Self::labels().iter().enumerate() {
+            let end = start + label.chars().count();
+            if (start..end).contains(&x) {
+                return Some(i);
+            }
+            start = end + 1;
+        }
+        None
+    }
+}
+
+impl View for WorkspacesView {
+    fn draw(&self, printer: &Printer) {
+        let active = config::read().workspaces.active;
+
+        let mut x = 0;
+        for (i, label) in Self::labels().iter().enumerate() {
+            printer.with_selection(i == active, |p| p.print((x, 0), label));
+            x += label.chars().count();
+            printer.print((x, 0), glyphs().vline);
+            x += 1;
+        }
+    }
+
+    fn required_size(&mut self, _: Vec2) -> Vec2 {
+        let width: usize = Self::labels().iter().map(|l| l.chars().count() + 1).sum();
+        Vec2::new(width, 1)
+    }
+
+    fn take_focus(&mut self, _: cursive::direction::Direction) -> Result<EventResult, CannotFocus> {
+        Err(CannotFocus)
+    }
+
+    fn on_event(&mut self, event: Event) -> EventResult {
+        match event {
+            Event::Mouse {
+                offset,
+                position,
+                event: MouseEvent::Press(MouseButton::Left),
+            } => {
+                let x = match position.checked_sub(offset) {
+                    Some(pos) if pos.y == 0 => pos.x,
+                    _ => return EventResult::Ignored,
+                };
+                match Self::tab_at(x) {
+                    Some(i) => EventResult::with_cb(move |siv| switch_workspace(siv, i)),
+                    None => EventResult::Ignored,
+                }
+            }
+            _ => EventResult::Ignored,
+        }
+    }
+}
+
+fn to_filter_sets(ws: &Workspace) -> FilterSets {
+    FILTER_KEYS
+        .iter()
+        .filter_map(|key| Some((*key, ws.filters.get(&key.to_string())?.clone())))
+        .collect()
+}
+
+/// Records the current filters, sort order, and search in the active workspace.
+fn save_current(siv: &mut Cursive) {
+    let filters = siv.call_on_name("filters", |v: &mut FiltersView| v.active_filters());
+    let torrents = siv.call_on_name("torrents", |v: &mut TorrentsView| (v.sort(), v.search()));
+
+    let mut cfg = config::write();
+    let active = cfg.workspaces.active;
+    let ws = match cfg.workspaces.workspaces.get_mut(active) {
+        Some(ws) => ws,
+        None => return,
+    };
+
+    if let Some(filters) = filters {
+        ws.filters = filters
+            .into_iter()
+            .map(|(key, vals)| (key.to_string(), vals))
+            .collect();
+    }
+    if let Some(((column, descending), search)) = torrents {
+        ws.sort_column = Some(column.as_ref().to_owned());
+        ws.descending_sort = descending;
+        ws.search = search;
+    }
+}
+
+/// Sets up the filters, sort order, and search from the active workspace.
+pub(crate) fn apply_active(siv: &mut Cursive) {
+    let ws = {
+        let cfg = config::read();
+        match cfg.workspaces.workspaces.get(cfg.workspaces.active) {
+            Some(ws) => ws.clone(),
+            None => return,
+        }
+    };
+
+    let filters = to_filter_sets(&ws);
+    siv.call_on_name("filters", |v: &mut FiltersView| {
+        v.set_active_filters(filters)
+    });
+
+    let column = ws.sort_column.as_deref().and_then(Column::from_name);
+    siv.call_on_name("torrents", |v: &mut TorrentsView| {
+        if let Some(column) = column {
+            v.set_sort(column, ws.descending_sort);
+        }
+        v.set_search(&ws.search);
+    });
+
+    siv.call_on_name("search", |v: &mut EditView| {
+        v.set_content(ws.search.clone());
+    });
+}
+
+pub(crate) fn switch_workspace(siv: &mut Cursive, index: usize) {
+    if index >= config::read().workspaces.workspaces.len() {
+        return;
+    }
+
+    save_current(siv);
+    {
+        let mut cfg = config::write();
+        cfg.workspaces.active = index;
+        cfg.save();
+    }
+    apply_active(siv);
+}
+
+pub(crate) fn cycle_workspace(siv: &mut Cursive) {
+    let (active, count) = {
+        let cfg = config::read();
+        (cfg.workspaces.active, cfg.workspaces.workspaces.len())
+    };
+    if count > 0 {
+        switch_workspace(siv, (active + 1) % count);
+    }
+}
@@ -0,0 +1,75 @@
+use cursive::Cursive;
+use once_cell::sync::Lazy;
+use tokio::sync::watch;
+
+use crate::menu::MainLayout;
+
+/// Which parts of the main UI can be seen, so that view threads can stop polling
+/// for things that nobody's looking at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Visibility {
+    // Whether a dialog or menu is on top of the main UI.
+    pub covered: bool,
+    pub torrents: bool,
+    pub tabs: bool,
+    // Whether dtui's terminal has focus. While it doesn't, everything slows to a trickle.
+    pub focused: bool,
+}
+
+pub(crate) const FPS: u32 = 4;
+const UNFOCUSED_FPS: u32 = 1;
+
+/// How many times longer view threads wait between updates while the terminal isn't focused.
+pub(crate) const UNFOCUSED_SLOWDOWN: u32 = 12;
+
+impl Default for Visibility {
+    fn default() -> Self {
+        Self {
+            covered: false,
+            torrents: true,
+            tabs: true,
+            focused: true,
+        }
+    }
+}
+
+static CHANNEL: Lazy<(watch::Sender<Visibility>, watch::Receiver<Visibility>)> =
+    Lazy::new(|| watch::channel(Visibility::default()));
+
+pub(crate) fn subscribe() -> watch::Receiver<Visibility> {
+    CHANNEL.1.clone()
+}
+
+/// Looks at what's on screen, and lets the view threads know if that's changed.
+/// Layers come and go from all over the place, so this just runs on every refresh.
+pub(crate) fn update(siv: &mut Cursive) {
+    let covered = siv.screen().len() > 1;
+    let (torrents, tabs) = siv
+        .call_on_name("main", |v: &mut MainLayout| {
+            (v.is_visible(0), v.is_visible(1))
+        })
+        .unwrap_or((true, true));
+
+    let old = *CHANNEL.1.borrow();
+    publish(Visibility {
+        covered,
+        torrents,
+        tabs,
+        ..old
+    });
+}
+
+/// Slows the UI and the view threads way down while the terminal isn't focused,
+/// and speeds them back up the moment it is again.
+pub(crate) fn set_focused(siv: &mut Cursive, focused: bool) {
+    siv.set_fps(if focused { FPS } else { UNFOCUSED_FPS });
+    let old = *CHANNEL.1.borrow();
+    publish(Visibility { focused, ..old });
+}
+
+fn publish(new: Visibility) {
+    if *CHANNEL.1.borrow() != new {
+        // The channel's own receiver keeps this from ever failing.
+        CHANNEL.0.send(new).ok();
+    }
+}
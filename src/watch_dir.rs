@@ -0,0 +1,153 @@
+use deluge_rpc::{Session, TorrentOptions};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio::sync::watch;
+use tokio::{task, time};
+
+use crate::config::{self, WatchDir};
+use crate::rpc::{self, Failure, Policy};
+use crate::shutdown;
+use crate::SessionHandle;
+
+const POLL_INTERVAL: time::Duration = time::Duration::from_secs(5);
+const PROCESSED_DIR: &str = "processed";
+
+impl WatchDir {
+    fn torrent_options(&self) -> TorrentOptions {
+        TorrentOptions {
+            add_paused: Some(self.add_paused),
+            download_location: self.download_location.clone(),
+            move_completed: self.move_completed_path.as_ref().map(|_| true),
+            move_completed_path: self.move_completed_path.clone(),
+            ..TorrentOptions::default()
+        }
+    }
+}
+
+/// A file's size and modification time, which stop changing once it's been fully written.
+type Stamp = (u64, SystemTime);
+
+/// Runs filesystem calls on a blocking thread, rather than holding up the task.
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    task::spawn_blocking(f)
+        .await
+        .expect("a filesystem call panicked")
+}
+
+fn pending_torrents(dir: &Path) -> Vec<(PathBuf, Stamp)> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "torrent"))
+        .filter_map(|path| {
+            let meta = std::fs::metadata(&path)
+                .ok()
+                .filter(|meta| meta.is_file())?;
+            let stamp = (meta.len(), meta.modified().ok()?);
+            Some((path, stamp))
+        })
+        .collect()
+}
+
+enum Upload {
+    Added,
+    Rejected,
+    // Couldn't read the file, or the daemon never answered. Either might work next time.
+    Retry,
+}
+
+async fn upload(
+    session: &Session,
+    policy: &Policy,
+    path: &Path,
+    options: &TorrentOptions,
+) -> Upload {
+    let owned = path.to_owned();
+    let filedump = match blocking(move || std::fs::read(owned)).await {
+        Ok(bytes) => base64::encode(bytes),
+        Err(_) => return Upload::Retry,
+    };
+    let filename = path.file_name().unwrap().to_string_lossy();
+
+    let fut = session.add_torrent_file(&filename, &filedump, options);
+    match rpc::request(policy, fut).await {
+        Ok(Some(_)) => Upload::Added,
+        Ok(None) | Err(Failure::Rpc(_)) => Upload::Rejected,
+        Err(Failure::NotResponding) => Upload::Retry,
+    }
+}
+
+fn mark_processed(dir: &Path, path: &Path) -> std::io::Result<()> {
+    let processed = dir.join(PROCESSED_DIR);
+    std::fs::create_dir_all(&processed)?;
+    std::fs::rename(path, processed.join(path.file_name().unwrap()))
+}
+
+/// Uploads new .torrent files from the configured watch directories for as long as dtui runs.
+pub(crate) async fn run(mut session_recv: watch::Receiver<SessionHandle>) {
+    // What each file looked like on the last scan. A file only gets uploaded once it looks
+    // the same on two scans in a row, so that one that's still being written isn't.
+    let mut last_seen: HashMap<PathBuf, Stamp> = HashMap::new();
+    // Files the daemon rejected, or that it took but that couldn't be moved out of the way.
+    // We leave them where they are, and don't retry them unless they change.
+    let mut failed: HashMap<PathBuf, Stamp> = HashMap::new();
+    let mut interval = time::interval(POLL_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => (),
+            _ = shutdown::signaled() => return,
+            x = session_recv.changed() => match x {
+                Ok(()) => continue,
+                Err(_) => return,
+            },
+        }
+
+        let handle = session_recv.borrow().clone();
+        let session = match handle.get_session() {
+            Some(session) => session,
+            None => continue,
+        };
+
+        let policy = task::block_in_place(|| Policy::for_host(handle.get_id()));
+        let watch_dirs = config::read().watch_dirs.clone();
+        let mut seen = HashMap::new();
+
+        for dir in &watch_dirs {
+            let options = dir.torrent_options();
+            let dir_path = dir.path.clone();
+            for (path, stamp) in blocking(move || pending_torrents(&dir_path)).await {
+                seen.insert(path.clone(), stamp);
+                if last_seen.get(&path) != Some(&stamp) || failed.get(&path) == Some(&stamp) {
+                    continue;
+                }
+
+                let processed = match upload(session, &policy, &path, &options).await {
+                    Upload::Added => {
+                        // If it can't be moved, it at least shouldn't be added again.
+                        let (dir_path, path) = (dir.path.clone(), path.clone());
+                        blocking(move || mark_processed(&dir_path, &path))
+                            .await
+                            .is_ok()
+                    }
+                    Upload::Rejected => false,
+                    Upload::Retry => continue,
+                };
+                if processed {
+                    failed.remove(&path);
+                } else {
+                    failed.insert(path, stamp);
+                }
+            }
+        }
+
+        failed.retain(|path, _| seen.contains_key(path));
+        last_seen = seen;
+    }
+}
@@ -0,0 +1,127 @@
+//! Deluge's RPC framing, for the parts of dtui that speak it themselves
+//! rather than through deluge-rpc: the demo daemon, and the bridge to 1.x daemons.
+
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use rustls::client::{ServerCertVerified, ServerCertVerifier};
+use rustls::{Certificate, ClientConfig, PrivateKey, ServerConfig, ServerName};
+use serde_json::Value;
+use std::convert::{TryFrom, TryInto};
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+// Every message is a header byte and a big-endian length, then that many bytes of zlib'd rencode.
+// 2.x puts a protocol version in that first byte, where 1.3 always put a 'D'.
+pub(crate) const DELUGE_2: u8 = 1;
+pub(crate) const DELUGE_1: u8 = b'D';
+const HEADER_LEN: usize = 5;
+
+fn invalid(e: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+/// Reads one message's body, still compressed.
+pub(crate) async fn read_frame(
+    reader: &mut (impl AsyncRead + Unpin),
+    version: u8,
+) -> io::Result<Vec<u8>> {
+    let mut header = [0; HEADER_LEN];
+    reader.read_exact(&mut header).await?;
+    if header[0] != version {
+        return Err(invalid(format!("unexpected header byte {}", header[0])));
+    }
+
+    let len = u32::from_be_bytes(header[1..].try_into().unwrap());
+    let mut body = vec![0; len as usize];
+    reader.read_exact(&mut body).await?;
+    Ok(body)
+}
+
+pub(crate) async fn write_frame(
+    writer: &mut (impl AsyncWrite + Unpin),
+    version: u8,
+    body: &[u8],
+) -> io::Result<()> {
+    let mut frame = Vec::with_capacity(HEADER_LEN + body.len());
+    frame.push(version);
+    frame.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    frame.extend_from_slice(body);
+    writer.write_all(&frame).await?;
+    writer.flush().await
+}
+
+pub(crate) fn inflate(body: &[u8]) -> io::Result<Vec<u8>> {
+    let mut decoded = Vec::new();
+    ZlibDecoder::new(body).read_to_end(&mut decoded)?;
+    Ok(decoded)
+}
+
+pub(crate) fn decode(body: &[u8]) -> io::Result<Value> {
+    rencode::from_bytes(&inflate(body)?).map_err(invalid)
+}
+
+pub(crate) fn encode(msg: &Value) -> io::Result<Vec<u8>> {
+    let encoded = rencode::to_bytes(msg).map_err(invalid)?;
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&encoded)?;
+    encoder.finish()
+}
+
+pub(crate) async fn read(reader: &mut (impl AsyncRead + Unpin)) -> io::Result<Value> {
+    decode(&read_frame(reader, DELUGE_2).await?)
+}
+
+pub(crate) async fn write(writer: &mut (impl AsyncWrite + Unpin), msg: &Value) -> io::Result<()> {
+    write_frame(writer, DELUGE_2, &encode(msg)?).await
+}
+
+/// Deluge clients expect TLS, but not a certificate worth checking, so a throwaway one will do.
+pub(crate) fn acceptor() -> TlsAcceptor {
+    let cert = rcgen::generate_simple_self_signed(vec![String::from("localhost")])
+        .expect("failed to generate a certificate");
+    let der = cert
+        .serialize_der()
+        .expect("failed to serialize a certificate");
+    let key = PrivateKey(cert.serialize_private_key_der());
+
+    let config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(vec![Certificate(der)], key)
+        .expect("a freshly generated certificate should be usable");
+    TlsAcceptor::from(Arc::new(config))
+}
+
+/// Daemons make up their own certificates, so there's nothing to check them against.
+struct AnyCertificate;
+
+impl ServerCertVerifier for AnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+pub(crate) fn connector() -> TlsConnector {
+    let config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(AnyCertificate))
+        .with_no_client_auth();
+    TlsConnector::from(Arc::new(config))
+}
+
+/// What to tell the daemon's TLS stack it's called. It doesn't care.
+pub(crate) fn server_name() -> ServerName {
+    ServerName::try_from("deluge").unwrap()
+}